@@ -6,6 +6,7 @@ use super::{
     datasets::DatasetsAction,
     encrypt_and_index::EncryptAndIndexAction,
     findex::{insert_or_delete::InsertOrDeleteAction, search::SearchAction},
+    indexes::IndexesAction,
     login::LoginAction,
     permissions::PermissionsAction,
     search_and_decrypt::SearchAndDecryptAction,
@@ -30,6 +31,9 @@ pub enum FindexActions {
     #[command(subcommand)]
     Datasets(DatasetsAction),
 
+    #[command(subcommand)]
+    Indexes(IndexesAction),
+
     Login(LoginAction),
     /// Logout from the Identity Provider.
     ///
@@ -44,6 +48,7 @@ impl FindexActions {
     /// - indexing, searching with or without datasets-encryption (indexes are always encrypted),
     /// - permissions management,
     /// - datasets management,
+    /// - indexes backup and restore,
     /// - login and logout,
     ///
     /// # Errors
@@ -64,6 +69,9 @@ impl FindexActions {
             Self::Permissions(action) => {
                 println!("{}", action.run(findex_client).await?);
             }
+            Self::Indexes(action) => {
+                println!("{}", action.run(findex_client).await?);
+            }
             Self::ServerVersion(action) => {
                 println!("{}", action.run(findex_client).await?);
             }
@@ -3,12 +3,15 @@ use std::{collections::HashMap, error::Error};
 use base64::{Engine, engine::general_purpose};
 use clap::Parser;
 use cosmian_findex_client::RestClient;
-use cosmian_findex_structs::EncryptedEntries;
+use cosmian_findex_structs::{EncryptedEntries, VersionedEntries, VersionedEntry};
 use uuid::Uuid;
 
-use crate::error::{
-    FindexCliError,
-    result::{FindexCliResult, FindexCliResultHelper},
+use crate::{
+    cli_error,
+    error::{
+        FindexCliError,
+        result::{FindexCliResult, FindexCliResultHelper},
+    },
 };
 
 /// Manage encrypted datasets
@@ -17,6 +20,9 @@ pub enum DatasetsAction {
     Add(AddEntries),
     Delete(DeleteEntries),
     Get(GetEntries),
+    GetVersioned(GetVersionedEntries),
+    Update(UpdateEntries),
+    List(ListEntries),
 }
 
 impl DatasetsAction {
@@ -33,6 +39,12 @@ impl DatasetsAction {
                 .run(rest_client)
                 .await
                 .map(|entries| entries.to_string()),
+            Self::GetVersioned(action) => action
+                .run(rest_client)
+                .await
+                .map(|entries| entries.to_string()),
+            Self::Update(action) => action.run(rest_client).await,
+            Self::List(action) => action.run(rest_client).await,
         }
     }
 }
@@ -149,3 +161,130 @@ impl GetEntries {
         Ok(encrypted_entries)
     }
 }
+
+/// Return datasets entries matching given UUID, along with their versions.
+#[derive(Parser, Debug)]
+pub struct GetVersionedEntries {
+    /// The index id
+    #[clap(long, required = true)]
+    pub index_id: Uuid,
+
+    /// The entries uuids
+    #[clap(long, required = true)]
+    pub uuids: Vec<Uuid>,
+}
+
+impl GetVersionedEntries {
+    /// Runs the `GetVersionedEntries` action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query execution on the Findex server fails.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<VersionedEntries> {
+        let versioned_entries = rest_client
+            .get_versioned_entries(&self.index_id, &self.uuids)
+            .await
+            .with_context(|| "Can't execute the get entries query on the findex server")?;
+
+        Ok(versioned_entries)
+    }
+}
+
+/// Update datasets entries, only if none of them has been written since
+/// their versions were read. Nothing is written otherwise.
+#[derive(Parser, Debug)]
+pub struct UpdateEntries {
+    /// The index ID
+    #[clap(long, required = true)]
+    pub index_id: Uuid,
+
+    /// The entries to update under the format `KEY=VALUE` where:
+    /// - `KEY` is a UUID
+    /// - `VALUE` is a base64 encoded string
+    ///
+    /// Can be repeated multiple times
+    #[arg(short = 'D', value_parser = parse_key_val::<Uuid, String>)]
+    pub entries: Vec<(Uuid, String)>,
+
+    /// The expected versions of the entries under the format `KEY=VERSION`,
+    /// 0 standing for an entry that does not exist yet.
+    ///
+    /// Can be repeated multiple times, once per entry
+    #[arg(short = 'V', value_parser = parse_key_val::<Uuid, u64>)]
+    pub versions: Vec<(Uuid, u64)>,
+}
+
+impl UpdateEntries {
+    /// Runs the `UpdateEntries` action.
+    ///
+    /// # Errors
+    /// Returns an error if the version of an entry is not given.
+    /// Returns an error if the base64 decoding fails.
+    /// Returns an error if the query execution on the Findex server fails,
+    /// in particular if the version of an entry changed.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<String> {
+        let versions = self.versions.iter().copied().collect::<HashMap<_, _>>();
+        let versioned_entries = self
+            .entries
+            .iter()
+            .map(|(key, value)| {
+                let version = *versions
+                    .get(key)
+                    .ok_or_else(|| cli_error!("Missing the version of the entry {key}"))?;
+                let entry = general_purpose::STANDARD.decode(value)?;
+                Ok((*key, VersionedEntry { version, entry }))
+            })
+            .collect::<FindexCliResult<VersionedEntries>>()?;
+
+        let response = rest_client
+            .update_entries(&self.index_id, &versioned_entries)
+            .await
+            .with_context(|| "Can't execute the update entries query on the findex server")?;
+
+        Ok(response.to_string())
+    }
+}
+
+/// List the UUIDs and the sizes of all the datasets entries of an index.
+#[derive(Parser, Debug)]
+pub struct ListEntries {
+    /// The index ID
+    #[clap(long, required = true)]
+    pub index_id: Uuid,
+
+    /// The number of entries requested from the server at once
+    #[clap(long, default_value = "1000")]
+    pub page_size: usize,
+}
+
+impl ListEntries {
+    /// Runs the `ListEntries` action.
+    ///
+    /// Prints one entry per line: its UUID and the size in bytes of its
+    /// encrypted content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query execution on the Findex server fails.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<String> {
+        let mut lines = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = rest_client
+                .list_entries(&self.index_id, cursor.as_deref(), self.page_size)
+                .await
+                .with_context(|| "Can't execute the list entries query on the findex server")?;
+            lines.extend(
+                page.entries
+                    .iter()
+                    .map(|entry| format!("{} {}", entry.uuid, entry.size)),
+            );
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+}
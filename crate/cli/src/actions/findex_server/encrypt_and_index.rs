@@ -6,10 +6,8 @@ use std::{
 
 use clap::Parser;
 use cosmian_findex_client::{
-    RestClient,
-    reexport::cosmian_findex_structs::{
-        CUSTOM_WORD_LENGTH, EncryptedEntries, Keyword, KeywordToDataSetsMap, Uuids,
-    },
+    DatasetEntry, EntryEncryption, EntryKey, RestClient,
+    reexport::cosmian_findex_structs::{EncryptedEntries, Keyword, KeywordToDataSetsMap, Uuids},
 };
 use cosmian_findex_structs::Value;
 use cosmian_kms_cli::{
@@ -18,11 +16,11 @@ use cosmian_kms_cli::{
         KmsClient, reexport::cosmian_kms_client_utils::symmetric_utils::DataEncryptionAlgorithm,
     },
 };
-use tracing::{debug, trace};
+use tracing::trace;
+use uuid::Uuid;
 
-use super::findex::parameters::FindexParameters;
+use super::findex::{columns::ColumnParameters, parameters::FindexParameters};
 use crate::{
-    actions::findex_server::findex::findex_instance::FindexInstance,
     cli_bail,
     error::result::{FindexCliResult, FindexCliResultHelper},
 };
@@ -42,6 +40,9 @@ pub struct EncryptAndIndexAction {
     #[clap(long)]
     pub csv: PathBuf,
 
+    #[clap(flatten)]
+    pub column_parameters: ColumnParameters,
+
     /// The key encryption key (KEK) unique identifier.
     /// If provided, all encryption is done client side. The KEK is first
     /// exported locally and is used in the KEM to encapsulates the ephemeral
@@ -104,6 +105,15 @@ impl EncryptAndIndexAction {
         let mut encrypted_entries = EncryptedEntries::new();
         let mut keywords_values: HashMap<Keyword, HashSet<Value>> = HashMap::new();
 
+        let expansion = self
+            .findex_parameters
+            .requested_expansion()?
+            .unwrap_or_default();
+        let tokenizer = self
+            .findex_parameters
+            .requested_tokenizer()?
+            .unwrap_or_default();
+
         let encrypt_action = EncryptAction::default();
         // Generate an ephemeral key (DEK) and wrap it with the KEK.
         let (dek, encapsulation) = encrypt_action
@@ -117,6 +127,7 @@ impl EncryptAndIndexAction {
 
         let file = File::open(csv.clone())?;
         let mut rdr = csv::Reader::from_reader(file);
+        let columns = self.column_parameters.select(rdr.byte_headers()?)?;
         for result in rdr.byte_records() {
             // The iterator yields Result<StringRecord, Error>, so we check the
             // error here.
@@ -130,14 +141,13 @@ impl EncryptAndIndexAction {
                 record_bytes,
                 authentication_data.clone(),
             )?;
-            let new_uuid = uuid::Uuid::new_v4();
+            let new_uuid = columns.id(&record)?.map_or_else(Uuid::new_v4, |id| {
+                record_uuid(&self.findex_parameters.index_id, id)
+            });
             encrypted_entries.insert(new_uuid, encrypted_record);
 
             let indexed_value = Value::from(new_uuid.as_bytes().to_vec());
-            for keyword in record
-                .iter()
-                .map(|kw| Keyword::from(kw.to_ascii_lowercase().as_slice()))
-            {
+            for keyword in columns.keywords(&record, tokenizer, &expansion) {
                 trace!("keyword: {}", keyword);
                 keywords_values
                     .entry(keyword)
@@ -149,200 +159,91 @@ impl EncryptAndIndexAction {
         Ok((encrypted_entries, KeywordToDataSetsMap(keywords_values)))
     }
 
-    pub(crate) async fn client_side_encrypt_entries(
-        &self,
-        csv: PathBuf,
-        kms_rest_client: &KmsClient,
-        key_encryption_key_id: &str,
-        nonce: Option<Vec<u8>>,
-        authentication_data: Option<Vec<u8>>,
-    ) -> FindexCliResult<(EncryptedEntries, KeywordToDataSetsMap)> {
-        let encrypt_action = EncryptAction::default();
-        let (dek, encapsulation) = encrypt_action
-            .client_side_kem_encapsulation(
-                kms_rest_client,
-                key_encryption_key_id,
-                self.data_encryption_algorithm,
-            )
-            .await?;
-
-        let mut encrypted_entries = EncryptedEntries::new();
-        let mut keywords_map: HashMap<Keyword, HashSet<Value>> = HashMap::new();
-
-        let mut reader = csv::Reader::from_reader(File::open(csv)?);
-        for result in reader.byte_records() {
-            let record = result?;
-            let new_uuid = uuid::Uuid::new_v4();
-
-            let encrypted_record = encrypt_action.client_side_encrypt_with_buffer(
-                &dek,
-                &encapsulation,
-                self.data_encryption_algorithm,
-                nonce.clone(),
-                record.as_slice(),
-                authentication_data.clone(),
-            )?;
-            encrypted_entries.insert(new_uuid, encrypted_record);
-
-            let indexed_value = Value::from(new_uuid.as_bytes().to_vec());
-            for kw in &record {
-                let keyword = Keyword::from(kw.to_ascii_lowercase().as_slice());
-                keywords_map
-                    .entry(keyword)
-                    .or_default()
-                    .insert(indexed_value.clone());
-            }
-        }
-
-        Ok((encrypted_entries, KeywordToDataSetsMap(keywords_map)))
-    }
-
-    pub(crate) async fn server_side_encrypt_entries(
-        &self,
-        csv: PathBuf,
-        kms_rest_client: &KmsClient,
-        data_encryption_key_id: &str,
-        nonce: Option<Vec<u8>>,
-        authentication_data: Option<Vec<u8>>,
-    ) -> FindexCliResult<(EncryptedEntries, KeywordToDataSetsMap)> {
-        let encrypt_action = EncryptAction::default();
-        let mut encrypted_entries = EncryptedEntries::new();
-        let mut keywords_map: HashMap<Keyword, HashSet<Value>> = HashMap::new();
-
-        let mut reader = csv::Reader::from_reader(File::open(csv)?);
-        for result in reader.byte_records() {
-            let record = result?;
-            let new_uuid = uuid::Uuid::new_v4();
-
-            let (encrypted_nonce, encrypted_data, encrypted_tag) = encrypt_action
-                .server_side_encrypt(
-                    kms_rest_client,
-                    data_encryption_key_id,
-                    self.data_encryption_algorithm.into(),
-                    nonce.clone(),
-                    record.as_slice().to_vec(),
-                    authentication_data.clone(),
-                )
-                .await?;
-
-            let mut payload = Vec::new();
-            if let Some(nonce_bytes) = encrypted_nonce.as_ref() {
-                payload.extend_from_slice(nonce_bytes);
-            }
-            payload.extend_from_slice(&encrypted_data);
-            if let Some(tag_bytes) = encrypted_tag.as_ref() {
-                payload.extend_from_slice(tag_bytes);
-            }
-            encrypted_entries.insert(new_uuid, payload);
-
-            let indexed_value = Value::from(new_uuid.as_bytes().to_vec());
-            for kw in &record {
-                let keyword = Keyword::from(kw.to_ascii_lowercase().as_slice());
-                keywords_map
-                    .entry(keyword)
-                    .or_default()
-                    .insert(indexed_value.clone());
-            }
-        }
-
-        Ok((encrypted_entries, KeywordToDataSetsMap(keywords_map)))
-    }
-
     /// Adds the data from the CSV file to the Findex index.
     ///
     /// # Errors
     ///
     /// This function will return an error if:
     /// - There is an error instantiating the Findex client.
-    /// - There is an error converting the CSV file to a hashmap.
-    /// - There is an error adding the data to the Findex index.
+    /// - There is an error reading the CSV file.
+    /// - There is an error encrypting the data or adding it to the Findex
+    ///   index.
     /// - There is an error writing the result to the console.
     #[expect(clippy::print_stdout)]
-    #[allow(clippy::cognitive_complexity)]
     pub async fn run(
         &self,
         rest_client: RestClient,
         kms_rest_client: KmsClient,
     ) -> FindexCliResult<Uuids> {
-        let nonce = self
-            .nonce
-            .as_deref()
-            .map(hex::decode)
-            .transpose()
-            .with_context(|| "failed to decode the nonce")?;
-
-        let authentication_data = self
-            .authentication_data
-            .as_deref()
-            .map(hex::decode)
-            .transpose()
-            .with_context(|| "failed to decode the authentication data")?;
-
-        let (encrypted_entries, keywords_indexed_value_map) = match (
+        let key = match (
             self.key_encryption_key_id.clone(),
             self.data_encryption_key_id.clone(),
         ) {
             (Some(key_encryption_key_id), None) => {
-                debug!(
-                    "Findex: Client-side encryption with key encryption key: \
-                     {key_encryption_key_id}"
-                );
-                self.client_side_encrypt_entries(
-                    self.csv.clone(),
-                    &kms_rest_client,
-                    &key_encryption_key_id,
-                    nonce,
-                    authentication_data,
-                )
-                .await?
+                EntryKey::KeyEncryptionKey(key_encryption_key_id)
             }
             (None, Some(data_encryption_key_id)) => {
-                debug!(
-                    "Findex: Server-side encryption with data encryption key: \
-                     {data_encryption_key_id}"
-                );
-                self.server_side_encrypt_entries(
-                    self.csv.clone(),
-                    &kms_rest_client,
-                    &data_encryption_key_id,
-                    nonce,
-                    authentication_data,
-                )
-                .await?
+                EntryKey::DataEncryptionKey(data_encryption_key_id)
             }
             _ => {
                 cli_bail!("Either a key encryption key or a data encryption key must be provided")
             }
         };
+        let encryption = EntryEncryption {
+            key,
+            algorithm: self.data_encryption_algorithm,
+            nonce: self
+                .nonce
+                .as_deref()
+                .map(hex::decode)
+                .transpose()
+                .with_context(|| "failed to decode the nonce")?,
+            authentication_data: self
+                .authentication_data
+                .as_deref()
+                .map(hex::decode)
+                .transpose()
+                .with_context(|| "failed to decode the authentication data")?,
+        };
 
-        debug!(
-            "Findex: Adding entries to the index: {}",
-            self.findex_parameters.index_id
-        );
-        rest_client
-            .add_entries(&self.findex_parameters.index_id, &encrypted_entries)
+        let requested_tokenizer = self.findex_parameters.requested_tokenizer()?;
+        let requested_expansion = self.findex_parameters.requested_expansion()?;
+        let mut reader = csv::Reader::from_reader(File::open(&self.csv)?);
+        let columns = self.column_parameters.select(reader.byte_headers()?)?;
+        let findex_client = self
+            .findex_parameters
+            .clone()
+            .instantiate_findex_client(rest_client, kms_rest_client)
             .await?;
-
-        let findex_instance = FindexInstance::<CUSTOM_WORD_LENGTH>::instantiate_findex(
-            rest_client,
-            kms_rest_client,
-            self.findex_parameters.clone().instantiate_keys()?,
-        )
-        .await?;
-
-        let written_keywords = findex_instance
-            .insert_or_delete(
-                keywords_indexed_value_map.0,
-                true,
-                self.findex_parameters.num_threads,
-            )
+        let settings = findex_client
+            .record_settings(requested_tokenizer, requested_expansion)
             .await?;
 
-        trace!("indexing done: keywords: {written_keywords}");
+        let mut entries = Vec::new();
+        for result in reader.byte_records() {
+            let record = result?;
+            let keywords = columns.keywords(&record, settings.tokenizer, &settings.expansion);
+            let mut entry = DatasetEntry::new(record.as_slice().to_vec(), keywords);
+            if let Some(id) = columns.id(&record)? {
+                entry = entry.with_id(record_uuid(findex_client.index_id(), id));
+            }
+            entries.push(entry);
+        }
 
-        let uuids = encrypted_entries.get_uuids();
+        let uuids = findex_client
+            .encrypt_and_index(entries, &encryption)
+            .await?;
         println!("Data behind those UUIDS were encrypted and indexed: {uuids}");
 
         Ok(uuids)
     }
 }
+
+/// The UUID of a record: its ID if it is a UUID, or else a UUID derived from
+/// its ID and the index, so that indexing the record again replaces it.
+fn record_uuid(index_id: &Uuid, id: &[u8]) -> Uuid {
+    std::str::from_utf8(id)
+        .ok()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| Uuid::new_v5(index_id, id))
+}
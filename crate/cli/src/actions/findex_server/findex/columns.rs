@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use clap::Parser;
+use cosmian_findex_client::{KeywordExpansion, Tokenizer};
+use cosmian_findex_structs::Keyword;
+use csv::ByteRecord;
+
+use crate::{cli_error, error::result::FindexCliResult};
+
+/// The columns of the CSV file to index, named as in its header.
+#[derive(Clone, Parser, Debug, Default)]
+#[clap(verbatim_doc_comment)]
+pub struct ColumnParameters {
+    /// A column to index. Can be repeated.
+    /// All the columns are indexed by default.
+    /// With the `scope-fields` step of the tokenizer, the keywords of a column
+    /// are scoped to its name, e.g. `city:paris`.
+    #[clap(long = "column")]
+    pub columns: Vec<String>,
+
+    /// The column holding the IDs of the records.
+    /// The IDs are indexed instead of the records themselves and, when
+    /// encrypting, the records are stored under their ID if it is a UUID, or
+    /// else under a UUID derived from it, instead of a random UUID.
+    #[clap(long)]
+    pub id_column: Option<String>,
+}
+
+impl ColumnParameters {
+    /// Find the selected columns in the header of the CSV file.
+    ///
+    /// # Errors
+    /// - if a selected column is not in the header
+    pub(crate) fn select(&self, header: &ByteRecord) -> FindexCliResult<Columns> {
+        let names = header
+            .iter()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect::<Vec<_>>();
+        let position = |column: &str| {
+            names
+                .iter()
+                .position(|name| name == column)
+                .ok_or_else(|| cli_error!("the column `{column}` is not in the CSV header"))
+        };
+        let indexed = if self.columns.is_empty() {
+            names.iter().cloned().enumerate().collect()
+        } else {
+            self.columns
+                .iter()
+                .map(|column| position(column).map(|position| (position, column.clone())))
+                .collect::<FindexCliResult<Vec<_>>>()?
+        };
+        Ok(Columns {
+            indexed,
+            id: self.id_column.as_deref().map(position).transpose()?,
+        })
+    }
+}
+
+/// The positions of the selected columns in the records.
+pub(crate) struct Columns {
+    /// The positions and names of the indexed columns
+    indexed: Vec<(usize, String)>,
+    id: Option<usize>,
+}
+
+impl Columns {
+    /// The keywords indexing a record: the keywords of its indexed cells, and
+    /// their synthetic keywords.
+    pub(crate) fn keywords(
+        &self,
+        record: &ByteRecord,
+        tokenizer: Tokenizer,
+        expansion: &KeywordExpansion,
+    ) -> HashSet<Keyword> {
+        self.indexed
+            .iter()
+            .filter_map(|(position, name)| Some((name, record.get(*position)?)))
+            .flat_map(|(name, cell)| {
+                tokenizer.field_keywords(name, &String::from_utf8_lossy(cell), expansion)
+            })
+            .collect()
+    }
+
+    /// The ID of a record, if an ID column is selected.
+    ///
+    /// # Errors
+    /// - if the record has no ID cell
+    pub(crate) fn id<'a>(&self, record: &'a ByteRecord) -> FindexCliResult<Option<&'a [u8]>> {
+        self.id
+            .map(|position| {
+                record
+                    .get(position)
+                    .ok_or_else(|| cli_error!("the record {record:?} has no ID"))
+            })
+            .transpose()
+    }
+}
@@ -6,14 +6,12 @@ use std::{
 
 use clap::Parser;
 use cosmian_findex_client::RestClient;
-use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Keyword, Keywords, Value};
+use cosmian_findex_structs::{Keyword, Keywords, Value};
 use cosmian_kms_cli::reexport::cosmian_kms_client::KmsClient;
 use tracing::trace;
 
-use super::parameters::FindexParameters;
-use crate::{
-    actions::findex_server::findex::findex_instance::FindexInstance, error::result::FindexCliResult,
-};
+use super::{columns::ColumnParameters, parameters::FindexParameters};
+use crate::error::result::FindexCliResult;
 
 #[derive(Parser, Debug)]
 #[clap(verbatim_doc_comment)]
@@ -23,12 +21,15 @@ pub struct InsertOrDeleteAction {
     /// The path to the CSV file containing the data to index
     #[clap(long)]
     pub csv: PathBuf,
+    #[clap(flatten)]
+    pub column_parameters: ColumnParameters,
 }
 
 impl InsertOrDeleteAction {
     /// First, converts a CSV file to a hashmap where the keys are keywords and
-    /// the values are sets of indexed values (Data). Then, inserts or deletes
-    /// using the Findex instance.
+    /// the values are sets of indexed values (Data): the records, or their ID
+    /// if an ID column is given. Then, inserts or deletes using the Findex
+    /// instance.
     ///
     /// # Errors
     ///
@@ -46,38 +47,44 @@ impl InsertOrDeleteAction {
         kms_client: KmsClient,
         is_insert: bool,
     ) -> FindexCliResult<Keywords> {
-        let file = File::open(&self.csv)?;
-
-        let bindings = csv::Reader::from_reader(file).byte_records().fold(
-            HashMap::new(),
-            |mut acc: HashMap<Keyword, HashSet<Value>>, result| {
-                if let Ok(record) = result {
-                    let indexed_value = Value::from(record.as_slice());
-                    // Extract keywords from the record and associate them with the indexed values
-                    // Index the lowercase only
-                    for keyword in record
-                        .iter()
-                        .map(|x| Keyword::from(x.to_ascii_lowercase().as_slice()))
-                    {
-                        acc.entry(keyword)
-                            .or_default()
-                            .insert(indexed_value.clone());
-                    }
-                }
-                acc
-            },
-        );
+        let mut reader = csv::Reader::from_reader(File::open(&self.csv)?);
+        let columns = self.column_parameters.select(reader.byte_headers()?)?;
+        let requested_tokenizer = self.findex_parameters.requested_tokenizer()?;
+        let requested_expansion = self.findex_parameters.requested_expansion()?;
+        let findex_client = self
+            .findex_parameters
+            .clone()
+            .instantiate_findex_client(rest_client, kms_client)
+            .await?;
+        let settings = if is_insert {
+            findex_client
+                .record_settings(requested_tokenizer, requested_expansion)
+                .await?
+        } else {
+            findex_client
+                .settings(requested_tokenizer, requested_expansion)
+                .await?
+        };
 
-        let findex_instance = FindexInstance::<CUSTOM_WORD_LENGTH>::instantiate_findex(
-            rest_client,
-            kms_client,
-            self.findex_parameters.clone().instantiate_keys()?,
-        )
-        .await?;
+        let mut bindings: HashMap<Keyword, HashSet<Value>> = HashMap::new();
+        for result in reader.byte_records() {
+            let record = result?;
+            let indexed_value =
+                Value::from(columns.id(&record)?.unwrap_or_else(|| record.as_slice()));
+            // Extract keywords from the record and associate them with the indexed values
+            for keyword in columns.keywords(&record, settings.tokenizer, &settings.expansion) {
+                bindings
+                    .entry(keyword)
+                    .or_default()
+                    .insert(indexed_value.clone());
+            }
+        }
 
-        let written_keywords = findex_instance
-            .insert_or_delete(bindings, is_insert, self.findex_parameters.num_threads)
-            .await?;
+        let written_keywords = if is_insert {
+            findex_client.insert(bindings).await?
+        } else {
+            findex_client.delete(bindings).await?
+        };
         let operation_name = if is_insert { "Indexing" } else { "Deleting" };
 
         trace!("{operation_name} is done. Keywords: {written_keywords}");
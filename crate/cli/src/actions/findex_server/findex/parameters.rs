@@ -1,4 +1,6 @@
 use clap::Parser;
+use cosmian_findex_client::{FindexClient, FindexKeys, KeywordExpansion, RestClient, Tokenizer};
+use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
 use cosmian_kms_cli::{
     actions::kms::symmetric::keys::create_key::CreateKeyAction,
     reexport::cosmian_kms_client::{
@@ -8,7 +10,6 @@ use cosmian_kms_cli::{
 use tracing::trace;
 use uuid::Uuid;
 
-use super::findex_instance::FindexKeys;
 use crate::error::{FindexCliError, result::FindexCliResult};
 
 pub const HMAC_KEY_SIZE: u32 = 256;
@@ -49,6 +50,39 @@ pub struct FindexParameters {
     /// The number of threads to use for parallel operations
     #[clap(short = 't', long)]
     pub num_threads: Option<usize>,
+
+    /// Also index the prefixes of the keywords, from this number of
+    /// characters, so that `prefix*` searches the keywords starting with
+    /// `prefix`. Each prefix is an extra keyword in the index, and the short
+    /// prefixes match many records, whose volume the server sees when they
+    /// are searched. Like the tokenizer, the synthetic keywords are recorded
+    /// in the index the first time it is indexed.
+    #[clap(long, requires = "prefix_max_length")]
+    pub prefix_min_length: Option<usize>,
+
+    /// The maximum number of characters of the indexed prefixes. Longer
+    /// prefixes are searched by their first characters.
+    #[clap(long, requires = "prefix_min_length")]
+    pub prefix_max_length: Option<usize>,
+
+    /// Also index the Soundex codes of the keywords, so that `~word` searches
+    /// the keywords sounding like `word`. Each code is an extra keyword in the
+    /// index, shared by many words, whose volume the server sees when they
+    /// are searched. Like the tokenizer, the synthetic keywords are recorded
+    /// in the index the first time it is indexed.
+    #[clap(long)]
+    pub phonetic: bool,
+
+    /// The tokenizer turning the CSV cells into keywords, as comma separated
+    /// steps among `split-whitespace` or `split-words`, `nfkc-casefold`,
+    /// `strip-accents`, `stop-words:<language>`, `stem:<language>` and
+    /// `scope-fields`, the languages being english, french or german, e.g.
+    /// `split-words,nfkc-casefold,stop-words:french,stem:french,strip-accents`.
+    /// It is recorded in the index the first time it is indexed, and then
+    /// used by all the operations on the index. By default, each cell is a
+    /// keyword, in ASCII lowercase.
+    #[clap(long)]
+    pub tokenizer: Option<String>,
 }
 
 impl FindexParameters {
@@ -107,6 +141,10 @@ impl FindexParameters {
                 ),
                 index_id,
                 num_threads,
+                prefix_min_length: None,
+                prefix_max_length: None,
+                phonetic: false,
+                tokenizer: None,
             })
         } else {
             Ok(Self {
@@ -118,6 +156,10 @@ impl FindexParameters {
                 aes_xts_key_id: None,
                 index_id,
                 num_threads,
+                prefix_min_length: None,
+                prefix_max_length: None,
+                phonetic: false,
+                tokenizer: None,
             })
         }
     }
@@ -147,4 +189,54 @@ impl FindexParameters {
             )),
         }
     }
+
+    /// The synthetic keywords given by the prefix and phonetic options, if
+    /// any.
+    ///
+    /// # Errors
+    /// - if the prefix lengths are invalid
+    pub(crate) fn requested_expansion(&self) -> FindexCliResult<Option<KeywordExpansion>> {
+        let mut expansion = None;
+        if let (Some(min_length), Some(max_length)) =
+            (self.prefix_min_length, self.prefix_max_length)
+        {
+            expansion = Some(KeywordExpansion::default().with_prefixes(min_length, max_length)?);
+        }
+        if self.phonetic {
+            expansion = Some(expansion.unwrap_or_default().with_phonetic_keys());
+        }
+        Ok(expansion)
+    }
+
+    /// The tokenizer given by the `tokenizer` option, if any.
+    ///
+    /// # Errors
+    /// - if the tokenizer is invalid
+    pub(crate) fn requested_tokenizer(&self) -> FindexCliResult<Option<Tokenizer>> {
+        Ok(self
+            .tokenizer
+            .as_deref()
+            .map(str::parse::<Tokenizer>)
+            .transpose()?)
+    }
+
+    /// Instantiates the Findex client of the index, running at most
+    /// `num_threads` operations at once if given.
+    ///
+    /// # Errors
+    /// - if no key id is provided
+    /// - if the seed cannot be retrieved from the KMS
+    pub(crate) async fn instantiate_findex_client(
+        self,
+        rest_client: RestClient,
+        kms_client: KmsClient,
+    ) -> FindexCliResult<FindexClient<CUSTOM_WORD_LENGTH>> {
+        let num_threads = self.num_threads;
+        let mut findex_client =
+            FindexClient::new(rest_client, kms_client, self.instantiate_keys()?).await?;
+        if let Some(num_threads) = num_threads {
+            findex_client = findex_client.with_max_concurrency(num_threads);
+        }
+        Ok(findex_client)
+    }
 }
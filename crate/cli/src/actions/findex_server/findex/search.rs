@@ -1,9 +1,9 @@
 use clap::Parser;
-use cosmian_findex_client::RestClient;
-use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, SearchResults};
+use cosmian_findex_client::{IndexSettings, Query, RestClient, Term};
+use cosmian_findex_structs::SearchResults;
 use cosmian_kms_cli::reexport::cosmian_kms_client::KmsClient;
 
-use super::{findex_instance::FindexInstance, parameters::FindexParameters};
+use super::parameters::FindexParameters;
 use crate::{cli_error, error::result::FindexCliResult};
 
 /// Search words among encrypted indexes.
@@ -13,8 +13,20 @@ pub struct SearchAction {
     #[clap(flatten)]
     pub findex_parameters: FindexParameters,
     /// The word to search. Can be repeated.
+    /// The results must match all the words.
     #[clap(long)]
     pub keyword: Vec<String>,
+
+    /// A boolean query over the words, instead of the words to search, e.g.
+    /// `(alice OR bob) AND paris NOT archived`.
+    /// AND binds tighter than OR, which binds tighter than NOT; NOT excludes
+    /// the results of the query following it from those of the query
+    /// preceding it. Parentheses group sub-queries, and double quotes group
+    /// the words containing spaces or operators. The quoted words, and the
+    /// words escaping a character with a backslash, are searched as is, e.g.
+    /// `"C*"` searches the word `C*` and not the words starting with `C`.
+    #[clap(long, conflicts_with = "keyword")]
+    pub query: Option<String>,
 }
 
 impl SearchAction {
@@ -44,15 +56,42 @@ impl SearchAction {
             }
         }
 
-        let findex_instance = FindexInstance::<CUSTOM_WORD_LENGTH>::instantiate_findex(
-            rest_client,
-            kms_client,
-            self.findex_parameters.clone().instantiate_keys()?,
-        )
-        .await?;
+        let findex_client = self
+            .findex_parameters
+            .clone()
+            .instantiate_findex_client(rest_client, kms_client)
+            .await?;
+
+        let settings = findex_client
+            .settings(
+                self.findex_parameters.requested_tokenizer()?,
+                self.findex_parameters.requested_expansion()?,
+            )
+            .await?;
 
-        findex_instance
-            .search(&self.keyword, self.findex_parameters.num_threads)
-            .await
+        Ok(findex_client
+            .query(&build_query(
+                &self.keyword,
+                self.query.as_deref(),
+                &settings,
+            )?)
+            .await?)
     }
 }
+
+/// Build the query searching all the given keywords, or parse the given
+/// query. Each term searches the keywords the tokenizer of the index gives for
+/// it, and the prefix and phonetic terms the synthetic keywords of the index,
+/// unless they are quoted.
+pub(crate) fn build_query(
+    keywords: &[String],
+    query: Option<&str>,
+    settings: &IndexSettings,
+) -> FindexCliResult<Query> {
+    let query = match query {
+        Some(query) => query.parse::<Query<Term>>()?,
+        None => Query::And(keywords.iter().map(Term::new).map(Query::Keyword).collect()),
+    };
+    Ok(query
+        .try_flat_map(&mut |term| settings.tokenizer.search_query(&term, &settings.expansion))?)
+}
@@ -0,0 +1,166 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use cosmian_findex_client::RestClient;
+use cosmian_findex_structs::IndexArchive;
+use cosmian_kms_cli::reexport::cosmian_kms_crypto::reexport::cosmian_crypto_core::bytes_ser_de::Serializable;
+use uuid::Uuid;
+
+use crate::error::result::{FindexCliResult, FindexCliResultHelper};
+
+/// Back up, restore and copy indexes
+#[derive(Parser, Debug)]
+pub enum IndexesAction {
+    Export(ExportIndex),
+    Import(ImportIndex),
+    Clone(CloneIndex),
+}
+
+impl IndexesAction {
+    /// Processes the indexes action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there was a problem running the action.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<String> {
+        match self {
+            Self::Export(action) => action.run(rest_client).await,
+            Self::Import(action) => action
+                .run(rest_client)
+                .await
+                .map(|id| format!("Imported Index ID: {id}")),
+            Self::Clone(action) => action
+                .run(rest_client)
+                .await
+                .map(|id| format!("Cloned Index ID: {id}")),
+        }
+    }
+}
+
+/// Export an index to an archive file.
+///
+/// The archive holds the encrypted index and the encrypted datasets entries.
+/// It reflects the state of the index at the end of the export, even if the
+/// index is written to meanwhile. Requires an `admin` permission on the index.
+#[derive(Parser, Debug)]
+pub struct ExportIndex {
+    /// The index ID
+    #[clap(long, required = true)]
+    pub index_id: Uuid,
+
+    /// The archive file to write
+    #[clap(long, short = 'o', required = true)]
+    pub output_file: PathBuf,
+
+    /// Also export the permissions of the users on the index
+    #[clap(long, default_value = "false")]
+    pub permissions: bool,
+}
+
+impl ExportIndex {
+    /// Runs the `ExportIndex` action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query execution on the Findex server fails.
+    /// Returns an error if the archive file cannot be written.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<String> {
+        let archive = rest_client
+            .export_index(&self.index_id, self.permissions)
+            .await
+            .with_context(|| "Can't execute the export index query on the findex server")?;
+        fs::write(&self.output_file, &*archive.serialize()?).with_context(|| {
+            format!(
+                "Can't write the archive file {}",
+                self.output_file.display()
+            )
+        })?;
+
+        Ok(format!(
+            "Index {} exported to {}: {} words and {} entries",
+            self.index_id,
+            self.output_file.display(),
+            archive.words.len(),
+            archive.entries.len()
+        ))
+    }
+}
+
+/// Import an archive file into an index.
+///
+/// Without `--index-id` nor `--keep-index-id`, the archive is imported into a
+/// new index. Importing into an existing index requires an `admin` permission
+/// on it. The importing user is granted the `admin` permission on the index.
+#[derive(Parser, Debug)]
+pub struct ImportIndex {
+    /// The archive file to read
+    #[clap(long, short = 'i', required = true)]
+    pub input_file: PathBuf,
+
+    /// The index to import the archive into
+    #[clap(long, conflicts_with = "keep_index_id")]
+    pub index_id: Option<Uuid>,
+
+    /// Import the archive into the index it was exported from
+    #[clap(long, default_value = "false")]
+    pub keep_index_id: bool,
+
+    /// Also restore the permissions held by the archive
+    #[clap(long, default_value = "false")]
+    pub permissions: bool,
+}
+
+impl ImportIndex {
+    /// Runs the `ImportIndex` action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive file cannot be read.
+    /// Returns an error if the query execution on the Findex server fails.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<Uuid> {
+        let bytes = fs::read(&self.input_file).with_context(|| {
+            format!("Can't read the archive file {}", self.input_file.display())
+        })?;
+        let archive = IndexArchive::deserialize(&bytes)?;
+        let index_id = if self.keep_index_id {
+            Some(archive.index_id)
+        } else {
+            self.index_id
+        };
+
+        let response = rest_client
+            .import_index(&archive, index_id.as_ref(), self.permissions)
+            .await
+            .with_context(|| "Can't execute the import index query on the findex server")?;
+
+        Ok(response.index_id)
+    }
+}
+
+/// Copy an index into a new index, on the server side.
+///
+/// The clone holds the same encrypted index and datasets entries, and can be
+/// used with the same keys as the original index. Requires an `admin`
+/// permission on the index, and results in an `admin` permission on the clone.
+#[derive(Parser, Debug)]
+pub struct CloneIndex {
+    /// The ID of the index to clone
+    #[clap(long, required = true)]
+    pub index_id: Uuid,
+}
+
+impl CloneIndex {
+    /// Runs the `CloneIndex` action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query execution on the Findex server fails.
+    pub async fn run(&self, rest_client: RestClient) -> FindexCliResult<Uuid> {
+        let response = rest_client
+            .clone_index(&self.index_id)
+            .await
+            .with_context(|| "Can't execute the clone index query on the findex server")?;
+
+        Ok(response.index_id)
+    }
+}
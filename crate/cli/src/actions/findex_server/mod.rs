@@ -2,6 +2,7 @@ pub mod actions;
 pub mod datasets;
 pub mod encrypt_and_index;
 pub mod findex;
+pub mod indexes;
 pub mod login;
 pub mod permissions;
 pub mod search_and_decrypt;
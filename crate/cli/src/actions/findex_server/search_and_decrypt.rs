@@ -1,19 +1,11 @@
 use clap::Parser;
-use cosmian_findex_client::{
-    RestClient,
-    reexport::cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Uuids},
+use cosmian_findex_client::{EntryEncryption, EntryKey, RestClient};
+use cosmian_kms_cli::reexport::cosmian_kms_client::{
+    KmsClient, reexport::cosmian_kms_client_utils::symmetric_utils::DataEncryptionAlgorithm,
 };
-use cosmian_kms_cli::{
-    actions::kms::symmetric::DecryptAction,
-    reexport::cosmian_kms_client::{
-        KmsClient, reexport::cosmian_kms_client_utils::symmetric_utils::DataEncryptionAlgorithm,
-    },
-};
-use tracing::trace;
 
-use super::findex::parameters::FindexParameters;
+use super::findex::{parameters::FindexParameters, search::build_query};
 use crate::{
-    actions::findex_server::findex::findex_instance::FindexInstance,
     cli_bail, cli_error,
     error::result::{FindexCliResult, FindexCliResultHelper},
 };
@@ -26,9 +18,21 @@ pub struct SearchAndDecryptAction {
     pub findex_parameters: FindexParameters,
 
     /// The word to search. Can be repeated.
+    /// The results must match all the words.
     #[clap(long)]
     pub keyword: Vec<String>,
 
+    /// A boolean query over the words, instead of the words to search, e.g.
+    /// `(alice OR bob) AND paris NOT archived`.
+    /// AND binds tighter than OR, which binds tighter than NOT; NOT excludes
+    /// the results of the query following it from those of the query
+    /// preceding it. Parentheses group sub-queries, and double quotes group
+    /// the words containing spaces or operators. The quoted words, and the
+    /// words escaping a character with a backslash, are searched as is, e.g.
+    /// `"C*"` searches the word `C*` and not the words starting with `C`.
+    #[clap(long, conflicts_with = "keyword")]
+    pub query: Option<String>,
+
     /// The Key Encryption key (KEM) unique identifier.
     /// If not specified, tags should be specified
     #[clap(long = "kek-id", group = "kem", conflicts_with = "dem")]
@@ -91,70 +95,52 @@ impl SearchAndDecryptAction {
             }
         }
 
-        let findex_instance = FindexInstance::<CUSTOM_WORD_LENGTH>::instantiate_findex(
-            rest_client.clone(),
-            kms_rest_client.clone(),
-            self.findex_parameters.clone().instantiate_keys()?,
-        )
-        .await?;
+        let key = match (
+            self.key_encryption_key_id.clone(),
+            self.data_encryption_key_id.clone(),
+        ) {
+            (Some(key_encryption_key_id), None) => {
+                EntryKey::KeyEncryptionKey(key_encryption_key_id)
+            }
+            (None, Some(data_encryption_key_id)) => {
+                EntryKey::DataEncryptionKey(data_encryption_key_id)
+            }
+            _ => {
+                cli_bail!("Either a key encryption key or a data encryption key must be provided")
+            }
+        };
+        let encryption = EntryEncryption {
+            key,
+            algorithm: self.data_encryption_algorithm,
+            nonce: None,
+            authentication_data: self
+                .authentication_data
+                .as_deref()
+                .map(hex::decode)
+                .transpose()
+                .with_context(|| "failed to decode the authentication data")?,
+        };
 
-        let search_results = findex_instance
-            .search(&self.keyword, self.findex_parameters.num_threads)
+        let findex_client = self
+            .findex_parameters
+            .clone()
+            .instantiate_findex_client(rest_client, kms_rest_client.clone())
             .await?;
-
-        trace!("Search results: {search_results}");
-        let uuids = Uuids::try_from(search_results)?;
-        trace!("UUIDs of encrypted entries: {uuids}");
-        let encrypted_entries = rest_client
-            .get_entries(&self.findex_parameters.index_id, &uuids)
+        let settings = findex_client
+            .settings(
+                self.findex_parameters.requested_tokenizer()?,
+                self.findex_parameters.requested_expansion()?,
+            )
+            .await?;
+        let decrypted_records = findex_client
+            .search_and_decrypt(
+                &build_query(&self.keyword, self.query.as_deref(), &settings)?,
+                &encryption,
+            )
             .await?;
 
-        let authentication_data = self
-            .authentication_data
-            .as_deref()
-            .map(hex::decode)
-            .transpose()
-            .with_context(|| "failed to decode the authentication data")?;
-
-        let decrypt_action = DecryptAction::default();
-        let mut results = Vec::with_capacity(encrypted_entries.len());
-        let mut decrypted_records: Vec<Vec<u8>> = Vec::with_capacity(encrypted_entries.len());
-        for (_uuid, ciphertext) in encrypted_entries.iter() {
-            let decrypted_record: Vec<u8> = match (
-                self.key_encryption_key_id.as_ref(),
-                self.data_encryption_key_id.as_ref(),
-            ) {
-                (Some(key_encryption_key_id), None) => {
-                    decrypt_action
-                        .client_side_decrypt_with_buffer(
-                            kms_rest_client,
-                            self.data_encryption_algorithm,
-                            key_encryption_key_id,
-                            ciphertext,
-                            authentication_data.clone(),
-                        )
-                        .await?
-                }
-                (None, Some(data_encryption_key_id)) => decrypt_action
-                    .server_side_decrypt(
-                        kms_rest_client,
-                        self.data_encryption_algorithm.into(),
-                        data_encryption_key_id,
-                        ciphertext.clone(),
-                        authentication_data.clone(),
-                    )
-                    .await?
-                    .to_vec(),
-                _ => {
-                    cli_bail!(
-                        "Either a key encryption key or a data encryption key must be provided"
-                    )
-                }
-            };
-            decrypted_records.push(decrypted_record);
-        }
-
-        for decrypted_record in decrypted_records {
+        let mut results = Vec::with_capacity(decrypted_records.len());
+        for (_uuid, decrypted_record) in decrypted_records {
             let decrypted_record_str = std::str::from_utf8(&decrypted_record)?;
             results.push(decrypted_record_str.to_owned());
         }
@@ -1,14 +1,17 @@
 use std::ops::Deref;
 
 use base64::{Engine, engine::general_purpose};
-use cosmian_findex_client::RestClient;
+use cosmian_findex_client::{Compression, RestClient, RestClientConfig};
 use cosmian_findex_structs::EncryptedEntries;
 use cosmian_logger::log_init;
+use futures::{TryStreamExt, stream};
 use test_findex_server::start_default_test_findex_server;
 use uuid::Uuid;
 
 use crate::{
-    actions::findex_server::datasets::{AddEntries, DeleteEntries, GetEntries},
+    actions::findex_server::datasets::{
+        AddEntries, DeleteEntries, GetEntries, GetVersionedEntries, ListEntries, UpdateEntries,
+    },
     error::result::FindexCliResult,
 };
 
@@ -82,6 +85,38 @@ pub(crate) async fn test_datasets() -> FindexCliResult<()> {
         dataset_get_entries(ctx.get_owner_client(), &index_id, uuids.clone()).await?;
     assert_eq!(added_entries.len(), entries_number);
 
+    // List the added entries, over several pages
+    let listed_entries = ListEntries {
+        index_id,
+        page_size: 30,
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_eq!(listed_entries.lines().count(), entries_number);
+    assert!(
+        uuids
+            .iter()
+            .all(|uuid| listed_entries.contains(&uuid.to_string()))
+    );
+
+    // Update an entry with its current version, then with a stale one
+    let uuid = uuids[0];
+    let update = |version| UpdateEntries {
+        index_id,
+        entries: vec![(uuid, general_purpose::STANDARD.encode("updated"))],
+        versions: vec![(uuid, version)],
+    };
+    update(1).run(ctx.get_owner_client()).await?;
+    update(1).run(ctx.get_owner_client()).await.unwrap_err();
+    let versioned_entries = GetVersionedEntries {
+        index_id,
+        uuids: vec![uuid],
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_eq!(versioned_entries[&uuid].version, 2);
+    assert_eq!(versioned_entries[&uuid].entry, b"updated");
+
     dataset_delete_entries(
         ctx.get_owner_client(),
         &index_id,
@@ -93,5 +128,58 @@ pub(crate) async fn test_datasets() -> FindexCliResult<()> {
     let deleted_entries = dataset_get_entries(ctx.get_owner_client(), &index_id, uuids).await?;
     assert_eq!(deleted_entries.len(), 0);
 
+    // Add and get entries of another dataset, streamed in batches
+    let index_id = Uuid::new_v4();
+    let batches = (0..3)
+        .map(|batch| {
+            (0..10)
+                .map(|i| (Uuid::new_v4(), format!("entry{batch}-{i}").into_bytes()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let uuid_batches = batches
+        .iter()
+        .map(|entries| entries.iter().map(|(uuid, _)| *uuid).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    ctx.get_owner_client()
+        .add_entries_stream(
+            &index_id,
+            stream::iter(batches.clone().into_iter().map(EncryptedEntries::from_iter)),
+        )
+        .await?;
+    let streamed_entries = ctx
+        .get_owner_client()
+        .get_entries_stream(&index_id, stream::iter(uuid_batches))
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(
+        streamed_entries,
+        batches
+            .into_iter()
+            .map(EncryptedEntries::from_iter)
+            .collect::<Vec<_>>()
+    );
+
+    // Add and get entries with compressed bodies
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let rest_client = RestClient::new(RestClientConfig {
+            compression: Some(compression),
+            ..ctx.owner_client_conf.clone()
+        })?;
+        let entries = (0..10)
+            .map(|i| (Uuid::new_v4(), vec![i; 1000]))
+            .collect::<Vec<_>>();
+        rest_client
+            .add_entries(&index_id, &entries.iter().cloned().collect())
+            .await?;
+        let uuids = entries.iter().map(|(uuid, _)| *uuid).collect::<Vec<_>>();
+        let compressed_entries = rest_client.get_entries(&index_id, &uuids).await?;
+        assert_eq!(
+            compressed_entries,
+            entries.into_iter().collect::<EncryptedEntries>()
+        );
+    }
+
     Ok(())
 }
@@ -1,10 +1,15 @@
 use std::path::PathBuf;
 
+use cosmian_findex_client::{FindexGrpcClient, FindexRestClient};
 use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Value};
 use cosmian_logger::log_init;
 #[cfg(not(target_os = "windows"))]
 use cosmian_sse_memories::test_utils::test_guarded_write_concurrent;
-use cosmian_sse_memories::test_utils::{gen_seed, test_single_write_and_read, test_wrong_guard};
+use cosmian_sse_memories::{
+    Address, MemoryADT,
+    test_utils::{gen_seed, test_single_write_and_read, test_wrong_guard},
+};
+use futures::{TryStreamExt, stream};
 use test_findex_server::{
     start_default_test_findex_server, start_default_test_findex_server_with_cert_auth,
 };
@@ -16,8 +21,8 @@ use super::utils::HUGE_DATASET;
 use crate::{
     actions::findex_server::{
         findex::{
-            insert_or_delete::InsertOrDeleteAction, parameters::FindexParameters,
-            search::SearchAction,
+            columns::ColumnParameters, insert_or_delete::InsertOrDeleteAction,
+            parameters::FindexParameters, search::SearchAction,
         },
         tests::{
             findex::utils::{SMALL_DATASET, create_encryption_layer, insert_search_delete},
@@ -220,6 +225,7 @@ pub(crate) async fn test_findex_searching_with_bad_key() -> FindexCliResult<()>
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(&search_options.dataset_path),
+        column_parameters: ColumnParameters::default(),
     }
     .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
     .await?;
@@ -235,6 +241,7 @@ pub(crate) async fn test_findex_searching_with_bad_key() -> FindexCliResult<()>
         )
         .await?,
         keyword: search_options.keywords.clone(),
+        query: None,
     }
     .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
     .await?;
@@ -242,6 +249,273 @@ pub(crate) async fn test_findex_searching_with_bad_key() -> FindexCliResult<()>
     Ok(())
 }
 
+#[tokio::test]
+pub(crate) async fn test_findex_query() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let ctx_kms = start_default_test_kms_server().await;
+
+    let findex_parameters = FindexParameters::new(
+        Uuid::new_v4(),
+        ctx_kms.get_owner_client(),
+        false,
+        findex_number_of_threads(),
+    )
+    .await?;
+    InsertOrDeleteAction {
+        findex_parameters: findex_parameters.clone(),
+        csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
+    }
+    .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+    .await?;
+
+    let southborough = Value::from("SouthboroughMAUnited States9686");
+    for (query, expected) in [
+        ("Southborough OR unknown", true),
+        ("(Southborough OR unknown) AND MA", true),
+        ("Southborough AND unknown", false),
+        ("Southborough NOT MA", false),
+        ("Southborough NOT (unknown OR \"other unknown\")", true),
+    ] {
+        let search_results = SearchAction {
+            findex_parameters: findex_parameters.clone(),
+            keyword: vec![],
+            query: Some(query.to_owned()),
+        }
+        .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await?;
+        assert_eq!(search_results.contains(&southborough), expected, "{query}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+pub(crate) async fn test_findex_keyword_expansion() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let ctx_kms = start_default_test_kms_server().await;
+
+    let findex_parameters = FindexParameters {
+        prefix_min_length: Some(3),
+        prefix_max_length: Some(5),
+        phonetic: true,
+        ..FindexParameters::new(
+            Uuid::new_v4(),
+            ctx_kms.get_owner_client(),
+            false,
+            findex_number_of_threads(),
+        )
+        .await?
+    };
+    InsertOrDeleteAction {
+        findex_parameters: findex_parameters.clone(),
+        csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
+    }
+    .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+    .await?;
+
+    let search = |findex_parameters: FindexParameters, query: &str| SearchAction {
+        findex_parameters,
+        keyword: vec![],
+        query: Some(query.to_owned()),
+    };
+    // The searches use the recorded expansion, without the options
+    let recorded = FindexParameters {
+        prefix_min_length: None,
+        prefix_max_length: None,
+        phonetic: false,
+        ..findex_parameters.clone()
+    };
+    let southborough = Value::from("SouthboroughMAUnited States9686");
+    for (query, expected) in [
+        ("southb*", true),
+        ("Southborough*", true),
+        ("~Sowthburrow", true),
+        ("~Southborough AND MA", true),
+        ("north*", false),
+        // The quoted and escaped terms are searched as is
+        ("\"southb*\"", false),
+        ("southb\\*", false),
+    ] {
+        for findex_parameters in [&findex_parameters, &recorded] {
+            let search_results = search(findex_parameters.clone(), query)
+                .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+                .await?;
+            assert_eq!(search_results.contains(&southborough), expected, "{query}");
+        }
+    }
+
+    // The prefixes shorter than the indexed ones cannot be searched
+    assert!(
+        search(findex_parameters.clone(), "so*")
+            .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+            .await
+            .is_err()
+    );
+
+    // The options must match the recorded expansion
+    assert!(
+        search(
+            FindexParameters {
+                phonetic: false,
+                ..findex_parameters.clone()
+            },
+            "southb*",
+        )
+        .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await
+        .is_err()
+    );
+
+    // Without the expansion, only the literal prefix terms can be searched
+    let findex_parameters = FindexParameters::new(
+        Uuid::new_v4(),
+        ctx_kms.get_owner_client(),
+        false,
+        findex_number_of_threads(),
+    )
+    .await?;
+    InsertOrDeleteAction {
+        findex_parameters: findex_parameters.clone(),
+        csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
+    }
+    .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+    .await?;
+    assert!(
+        search(findex_parameters.clone(), "southb*")
+            .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+            .await
+            .is_err()
+    );
+    let search_results = search(findex_parameters, "\"southb*\"")
+        .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await?;
+    assert!(!search_results.contains(&southborough));
+    Ok(())
+}
+
+#[tokio::test]
+pub(crate) async fn test_findex_tokenizer() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let ctx_kms = start_default_test_kms_server().await;
+
+    let findex_parameters = FindexParameters::new(
+        Uuid::new_v4(),
+        ctx_kms.get_owner_client(),
+        false,
+        findex_number_of_threads(),
+    )
+    .await?;
+    let with_tokenizer = |tokenizer: &str| FindexParameters {
+        tokenizer: Some(tokenizer.to_owned()),
+        ..findex_parameters.clone()
+    };
+    let insert = |findex_parameters| InsertOrDeleteAction {
+        findex_parameters,
+        csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
+    };
+    insert(with_tokenizer("split-words,nfkc-casefold"))
+        .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await?;
+
+    // The index can no longer be indexed with another tokenizer
+    assert!(
+        insert(with_tokenizer("split-whitespace"))
+            .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+            .await
+            .is_err()
+    );
+
+    // The searches use the recorded tokenizer, which indexed the words of the
+    // cells
+    let southborough = Value::from("SouthboroughMAUnited States9686");
+    for query in ["STATES", "\"united states\" AND Southborough"] {
+        let search_results = SearchAction {
+            findex_parameters: findex_parameters.clone(),
+            keyword: vec![],
+            query: Some(query.to_owned()),
+        }
+        .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await?;
+        assert!(search_results.contains(&southborough), "{query}");
+    }
+    Ok(())
+}
+
+#[tokio::test]
+pub(crate) async fn test_findex_columns() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let ctx_kms = start_default_test_kms_server().await;
+
+    let findex_parameters = FindexParameters {
+        tokenizer: Some("scope-fields".to_owned()),
+        ..FindexParameters::new(
+            Uuid::new_v4(),
+            ctx_kms.get_owner_client(),
+            false,
+            findex_number_of_threads(),
+        )
+        .await?
+    };
+    // Index the cities only, under the population of the records
+    InsertOrDeleteAction {
+        findex_parameters: findex_parameters.clone(),
+        csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters {
+            columns: vec!["city".to_owned()],
+            id_column: Some("population".to_owned()),
+        },
+    }
+    .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+    .await?;
+
+    let search = |query: &str| SearchAction {
+        findex_parameters: findex_parameters.clone(),
+        keyword: vec![],
+        query: Some(query.to_owned()),
+    };
+    let search_results = search("city:Southborough")
+        .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await?;
+    assert_eq!(search_results.len(), 1);
+    assert!(search_results.contains(&Value::from("9686")));
+    for query in ["region:southborough", "region:ma"] {
+        let search_results = search(query)
+            .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+            .await?;
+        assert!(search_results.is_empty(), "{query}");
+    }
+    // The terms must name their field
+    assert!(
+        search("southborough")
+            .run(ctx.get_owner_client(), ctx_kms.get_owner_client())
+            .await
+            .is_err()
+    );
+
+    // The columns must be in the header
+    assert!(
+        InsertOrDeleteAction {
+            findex_parameters,
+            csv: PathBuf::from(SMALL_DATASET),
+            column_parameters: ColumnParameters {
+                columns: vec!["unknown".to_owned()],
+                id_column: None,
+            },
+        }
+        .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
+        .await
+        .is_err()
+    );
+    Ok(())
+}
+
 #[tokio::test]
 pub(crate) async fn test_findex_sequential_read_write() -> FindexCliResult<()> {
     log_init(None);
@@ -275,3 +549,78 @@ async fn test_findex_concurrent_read_write() -> FindexCliResult<()> {
     .await;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_findex_grpc_read_write() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let grpc_url = ctx.grpc_url.clone().unwrap();
+
+    let memory =
+        FindexGrpcClient::<CUSTOM_WORD_LENGTH>::connect(&grpc_url, None, Uuid::new_v4()).await?;
+    test_single_write_and_read::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+    test_wrong_guard(&memory, gen_seed()).await;
+
+    // Pipeline guarded writes on another index, then read the written words
+    let memory =
+        FindexGrpcClient::<CUSTOM_WORD_LENGTH>::connect(&grpc_url, None, Uuid::new_v4()).await?;
+    let addresses = (0..10_u8)
+        .map(|i| Address::from([i; 16]))
+        .collect::<Vec<_>>();
+    let writes = addresses
+        .iter()
+        .zip(0_u8..)
+        .map(|(address, i)| ((*address, None), vec![(*address, [i; CUSTOM_WORD_LENGTH])]))
+        .collect::<Vec<_>>();
+    let guards = memory
+        .guarded_write_stream(stream::iter(writes))
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(guards, vec![None; 10]);
+    let words = memory.batch_read(addresses).await?;
+    assert_eq!(
+        words,
+        (0..10_u8)
+            .map(|i| Some([i; CUSTOM_WORD_LENGTH]))
+            .collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_findex_batch_read_indexes() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+    let rest_client = ctx.get_owner_client();
+
+    // Write a word at the same address of two indexes
+    let address = Address::from([1; 16]);
+    let mut index_ids = Vec::new();
+    for i in 1..=2_u8 {
+        let index_id = create_index_id(rest_client.clone()).await?;
+        let memory = FindexRestClient::<CUSTOM_WORD_LENGTH>::new(rest_client.clone(), index_id);
+        memory
+            .guarded_write((address, None), vec![(address, [i; CUSTOM_WORD_LENGTH])])
+            .await?;
+        index_ids.push(index_id);
+    }
+
+    let missing_address = Address::from([2; 16]);
+    let words = rest_client
+        .batch_read_indexes::<CUSTOM_WORD_LENGTH>(
+            index_ids
+                .iter()
+                .map(|index_id| (*index_id, vec![address, missing_address]))
+                .collect(),
+        )
+        .await?;
+    assert_eq!(words.len(), 2);
+    for (index_id, i) in index_ids.iter().zip(1..=2_u8) {
+        assert_eq!(
+            words.get(index_id),
+            Some(&vec![Some([i; CUSTOM_WORD_LENGTH]), None])
+        );
+    }
+    Ok(())
+}
@@ -10,8 +10,8 @@ use uuid::Uuid;
 use crate::{
     actions::findex_server::{
         findex::{
-            insert_or_delete::InsertOrDeleteAction, parameters::FindexParameters,
-            search::SearchAction,
+            columns::ColumnParameters, insert_or_delete::InsertOrDeleteAction,
+            parameters::FindexParameters, search::SearchAction,
         },
         tests::{
             findex::{
@@ -56,6 +56,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
     }
     .insert(ctx.get_owner_client(), ctx_kms.get_owner_client())
     .await?;
@@ -73,6 +74,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     let search_results = SearchAction {
         findex_parameters: findex_parameters.clone(),
         keyword: search_options.keywords.clone(),
+        query: None,
     }
     .run(ctx.get_user_client(), ctx_kms.get_owner_client())
     .await?;
@@ -85,6 +87,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
     }
     .insert(ctx.get_user_client(), ctx_kms.get_owner_client())
     .await
@@ -106,6 +109,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     let search_results = SearchAction {
         findex_parameters: findex_parameters.clone(),
         keyword: search_options.keywords.clone(),
+        query: None,
     }
     .run(ctx.get_user_client(), ctx_kms.get_owner_client())
     .await?;
@@ -118,6 +122,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(SMALL_DATASET),
+        column_parameters: ColumnParameters::default(),
     }
     .insert(ctx.get_user_client(), ctx_kms.get_owner_client())
     .await?;
@@ -142,6 +147,7 @@ pub(crate) async fn test_findex_set_and_revoke_permission() -> FindexCliResult<(
     let _search_results = SearchAction {
         findex_parameters: findex_parameters.clone(),
         keyword: search_options.keywords.clone(),
+        query: None,
     }
     .run(ctx.get_user_client(), ctx_kms.get_owner_client())
     .await
@@ -10,8 +10,8 @@ use super::basic::findex_number_of_threads;
 use crate::{
     actions::findex_server::{
         findex::{
-            insert_or_delete::InsertOrDeleteAction, parameters::FindexParameters,
-            search::SearchAction,
+            columns::ColumnParameters, insert_or_delete::InsertOrDeleteAction,
+            parameters::FindexParameters, search::SearchAction,
         },
         tests::search_options::SearchOptions,
     },
@@ -33,6 +33,7 @@ pub(crate) async fn insert_search_delete(
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(&search_options.dataset_path),
+        column_parameters: ColumnParameters::default(),
     }
     .insert(rest_client.clone(), kms_client.clone())
     .await?;
@@ -41,6 +42,7 @@ pub(crate) async fn insert_search_delete(
     let search_results = SearchAction {
         findex_parameters: findex_parameters.clone(),
         keyword: search_options.keywords.clone(),
+        query: None,
     }
     .run(rest_client.clone(), kms_client.clone())
     .await?;
@@ -53,6 +55,7 @@ pub(crate) async fn insert_search_delete(
     InsertOrDeleteAction {
         findex_parameters: findex_parameters.clone(),
         csv: PathBuf::from(search_options.dataset_path),
+        column_parameters: ColumnParameters::default(),
     }
     .delete(rest_client.clone(), kms_client.clone())
     .await?;
@@ -61,6 +64,7 @@ pub(crate) async fn insert_search_delete(
     let search_results = SearchAction {
         findex_parameters: findex_parameters.clone(),
         keyword: search_options.keywords,
+        query: None,
     }
     .run(rest_client.clone(), kms_client)
     .await?;
@@ -0,0 +1,110 @@
+use std::{env, fs};
+
+use base64::{Engine, engine::general_purpose};
+use cosmian_logger::log_init;
+use test_findex_server::start_default_test_findex_server;
+use uuid::Uuid;
+
+use crate::{
+    actions::findex_server::{
+        datasets::{AddEntries, GetEntries},
+        indexes::{CloneIndex, ExportIndex, ImportIndex},
+        tests::permissions::create_index_id,
+    },
+    error::result::FindexCliResult,
+};
+
+#[ignore = "Tests too flaky on CI"]
+#[tokio::test]
+pub(crate) async fn test_export_and_import_index() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+
+    let index_id = create_index_id(ctx.get_owner_client()).await?;
+    let entries: Vec<(Uuid, String)> = (1..=10)
+        .map(|i| {
+            (
+                Uuid::new_v4(),
+                general_purpose::STANDARD.encode(format!("entry{i}")),
+            )
+        })
+        .collect();
+    let uuids: Vec<Uuid> = entries.iter().map(|(uuid, _)| *uuid).collect();
+    AddEntries { index_id, entries }
+        .run(ctx.get_owner_client())
+        .await?;
+
+    let archive_file = env::temp_dir().join(format!("{index_id}.fidx"));
+    ExportIndex {
+        index_id,
+        output_file: archive_file.clone(),
+        permissions: true,
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+
+    // Import the archive into a new index
+    let new_index_id = ImportIndex {
+        input_file: archive_file.clone(),
+        index_id: None,
+        keep_index_id: false,
+        permissions: true,
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_ne!(new_index_id, index_id);
+    let imported_entries = GetEntries {
+        index_id: new_index_id,
+        uuids: uuids.clone(),
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_eq!(imported_entries.len(), uuids.len());
+
+    // Import the archive back into the exported index
+    let same_index_id = ImportIndex {
+        input_file: archive_file.clone(),
+        index_id: None,
+        keep_index_id: true,
+        permissions: false,
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_eq!(same_index_id, index_id);
+
+    fs::remove_file(archive_file)?;
+    Ok(())
+}
+
+#[ignore = "Tests too flaky on CI"]
+#[tokio::test]
+pub(crate) async fn test_clone_index() -> FindexCliResult<()> {
+    log_init(None);
+    let ctx = start_default_test_findex_server().await;
+
+    let index_id = create_index_id(ctx.get_owner_client()).await?;
+    let entries: Vec<(Uuid, String)> = (1..=10)
+        .map(|i| {
+            (
+                Uuid::new_v4(),
+                general_purpose::STANDARD.encode(format!("entry{i}")),
+            )
+        })
+        .collect();
+    let uuids: Vec<Uuid> = entries.iter().map(|(uuid, _)| *uuid).collect();
+    AddEntries { index_id, entries }
+        .run(ctx.get_owner_client())
+        .await?;
+
+    let clone_id = CloneIndex { index_id }.run(ctx.get_owner_client()).await?;
+    assert_ne!(clone_id, index_id);
+    let cloned_entries = GetEntries {
+        index_id: clone_id,
+        uuids: uuids.clone(),
+    }
+    .run(ctx.get_owner_client())
+    .await?;
+    assert_eq!(cloned_entries.len(), uuids.len());
+
+    Ok(())
+}
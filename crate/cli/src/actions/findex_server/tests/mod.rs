@@ -1,5 +1,6 @@
 mod auth_tests;
 mod datasets;
 mod findex;
+mod indexes;
 mod permissions;
 pub(crate) mod search_options;
@@ -19,8 +19,10 @@ use uuid::Uuid;
 
 use crate::{
     actions::findex_server::{
-        datasets::DeleteEntries, encrypt_and_index::EncryptAndIndexAction,
-        findex::parameters::FindexParameters, permissions::CreateIndex,
+        datasets::DeleteEntries,
+        encrypt_and_index::EncryptAndIndexAction,
+        findex::{columns::ColumnParameters, parameters::FindexParameters},
+        permissions::CreateIndex,
         search_and_decrypt::SearchAndDecryptAction,
     },
     error::result::FindexCliResult,
@@ -109,6 +111,7 @@ impl TestsCliContext {
         let action = EncryptAndIndexAction {
             findex_parameters: params.clone(),
             csv: self.search_options.dataset_path.clone().into(),
+            column_parameters: ColumnParameters::default(),
             key_encryption_key_id: self.kek_id.as_ref().map(ToString::to_string),
             data_encryption_key_id: None,
             data_encryption_algorithm: DataEncryptionAlgorithm::AesGcm,
@@ -127,6 +130,7 @@ impl TestsCliContext {
             data_encryption_key_id: None,
             data_encryption_algorithm: DataEncryptionAlgorithm::AesGcm,
             keyword: self.search_options.keywords.clone(),
+            query: None,
             authentication_data: None,
         }
         .run(self.findex.clone(), &self.kms)
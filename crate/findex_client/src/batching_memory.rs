@@ -0,0 +1,377 @@
+use std::{
+    fmt::{self, Display},
+    mem,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use cosmian_sse_memories::MemoryADT;
+use futures::channel::oneshot;
+use tracing::{debug, trace};
+
+/// The error of a merged read, shared by all the reads merged into it. It is
+/// displayed as this error, which it gives access to.
+#[derive(Debug)]
+pub struct MergedReadError<Error>(Arc<Error>);
+
+impl<Error> MergedReadError<Error> {
+    /// The error of the merged read
+    #[must_use]
+    pub fn error(&self) -> &Error {
+        &self.0
+    }
+}
+
+impl<Error> Clone for MergedReadError<Error> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<Error: Display> Display for MergedReadError<Error> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<Error: std::error::Error> std::error::Error for MergedReadError<Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// The words of a read, or the error of its batch. A read is sent `None` if
+/// the batch did not return its words, in which case it is read on its own.
+type ReadResult<Word, Error> = Option<Result<Vec<Option<Word>>, MergedReadError<Error>>>;
+
+/// A read waiting for its batch to be sent.
+struct PendingRead<Address, Word, Error> {
+    addresses: Vec<Address>,
+    sender: oneshot::Sender<ReadResult<Word, Error>>,
+}
+
+struct Batch<Address, Word, Error> {
+    reads: Vec<PendingRead<Address, Word, Error>>,
+    /// The number of addresses of the reads
+    length: usize,
+}
+
+type MemoryBatch<Memory> = Batch<
+    <Memory as MemoryADT>::Address,
+    <Memory as MemoryADT>::Word,
+    <Memory as MemoryADT>::Error,
+>;
+
+/// A `MemoryADT` decorator merging the concurrent `batch_read` calls.
+///
+/// The reads made during a short window, opened by the first one, are sent
+/// together and their words split back out. This saves round-trips when
+/// concurrent tasks search or index many keywords through the clones of the
+/// same memory, which share the batches.
+///
+/// The batch is sent before the end of the window when it holds the maximum
+/// number of addresses. If the merged read fails, its error is returned to
+/// all the reads it was made of, as a [`MergedReadError`]. The guarded writes
+/// are not batched.
+pub struct BatchingMemory<Memory: MemoryADT> {
+    mem: Memory,
+    window: Duration,
+    max_addresses: usize,
+    batch: Arc<Mutex<MemoryBatch<Memory>>>,
+}
+
+impl<Memory: MemoryADT + Clone> Clone for BatchingMemory<Memory> {
+    fn clone(&self) -> Self {
+        Self {
+            mem: self.mem.clone(),
+            window: self.window,
+            max_addresses: self.max_addresses,
+            batch: self.batch.clone(),
+        }
+    }
+}
+
+impl<Memory: MemoryADT> BatchingMemory<Memory> {
+    /// The default maximum number of addresses of a batch
+    pub const DEFAULT_MAX_ADDRESSES: usize = 10_000;
+
+    /// Merge the reads made during `window`.
+    pub fn new(mem: Memory, window: Duration) -> Self {
+        Self {
+            mem,
+            window,
+            max_addresses: Self::DEFAULT_MAX_ADDRESSES,
+            batch: Arc::new(Mutex::new(Batch {
+                reads: Vec::new(),
+                length: 0,
+            })),
+        }
+    }
+
+    /// Send a batch as soon as it holds `max_addresses` addresses.
+    #[must_use]
+    pub const fn with_max_addresses(mut self, max_addresses: usize) -> Self {
+        self.max_addresses = max_addresses;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryBatch<Memory>> {
+        // The batch is consistent between the statements holding the lock.
+        self.batch.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<Memory> BatchingMemory<Memory>
+where
+    Memory: Send + Sync + MemoryADT,
+    Memory::Address: Send + Clone,
+    Memory::Word: Send,
+{
+    /// Send the pending reads in a single call, and split the words read
+    /// between them.
+    async fn flush(&self) {
+        let reads = {
+            let mut batch = self.lock();
+            batch.length = 0;
+            mem::take(&mut batch.reads)
+        };
+        if reads.is_empty() {
+            return;
+        }
+
+        let addresses = reads
+            .iter()
+            .flat_map(|read| read.addresses.iter().cloned())
+            .collect::<Vec<_>>();
+        trace!(
+            "batch_read: merging {} reads of {} addresses",
+            reads.len(),
+            addresses.len()
+        );
+        let mut words = match self.mem.batch_read(addresses).await {
+            Ok(words) => Ok(words.into_iter()),
+            Err(e) => {
+                debug!("batch_read: the merged read failed: {e}");
+                Err(MergedReadError(Arc::new(e)))
+            }
+        };
+
+        for read in reads {
+            let result = match &mut words {
+                Ok(words) => {
+                    let read_words = words.take(read.addresses.len()).collect::<Vec<_>>();
+                    (read_words.len() == read.addresses.len()).then_some(Ok(read_words))
+                }
+                Err(e) => Some(Err(e.clone())),
+            };
+            if read.sender.send(result).is_err() {
+                trace!("batch_read: the read was cancelled");
+            }
+        }
+    }
+}
+
+impl<Memory> MemoryADT for BatchingMemory<Memory>
+where
+    Memory: Send + Sync + Clone + MemoryADT + 'static,
+    Memory::Address: Send + Sync + Clone + 'static,
+    Memory::Word: Send + Sync + 'static,
+    Memory::Error: From<MergedReadError<Memory::Error>> + 'static,
+{
+    type Address = Memory::Address;
+    type Error = Memory::Error;
+    type Word = Memory::Word;
+
+    async fn batch_read(
+        &self,
+        addresses: Vec<Self::Address>,
+    ) -> Result<Vec<Option<Self::Word>>, Self::Error> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let (opens_window, full) = {
+            let mut batch = self.lock();
+            let opens_window = batch.reads.is_empty();
+            batch.length += addresses.len();
+            batch.reads.push(PendingRead {
+                addresses: addresses.clone(),
+                sender,
+            });
+            (opens_window, batch.length >= self.max_addresses)
+        };
+
+        if full {
+            self.flush().await;
+        } else if opens_window {
+            // The batch is sent even if this read is cancelled.
+            let memory = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(memory.window).await;
+                memory.flush().await;
+            });
+        }
+
+        match receiver.await {
+            Ok(Some(Ok(words))) => Ok(words),
+            Ok(Some(Err(e))) => Err(e.into()),
+            // The merged read did not return the words of this read, or was
+            // dropped
+            Ok(None) | Err(oneshot::Canceled) => self.mem.batch_read(addresses).await,
+        }
+    }
+
+    async fn guarded_write(
+        &self,
+        guard: (Self::Address, Option<Self::Word>),
+        bindings: Vec<(Self::Address, Self::Word)>,
+    ) -> Result<Option<Self::Word>, Self::Error> {
+        self.mem.guarded_write(guard, bindings).await
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
+    use cosmian_sse_memories::{
+        ADDRESS_LENGTH, Address, InMemory, MemoryADT,
+        test_utils::{
+            gen_seed, test_guarded_write_concurrent, test_rw_same_address,
+            test_single_write_and_read, test_wrong_guard,
+        },
+    };
+    use futures::future::join_all;
+
+    use super::{BatchingMemory, MergedReadError};
+
+    type Word = [u8; CUSTOM_WORD_LENGTH];
+
+    type InMemoryError = <InMemory<Address<ADDRESS_LENGTH>, Word> as MemoryADT>::Error;
+
+    #[derive(Debug, thiserror::Error)]
+    enum TestError {
+        #[error(transparent)]
+        Memory(InMemoryError),
+        #[error(transparent)]
+        MergedRead(#[from] MergedReadError<TestError>),
+        #[error("the memory is unavailable")]
+        Unavailable,
+    }
+
+    /// A memory counting the reads it serves, which fail once it is made
+    /// unavailable.
+    #[derive(Clone, Default)]
+    struct CountingMemory {
+        mem: InMemory<Address<ADDRESS_LENGTH>, Word>,
+        reads: Arc<AtomicUsize>,
+        unavailable: Arc<AtomicBool>,
+    }
+
+    impl MemoryADT for CountingMemory {
+        type Address = Address<ADDRESS_LENGTH>;
+        type Error = TestError;
+        type Word = Word;
+
+        async fn batch_read(
+            &self,
+            addresses: Vec<Self::Address>,
+        ) -> Result<Vec<Option<Self::Word>>, Self::Error> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            if self.unavailable.load(Ordering::Relaxed) {
+                return Err(TestError::Unavailable);
+            }
+            self.mem
+                .batch_read(addresses)
+                .await
+                .map_err(TestError::Memory)
+        }
+
+        async fn guarded_write(
+            &self,
+            guard: (Self::Address, Option<Self::Word>),
+            bindings: Vec<(Self::Address, Self::Word)>,
+        ) -> Result<Option<Self::Word>, Self::Error> {
+            self.mem
+                .guarded_write(guard, bindings)
+                .await
+                .map_err(TestError::Memory)
+        }
+    }
+
+    fn address(i: u8) -> Address<ADDRESS_LENGTH> {
+        Address::from([i; ADDRESS_LENGTH])
+    }
+
+    #[tokio::test]
+    async fn test_batching_memory_adt() {
+        let memory = BatchingMemory::new(CountingMemory::default(), Duration::from_millis(1));
+        test_single_write_and_read::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_wrong_guard::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_rw_same_address::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_guarded_write_concurrent::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed(), Some(100))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_batching_memory() {
+        let counting = CountingMemory::default();
+        let memory = BatchingMemory::new(counting.clone(), Duration::from_millis(20));
+        for i in 0..10 {
+            memory
+                .guarded_write(
+                    (address(i), None),
+                    vec![(address(i), [i; CUSTOM_WORD_LENGTH])],
+                )
+                .await
+                .unwrap();
+        }
+
+        // The concurrent reads are merged, and get their own words
+        let reads = join_all((0..20).map(|i| {
+            let memory = memory.clone();
+            async move { memory.batch_read(vec![address(i), address(i + 1)]).await }
+        }))
+        .await;
+        assert_eq!(counting.reads.load(Ordering::Relaxed), 1);
+        for (i, words) in (0..20).zip(reads) {
+            let expected = [i, i + 1]
+                .map(|i| (i < 10).then_some([i; CUSTOM_WORD_LENGTH]))
+                .to_vec();
+            assert_eq!(words.unwrap(), expected);
+        }
+
+        // A full batch is sent without waiting for the end of the window
+        let memory = memory.with_max_addresses(4);
+        let reads = join_all((0..4).map(|i| memory.batch_read(vec![address(i), address(i)]))).await;
+        assert_eq!(counting.reads.load(Ordering::Relaxed), 3);
+        assert!(reads.into_iter().all(|words| words.unwrap().len() == 2));
+    }
+
+    #[tokio::test]
+    async fn test_batching_memory_error() {
+        let counting = CountingMemory::default();
+        let memory = BatchingMemory::new(counting.clone(), Duration::from_millis(20));
+        counting.unavailable.store(true, Ordering::Relaxed);
+
+        // The error of the merged read is returned to all the reads, which are
+        // not sent again one by one
+        let reads = join_all((0..10).map(|i| memory.batch_read(vec![address(i)]))).await;
+        assert_eq!(counting.reads.load(Ordering::Relaxed), 1);
+        for read in reads {
+            let TestError::MergedRead(e) = read.unwrap_err() else {
+                panic!("the error of the merged read must be returned");
+            };
+            assert!(matches!(e.error(), TestError::Unavailable));
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    hash::Hash,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use cosmian_sse_memories::MemoryADT;
+use tracing::trace;
+
+use crate::lru::Lru;
+
+/// The words read from or written to a memory.
+struct Cache<Address, Word> {
+    words: Lru<Address, Option<Word>>,
+    /// Incremented at each write, so that a read started before a write
+    /// does not cache the words it may have overwritten
+    epoch: u64,
+}
+
+/// A `MemoryADT` decorator serving the repeated reads of the same addresses
+/// from a bounded LRU cache, e.g. the chains of the keywords searched often.
+///
+/// The words written through the cache are cached. The word returned by a
+/// failed guarded write replaces the cached guard word, which lets Findex
+/// retry its write with the up-to-date value: the writes stay correct when
+/// the cache is stale. The reads however may miss the words written by other
+/// clients until their address is evicted, or until its time-to-live expires
+/// if one is set.
+///
+/// It composes under the encryption layers, e.g.
+/// `KmsEncryptionLayer<WORD_LENGTH, CachingMemory<FindexRestClient<WORD_LENGTH>>>`,
+/// in which case it caches the encrypted words. The clones share the cache.
+pub struct CachingMemory<Memory: MemoryADT> {
+    mem: Memory,
+    cache: Arc<Mutex<Cache<Memory::Address, Memory::Word>>>,
+}
+
+impl<Memory: MemoryADT + Clone> Clone for CachingMemory<Memory> {
+    fn clone(&self) -> Self {
+        Self {
+            mem: self.mem.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<Memory: MemoryADT> CachingMemory<Memory>
+where
+    Memory::Address: Hash + Eq + Clone,
+    Memory::Word: Clone,
+{
+    /// Cache the words of at most `capacity` addresses of the given memory.
+    pub fn new(mem: Memory, capacity: usize) -> Self {
+        Self {
+            mem,
+            cache: Arc::new(Mutex::new(Cache {
+                words: Lru::new(capacity, None),
+                epoch: 0,
+            })),
+        }
+    }
+
+    /// Read again from the memory the words cached for longer than `ttl`.
+    #[must_use]
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        self.lock().words.ttl = Some(ttl);
+        self
+    }
+
+    /// Empty the cache.
+    pub fn clear(&self) {
+        self.lock().words.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Cache<Memory::Address, Memory::Word>> {
+        // The cache is consistent between the statements holding the lock.
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<Memory> MemoryADT for CachingMemory<Memory>
+where
+    Memory: Send + Sync + MemoryADT,
+    Memory::Address: Send + Sync + Hash + Eq + Clone,
+    Memory::Word: Send + Sync + Clone + PartialEq,
+{
+    type Address = Memory::Address;
+    type Error = Memory::Error;
+    type Word = Memory::Word;
+
+    async fn batch_read(
+        &self,
+        addresses: Vec<Self::Address>,
+    ) -> Result<Vec<Option<Self::Word>>, Self::Error> {
+        let (mut words, epoch) = {
+            let mut cache = self.lock();
+            let words = addresses
+                .iter()
+                .map(|address| cache.words.get(address).cloned())
+                .collect::<Vec<_>>();
+            (words, cache.epoch)
+        };
+        let misses = addresses
+            .into_iter()
+            .zip(&words)
+            .enumerate()
+            .filter(|(_, (_, word))| word.is_none())
+            .map(|(position, (address, _))| (position, address))
+            .collect::<Vec<_>>();
+
+        trace!(
+            "batch_read: {} cached words, {} words to read",
+            words.len() - misses.len(),
+            misses.len()
+        );
+
+        if !misses.is_empty() {
+            let read = self
+                .mem
+                .batch_read(misses.iter().map(|(_, address)| address.clone()).collect())
+                .await?;
+            let mut cache = self.lock();
+            // A write completed during the read may have overwritten the
+            // words read, which must then not be cached.
+            let cacheable = cache.epoch == epoch;
+            for ((position, address), word) in misses.into_iter().zip(read) {
+                if cacheable {
+                    cache.words.insert(address, word.clone());
+                }
+                if let Some(slot) = words.get_mut(position) {
+                    *slot = Some(word);
+                }
+            }
+            drop(cache);
+        }
+
+        Ok(words.into_iter().map(Option::flatten).collect())
+    }
+
+    async fn guarded_write(
+        &self,
+        guard: (Self::Address, Option<Self::Word>),
+        bindings: Vec<(Self::Address, Self::Word)>,
+    ) -> Result<Option<Self::Word>, Self::Error> {
+        let (guard_address, guard_word) = guard.clone();
+        let result = self.mem.guarded_write(guard, bindings.clone()).await;
+
+        let mut cache = self.lock();
+        cache.epoch += 1;
+        match &result {
+            Ok(current) if *current == guard_word => {
+                trace!("guarded_write: caching {} written words", bindings.len());
+                // The guard word is overwritten if it is bound.
+                cache.words.insert(guard_address, guard_word);
+                for (address, word) in bindings {
+                    cache.words.insert(address, Some(word));
+                }
+            }
+            // The guard failed: nothing was written, and the current guard
+            // word is returned.
+            Ok(current) => cache.words.insert(guard_address, current.clone()),
+            // Whether the bindings were written is unknown.
+            Err(_) => {
+                cache.words.remove(&guard_address);
+                for (address, _) in &bindings {
+                    cache.words.remove(address);
+                }
+            }
+        }
+        drop(cache);
+
+        result
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
+    use cosmian_sse_memories::{
+        ADDRESS_LENGTH, Address, InMemory, MemoryADT,
+        test_utils::{
+            gen_seed, test_guarded_write_concurrent, test_rw_same_address,
+            test_single_write_and_read, test_wrong_guard,
+        },
+    };
+
+    use super::CachingMemory;
+
+    type Memory = InMemory<Address<ADDRESS_LENGTH>, [u8; CUSTOM_WORD_LENGTH]>;
+
+    #[tokio::test]
+    async fn test_caching_memory_adt() {
+        let memory = CachingMemory::new(Memory::default(), 1_000);
+        test_single_write_and_read::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_wrong_guard::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_rw_same_address::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed()).await;
+        test_guarded_write_concurrent::<CUSTOM_WORD_LENGTH, _>(&memory, gen_seed(), Some(100))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_caching_memory_refresh() {
+        let memory = Memory::default();
+        let cached = CachingMemory::new(memory.clone(), 2);
+        let address = Address::from([1; ADDRESS_LENGTH]);
+        let word = [1; CUSTOM_WORD_LENGTH];
+
+        // A word written by another client is not seen once its address is
+        // cached...
+        assert_eq!(cached.batch_read(vec![address]).await.unwrap(), [None]);
+        memory
+            .guarded_write((address, None), vec![(address, word)])
+            .await
+            .unwrap();
+        assert_eq!(cached.batch_read(vec![address]).await.unwrap(), [None]);
+
+        // ... until a guarded write fails on it.
+        assert_eq!(
+            cached
+                .guarded_write((address, None), vec![(address, [2; CUSTOM_WORD_LENGTH])])
+                .await
+                .unwrap(),
+            Some(word)
+        );
+        assert_eq!(
+            cached.batch_read(vec![address]).await.unwrap(),
+            [Some(word)]
+        );
+
+        // The written words are cached, and the least recently used ones are
+        // evicted.
+        let other = Address::from([2; ADDRESS_LENGTH]);
+        let last = Address::from([3; ADDRESS_LENGTH]);
+        cached
+            .guarded_write((other, None), vec![(other, word), (last, word)])
+            .await
+            .unwrap();
+        for address in [address, last] {
+            memory
+                .guarded_write(
+                    (address, Some(word)),
+                    vec![(address, [3; CUSTOM_WORD_LENGTH])],
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            cached.batch_read(vec![address, last]).await.unwrap(),
+            [Some([3; CUSTOM_WORD_LENGTH]), Some(word)]
+        );
+        cached.clear();
+        assert_eq!(
+            cached.batch_read(vec![last]).await.unwrap(),
+            [Some([3; CUSTOM_WORD_LENGTH])]
+        );
+    }
+}
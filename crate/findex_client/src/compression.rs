@@ -0,0 +1,77 @@
+use std::io::{Read, Write};
+
+use flate2::{read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ClientError, result::ClientResult};
+
+/// The compression of the binary request and response bodies.
+///
+/// Encrypted words and entries hardly compress, hence the compression is
+/// opt-in; it mostly pays off on the serialization headers of large batches,
+/// over slow links.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The content encoding of the compressed bodies.
+    #[must_use]
+    pub const fn encoding(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Compress a request body.
+    pub(crate) fn compress(self, bytes: &[u8]) -> ClientResult<Vec<u8>> {
+        Ok(match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish()?
+            }
+            Self::Zstd => zstd::encode_all(bytes, zstd::DEFAULT_COMPRESSION_LEVEL)?,
+        })
+    }
+}
+
+/// Decompress a response body, given its content encoding.
+pub(crate) fn decompress(encoding: Option<&str>, bytes: Vec<u8>) -> ClientResult<Vec<u8>> {
+    match encoding {
+        None | Some("identity") => Ok(bytes),
+        Some("gzip") => {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+            Ok(decompressed)
+        }
+        Some("zstd") => Ok(zstd::decode_all(bytes.as_slice())?),
+        Some(encoding) => Err(ClientError::Default(format!(
+            "unsupported content encoding of the response: {encoding}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, clippy::unwrap_used)]
+mod tests {
+    use super::{Compression, decompress};
+    use crate::ClientResult;
+
+    #[test]
+    fn test_compression() -> ClientResult<()> {
+        let bytes = [[0_u8; 8], [1; 8]].repeat(1000).concat();
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let compressed = compression.compress(&bytes)?;
+            assert!(compressed.len() < bytes.len());
+            assert_eq!(decompress(Some(compression.encoding()), compressed)?, bytes);
+        }
+        assert_eq!(decompress(None, bytes.clone())?, bytes);
+        decompress(Some("br"), bytes).unwrap_err();
+        Ok(())
+    }
+}
@@ -1,9 +1,18 @@
 use cosmian_http_client::HttpClientConfig;
 use serde::{Deserialize, Serialize};
 
+use crate::{Compression, RetryPolicy};
+
 #[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
 pub struct RestClientConfig {
     pub http_config: HttpClientConfig,
+    /// The compression of the binary request and response bodies, disabled
+    /// by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<Compression>,
+    /// The retries of the failed Findex memory requests, disabled by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
 }
 
 impl Default for RestClientConfig {
@@ -13,6 +22,8 @@ impl Default for RestClientConfig {
                 server_url: "http://127.0.0.1:6668".to_owned(),
                 ..HttpClientConfig::default()
             },
+            compression: None,
+            retry: None,
         }
     }
 }
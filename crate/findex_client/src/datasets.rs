@@ -1,14 +1,41 @@
-use cosmian_findex_structs::{EncryptedEntries, Uuids};
+use cosmian_findex_structs::{EncryptedEntries, Uuids, VersionedEntries};
 use cosmian_kms_cli::reexport::cosmian_kms_crypto::reexport::cosmian_crypto_core::bytes_ser_de::Serializable;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use tracing::{instrument, trace};
 use uuid::Uuid;
 
 use crate::{
     RestClient,
     error::{ClientError, result::ClientResult},
-    rest_client::{SuccessResponse, handle_error, handle_status_code},
+    rest_client::{SuccessResponse, handle_error, handle_status_code, response_bytes},
 };
 
+/// The ID and the size of a dataset entry
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EntryInfo {
+    pub uuid: Uuid,
+    /// The size of the encrypted entry, in bytes
+    pub size: usize,
+}
+
+/// The entries staged under a transaction
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StagedEntries {
+    pub success: String,
+    pub index_id: Uuid,
+    /// The ID of the transaction to commit the entries with
+    pub transaction_id: Uuid,
+}
+
+/// A page of dataset entries
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EntriesPage {
+    pub entries: Vec<EntryInfo>,
+    /// The cursor of the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
 impl RestClient {
     /// Add encrypted entries to a dataset.
     /// # Errors
@@ -24,10 +51,7 @@ impl RestClient {
         trace!("POST: {server_url}");
         let encrypted_entries = encrypted_entries.serialize()?;
         let response = self
-            .http_client
-            .client
-            .post(server_url)
-            .body(encrypted_entries.to_vec())
+            .post_bytes(&server_url, encrypted_entries.to_vec())?
             .send()
             .await?;
 
@@ -48,13 +72,7 @@ impl RestClient {
         trace!("POST: {server_url}");
 
         let uuids = Uuids::from(uuids).serialize()?;
-        let response = self
-            .http_client
-            .client
-            .post(server_url)
-            .body(uuids.to_vec())
-            .send()
-            .await?;
+        let response = self.post_bytes(&server_url, uuids.to_vec())?.send().await?;
 
         handle_status_code(response, &endpoint).await
     }
@@ -73,17 +91,170 @@ impl RestClient {
         trace!("POST: {server_url}");
 
         let uuids = Uuids::from(uuids).serialize()?;
+        let request = self.post_bytes(&server_url, uuids.to_vec())?;
+        let response = self.accept_compressed(request).send().await?;
+        if response.status().is_success() {
+            let encrypted_entries =
+                EncryptedEntries::deserialize(&response_bytes(response).await?)?;
+            return Ok(encrypted_entries);
+        }
+
+        Err(ClientError::RequestFailed(
+            handle_error(&endpoint, response).await?,
+        ))
+    }
+
+    /// Get entries from a dataset along with their versions.
+    /// # Errors
+    /// Fails if entries cannot be retrieved from the dataset.
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    pub async fn get_versioned_entries(
+        &self,
+        index_id: &Uuid,
+        uuids: &[Uuid],
+    ) -> ClientResult<VersionedEntries> {
+        let endpoint = format!("/datasets/{index_id}/get_entries");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+
+        let uuids = Uuids::from(uuids).serialize()?;
+        let request = self
+            .post_bytes(&server_url, uuids.to_vec())?
+            .query(&[("versions", "true")]);
+        let response = self.accept_compressed(request).send().await?;
+        if response.status().is_success() {
+            return Ok(VersionedEntries::deserialize(
+                &response_bytes(response).await?,
+            )?);
+        }
+
+        Err(ClientError::RequestFailed(
+            handle_error(&endpoint, response).await?,
+        ))
+    }
+
+    /// Write entries to a dataset only if they all still have the given
+    /// versions, a missing entry having the version 0.
+    /// # Errors
+    /// Fails with [`ClientError::Conflict`] if the version of an entry
+    /// changed, in which case no entry is written.
+    #[instrument(ret(Display), err, skip_all, level = "trace")]
+    pub async fn update_entries(
+        &self,
+        index_id: &Uuid,
+        versioned_entries: &VersionedEntries,
+    ) -> ClientResult<SuccessResponse> {
+        let endpoint = format!("/datasets/{index_id}/update_entries");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let versioned_entries = versioned_entries.serialize()?;
+        let response = self
+            .post_bytes(&server_url, versioned_entries.to_vec())?
+            .send()
+            .await?;
+        if response.status() == StatusCode::CONFLICT {
+            return Err(ClientError::Conflict(
+                handle_error(&endpoint, response).await?,
+            ));
+        }
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// Stage encrypted entries under a new transaction: they are added to the
+    /// dataset by [`Self::commit_entries`], typically once they are indexed,
+    /// and discarded by the server if the transaction is not committed in
+    /// time.
+    /// # Errors
+    /// Fails if entries cannot be staged.
+    #[instrument(err, skip_all, level = "trace")]
+    pub async fn stage_entries(
+        &self,
+        index_id: &Uuid,
+        encrypted_entries: &EncryptedEntries,
+    ) -> ClientResult<StagedEntries> {
+        let endpoint = format!("/datasets/{index_id}/stage_entries");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let encrypted_entries = encrypted_entries.serialize()?;
+        let response = self
+            .post_bytes(&server_url, encrypted_entries.to_vec())?
+            .send()
+            .await?;
+        if response.status().is_success() {
+            return Ok(response.json::<StagedEntries>().await?);
+        }
+
+        Err(ClientError::RequestFailed(
+            handle_error(&endpoint, response).await?,
+        ))
+    }
+
+    /// Add the entries staged under a transaction to the dataset.
+    /// # Errors
+    /// Fails if the transaction is unknown or has expired.
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    pub async fn commit_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> ClientResult<SuccessResponse> {
+        let endpoint = format!("/datasets/{index_id}/commit_entries/{transaction_id}");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let response = self.http_client.client.post(server_url).send().await?;
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// Discard the entries staged under a transaction.
+    /// # Errors
+    /// Fails if the request to the server fails.
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    pub async fn abort_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> ClientResult<SuccessResponse> {
+        let endpoint = format!("/datasets/{index_id}/abort_entries/{transaction_id}");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let response = self.http_client.client.post(server_url).send().await?;
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// List the IDs and the sizes of the entries of a dataset, one page at a
+    /// time: the first page is returned without a cursor, the following ones
+    /// with the cursor returned with the previous page. A page holds at most
+    /// `limit` entries and may be empty while the listing is not over, which
+    /// it is once no cursor is returned.
+    /// # Errors
+    /// Fails if the entries cannot be listed.
+    #[instrument(err, skip(self), level = "trace")]
+    pub async fn list_entries(
+        &self,
+        index_id: &Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> ClientResult<EntriesPage> {
+        let endpoint = format!("/datasets/{index_id}/entries");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("GET: {server_url}");
+
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_owned()));
+        }
         let response = self
             .http_client
             .client
-            .post(server_url)
-            .body(uuids.to_vec())
+            .get(server_url)
+            .query(&query)
             .send()
             .await?;
         if response.status().is_success() {
-            let response_bytes = response.bytes().await.map(|r| r.to_vec())?;
-            let encrypted_entries = EncryptedEntries::deserialize(&response_bytes)?;
-            return Ok(encrypted_entries);
+            return Ok(response.json::<EntriesPage>().await?);
         }
 
         Err(ClientError::RequestFailed(
@@ -1,10 +1,15 @@
 use std::io;
 
 use cosmian_findex_structs::StructsError;
-use cosmian_kms_cli::reexport::cosmian_kms_client::{KmsClientError, cosmian_kmip::KmipError};
+use cosmian_kms_cli::{
+    error::KmsCliError,
+    reexport::cosmian_kms_client::{KmsClientError, cosmian_kmip::KmipError},
+};
 use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
 use thiserror::Error;
 
+use crate::MergedReadError;
+
 pub(crate) mod result;
 
 #[derive(Error, Debug)]
@@ -13,12 +18,24 @@ pub enum ClientError {
     Default(String),
     #[error("REST Request Failed: {0}")]
     RequestFailed(String),
+    #[error("Conflicting versions: {0}")]
+    Conflict(String),
+    #[error("Server unavailable: {0}")]
+    ServerUnavailable(String),
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+    #[error(transparent)]
+    MergedReadError(#[from] MergedReadError<ClientError>),
     #[error(transparent)]
     FindexError(#[from] cosmian_findex::Error<Address<ADDRESS_LENGTH>>),
     #[error(transparent)]
     StructsError(#[from] StructsError),
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
+    #[error("gRPC Request Failed: {0}")]
+    GrpcStatus(Box<tonic::Status>),
+    #[error(transparent)]
+    GrpcTransportError(#[from] tonic::transport::Error),
     #[error(transparent)]
     IoError(#[from] io::Error),
     #[error(transparent)]
@@ -26,11 +43,19 @@ pub enum ClientError {
     #[error(transparent)]
     KmsClientError(#[from] KmsClientError),
     #[error(transparent)]
+    KmsCliError(#[from] KmsCliError),
+    #[error(transparent)]
     TryFromSliceError(#[from] std::array::TryFromSliceError),
     #[error(transparent)]
     TryFromIntError(#[from] std::num::TryFromIntError),
 }
 
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        Self::GrpcStatus(Box::new(status))
+    }
+}
+
 /// Construct a server error from a string.
 #[macro_export]
 macro_rules! client_error {
@@ -0,0 +1,349 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use cosmian_findex_structs::Keyword;
+
+use crate::{ClientError, ClientResult};
+
+/// The tag of the keywords indexing the prefixes of the keywords
+const PREFIX_TAG: &[u8] = b"\0prefix\0";
+/// The tag of the keywords indexing the Soundex codes of the keywords
+const SOUNDEX_TAG: &[u8] = b"\0soundex\0";
+/// The name of the expansion indexing no synthetic keyword
+const NO_EXPANSION: &str = "none";
+
+/// The synthetic keywords indexed along with the keywords, which let the
+/// searches match them by prefix or by sound.
+///
+/// Each synthetic keyword is tagged, so that it cannot match a keyword. A
+/// query term `rob*` searches the keywords starting with `rob`, and a query
+/// term `~robert` the keywords sounding like `robert`, provided their
+/// synthetic keywords are indexed, otherwise the searches are rejected; the
+/// other terms, and the literal ones, are searched as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeywordExpansion {
+    /// The minimum and maximum lengths, in characters, of the prefixes
+    /// indexed, if any
+    prefix_lengths: Option<(usize, usize)>,
+    /// Whether the Soundex codes of the keywords are indexed
+    phonetic: bool,
+}
+
+impl KeywordExpansion {
+    /// Index the prefixes of `min_length` to `max_length` characters of the
+    /// keywords. A prefix search of a longer prefix matches all the keywords
+    /// starting with its first `max_length` characters.
+    ///
+    /// # Errors
+    /// - If the minimum length is 0 or greater than the maximum length
+    pub fn with_prefixes(mut self, min_length: usize, max_length: usize) -> ClientResult<Self> {
+        if min_length == 0 || max_length < min_length {
+            return Err(ClientError::Default(format!(
+                "invalid prefix lengths: {min_length} to {max_length} characters"
+            )));
+        }
+        self.prefix_lengths = Some((min_length, max_length));
+        Ok(self)
+    }
+
+    /// Index the Soundex codes of the keywords, which are the same for the
+    /// English words sounding alike.
+    #[must_use]
+    pub const fn with_phonetic_keys(mut self) -> Self {
+        self.phonetic = true;
+        self
+    }
+
+    /// The synthetic keywords to index along with the keyword.
+    #[must_use]
+    pub fn synthetic_keywords(&self, keyword: &str) -> Vec<Keyword> {
+        let mut keywords = Vec::new();
+        if let Some((min_length, max_length)) = self.prefix_lengths {
+            // The end of the prefixes of 1, 2, ... characters
+            let ends = keyword
+                .char_indices()
+                .skip(1)
+                .map(|(end, _)| end)
+                .chain([keyword.len()]);
+            keywords.extend(
+                (1..=max_length)
+                    .zip(ends)
+                    .filter(|(length, _)| *length >= min_length)
+                    .filter_map(|(_, end)| keyword.get(..end))
+                    .map(|prefix| tagged(PREFIX_TAG, prefix)),
+            );
+        }
+        if self.phonetic {
+            keywords.extend(soundex(keyword).map(|code| tagged(SOUNDEX_TAG, &code)));
+        }
+        keywords
+    }
+
+    /// The keyword to search for a query term.
+    ///
+    /// # Errors
+    /// - If the term is a prefix search and the prefixes are not indexed
+    /// - If the prefix of a prefix search is shorter than the indexed ones
+    /// - If the term is a phonetic search and the Soundex codes are not
+    ///   indexed
+    /// - If the term of a phonetic search contains no letter
+    pub fn search_keyword(&self, term: &str) -> ClientResult<Keyword> {
+        if let Some(prefix) = term.strip_suffix('*') {
+            let Some((min_length, max_length)) = self.prefix_lengths else {
+                return Err(ClientError::InvalidQuery(format!(
+                    "the prefix search `{term}` requires the prefixes of the keywords to be \
+                     indexed, quote it to search it as is"
+                )));
+            };
+            let length = prefix.chars().count();
+            if length < min_length {
+                return Err(ClientError::InvalidQuery(format!(
+                    "the prefix `{prefix}` is shorter than the indexed prefixes, of at least \
+                     {min_length} characters"
+                )));
+            }
+            let end = prefix
+                .char_indices()
+                .nth(max_length)
+                .map_or(prefix.len(), |(end, _)| end);
+            return Ok(tagged(PREFIX_TAG, prefix.get(..end).unwrap_or(prefix)));
+        }
+        if let Some(word) = term.strip_prefix('~') {
+            if !self.phonetic {
+                return Err(ClientError::InvalidQuery(format!(
+                    "the phonetic search `{term}` requires the Soundex codes of the keywords to \
+                     be indexed, quote it to search it as is"
+                )));
+            }
+            let code = soundex(word).ok_or_else(|| {
+                ClientError::InvalidQuery(format!("the phonetic search `{term}` has no letter"))
+            })?;
+            return Ok(tagged(SOUNDEX_TAG, &code));
+        }
+        Ok(Keyword::from(term.as_bytes()))
+    }
+}
+
+/// The synthetic keywords separated by commas, e.g. `prefixes:3-5,phonetic`,
+/// or `none` when there are none.
+impl Display for KeywordExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keywords = Vec::new();
+        if let Some((min_length, max_length)) = self.prefix_lengths {
+            keywords.push(format!("prefixes:{min_length}-{max_length}"));
+        }
+        if self.phonetic {
+            keywords.push("phonetic".to_owned());
+        }
+        if keywords.is_empty() {
+            f.write_str(NO_EXPANSION)
+        } else {
+            f.write_str(&keywords.join(","))
+        }
+    }
+}
+
+impl FromStr for KeywordExpansion {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |keywords: &str| {
+            ClientError::Default(format!("unknown synthetic keywords `{keywords}`"))
+        };
+        let mut expansion = Self::default();
+        for keywords in s.split(',').map(str::trim) {
+            expansion = match keywords.split_once(':') {
+                None => match keywords {
+                    NO_EXPANSION => expansion,
+                    "phonetic" => expansion.with_phonetic_keys(),
+                    _ => return Err(invalid(keywords)),
+                },
+                Some(("prefixes", lengths)) => {
+                    let (min_length, max_length) = lengths
+                        .split_once('-')
+                        .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+                        .ok_or_else(|| invalid(keywords))?;
+                    expansion.with_prefixes(min_length, max_length)?
+                }
+                Some(_) => return Err(invalid(keywords)),
+            };
+        }
+        Ok(expansion)
+    }
+}
+
+fn tagged(tag: &[u8], keyword: &str) -> Keyword {
+    Keyword::from([tag, keyword.as_bytes()].concat())
+}
+
+/// The American Soundex code of the ASCII letters of a word: its first
+/// letter followed by the digits of the groups of consonants sounding alike,
+/// e.g. `R163` for `Robert` and `Rupert`.
+fn soundex(word: &str) -> Option<String> {
+    /// The digit of a letter, `0` for the vowels which separate the
+    /// consonants, and none for `H` and `W` which do not.
+    const fn digit(letter: char) -> Option<u8> {
+        match letter {
+            'B' | 'F' | 'P' | 'V' => Some(1),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some(2),
+            'D' | 'T' => Some(3),
+            'L' => Some(4),
+            'M' | 'N' => Some(5),
+            'R' => Some(6),
+            'H' | 'W' => None,
+            _ => Some(0),
+        }
+    }
+
+    let mut letters = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|letter| letter.to_ascii_uppercase());
+    let first = letters.next()?;
+    let mut code = String::from(first);
+    let mut previous = digit(first);
+    for letter in letters {
+        match digit(letter) {
+            None => {}
+            Some(digit) => {
+                if digit != 0 && previous != Some(digit) {
+                    code.push(char::from(b'0' + digit));
+                    if code.len() == 4 {
+                        break;
+                    }
+                }
+                previous = Some(digit);
+            }
+        }
+    }
+    while code.len() < 4 {
+        code.push('0');
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, clippy::unwrap_used)]
+mod tests {
+    use cosmian_findex_structs::Keyword;
+
+    use super::{KeywordExpansion, PREFIX_TAG, SOUNDEX_TAG, soundex, tagged};
+    use crate::ClientResult;
+
+    #[test]
+    fn test_soundex() {
+        for (word, code) in [
+            ("Robert", "R163"),
+            ("Rupert", "R163"),
+            ("Ashcraft", "A261"),
+            ("Tymczak", "T522"),
+            ("Pfister", "P236"),
+            ("Honeyman", "H555"),
+            ("Lee", "L000"),
+            ("o'brien", "O165"),
+        ] {
+            assert_eq!(soundex(word).as_deref(), Some(code), "{word}");
+        }
+        assert_eq!(soundex("42"), None);
+    }
+
+    #[test]
+    fn test_keyword_expansion() -> ClientResult<()> {
+        let expansion = KeywordExpansion::default()
+            .with_prefixes(2, 4)?
+            .with_phonetic_keys();
+        assert_eq!(
+            expansion.synthetic_keywords("rÖbert"),
+            [
+                tagged(PREFIX_TAG, "rÖ"),
+                tagged(PREFIX_TAG, "rÖb"),
+                tagged(PREFIX_TAG, "rÖbe"),
+                tagged(SOUNDEX_TAG, "R163"),
+            ]
+        );
+        assert_eq!(
+            expansion.synthetic_keywords("rob"),
+            [
+                tagged(PREFIX_TAG, "ro"),
+                tagged(PREFIX_TAG, "rob"),
+                tagged(SOUNDEX_TAG, "R100"),
+            ]
+        );
+        assert!(
+            KeywordExpansion::default()
+                .synthetic_keywords("rob")
+                .is_empty()
+        );
+
+        // The searches map to the synthetic keywords
+        assert_eq!(
+            expansion.search_keyword("rob")?,
+            Keyword::from(b"rob".as_slice())
+        );
+        assert_eq!(expansion.search_keyword("rÖb*")?, tagged(PREFIX_TAG, "rÖb"));
+        assert_eq!(
+            expansion.search_keyword("robert*")?,
+            tagged(PREFIX_TAG, "robe")
+        );
+        assert_eq!(
+            expansion.search_keyword("~rupert")?,
+            tagged(SOUNDEX_TAG, "R163")
+        );
+        expansion.search_keyword("r*").unwrap_err();
+        expansion.search_keyword("~42").unwrap_err();
+
+        // The prefix and phonetic searches require their keywords to be indexed
+        let expansion = KeywordExpansion::default();
+        expansion.search_keyword("rob*").unwrap_err();
+        expansion.search_keyword("~rob").unwrap_err();
+        KeywordExpansion::default()
+            .with_phonetic_keys()
+            .search_keyword("rob*")
+            .unwrap_err();
+        KeywordExpansion::default()
+            .with_prefixes(3, 5)?
+            .search_keyword("~rob")
+            .unwrap_err();
+
+        KeywordExpansion::default().with_prefixes(0, 3).unwrap_err();
+        KeywordExpansion::default().with_prefixes(3, 2).unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_expansion_names() -> ClientResult<()> {
+        for expansion in [
+            KeywordExpansion::default(),
+            KeywordExpansion::default().with_prefixes(3, 5)?,
+            KeywordExpansion::default().with_phonetic_keys(),
+            KeywordExpansion::default()
+                .with_prefixes(1, 1)?
+                .with_phonetic_keys(),
+        ] {
+            assert_eq!(
+                expansion.to_string().parse::<KeywordExpansion>()?,
+                expansion
+            );
+        }
+        assert_eq!(
+            KeywordExpansion::default()
+                .with_prefixes(3, 5)?
+                .with_phonetic_keys()
+                .to_string(),
+            "prefixes:3-5,phonetic"
+        );
+        assert_eq!(KeywordExpansion::default().to_string(), "none");
+        for invalid in [
+            "prefix",
+            "prefixes:3",
+            "prefixes:5-3",
+            "prefixes:a-b",
+            "soundex",
+        ] {
+            invalid.parse::<KeywordExpansion>().unwrap_err();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+
+use cosmian_findex_structs::{EncryptedEntries, Keyword, SearchResults, Uuids, Value};
+use cosmian_kms_cli::{
+    actions::kms::symmetric::{DecryptAction, EncryptAction},
+    reexport::cosmian_kms_client::reexport::cosmian_kms_client_utils::symmetric_utils::DataEncryptionAlgorithm,
+};
+use futures::{StreamExt, TryStreamExt, stream};
+use tracing::{debug, trace, warn};
+use uuid::Uuid;
+
+use super::FindexClient;
+use crate::{ClientResult, Query};
+
+/// The key encrypting the dataset entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKey {
+    /// The ID of a key encryption key (KEK) of the KMS. The entries are
+    /// encrypted client-side with an ephemeral data encryption key, which is
+    /// wrapped by the KEK and stored with them.
+    KeyEncryptionKey(String),
+    /// The ID of a data encryption key (DEK) of the KMS. The entries are
+    /// encrypted server-side, by the KMS.
+    DataEncryptionKey(String),
+}
+
+/// How the dataset entries are encrypted.
+#[derive(Debug, Clone)]
+pub struct EntryEncryption {
+    pub key: EntryKey,
+    pub algorithm: DataEncryptionAlgorithm,
+    /// The nonce, IV or XTS tweak. A random one is generated if none is given.
+    pub nonce: Option<Vec<u8>>,
+    /// The additional authentication data, needed back for decryption. It is
+    /// ignored with XTS.
+    pub authentication_data: Option<Vec<u8>>,
+}
+
+impl EntryEncryption {
+    #[must_use]
+    pub const fn new(key: EntryKey, algorithm: DataEncryptionAlgorithm) -> Self {
+        Self {
+            key,
+            algorithm,
+            nonce: None,
+            authentication_data: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_nonce(mut self, nonce: Vec<u8>) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    #[must_use]
+    pub fn with_authentication_data(mut self, authentication_data: Vec<u8>) -> Self {
+        self.authentication_data = Some(authentication_data);
+        self
+    }
+}
+
+/// A dataset entry, and the keywords indexing it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DatasetEntry {
+    pub data: Vec<u8>,
+    pub keywords: HashSet<Keyword>,
+    /// The UUID of the entry, a random one is generated if none is given
+    pub id: Option<Uuid>,
+}
+
+impl DatasetEntry {
+    #[must_use]
+    pub const fn new(data: Vec<u8>, keywords: HashSet<Keyword>) -> Self {
+        Self {
+            data,
+            keywords,
+            id: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+}
+
+impl<const WORD_LENGTH: usize> FindexClient<WORD_LENGTH> {
+    /// Encrypt the entries, add them to the dataset of the index under their
+    /// UUID, or a fresh one, and index these UUIDs under the keywords of their
+    /// entry.
+    ///
+    /// The entries are staged until they are indexed, so that a failure in
+    /// between does not leave entries that no search can reach.
+    ///
+    /// # Errors
+    /// - If the entries cannot be encrypted
+    /// - If the entries cannot be staged, indexed or committed
+    pub async fn encrypt_and_index(
+        &self,
+        entries: Vec<DatasetEntry>,
+        encryption: &EntryEncryption,
+    ) -> ClientResult<Uuids> {
+        let uuids = entries
+            .iter()
+            .map(|entry| entry.id.unwrap_or_else(Uuid::new_v4))
+            .collect::<Vec<_>>();
+        let mut bindings: HashMap<Keyword, HashSet<Value>> = HashMap::new();
+        for (uuid, entry) in uuids.iter().zip(&entries) {
+            let indexed_value = Value::from(uuid.as_bytes().to_vec());
+            for keyword in &entry.keywords {
+                trace!("keyword: {keyword}");
+                bindings
+                    .entry(keyword.clone())
+                    .or_default()
+                    .insert(indexed_value.clone());
+            }
+        }
+        let ciphertexts = self
+            .encrypt_entries(
+                entries.into_iter().map(|entry| entry.data).collect(),
+                encryption,
+            )
+            .await?;
+        let encrypted_entries = uuids
+            .into_iter()
+            .zip(ciphertexts)
+            .collect::<EncryptedEntries>();
+
+        debug!("Findex: Staging entries in the index: {}", self.index_id);
+        let transaction_id = self
+            .rest_client
+            .stage_entries(&self.index_id, &encrypted_entries)
+            .await?
+            .transaction_id;
+
+        // The server discards the staged entries which are never committed.
+        if let Err(e) = self.insert(bindings).await {
+            if let Err(abort_error) = self
+                .rest_client
+                .abort_entries(&self.index_id, &transaction_id)
+                .await
+            {
+                warn!("Failed to abort the transaction {transaction_id}: {abort_error}");
+            }
+            return Err(e);
+        }
+
+        debug!(
+            "Findex: Committing the entries to the index: {}",
+            self.index_id
+        );
+        self.rest_client
+            .commit_entries(&self.index_id, &transaction_id)
+            .await?;
+
+        Ok(encrypted_entries.get_uuids())
+    }
+
+    /// Run the query, then fetch and decrypt the entries found.
+    ///
+    /// # Errors
+    /// - If the search fails
+    /// - If the entries cannot be fetched or decrypted
+    pub async fn search_and_decrypt(
+        &self,
+        query: &Query,
+        encryption: &EntryEncryption,
+    ) -> ClientResult<Vec<(Uuid, Vec<u8>)>> {
+        let search_results = self.query(query).await?;
+        self.decrypt_entries(search_results, encryption).await
+    }
+
+    /// Fetch and decrypt the entries of the dataset found by a search.
+    ///
+    /// # Errors
+    /// - If the search results are not UUIDs
+    /// - If the entries cannot be fetched or decrypted
+    pub async fn decrypt_entries(
+        &self,
+        search_results: SearchResults,
+        encryption: &EntryEncryption,
+    ) -> ClientResult<Vec<(Uuid, Vec<u8>)>> {
+        trace!("Search results: {search_results}");
+        let uuids = Uuids::try_from(search_results)?;
+        trace!("UUIDs of encrypted entries: {uuids}");
+        let encrypted_entries = self.rest_client.get_entries(&self.index_id, &uuids).await?;
+
+        let decrypt_action = &DecryptAction::default();
+        let kms_client = &self.kms_client;
+        stream::iter(encrypted_entries.entries)
+            .map(|(uuid, ciphertext)| async move {
+                let plaintext = match &encryption.key {
+                    EntryKey::KeyEncryptionKey(key_encryption_key_id) => {
+                        decrypt_action
+                            .client_side_decrypt_with_buffer(
+                                kms_client,
+                                encryption.algorithm,
+                                key_encryption_key_id,
+                                &ciphertext,
+                                encryption.authentication_data.clone(),
+                            )
+                            .await?
+                    }
+                    EntryKey::DataEncryptionKey(data_encryption_key_id) => decrypt_action
+                        .server_side_decrypt(
+                            kms_client,
+                            encryption.algorithm.into(),
+                            data_encryption_key_id,
+                            ciphertext,
+                            encryption.authentication_data.clone(),
+                        )
+                        .await?
+                        .to_vec(),
+                };
+                ClientResult::Ok((uuid, plaintext))
+            })
+            .buffered(self.max_concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Encrypt the entries, in order.
+    async fn encrypt_entries(
+        &self,
+        entries: Vec<Vec<u8>>,
+        encryption: &EntryEncryption,
+    ) -> ClientResult<Vec<Vec<u8>>> {
+        let encrypt_action = &EncryptAction::default();
+        match &encryption.key {
+            EntryKey::KeyEncryptionKey(key_encryption_key_id) => {
+                debug!(
+                    "Findex: Client-side encryption with key encryption key: \
+                     {key_encryption_key_id}"
+                );
+                // Generate an ephemeral key (DEK) and wrap it with the KEK.
+                let (dek, encapsulation) = encrypt_action
+                    .client_side_kem_encapsulation(
+                        &self.kms_client,
+                        key_encryption_key_id,
+                        encryption.algorithm,
+                    )
+                    .await?;
+                entries
+                    .iter()
+                    .map(|entry| {
+                        Ok(encrypt_action.client_side_encrypt_with_buffer(
+                            &dek,
+                            &encapsulation,
+                            encryption.algorithm,
+                            encryption.nonce.clone(),
+                            entry,
+                            encryption.authentication_data.clone(),
+                        )?)
+                    })
+                    .collect()
+            }
+            EntryKey::DataEncryptionKey(data_encryption_key_id) => {
+                debug!(
+                    "Findex: Server-side encryption with data encryption key: \
+                     {data_encryption_key_id}"
+                );
+                let kms_client = &self.kms_client;
+                stream::iter(entries)
+                    .map(|entry| async move {
+                        let (nonce, data, tag) = encrypt_action
+                            .server_side_encrypt(
+                                kms_client,
+                                data_encryption_key_id,
+                                encryption.algorithm.into(),
+                                encryption.nonce.clone(),
+                                entry,
+                                encryption.authentication_data.clone(),
+                            )
+                            .await?;
+                        let mut payload = Vec::new();
+                        if let Some(nonce) = nonce.as_ref() {
+                            payload.extend_from_slice(nonce);
+                        }
+                        payload.extend_from_slice(&data);
+                        if let Some(tag) = tag.as_ref() {
+                            payload.extend_from_slice(tag);
+                        }
+                        ClientResult::Ok(payload)
+                    })
+                    .buffered(self.max_concurrency)
+                    .try_collect()
+                    .await
+            }
+        }
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+use cosmian_findex::{
+    Findex, IndexADT, KEY_LENGTH, MemoryEncryptionLayer, generic_decode, generic_encode,
+};
+use cosmian_findex_structs::{Keyword, Keywords, SearchResults, Value};
+use cosmian_kms_cli::reexport::{
+    cosmian_kms_client::{KmsClient, kmip_2_1::kmip_operations::Get},
+    cosmian_kms_crypto::reexport::cosmian_crypto_core::{Secret, reexport::zeroize::Zeroizing},
+};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    task::JoinHandle,
+};
+use tracing::trace;
+use uuid::Uuid;
+
+use crate::{
+    BatchingMemory, ClientError, ClientResult, FindexRestClient, KeywordExpansion,
+    KmsEncryptionLayer, Query, RestClient, Tokenizer, error::result::FindexRestClientResultHelper,
+};
+
+mod entries;
+mod settings;
+
+pub use entries::{DatasetEntry, EntryEncryption, EntryKey};
+pub use settings::IndexSettings;
+
+/// The window during which the reads of the concurrent keyword tasks are
+/// merged into a single request.
+const READ_BATCHING_WINDOW: Duration = Duration::from_millis(2);
+
+/// The number of addresses whose permutation by the KMS is cached.
+const TOKEN_CACHE_CAPACITY: usize = 100_000;
+
+/// The keys of an index, stored in the KMS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindexKeys {
+    /// The index is encrypted by the client, with a seed fetched from the KMS.
+    ClientSideEncryption { index_id: Uuid, seed_key_id: String },
+    /// The index is encrypted by the KMS, with its HMAC and AES-XTS keys.
+    ServerSideEncryption {
+        aes_xts_key_id: String,
+        hmac_key_id: String,
+        index_id: Uuid,
+    },
+}
+
+impl FindexKeys {
+    #[must_use]
+    pub const fn index_id(&self) -> &Uuid {
+        match self {
+            Self::ClientSideEncryption { index_id, .. }
+            | Self::ServerSideEncryption { index_id, .. } => index_id,
+        }
+    }
+}
+
+/// How the results of several keywords are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// The values indexed by all the keywords
+    #[default]
+    And,
+    /// The values indexed by any of the keywords
+    Or,
+}
+
+type Memory<const WORD_LENGTH: usize> = BatchingMemory<FindexRestClient<WORD_LENGTH>>;
+
+type ClientSideMemory<const WORD_LENGTH: usize> =
+    MemoryEncryptionLayer<WORD_LENGTH, Memory<WORD_LENGTH>>;
+type ServerSideMemory<const WORD_LENGTH: usize> =
+    KmsEncryptionLayer<WORD_LENGTH, Memory<WORD_LENGTH>>;
+
+/// A Findex instance, along with its encrypted memory which also records the
+/// settings of the index.
+#[derive(Clone)]
+enum FindexInstance<const WORD_LENGTH: usize> {
+    ClientSideEncryption(
+        Box<(
+            Findex<WORD_LENGTH, Value, String, ClientSideMemory<WORD_LENGTH>>,
+            ClientSideMemory<WORD_LENGTH>,
+        )>,
+    ),
+    ServerSideEncryption(
+        Box<(
+            Findex<WORD_LENGTH, Value, String, ServerSideMemory<WORD_LENGTH>>,
+            ServerSideMemory<WORD_LENGTH>,
+        )>,
+    ),
+}
+
+impl<const WORD_LENGTH: usize> FindexInstance<WORD_LENGTH> {
+    async fn search(&self, keyword: &Keyword) -> ClientResult<HashSet<Value>> {
+        Ok(match self {
+            Self::ClientSideEncryption(instance) => instance.0.search(keyword).await?,
+            Self::ServerSideEncryption(instance) => instance.0.search(keyword).await?,
+        })
+    }
+
+    async fn insert_or_delete(
+        self,
+        keyword: Keyword,
+        values: HashSet<Value>,
+        is_insert: bool,
+    ) -> ClientResult<()> {
+        match self {
+            Self::ClientSideEncryption(instance) => {
+                if is_insert {
+                    instance.0.insert(keyword, values).await?;
+                } else {
+                    instance.0.delete(keyword, values).await?;
+                }
+            }
+            Self::ServerSideEncryption(instance) => {
+                if is_insert {
+                    instance.0.insert(keyword, values).await?;
+                } else {
+                    instance.0.delete(keyword, values).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the word stored at an address of the memory.
+    async fn read(
+        &self,
+        address: Address<ADDRESS_LENGTH>,
+    ) -> ClientResult<Option<[u8; WORD_LENGTH]>> {
+        let words = match self {
+            Self::ClientSideEncryption(instance) => instance.1.batch_read(vec![address]).await?,
+            Self::ServerSideEncryption(instance) => instance.1.batch_read(vec![address]).await?,
+        };
+        Ok(words.into_iter().next().flatten())
+    }
+
+    /// Write a word at an address of the memory unless a word is already
+    /// stored there, and return this word if any.
+    async fn write_once(
+        &self,
+        address: Address<ADDRESS_LENGTH>,
+        word: [u8; WORD_LENGTH],
+    ) -> ClientResult<Option<[u8; WORD_LENGTH]>> {
+        let guard = (address, None);
+        let bindings = vec![(address, word)];
+        Ok(match self {
+            Self::ClientSideEncryption(instance) => {
+                instance.1.guarded_write(guard, bindings).await?
+            }
+            Self::ServerSideEncryption(instance) => {
+                instance.1.guarded_write(guard, bindings).await?
+            }
+        })
+    }
+}
+
+/// A Findex index and its dataset, stored on a Findex server.
+///
+/// The index is encrypted either by the client, with a seed fetched from the
+/// KMS, or by the KMS itself, depending on the given keys. The keywords of an
+/// operation are searched or indexed concurrently, by at most
+/// `max_concurrency` tasks shared by the clones of the client.
+#[derive(Clone)]
+pub struct FindexClient<const WORD_LENGTH: usize> {
+    rest_client: RestClient,
+    kms_client: KmsClient,
+    index_id: Uuid,
+    instance: FindexInstance<WORD_LENGTH>,
+    max_concurrency: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<const WORD_LENGTH: usize> FindexClient<WORD_LENGTH> {
+    /// The default maximum number of keywords searched or indexed at once
+    pub const DEFAULT_MAX_CONCURRENCY: usize = 256;
+
+    /// Instantiates the client of an index.
+    ///
+    /// # Errors
+    /// - If the seed cannot be retrieved from the KMS
+    pub async fn new(
+        rest_client: RestClient,
+        kms_client: KmsClient,
+        keys: FindexKeys,
+    ) -> ClientResult<Self> {
+        let index_id = *keys.index_id();
+        let memory = BatchingMemory::new(
+            FindexRestClient::new(rest_client.clone(), index_id),
+            READ_BATCHING_WINDOW,
+        );
+        let instance = match keys {
+            FindexKeys::ClientSideEncryption { seed_key_id, .. } => {
+                trace!("Using client side encryption");
+                let seed = retrieve_key_from_kms(&seed_key_id, kms_client.clone()).await?;
+                let encryption_layer = MemoryEncryptionLayer::<WORD_LENGTH, _>::new(&seed, memory);
+                FindexInstance::ClientSideEncryption(Box::new((
+                    Findex::new(encryption_layer.clone(), generic_encode, generic_decode),
+                    encryption_layer,
+                )))
+            }
+            FindexKeys::ServerSideEncryption {
+                hmac_key_id,
+                aes_xts_key_id,
+                ..
+            } => {
+                trace!("Using KMS server side encryption");
+                let encryption_layer = KmsEncryptionLayer::<WORD_LENGTH, _>::new(
+                    kms_client.clone(),
+                    hmac_key_id,
+                    aes_xts_key_id,
+                    memory,
+                )
+                .with_token_cache(TOKEN_CACHE_CAPACITY, None);
+                FindexInstance::ServerSideEncryption(Box::new((
+                    Findex::new(encryption_layer.clone(), generic_encode, generic_decode),
+                    encryption_layer,
+                )))
+            }
+        };
+        Ok(Self {
+            rest_client,
+            kms_client,
+            index_id,
+            instance,
+            max_concurrency: Self::DEFAULT_MAX_CONCURRENCY,
+            semaphore: Arc::new(Semaphore::new(Self::DEFAULT_MAX_CONCURRENCY)),
+        })
+    }
+
+    /// Search or index at most `max_concurrency` keywords at once, and
+    /// encrypt or decrypt at most as many entries at once through the KMS.
+    #[must_use]
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self.semaphore = Arc::new(Semaphore::new(self.max_concurrency));
+        self
+    }
+
+    #[must_use]
+    pub const fn index_id(&self) -> &Uuid {
+        &self.index_id
+    }
+
+    /// Run a task once a permit of the client is acquired.
+    fn spawn<T, Task>(&self, task: Task) -> JoinHandle<ClientResult<T>>
+    where
+        T: Send + 'static,
+        Task: Future<Output = ClientResult<T>> + Send + 'static,
+    {
+        let semaphore = self.semaphore.clone();
+        tokio::spawn(async move {
+            let _permit = acquire(&semaphore).await?;
+            task.await
+        })
+    }
+
+    /// Search multiple keywords, combining their results as requested.
+    ///
+    /// # Errors
+    /// - If any of the concurrent searches fails
+    pub async fn search(
+        &self,
+        keywords: &[Keyword],
+        mode: SearchMode,
+    ) -> ClientResult<SearchResults> {
+        let keywords = keywords.iter().cloned().map(Query::Keyword).collect();
+        self.query(&match mode {
+            SearchMode::And => Query::And(keywords),
+            SearchMode::Or => Query::Or(keywords),
+        })
+        .await
+    }
+
+    /// Search the keywords of a query, combining their results as its
+    /// operators require. The keywords are searched concurrently, and the
+    /// searches which can no longer change the results are cancelled.
+    ///
+    /// # Errors
+    /// - If any of the concurrent searches fails
+    pub async fn query(&self, query: &Query) -> ClientResult<SearchResults> {
+        trace!("query: {query}");
+        let lookup = |keyword: &Keyword| {
+            let instance = self.instance.clone();
+            let semaphore = self.semaphore.clone();
+            let keyword = keyword.clone();
+            async move {
+                let _permit = acquire(&semaphore).await?;
+                instance.search(&keyword).await
+            }
+        };
+        Ok(SearchResults(query.evaluate(&lookup).await?))
+    }
+
+    /// The settings recorded in the index, if any.
+    ///
+    /// # Errors
+    /// - If the recorded settings cannot be read, or are invalid
+    pub async fn recorded_settings(&self) -> ClientResult<Option<IndexSettings>> {
+        self.instance
+            .read(IndexSettings::address())
+            .await?
+            .map(|word| IndexSettings::from_word(&word))
+            .transpose()
+    }
+
+    /// The settings to search the index with: for both the tokenizer and the
+    /// expansion, the recorded one, or else the requested one, or else the
+    /// default one.
+    ///
+    /// # Errors
+    /// - If the recorded settings cannot be read
+    /// - If the requested settings differ from the recorded ones
+    pub async fn settings(
+        &self,
+        tokenizer: Option<Tokenizer>,
+        expansion: Option<KeywordExpansion>,
+    ) -> ClientResult<IndexSettings> {
+        let recorded = self.recorded_settings().await?;
+        self.resolve_settings(recorded, tokenizer, expansion)
+    }
+
+    /// The settings to index the index with, as for `settings`. They are
+    /// recorded in the index if none are, so that the next operations use
+    /// them.
+    ///
+    /// The settings are recorded only once: of two clients recording their
+    /// settings at the same time, the second one gets the settings of the
+    /// first one. An index which already holds data without recorded settings
+    /// was indexed with the default ones, which are the only ones it can
+    /// record.
+    ///
+    /// # Errors
+    /// - If the recorded settings cannot be read
+    /// - If the requested settings differ from the recorded ones
+    /// - If settings other than the default ones are requested for an index
+    ///   which already holds data without recorded settings
+    /// - If the settings cannot be recorded
+    pub async fn record_settings(
+        &self,
+        tokenizer: Option<Tokenizer>,
+        expansion: Option<KeywordExpansion>,
+    ) -> ClientResult<IndexSettings> {
+        if let Some(recorded) = self.recorded_settings().await? {
+            return self.resolve_settings(Some(recorded), tokenizer, expansion);
+        }
+        let settings = self.resolve_settings(None, tokenizer, expansion)?;
+        if settings != IndexSettings::default()
+            && !self.rest_client.is_index_empty(&self.index_id).await?
+        {
+            return Err(ClientError::Default(format!(
+                "the index {} already holds data indexed with the default settings, it cannot \
+                 be indexed with the tokenizer `{}` and the synthetic keywords `{}`",
+                self.index_id, settings.tokenizer, settings.expansion
+            )));
+        }
+        trace!(
+            "Recording the settings `{settings}` in the index {}",
+            self.index_id
+        );
+        match self
+            .instance
+            .write_once(IndexSettings::address(), settings.to_word()?)
+            .await?
+        {
+            None => Ok(settings),
+            // Another client recorded its settings first.
+            Some(word) => {
+                let recorded = IndexSettings::from_word(&word)?;
+                self.resolve_settings(Some(recorded), tokenizer, expansion)
+            }
+        }
+    }
+
+    fn resolve_settings(
+        &self,
+        recorded: Option<IndexSettings>,
+        tokenizer: Option<Tokenizer>,
+        expansion: Option<KeywordExpansion>,
+    ) -> ClientResult<IndexSettings> {
+        Ok(IndexSettings {
+            tokenizer: self.resolve_setting(
+                "tokenizer",
+                recorded.map(|settings| settings.tokenizer),
+                tokenizer,
+            )?,
+            expansion: self.resolve_setting(
+                "synthetic keywords",
+                recorded.map(|settings| settings.expansion),
+                expansion,
+            )?,
+        })
+    }
+
+    fn resolve_setting<Setting: PartialEq + Display + Default>(
+        &self,
+        name: &str,
+        recorded: Option<Setting>,
+        requested: Option<Setting>,
+    ) -> ClientResult<Setting> {
+        match (recorded, requested) {
+            (Some(recorded), Some(requested)) if recorded != requested => {
+                Err(ClientError::Default(format!(
+                    "the index {} is indexed with the {name} `{recorded}`, not `{requested}`",
+                    self.index_id
+                )))
+            }
+            (Some(setting), _) | (None, Some(setting)) => Ok(setting),
+            (None, None) => Ok(Setting::default()),
+        }
+    }
+
+    /// Index the given values under their keywords.
+    ///
+    /// # Errors
+    /// - If any of the concurrent insertions fails
+    pub async fn insert(
+        &self,
+        bindings: HashMap<Keyword, HashSet<Value>>,
+    ) -> ClientResult<Keywords> {
+        self.insert_or_delete(bindings, true).await
+    }
+
+    /// Remove the given values from the index of their keywords.
+    ///
+    /// # Errors
+    /// - If any of the concurrent deletions fails
+    pub async fn delete(
+        &self,
+        bindings: HashMap<Keyword, HashSet<Value>>,
+    ) -> ClientResult<Keywords> {
+        self.insert_or_delete(bindings, false).await
+    }
+
+    async fn insert_or_delete(
+        &self,
+        bindings: HashMap<Keyword, HashSet<Value>>,
+        is_insert: bool,
+    ) -> ClientResult<Keywords> {
+        let written_keywords = bindings.keys().cloned().collect::<Vec<_>>();
+
+        let handles = bindings
+            .into_iter()
+            .map(|(keyword, values)| {
+                self.spawn(
+                    self.instance
+                        .clone()
+                        .insert_or_delete(keyword, values, is_insert),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            join(handle).await?;
+        }
+
+        Ok(Keywords::from(written_keywords))
+    }
+}
+
+async fn acquire(semaphore: &Semaphore) -> ClientResult<SemaphorePermit<'_>> {
+    semaphore.acquire().await.map_err(|e| {
+        ClientError::Default(format!(
+            "Acquire error while trying to ask for permit: {e:?}"
+        ))
+    })
+}
+
+async fn join<T>(handle: JoinHandle<ClientResult<T>>) -> ClientResult<T> {
+    handle
+        .await
+        .map_err(|e| ClientError::Default(e.to_string()))?
+}
+
+/// Retrieve the key bytes of a key from KMS.
+async fn retrieve_key_from_kms(
+    key_id: &str,
+    kms_client: KmsClient,
+) -> ClientResult<Secret<KEY_LENGTH>> {
+    let mut secret = Zeroizing::new([0_u8; KEY_LENGTH]);
+    secret.copy_from_slice(
+        &kms_client
+            .get(Get::from(key_id))
+            .await?
+            .object
+            .key_block()?
+            .key_bytes()
+            .with_context(|| "findex::retrieve_key_from_kms")?,
+    );
+    Ok(Secret::from_unprotected_bytes(&mut secret))
+}
@@ -0,0 +1,132 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+
+use crate::{ClientError, ClientResult, KeywordExpansion, Tokenizer};
+
+/// The address of the word recording the settings of an index. It is
+/// encrypted as the addresses of the keywords, and written once, by a guarded
+/// write which fails if the word is already recorded.
+const SETTINGS_ADDRESS: [u8; ADDRESS_LENGTH] = *b"\0index-settings\0";
+
+/// The settings an index is built with, which all its clients must use.
+///
+/// They are recorded as lines of `name=value` in a single word, padded with
+/// zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IndexSettings {
+    /// The tokenizer turning the texts into keywords
+    pub tokenizer: Tokenizer,
+    /// The synthetic keywords indexed along with the keywords
+    pub expansion: KeywordExpansion,
+}
+
+impl IndexSettings {
+    pub(crate) fn address() -> Address<ADDRESS_LENGTH> {
+        Address::from(SETTINGS_ADDRESS)
+    }
+
+    /// The word recording the settings.
+    ///
+    /// # Errors
+    /// - If the settings do not fit in a word
+    pub(crate) fn to_word<const WORD_LENGTH: usize>(self) -> ClientResult<[u8; WORD_LENGTH]> {
+        let settings = self.to_string();
+        let mut word = [0; WORD_LENGTH];
+        word.get_mut(..settings.len())
+            .ok_or_else(|| {
+                ClientError::Default(format!(
+                    "the settings `{settings}` do not fit in a word of {WORD_LENGTH} bytes"
+                ))
+            })?
+            .copy_from_slice(settings.as_bytes());
+        Ok(word)
+    }
+
+    /// The settings recorded in a word.
+    ///
+    /// # Errors
+    /// - If the word does not record valid settings
+    pub(crate) fn from_word(word: &[u8]) -> ClientResult<Self> {
+        let end = word
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |i| i + 1);
+        std::str::from_utf8(word.get(..end).unwrap_or_default())
+            .map_err(|e| ClientError::Default(format!("invalid recorded settings: {e}")))?
+            .parse()
+    }
+}
+
+impl Display for IndexSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tokenizer={}\nexpansion={}",
+            self.tokenizer, self.expansion
+        )
+    }
+}
+
+impl FromStr for IndexSettings {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut settings = Self::default();
+        for line in s.lines() {
+            match line.split_once('=') {
+                Some(("tokenizer", tokenizer)) => settings.tokenizer = tokenizer.parse()?,
+                Some(("expansion", expansion)) => settings.expansion = expansion.parse()?,
+                _ => {
+                    return Err(ClientError::Default(format!(
+                        "unknown recorded setting `{line}`"
+                    )));
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, clippy::unwrap_used)]
+mod tests {
+    use super::IndexSettings;
+    use crate::{ClientResult, KeywordExpansion, Language, Splitting, Tokenizer};
+
+    #[test]
+    fn test_settings_word() -> ClientResult<()> {
+        let settings = IndexSettings {
+            tokenizer: Tokenizer::default()
+                .with_splitting(Splitting::Words)
+                .with_normalization()
+                .with_stemming(Language::French),
+            expansion: KeywordExpansion::default()
+                .with_prefixes(3, 5)?
+                .with_phonetic_keys(),
+        };
+        let word = settings.to_word::<200>()?;
+        assert_eq!(IndexSettings::from_word(&word)?, settings);
+        assert_eq!(
+            IndexSettings::from_word(&IndexSettings::default().to_word::<200>()?)?,
+            IndexSettings::default()
+        );
+        // The settings recorded without an expansion have none.
+        assert_eq!(
+            IndexSettings::from_word(b"tokenizer=split-words\0\0")?,
+            IndexSettings {
+                tokenizer: Tokenizer::default().with_splitting(Splitting::Words),
+                expansion: KeywordExpansion::default(),
+            }
+        );
+
+        // The settings must fit in a word, and be known.
+        settings.to_word::<16>().unwrap_err();
+        IndexSettings::from_word(b"tokenizer=ascii-lowercase\nother=1").unwrap_err();
+        IndexSettings::from_word(&[0xff; 8]).unwrap_err();
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use cosmian_findex_structs::grpc::{
+    BatchReadRequest, GuardedWriteRequest, findex_client::FindexClient,
+};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use futures::{Stream, StreamExt, TryStreamExt};
+use tonic::{
+    Request,
+    metadata::{AsciiMetadataValue, errors::InvalidMetadataValue},
+    transport::{Channel, ClientTlsConfig, Endpoint},
+};
+use tracing::trace;
+use uuid::Uuid;
+
+use crate::{ClientResult, error::ClientError};
+
+/// The maximum size of the messages, as accepted by the server
+const MAX_MESSAGE_SIZE: usize = 1 << 30;
+
+/// A guard, and the bindings to write if it holds
+pub type GuardedWrite<const WORD_LENGTH: usize> = (
+    (Address<ADDRESS_LENGTH>, Option<[u8; WORD_LENGTH]>),
+    Vec<(Address<ADDRESS_LENGTH>, [u8; WORD_LENGTH])>,
+);
+
+/// A Findex memory served by the gRPC API of the Findex server.
+#[derive(Clone)]
+pub struct FindexGrpcClient<const WORD_LENGTH: usize> {
+    client: FindexClient<Channel>,
+    /// The `authorization` metadata sent with each call
+    authorization: Option<AsciiMetadataValue>,
+    pub index_id: Uuid,
+}
+
+impl<const WORD_LENGTH: usize> FindexGrpcClient<WORD_LENGTH> {
+    /// Build a client over a channel to the gRPC port of the server, the
+    /// channel holding its TLS configuration and client certificate, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the access token is not a valid metadata value.
+    pub fn new(channel: Channel, access_token: Option<&str>, index_id: Uuid) -> ClientResult<Self> {
+        let authorization = access_token
+            .map(|token| AsciiMetadataValue::try_from(format!("Bearer {token}")))
+            .transpose()
+            .map_err(|e: InvalidMetadataValue| ClientError::Default(e.to_string()))?;
+        Ok(Self {
+            client: FindexClient::new(channel)
+                .max_decoding_message_size(MAX_MESSAGE_SIZE)
+                .max_encoding_message_size(MAX_MESSAGE_SIZE),
+            authorization,
+            index_id,
+        })
+    }
+
+    /// Connect to the gRPC port of the server, e.g. `http://127.0.0.1:6669`.
+    /// The `https` URLs are verified against the native root certificates.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the URL is invalid or the server cannot be reached.
+    pub async fn connect(
+        grpc_url: &str,
+        access_token: Option<&str>,
+        index_id: Uuid,
+    ) -> ClientResult<Self> {
+        let mut endpoint = Endpoint::from_shared(grpc_url.to_owned())?;
+        if grpc_url.starts_with("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        Self::new(endpoint.connect().await?, access_token, index_id)
+    }
+
+    fn request<T>(&self, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        if let Some(authorization) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", authorization.clone());
+        }
+        request
+    }
+
+    /// Pipeline guarded writes over a single call: the result of each write,
+    /// the word the guard address held, is yielded in order.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the call cannot be started; the failures of the writes are
+    /// yielded by the stream.
+    pub async fn guarded_write_stream<S>(
+        &self,
+        writes: S,
+    ) -> ClientResult<
+        impl Stream<Item = ClientResult<Option<[u8; WORD_LENGTH]>>> + use<S, WORD_LENGTH>,
+    >
+    where
+        S: Stream<Item = GuardedWrite<WORD_LENGTH>> + Send + 'static,
+    {
+        let index_id = self.index_id;
+        let requests = writes
+            .map(move |(guard, bindings)| GuardedWriteRequest::new(&index_id, &guard, &bindings));
+        let responses = self
+            .client
+            .clone()
+            .guarded_write_stream(self.request(requests))
+            .await?
+            .into_inner();
+        Ok(responses
+            .map_err(ClientError::from)
+            .and_then(|response| async move { Ok(response.previous_word()?) }))
+    }
+}
+
+impl<const WORD_LENGTH: usize> MemoryADT for FindexGrpcClient<WORD_LENGTH> {
+    type Address = Address<ADDRESS_LENGTH>;
+    type Error = ClientError;
+    type Word = [u8; WORD_LENGTH];
+
+    #[inline]
+    async fn batch_read(
+        &self,
+        addresses: Vec<Self::Address>,
+    ) -> Result<Vec<Option<[u8; WORD_LENGTH]>>, ClientError> {
+        trace!(
+            "gRPC batch_read of {} addresses for index {}",
+            addresses.len(),
+            self.index_id
+        );
+
+        let response = self
+            .client
+            .clone()
+            .batch_read(self.request(BatchReadRequest::new(&self.index_id, &addresses)))
+            .await?;
+        Ok(response.into_inner().words()?)
+    }
+
+    #[inline]
+    async fn guarded_write(
+        &self,
+        guard: (Self::Address, Option<Self::Word>),
+        bindings: Vec<(Self::Address, Self::Word)>,
+    ) -> Result<Option<[u8; WORD_LENGTH]>, ClientError> {
+        trace!(
+            "gRPC guarded_write of {} values for index {}",
+            bindings.len(),
+            self.index_id
+        );
+
+        let response = self
+            .client
+            .clone()
+            .guarded_write(self.request(GuardedWriteRequest::new(
+                &self.index_id,
+                &guard,
+                &bindings,
+            )))
+            .await?;
+        Ok(response.into_inner().previous_word()?)
+    }
+}
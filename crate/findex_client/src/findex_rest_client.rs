@@ -1,10 +1,23 @@
+use std::collections::HashMap;
+
 use base64::{Engine as _, engine::general_purpose};
-use cosmian_findex_structs::{Addresses, Bindings, Guard, OptionalWords};
+use cosmian_findex_structs::{
+    Addresses, Bindings, Guard, IndexedAddresses, IndexedWords, OptionalWords,
+};
 use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
-use tracing::{debug, trace, warn};
+use tracing::{debug, instrument, trace, warn};
 use uuid::Uuid;
 
-use crate::{RestClient, error::ClientError, rest_client::handle_error};
+use crate::{
+    RestClient,
+    error::{ClientError, result::ClientResult},
+    rest_client::{handle_error, response_bytes},
+    retry::RetrySafety,
+};
+
+/// The header making a write request safe to retry, see the server
+/// documentation on the idempotent writes.
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
 
 #[derive(Clone)]
 pub struct FindexRestClient<const WORD_LENGTH: usize> {
@@ -23,6 +36,41 @@ impl<const WORD_LENGTH: usize> FindexRestClient<WORD_LENGTH> {
     }
 }
 
+impl RestClient {
+    /// Read the words stored at addresses of several indexes in a single
+    /// request, the read permission being required on each index. The words
+    /// of each index are in the order of its addresses.
+    /// # Errors
+    /// Fails if any index cannot be read.
+    #[instrument(err, skip(self, addresses), level = "trace")]
+    pub async fn batch_read_indexes<const WORD_LENGTH: usize>(
+        &self,
+        addresses: HashMap<Uuid, Vec<Address<ADDRESS_LENGTH>>>,
+    ) -> ClientResult<HashMap<Uuid, Vec<Option<[u8; WORD_LENGTH]>>>> {
+        let endpoint = "/indexes/batch_read";
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+
+        trace!(
+            "Initiating batch_read of {} indexes at server_url: {server_url}",
+            addresses.len()
+        );
+
+        let request =
+            self.post_bytes(&server_url, IndexedAddresses::new(addresses).serialize()?)?;
+        let response = self
+            .send_with_retry(self.accept_compressed(request), RetrySafety::Read)
+            .await?;
+
+        if !response.status().is_success() {
+            warn!("batch_read failed on server url {:?}.", server_url);
+            let err = handle_error(endpoint, response).await?;
+            return Err(ClientError::RequestFailed(err));
+        }
+
+        Ok(IndexedWords::deserialize(&response_bytes(response).await?)?.into_inner())
+    }
+}
+
 impl<const WORD_LENGTH: usize> MemoryADT for FindexRestClient<WORD_LENGTH> {
     type Address = Address<ADDRESS_LENGTH>;
     type Error = ClientError;
@@ -43,13 +91,15 @@ impl<const WORD_LENGTH: usize> MemoryADT for FindexRestClient<WORD_LENGTH> {
             server_url
         );
 
+        let request = self
+            .rest_client
+            .post_bytes(&server_url, Addresses::new(addresses).serialize()?)?;
         let response = self
             .rest_client
-            .http_client
-            .client
-            .post(&server_url)
-            .body(Addresses::new(addresses).serialize()?)
-            .send()
+            .send_with_retry(
+                self.rest_client.accept_compressed(request),
+                RetrySafety::Read,
+            )
             .await?;
 
         if !response.status().is_success() {
@@ -58,7 +108,7 @@ impl<const WORD_LENGTH: usize> MemoryADT for FindexRestClient<WORD_LENGTH> {
             return Err(ClientError::RequestFailed(err));
         }
 
-        let words = OptionalWords::deserialize(&response.bytes().await?)?;
+        let words = OptionalWords::deserialize(&response_bytes(response).await?)?;
 
         trace!(
             "batch_read successful on server url {}. result: {}",
@@ -99,14 +149,19 @@ impl<const WORD_LENGTH: usize> MemoryADT for FindexRestClient<WORD_LENGTH> {
         request_bytes.extend_from_slice(&bindings_bytes);
         // END TODO
 
-        let response = self
+        let request = self
             .rest_client
-            .http_client
-            .client
-            .post(&server_url)
-            .body(request_bytes)
-            .send()
-            .await?;
+            .accept_compressed(self.rest_client.post_bytes(&server_url, request_bytes)?);
+        // The retries of a write sent with an idempotency key get the response
+        // of the first attempt which reached the server.
+        let (request, safety) = match &self.rest_client.config.retry {
+            Some(policy) if policy.idempotent_writes => (
+                request.header(IDEMPOTENCY_KEY, Uuid::new_v4().to_string()),
+                RetrySafety::IdempotentWrite,
+            ),
+            _ => (request, RetrySafety::Write),
+        };
+        let response = self.rest_client.send_with_retry(request, safety).await?;
 
         if !response.status().is_success() {
             warn!("guarded_write failed on server url {}.", server_url);
@@ -114,7 +169,7 @@ impl<const WORD_LENGTH: usize> MemoryADT for FindexRestClient<WORD_LENGTH> {
             return Err(ClientError::RequestFailed(err));
         }
 
-        let guard = OptionalWords::deserialize(&response.bytes().await?)?
+        let guard = OptionalWords::deserialize(&response_bytes(response).await?)?
             .into_inner()
             .first()
             .copied()
@@ -0,0 +1,102 @@
+use cosmian_findex_structs::IndexArchive;
+use cosmian_kms_cli::reexport::cosmian_kms_crypto::reexport::cosmian_crypto_core::bytes_ser_de::Serializable;
+use serde::Deserialize;
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::{
+    RestClient,
+    error::{ClientError, result::ClientResult},
+    rest_client::{SuccessResponse, handle_error, handle_status_code, response_bytes},
+};
+
+/// Whether an index holds no data
+#[derive(Deserialize)]
+struct IndexEmptiness {
+    empty: bool,
+}
+
+impl RestClient {
+    /// Export an index to an archive, optionally with the permissions of the
+    /// users on it.
+    /// # Errors
+    /// Fails if the index cannot be exported.
+    #[instrument(err, skip(self), level = "trace")]
+    pub async fn export_index(
+        &self,
+        index_id: &Uuid,
+        include_permissions: bool,
+    ) -> ClientResult<IndexArchive> {
+        let endpoint = format!("/indexes/{index_id}/export?permissions={include_permissions}");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let response = self
+            .accept_compressed(self.http_client.client.post(server_url))
+            .send()
+            .await?;
+        if response.status().is_success() {
+            return Ok(IndexArchive::deserialize(&response_bytes(response).await?)?);
+        }
+
+        Err(ClientError::RequestFailed(
+            handle_error(&endpoint, response).await?,
+        ))
+    }
+
+    /// Import an archive into the given index, or into a new index when no
+    /// index ID is given, optionally restoring the exported permissions.
+    /// # Errors
+    /// Fails if the archive cannot be imported.
+    #[instrument(ret(Display), err, skip(self, archive), level = "trace")]
+    pub async fn import_index(
+        &self,
+        archive: &IndexArchive,
+        index_id: Option<&Uuid>,
+        include_permissions: bool,
+    ) -> ClientResult<SuccessResponse> {
+        let index_id = index_id
+            .map(|index_id| format!("&index_id={index_id}"))
+            .unwrap_or_default();
+        let endpoint = format!("/indexes/import?permissions={include_permissions}{index_id}");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let archive = archive.serialize()?;
+        let response = self
+            .post_bytes(&server_url, archive.to_vec())?
+            .send()
+            .await?;
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// Copy an index into a new index administered by the caller.
+    /// # Errors
+    /// Fails if the index cannot be cloned.
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    pub async fn clone_index(&self, index_id: &Uuid) -> ClientResult<SuccessResponse> {
+        let endpoint = format!("/indexes/{index_id}/clone");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        let response = self.http_client.client.post(server_url).send().await?;
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// Tell whether an index holds no memory word and no dataset entry.
+    /// # Errors
+    /// Fails if the request to the server fails.
+    #[instrument(ret, err, skip(self), level = "trace")]
+    pub async fn is_index_empty(&self, index_id: &Uuid) -> ClientResult<bool> {
+        let endpoint = format!("/indexes/{index_id}/empty");
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("GET: {server_url}");
+        let response = self.http_client.client.get(server_url).send().await?;
+        if response.status().is_success() {
+            return Ok(response.json::<IndexEmptiness>().await?.empty);
+        }
+
+        Err(ClientError::RequestFailed(
+            handle_error(&endpoint, response).await?,
+        ))
+    }
+}
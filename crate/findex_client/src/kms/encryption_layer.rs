@@ -1,14 +1,18 @@
+use std::{sync::Arc, time::Duration};
+
 use cosmian_kms_cli::reexport::cosmian_kms_client::{
     KmsClient,
     cosmian_kmip::kmip_0::{
-        kmip_messages::{ResponseMessage, ResponseMessageBatchItemVersioned},
+        kmip_messages::{RequestMessage, ResponseMessage, ResponseMessageBatchItemVersioned},
         kmip_types::ResultStatusEnumeration,
     },
 };
 use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use futures::{StreamExt, TryStreamExt, stream};
 use tracing::trace;
 
-use crate::{ClientError, ClientResult};
+use super::token_cache::{SecretAddress, TokenCache};
+use crate::{ClientError, ClientResult, lru::Lru};
 
 /// The encryption layers is built on top of an encrypted memory implementing the `MemoryADT` and
 /// exposes a plaintext virtual memory interface implementing the `MemoryADT`.
@@ -23,6 +27,12 @@ pub struct KmsEncryptionLayer<
     pub(crate) hmac_key_id: String,
     pub(crate) aes_xts_key_id: String,
     pub(crate) mem: Memory,
+    /// The maximum number of operations of a KMIP request
+    pub(crate) chunk_size: usize,
+    /// The maximum number of KMIP requests sent at once
+    pub(crate) max_parallel_requests: usize,
+    /// The addresses already permuted, if the cache is enabled
+    pub(crate) token_cache: Option<Arc<TokenCache>>,
 }
 
 impl<
@@ -30,6 +40,11 @@ impl<
     Memory: Send + Sync + MemoryADT<Address = Address<ADDRESS_LENGTH>, Word = [u8; WORD_LENGTH]>,
 > KmsEncryptionLayer<WORD_LENGTH, Memory>
 {
+    /// The default maximum number of operations of a KMIP request
+    pub const DEFAULT_CHUNK_SIZE: usize = 1_000;
+    /// The default maximum number of KMIP requests sent at once
+    pub const DEFAULT_MAX_PARALLEL_REQUESTS: usize = 4;
+
     /// Instantiates a new memory encryption layer.
     pub const fn new(
         kms_client: KmsClient,
@@ -42,7 +57,56 @@ impl<
             hmac_key_id,
             aes_xts_key_id,
             mem,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            max_parallel_requests: Self::DEFAULT_MAX_PARALLEL_REQUESTS,
+            token_cache: None,
+        }
+    }
+
+    /// Cache the permutations of at most `capacity` addresses, for at most
+    /// `ttl` if given, instead of asking the KMS to permute them again. The
+    /// cached addresses are zeroized when evicted, and when the last clone of
+    /// the layer is dropped.
+    #[must_use]
+    pub fn with_token_cache(mut self, capacity: usize, ttl: Option<Duration>) -> Self {
+        self.token_cache = Some(Arc::new(TokenCache::new(Lru::new(capacity, ttl))));
+        self
+    }
+
+    /// Split the batches of MAC, encryption and decryption operations into
+    /// KMIP requests of at most `chunk_size` operations.
+    #[must_use]
+    pub const fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Send at most `max_parallel_requests` KMIP requests of a batch at once.
+    #[must_use]
+    pub const fn with_max_parallel_requests(mut self, max_parallel_requests: usize) -> Self {
+        self.max_parallel_requests = max_parallel_requests;
+        self
+    }
+
+    /// Send the KMIP requests of a batch, at most `max_parallel_requests` at
+    /// once, and concatenate the results extracted from their responses in
+    /// the order of the requests.
+    async fn send_requests<T>(
+        &self,
+        requests: Vec<RequestMessage>,
+        extract: impl Fn(&ResponseMessage) -> ClientResult<Vec<T>>,
+    ) -> ClientResult<Vec<T>> {
+        trace!("sending {} KMIP requests", requests.len());
+        let responses = stream::iter(requests)
+            .map(|request| self.kms_client.message(request))
+            .buffered(self.max_parallel_requests.max(1))
+            .try_collect::<Vec<_>>()
+            .await?;
+        let mut results = Vec::new();
+        for response in &responses {
+            results.extend(extract(response)?);
         }
+        Ok(results)
     }
 
     fn extract_words(message_response: &ResponseMessage) -> ClientResult<Vec<[u8; WORD_LENGTH]>> {
@@ -69,16 +133,65 @@ impl<
         }
     }
 
-    /// Compute multiple HMAC on given memory addresses.
+    /// Compute multiple HMAC on given memory addresses, the cached ones
+    /// excepted.
     pub(crate) async fn batch_permute<'a>(
         &self,
         addresses: impl Iterator<Item = &'a Memory::Address>,
+    ) -> ClientResult<Vec<Memory::Address>> {
+        let Some(token_cache) = &self.token_cache else {
+            return self.kms_permute(addresses).await;
+        };
+
+        let addresses = addresses.collect::<Vec<_>>();
+        let mut tokens = {
+            let mut cache = token_cache.lock();
+            addresses
+                .iter()
+                .map(|address| cache.get(&SecretAddress::from(*address)).map(Address::from))
+                .collect::<Vec<_>>()
+        };
+        let misses = addresses
+            .iter()
+            .zip(&tokens)
+            .filter(|(_, token)| token.is_none())
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        trace!(
+            "hmac: {} cached tokens, {} tokens to compute",
+            addresses.len() - misses.len(),
+            misses.len()
+        );
+
+        if !misses.is_empty() {
+            let computed = self.kms_permute(misses.iter().copied()).await?;
+            let mut cache = token_cache.lock();
+            for (address, token) in misses.into_iter().zip(&computed) {
+                cache.insert(SecretAddress::from(address), SecretAddress::from(token));
+            }
+            drop(cache);
+            let mut computed = computed.into_iter();
+            for token in tokens.iter_mut().filter(|token| token.is_none()) {
+                *token = computed.next();
+            }
+        }
+
+        tokens
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ClientError::Default("missing HMAC in the KMS response".to_owned()))
+    }
+
+    /// Compute multiple HMAC on given memory addresses with the KMS.
+    async fn kms_permute<'a>(
+        &self,
+        addresses: impl Iterator<Item = &'a Memory::Address>,
     ) -> ClientResult<Vec<Memory::Address>> {
         let tokens = self
-            .kms_client
-            .message(self.build_mac_message_request(addresses)?)
+            .send_requests(self.build_mac_message_requests(addresses)?, |response| {
+                Ok(response.extract_items_data()?)
+            })
             .await?
-            .extract_items_data()?
             .into_iter()
             .map(|mac| {
                 // Truncate to the first ADDRESS_LENGTH bytes
@@ -102,12 +215,11 @@ impl<
         &self,
         bindings: impl Iterator<Item = (&'a Memory::Address, &'a [u8; WORD_LENGTH])>,
     ) -> ClientResult<Vec<[u8; WORD_LENGTH]>> {
-        Self::extract_words(
-            &self
-                .kms_client
-                .message(self.build_encrypt_message_request(bindings)?)
-                .await?,
+        self.send_requests(
+            self.build_encrypt_message_requests(bindings)?,
+            Self::extract_words,
         )
+        .await
     }
 
     /// Decrypts these ciphertexts using the given addresses as tweak.
@@ -115,11 +227,10 @@ impl<
         &self,
         bindings: impl Iterator<Item = (&'a Memory::Address, &'a [u8; WORD_LENGTH])>,
     ) -> ClientResult<Vec<[u8; WORD_LENGTH]>> {
-        Self::extract_words(
-            &self
-                .kms_client
-                .message(self.build_decrypt_message_request(bindings)?)
-                .await?,
+        self.send_requests(
+            self.build_decrypt_message_requests(bindings)?,
+            Self::extract_words,
         )
+        .await
     }
 }
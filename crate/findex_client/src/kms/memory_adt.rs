@@ -393,6 +393,77 @@ mod tests {
         Ok(())
     }
 
+    /// Ensures the batches split into several KMIP requests, sent in
+    /// parallel, are reassembled in order.
+    #[tokio::test]
+    async fn test_chunked_batches() -> ClientResult<()> {
+        let mut rng = CsRng::from_entropy();
+        let ctx = start_default_test_kms_server().await;
+        let layer = create_test_layer(ctx.owner_client_config.clone())
+            .await?
+            .with_chunk_size(7)
+            .with_max_parallel_requests(3);
+
+        let header_addr = Address::<ADDRESS_LENGTH>::random(&mut rng);
+        let bindings = (0..100_u8)
+            .map(|i| {
+                (
+                    Address::<ADDRESS_LENGTH>::random(&mut rng),
+                    [i; CUSTOM_WORD_LENGTH],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            layer
+                .build_mac_message_requests(bindings.iter().map(|(a, _)| a))?
+                .len(),
+            15
+        );
+
+        assert_eq!(
+            layer
+                .guarded_write((header_addr, None), bindings.clone())
+                .await?,
+            None
+        );
+        assert_eq!(
+            layer
+                .batch_read(bindings.iter().map(|(a, _)| *a).collect())
+                .await?,
+            bindings
+                .into_iter()
+                .map(|(_, w)| Some(w))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    /// Ensures the cached permutations are the ones computed by the KMS.
+    #[tokio::test]
+    async fn test_token_cache() -> ClientResult<()> {
+        let mut rng = CsRng::from_entropy();
+        let ctx = start_default_test_kms_server().await;
+        let layer =
+            create_test_layer::<CUSTOM_WORD_LENGTH>(ctx.owner_client_config.clone()).await?;
+        let cached_layer = layer.clone().with_token_cache(2, None);
+
+        let addresses = (0..3)
+            .map(|_| Address::<ADDRESS_LENGTH>::random(&mut rng))
+            .collect::<Vec<_>>();
+        let tokens = layer.batch_permute(addresses.iter()).await?;
+        for _ in 0..2 {
+            assert_eq!(cached_layer.batch_permute(addresses.iter()).await?, tokens);
+        }
+        assert_eq!(
+            cached_layer
+                .token_cache
+                .as_ref()
+                .map(|cache| cache.lock().len()),
+            Some(2)
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sequential_read_write() -> ClientResult<()> {
         log_init(None);
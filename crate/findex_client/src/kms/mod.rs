@@ -1,5 +1,6 @@
 mod encryption_layer;
 mod memory_adt;
 mod requests;
+mod token_cache;
 
 pub use encryption_layer::KmsEncryptionLayer;
@@ -20,22 +20,34 @@ impl<
     Memory: Send + Sync + MemoryADT<Address = Address<ADDRESS_LENGTH>, Word = [u8; WORD_LENGTH]>,
 > KmsEncryptionLayer<WORD_LENGTH, Memory>
 {
-    fn build_message_request(
+    /// Split the operations of a batch into requests of at most `chunk_size`
+    /// operations. The size of the responses being bounded by the chunking,
+    /// it is not capped.
+    fn build_message_requests(
+        &self,
         items: Vec<RequestMessageBatchItemVersioned>,
-    ) -> ClientResult<RequestMessage> {
-        let items_number = i32::try_from(items.len())?;
-        Ok(RequestMessage {
-            request_header: RequestMessageHeader {
-                protocol_version: ProtocolVersion {
-                    protocol_version_major: 2,
-                    protocol_version_minor: 1,
+    ) -> ClientResult<Vec<RequestMessage>> {
+        let mut items = items.into_iter().peekable();
+        let mut requests = Vec::new();
+        while items.peek().is_some() {
+            let chunk = items
+                .by_ref()
+                .take(self.chunk_size.max(1))
+                .collect::<Vec<_>>();
+            requests.push(RequestMessage {
+                request_header: RequestMessageHeader {
+                    protocol_version: ProtocolVersion {
+                        protocol_version_major: 2,
+                        protocol_version_minor: 1,
+                    },
+                    maximum_response_size: None,
+                    batch_count: i32::try_from(chunk.len())?,
+                    ..Default::default()
                 },
-                maximum_response_size: Some(9999),
-                batch_count: items_number,
-                ..Default::default()
-            },
-            batch_item: items,
-        })
+                batch_item: chunk,
+            });
+        }
+        Ok(requests)
     }
 
     fn build_mac_request(&self, data: Vec<u8>) -> MAC {
@@ -50,10 +62,10 @@ impl<
         }
     }
 
-    pub(crate) fn build_mac_message_request<'a>(
+    pub(crate) fn build_mac_message_requests<'a>(
         &self,
         addresses: impl Iterator<Item = &'a Memory::Address>,
-    ) -> ClientResult<RequestMessage> {
+    ) -> ClientResult<Vec<RequestMessage>> {
         let items = addresses
             .map(|address| {
                 RequestMessageBatchItemVersioned::V21(RequestMessageBatchItem::new(Operation::MAC(
@@ -61,7 +73,7 @@ impl<
                 )))
             })
             .collect();
-        Self::build_message_request(items)
+        self.build_message_requests(items)
     }
 
     fn build_encrypt_request(&self, plaintext: Vec<u8>, nonce: Vec<u8>) -> ClientResult<Encrypt> {
@@ -79,10 +91,10 @@ impl<
         )?)
     }
 
-    pub(crate) fn build_encrypt_message_request<'a>(
+    pub(crate) fn build_encrypt_message_requests<'a>(
         &self,
         bindings: impl Iterator<Item = (&'a Memory::Address, &'a [u8; WORD_LENGTH])>,
-    ) -> ClientResult<RequestMessage> {
+    ) -> ClientResult<Vec<RequestMessage>> {
         let items = bindings
             .map(|(address, word)| {
                 self.build_encrypt_request(word.to_vec(), address.to_vec())
@@ -93,7 +105,7 @@ impl<
                     })
             })
             .collect::<Result<Vec<_>, _>>()?;
-        Self::build_message_request(items)
+        self.build_message_requests(items)
     }
 
     fn build_decrypt_request(&self, ciphertext: Vec<u8>, nonce: Vec<u8>) -> Decrypt {
@@ -110,10 +122,10 @@ impl<
         }
     }
 
-    pub(crate) fn build_decrypt_message_request<'a>(
+    pub(crate) fn build_decrypt_message_requests<'a>(
         &self,
         bindings: impl Iterator<Item = (&'a Memory::Address, &'a [u8; WORD_LENGTH])>,
-    ) -> ClientResult<RequestMessage> {
+    ) -> ClientResult<Vec<RequestMessage>> {
         let items = bindings
             .map(|(address, word)| {
                 RequestMessageBatchItemVersioned::V21(RequestMessageBatchItem::new(
@@ -123,6 +135,6 @@ impl<
                 ))
             })
             .collect::<Vec<_>>();
-        Self::build_message_request(items)
+        self.build_message_requests(items)
     }
 }
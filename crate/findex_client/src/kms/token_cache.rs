@@ -0,0 +1,43 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use zeroize::Zeroize;
+
+use crate::lru::Lru;
+
+/// An address zeroized when dropped.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SecretAddress([u8; ADDRESS_LENGTH]);
+
+impl From<&Address<ADDRESS_LENGTH>> for SecretAddress {
+    fn from(address: &Address<ADDRESS_LENGTH>) -> Self {
+        Self(**address)
+    }
+}
+
+impl From<&SecretAddress> for Address<ADDRESS_LENGTH> {
+    fn from(address: &SecretAddress) -> Self {
+        Self::from(address.0)
+    }
+}
+
+impl Drop for SecretAddress {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// The addresses permuted by the KMS, by plaintext address. The addresses are
+/// zeroized when they are evicted, and when the cache is dropped.
+pub(crate) struct TokenCache(Mutex<Lru<SecretAddress, SecretAddress>>);
+
+impl TokenCache {
+    pub(crate) const fn new(lru: Lru<SecretAddress, SecretAddress>) -> Self {
+        Self(Mutex::new(lru))
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Lru<SecretAddress, SecretAddress>> {
+        // The cache is consistent between the statements holding the lock.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
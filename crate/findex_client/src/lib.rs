@@ -44,22 +44,47 @@
     clippy::redundant_pub_crate
 )]
 
+mod batching_memory;
+mod caching_memory;
+mod compression;
 mod config;
 mod datasets;
 mod error;
+mod expansion;
+mod findex_client;
+mod findex_grpc_client;
 mod findex_rest_client;
+mod indexes;
 mod kms;
+mod lru;
 mod permissions;
+mod query;
 mod rest_client;
+mod retry;
+mod streams;
+mod tokenizer;
 
+pub use batching_memory::{BatchingMemory, MergedReadError};
+pub use caching_memory::CachingMemory;
+pub use compression::Compression;
 pub use config::RestClientConfig;
+pub use datasets::{EntriesPage, EntryInfo, StagedEntries};
 pub use error::{ClientError, result::ClientResult};
+pub use expansion::KeywordExpansion;
+pub use findex_client::{
+    DatasetEntry, EntryEncryption, EntryKey, FindexClient, FindexKeys, IndexSettings, SearchMode,
+};
+pub use findex_grpc_client::{FindexGrpcClient, GuardedWrite};
 pub use findex_rest_client::FindexRestClient;
 pub use kms::KmsEncryptionLayer;
+pub use query::{Query, Term};
 pub use rest_client::RestClient;
+pub use retry::RetryPolicy;
+pub use tokenizer::{Language, Splitting, Tokenizer};
 
 pub mod reexport {
     pub use cosmian_findex;
     pub use cosmian_findex_structs;
     pub use cosmian_http_client;
+    pub use tonic;
 }
@@ -0,0 +1,133 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// A cached value, with its position in the recency order.
+struct Entry<Value> {
+    value: Value,
+    tick: u64,
+    cached_at: Instant,
+}
+
+/// A map holding at most `capacity` entries, evicting the least recently used
+/// ones, and the ones older than its time-to-live, if any.
+pub(crate) struct Lru<Key, Value> {
+    capacity: usize,
+    pub(crate) ttl: Option<Duration>,
+    entries: HashMap<Key, Entry<Value>>,
+    /// The cached keys, from the least to the most recently used
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+}
+
+impl<Key: Hash + Eq + Clone, Value> Lru<Key, Value> {
+    pub(crate) fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get the value of a key, marking it as the most recently used.
+    pub(crate) fn get(&mut self, key: &Key) -> Option<&Value> {
+        let cached_at = self.entries.get(key)?.cached_at;
+        if self.ttl.is_some_and(|ttl| cached_at.elapsed() > ttl) {
+            self.remove(key);
+            return None;
+        }
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.tick);
+        self.tick += 1;
+        entry.tick = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(&entry.value)
+    }
+
+    pub(crate) fn insert(&mut self, key: Key, value: Value) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        let entry = Entry {
+            value,
+            tick: self.tick,
+            cached_at: Instant::now(),
+        };
+        if let Some(previous) = self.entries.insert(key, entry) {
+            self.order.remove(&previous.tick);
+        }
+        while self.entries.len() > self.capacity {
+            let Some((_, evicted)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&evicted);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Lru;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(2, None);
+        lru.insert(1, "one");
+        lru.insert(2, "two");
+        // Reading a key makes it the most recently used
+        assert_eq!(lru.get(&1), Some(&"one"));
+        lru.insert(3, "three");
+        assert_eq!(lru.len(), 2);
+        assert_eq!(lru.get(&2), None);
+        assert_eq!(lru.get(&1), Some(&"one"));
+        assert_eq!(lru.get(&3), Some(&"three"));
+
+        // Overwriting a key does not evict another one
+        lru.insert(3, "trois");
+        assert_eq!(lru.get(&1), Some(&"one"));
+        assert_eq!(lru.get(&3), Some(&"trois"));
+
+        lru.remove(&1);
+        assert_eq!(lru.get(&1), None);
+        lru.clear();
+        assert_eq!(lru.len(), 0);
+
+        // An empty cache holds nothing
+        let mut lru = Lru::new(0, None);
+        lru.insert(1, "one");
+        assert_eq!(lru.get(&1), None);
+    }
+
+    #[test]
+    fn test_lru_ttl() {
+        let mut lru = Lru::new(2, Some(Duration::ZERO));
+        lru.insert(1, "one");
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(lru.get(&1), None);
+        assert_eq!(lru.len(), 0);
+    }
+}
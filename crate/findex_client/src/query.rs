@@ -0,0 +1,503 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    future::Future,
+    hash::Hash,
+    iter::Peekable,
+    str::{Chars, FromStr},
+};
+
+use cosmian_findex_structs::Keyword;
+use futures::{
+    FutureExt, StreamExt, TryStreamExt,
+    future::{BoxFuture, Either, select},
+    stream::FuturesUnordered,
+};
+
+use crate::ClientError;
+
+/// A boolean query over the indexed keywords.
+///
+/// It is parsed from a string such as `(alice OR bob) AND paris NOT archived`,
+/// `NOT` excluding the results of the query following it from the results of
+/// the query preceding it. `AND` binds tighter than `OR`, which binds tighter
+/// than `NOT`; parentheses group sub-queries and double quotes group the
+/// keywords containing spaces, parentheses or operators. Since the index
+/// cannot list all its values, a query cannot start with `NOT`.
+///
+/// The parsed keywords are `Term`s, which the quotes and the backslashes
+/// escaping a character mark as literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query<Term = Keyword> {
+    Keyword(Term),
+    /// The values matching all the queries
+    And(Vec<Self>),
+    /// The values matching any of the queries
+    Or(Vec<Self>),
+    /// The values matching the base query but not the excluded one
+    Not {
+        base: Box<Self>,
+        excluded: Box<Self>,
+    },
+}
+
+/// A keyword of a parsed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    /// The keyword, without its quotes and escape characters
+    pub text: String,
+    /// Whether the keyword is quoted or escapes a character, in which case it
+    /// is searched as is, and not as a prefix or phonetic search
+    pub literal: bool,
+}
+
+impl Term {
+    /// A term whose prefix or phonetic search operators are interpreted
+    #[must_use]
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            literal: false,
+        }
+    }
+
+    /// A term searched as is
+    #[must_use]
+    pub fn literal(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            literal: true,
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl<Term> Query<Term> {
+    /// Map the keywords of the query, e.g. to normalize them.
+    #[must_use]
+    pub fn map<Other>(self, f: &mut impl FnMut(Term) -> Other) -> Query<Other> {
+        match self {
+            Self::Keyword(term) => Query::Keyword(f(term)),
+            Self::And(queries) => Query::And(queries.into_iter().map(|q| q.map(f)).collect()),
+            Self::Or(queries) => Query::Or(queries.into_iter().map(|q| q.map(f)).collect()),
+            Self::Not { base, excluded } => Query::Not {
+                base: Box::new(base.map(f)),
+                excluded: Box::new(excluded.map(f)),
+            },
+        }
+    }
+
+    /// Replace each keyword of the query by a sub-query, e.g. searching all
+    /// the words of a term, failing if any of the replacements fails.
+    ///
+    /// # Errors
+    /// - If the replacement of a keyword fails
+    pub fn try_flat_map<Other, Error>(
+        self,
+        f: &mut impl FnMut(Term) -> Result<Query<Other>, Error>,
+    ) -> Result<Query<Other>, Error> {
+        Ok(match self {
+            Self::Keyword(term) => f(term)?,
+            Self::And(queries) => Query::And(
+                queries
+                    .into_iter()
+                    .map(|q| q.try_flat_map(f))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Self::Or(queries) => Query::Or(
+                queries
+                    .into_iter()
+                    .map(|q| q.try_flat_map(f))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Self::Not { base, excluded } => Query::Not {
+                base: Box::new(base.try_flat_map(f)?),
+                excluded: Box::new(excluded.try_flat_map(f)?),
+            },
+        })
+    }
+
+    /// The keywords of the query.
+    #[must_use]
+    pub fn keywords(&self) -> Vec<&Term> {
+        match self {
+            Self::Keyword(term) => vec![term],
+            Self::And(queries) | Self::Or(queries) => {
+                queries.iter().flat_map(Self::keywords).collect()
+            }
+            Self::Not { base, excluded } => {
+                let mut keywords = base.keywords();
+                keywords.extend(excluded.keywords());
+                keywords
+            }
+        }
+    }
+}
+
+impl<Term: Sync> Query<Term> {
+    /// Evaluate the query, looking its keywords up concurrently.
+    ///
+    /// The lookups which can no longer change the results are cancelled: the
+    /// other operands of an `AND` once a result is empty, and the excluded
+    /// query of a `NOT` once the base results are empty.
+    pub(crate) fn evaluate<'a, Value, Error, Lookup, LookupFuture>(
+        &'a self,
+        lookup: &'a Lookup,
+    ) -> BoxFuture<'a, Result<HashSet<Value>, Error>>
+    where
+        Value: Hash + Eq + Send + 'a,
+        Error: Send + 'a,
+        Lookup: Fn(&'a Term) -> LookupFuture + Sync,
+        LookupFuture: Future<Output = Result<HashSet<Value>, Error>> + Send + 'a,
+    {
+        match self {
+            Self::Keyword(term) => lookup(term).boxed(),
+            Self::And(queries) => async move {
+                let mut results = queries
+                    .iter()
+                    .map(|query| query.evaluate(lookup))
+                    .collect::<FuturesUnordered<_>>();
+                let Some(mut acc) = results.next().await.transpose()? else {
+                    return Ok(HashSet::new());
+                };
+                // The empty set is the fixed point of the intersection.
+                while !acc.is_empty() {
+                    let Some(next) = results.next().await.transpose()? else {
+                        break;
+                    };
+                    acc.retain(|value| next.contains(value));
+                }
+                Ok(acc)
+            }
+            .boxed(),
+            Self::Or(queries) => queries
+                .iter()
+                .map(|query| query.evaluate(lookup))
+                .collect::<FuturesUnordered<_>>()
+                .try_fold(HashSet::new(), |mut acc, next| async move {
+                    acc.extend(next);
+                    Ok(acc)
+                })
+                .boxed(),
+            Self::Not { base, excluded } => async move {
+                let (mut base, excluded) =
+                    match select(base.evaluate(lookup), excluded.evaluate(lookup)).await {
+                        Either::Left((base, excluded)) => {
+                            let base = base?;
+                            if base.is_empty() {
+                                return Ok(base);
+                            }
+                            (base, excluded.await?)
+                        }
+                        Either::Right((excluded, base)) => (base.await?, excluded?),
+                    };
+                base.retain(|value| !excluded.contains(value));
+                Ok(base)
+            }
+            .boxed(),
+        }
+    }
+}
+
+impl<Term: Display> Display for Query<Term> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn join<Term: Display>(
+            f: &mut fmt::Formatter<'_>,
+            queries: &[Query<Term>],
+            operator: &str,
+        ) -> fmt::Result {
+            write!(f, "(")?;
+            for (i, query) in queries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {operator} ")?;
+                }
+                write!(f, "{query}")?;
+            }
+            write!(f, ")")
+        }
+
+        match self {
+            Self::Keyword(term) => write!(f, "\"{term}\""),
+            Self::And(queries) => join(f, queries, "AND"),
+            Self::Or(queries) => join(f, queries, "OR"),
+            Self::Not { base, excluded } => write!(f, "({base} NOT {excluded})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(Term),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open => write!(f, "'('"),
+            Self::Close => write!(f, "')'"),
+            Self::And => write!(f, "AND"),
+            Self::Or => write!(f, "OR"),
+            Self::Not => write!(f, "NOT"),
+            Self::Term(term) => write!(f, "\"{term}\""),
+        }
+    }
+}
+
+fn invalid(message: impl Display) -> ClientError {
+    ClientError::InvalidQuery(message.to_string())
+}
+
+fn escaped(chars: &mut Peekable<Chars<'_>>) -> Result<char, ClientError> {
+    chars
+        .next()
+        .ok_or_else(|| invalid("the query ends with an escape character"))
+}
+
+fn quoted_term(chars: &mut Peekable<Chars<'_>>) -> Result<Term, ClientError> {
+    let mut term = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(Term::literal(term)),
+            Some('\\') => term.push(escaped(chars)?),
+            Some(c) => term.push(c),
+            None => return Err(invalid("unterminated quoted keyword")),
+        }
+    }
+}
+
+/// An unquoted keyword, or operator, starting with the given character.
+fn word(first: char, chars: &mut Peekable<Chars<'_>>) -> Result<Token, ClientError> {
+    let mut word = String::new();
+    let mut literal = false;
+    let mut next = Some(first);
+    while let Some(c) = next {
+        if c == '\\' {
+            word.push(escaped(chars)?);
+            literal = true;
+        } else {
+            word.push(c);
+        }
+        next = chars.next_if(|c| !c.is_whitespace() && !"()\"".contains(*c));
+    }
+    Ok(match word.as_str() {
+        "AND" if !literal => Token::And,
+        "OR" if !literal => Token::Or,
+        "NOT" if !literal => Token::Not,
+        _ => Token::Term(Term {
+            text: word,
+            literal,
+        }),
+    })
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, ClientError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => tokens.push(Token::Term(quoted_term(&mut chars)?)),
+            c => tokens.push(word(c, &mut chars)?),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser, with a function per precedence level.
+struct Parser<Tokens: Iterator<Item = Token>>(Peekable<Tokens>);
+
+impl<Tokens: Iterator<Item = Token>> Parser<Tokens> {
+    fn difference(&mut self) -> Result<Query<Term>, ClientError> {
+        let mut query = self.disjunction()?;
+        while self.0.next_if_eq(&Token::Not).is_some() {
+            query = Query::Not {
+                base: Box::new(query),
+                excluded: Box::new(self.disjunction()?),
+            };
+        }
+        Ok(query)
+    }
+
+    fn disjunction(&mut self) -> Result<Query<Term>, ClientError> {
+        let mut queries = vec![self.conjunction()?];
+        while self.0.next_if_eq(&Token::Or).is_some() {
+            queries.push(self.conjunction()?);
+        }
+        Ok(Self::merge(queries, Query::Or))
+    }
+
+    fn conjunction(&mut self) -> Result<Query<Term>, ClientError> {
+        let mut queries = vec![self.operand()?];
+        while self.0.next_if_eq(&Token::And).is_some() {
+            queries.push(self.operand()?);
+        }
+        Ok(Self::merge(queries, Query::And))
+    }
+
+    fn operand(&mut self) -> Result<Query<Term>, ClientError> {
+        match self.0.next() {
+            Some(Token::Term(term)) => Ok(Query::Keyword(term)),
+            Some(Token::Open) => {
+                let query = self.difference()?;
+                match self.0.next() {
+                    Some(Token::Close) => Ok(query),
+                    Some(token) => Err(invalid(format!("expected ')', found {token}"))),
+                    None => Err(invalid("unclosed '('")),
+                }
+            }
+            Some(Token::Not) => Err(invalid(
+                "NOT must follow the query whose results it excludes",
+            )),
+            Some(token) => Err(invalid(format!("expected a keyword, found {token}"))),
+            None => Err(invalid("expected a keyword, found the end of the query")),
+        }
+    }
+
+    fn merge(
+        queries: Vec<Query<Term>>,
+        operator: fn(Vec<Query<Term>>) -> Query<Term>,
+    ) -> Query<Term> {
+        match <[_; 1]>::try_from(queries) {
+            Ok([query]) => query,
+            Err(queries) => operator(queries),
+        }
+    }
+}
+
+impl FromStr for Query<Term> {
+    type Err = ClientError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser(tokenize(query)?.into_iter().peekable());
+        let query = parser.difference()?;
+        if let Some(token) = parser.0.next() {
+            return Err(invalid(format!(
+                "unexpected {token}, the keywords must be separated by an operator"
+            )));
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        future,
+    };
+
+    use futures::future::{BoxFuture, FutureExt};
+
+    use super::{Query, Term};
+    use crate::ClientResult;
+
+    fn keyword(term: &str) -> Query<Term> {
+        Query::Keyword(Term::new(term))
+    }
+
+    fn literal(term: &str) -> Query<Term> {
+        Query::Keyword(Term::literal(term))
+    }
+
+    #[test]
+    fn test_parse_query() -> ClientResult<()> {
+        assert_eq!(
+            "(alice OR bob) AND paris NOT archived".parse::<Query<Term>>()?,
+            Query::Not {
+                base: Box::new(Query::And(vec![
+                    Query::Or(vec![keyword("alice"), keyword("bob")]),
+                    keyword("paris"),
+                ])),
+                excluded: Box::new(keyword("archived")),
+            }
+        );
+        // AND binds tighter than OR, which binds tighter than NOT
+        assert_eq!(
+            "a OR b AND c NOT d OR e".parse::<Query<Term>>()?,
+            Query::Not {
+                base: Box::new(Query::Or(vec![
+                    keyword("a"),
+                    Query::And(vec![keyword("b"), keyword("c")]),
+                ])),
+                excluded: Box::new(Query::Or(vec![keyword("d"), keyword("e")])),
+            }
+        );
+        assert_eq!(
+            r#" "new york" AND "OR" AND "say \"hi\"" AND and"#.parse::<Query<Term>>()?,
+            Query::And(vec![
+                literal("new york"),
+                literal("OR"),
+                literal("say \"hi\""),
+                keyword("and"),
+            ])
+        );
+        // The quoted and escaped keywords are literal
+        assert_eq!(
+            r#"rob* AND "rob*" AND rob\* AND \~rob AND \NOT AND a\ b"#.parse::<Query<Term>>()?,
+            Query::And(vec![
+                keyword("rob*"),
+                literal("rob*"),
+                literal("rob*"),
+                literal("~rob"),
+                literal("NOT"),
+                literal("a b"),
+            ])
+        );
+        assert_eq!(
+            "a NOT (b NOT c)".parse::<Query<Term>>()?.to_string(),
+            r#"("a" NOT ("b" NOT "c"))"#
+        );
+
+        for invalid in [
+            "", "NOT a", "a b", "a AND", "(a OR b", "a OR b)", "()", "\"a", "a\\",
+        ] {
+            assert!(invalid.parse::<Query<Term>>().is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_query() -> ClientResult<()> {
+        let index = HashMap::from([
+            ("alice", HashSet::from([1, 2, 3])),
+            ("bob", HashSet::from([4, 5])),
+            ("paris", HashSet::from([1, 2, 4, 6])),
+            ("archived", HashSet::from([2])),
+        ]);
+        // The keywords which are not indexed are never found: their lookup
+        // must be cancelled.
+        let lookup = |term: &Term| -> BoxFuture<'static, ClientResult<HashSet<i32>>> {
+            index.get(term.text.as_str()).map_or_else(
+                || future::pending().boxed(),
+                |values| future::ready(Ok(values.clone())).boxed(),
+            )
+        };
+
+        let query = "(alice OR bob) AND paris NOT archived".parse::<Query<Term>>()?;
+        assert_eq!(query.evaluate(&lookup).await?, HashSet::from([1, 4]));
+        let query = "bob AND archived AND pending".parse::<Query<Term>>()?;
+        assert_eq!(query.evaluate(&lookup).await?, HashSet::new());
+        let query = "(bob AND archived) NOT pending".parse::<Query<Term>>()?;
+        assert_eq!(query.evaluate(&lookup).await?, HashSet::new());
+        let query = "alice NOT paris NOT bob".parse::<Query<Term>>()?;
+        assert_eq!(query.evaluate(&lookup).await?, HashSet::from([3]));
+        assert_eq!(
+            query.map(&mut |term| term.text.len()).keywords(),
+            [&5, &5, &3]
+        );
+        Ok(())
+    }
+}
@@ -1,17 +1,22 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use cosmian_http_client::HttpClient;
-use reqwest::{Response, StatusCode};
+use reqwest::{
+    RequestBuilder, Response, StatusCode,
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+};
 use serde::{Deserialize, Serialize};
-use tracing::trace;
+use tracing::{trace, warn};
 use uuid::Uuid;
 
 use crate::{
+    compression::decompress,
     config::RestClientConfig,
     error::{
         ClientError,
         result::{ClientResult, FindexRestClientResultHelper},
     },
+    retry::{RetrySafety, RetryState},
 };
 
 // Response for success
@@ -31,6 +36,8 @@ impl Display for SuccessResponse {
 pub struct RestClient {
     pub http_client: HttpClient,
     pub config: RestClientConfig,
+    /// The retry budget and circuit breaker, shared by the clones
+    retry_state: Arc<RetryState>,
 }
 
 impl RestClient {
@@ -52,6 +59,7 @@ impl RestClient {
 
         Ok(Self {
             http_client: client,
+            retry_state: Arc::new(RetryState::new(config.retry.as_ref())),
             config,
         })
     }
@@ -71,6 +79,96 @@ impl RestClient {
             handle_error(endpoint, response).await?,
         ))
     }
+
+    /// Build a POST request with a binary body, which is compressed if the
+    /// compression is enabled.
+    pub(crate) fn post_bytes(
+        &self,
+        server_url: &str,
+        body: Vec<u8>,
+    ) -> ClientResult<RequestBuilder> {
+        let request = self.http_client.client.post(server_url);
+        Ok(match self.config.compression {
+            Some(compression) => request
+                .header(CONTENT_ENCODING, compression.encoding())
+                .body(compression.compress(&body)?),
+            None => request.body(body),
+        })
+    }
+
+    /// Ask the server to compress the binary response of the request, if the
+    /// compression is enabled. The response must then be read with
+    /// [`response_bytes`].
+    pub(crate) fn accept_compressed(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.config.compression {
+            Some(compression) => request.header(ACCEPT_ENCODING, compression.encoding()),
+            None => request,
+        }
+    }
+
+    /// Send a request, retrying it on the transient failures which are safe to
+    /// retry, if the retries are enabled. The circuit breaker then fails the
+    /// request immediately while the server is down.
+    pub(crate) async fn send_with_retry(
+        &self,
+        request: RequestBuilder,
+        safety: RetrySafety,
+    ) -> ClientResult<Response> {
+        let Some(policy) = &self.config.retry else {
+            return Ok(request.send().await?);
+        };
+        self.retry_state.deposit(policy);
+
+        let mut retries = 0;
+        loop {
+            self.retry_state.check_circuit()?;
+            // A request with a streamed body cannot be sent twice
+            let Some(attempt) = request.try_clone() else {
+                return Ok(request.send().await?);
+            };
+            let result = attempt.send().await;
+            let retry = match &result {
+                Ok(response) => {
+                    self.retry_state
+                        .record(policy, response.status().is_server_error());
+                    safety.retries_status(response.status())
+                }
+                Err(e) => {
+                    self.retry_state.record(policy, true);
+                    safety.retries_error(e)
+                }
+            };
+            if !retry || retries >= policy.max_retries || !self.retry_state.withdraw() {
+                return Ok(result?);
+            }
+
+            retries += 1;
+            let backoff = policy.backoff(retries);
+            warn!(
+                "request failed ({}), retry {retries} in {} ms",
+                match &result {
+                    Ok(response) => response.status().to_string(),
+                    Err(e) => e.to_string(),
+                },
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
+}
+
+/// Read the binary body of a response, decompressing it if needed.
+pub(crate) async fn response_bytes(response: Response) -> ClientResult<Vec<u8>> {
+    let encoding = content_encoding(&response);
+    decompress(encoding.as_deref(), response.bytes().await?.to_vec())
+}
+
+fn content_encoding(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|encoding| encoding.to_str().ok())
+        .map(str::to_owned)
 }
 
 /// Handle the status code of the response.
@@ -98,7 +196,8 @@ pub(crate) async fn handle_error(
 ) -> Result<String, ClientError> {
     trace!("Error response received on {endpoint}: Response: {response:?}");
     let status = response.status();
-    let text = response.text().await?;
+    // The error of a request accepting a compressed response may be compressed.
+    let text = String::from_utf8_lossy(&response_bytes(response).await?).into_owned();
 
     Ok(format!(
         "{}: {}",
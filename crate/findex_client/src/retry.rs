@@ -0,0 +1,297 @@
+//! The retries of the requests failing on a transient error, and the circuit
+//! breaker failing the requests fast while the server is down.
+//!
+//! A request is only retried when it is safe to do so: reading is always safe,
+//! while a write is either sent with an `Idempotency-Key` header, the server
+//! answering its retries with the original response, or only retried when it
+//! is known not to have reached the server.
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use reqwest::{Error, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ClientError, result::ClientResult};
+
+/// The retry policy of the Findex memory requests, `batch_read` and
+/// `guarded_write`. The missing fields take their default value.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// The maximum number of retries of a request
+    pub max_retries: u32,
+    /// The delay before the first retry, in milliseconds. It doubles at each
+    /// retry, and a random jitter of up to half of it is applied.
+    pub initial_backoff_ms: u64,
+    /// The maximum delay between two retries, in milliseconds
+    pub max_backoff_ms: u64,
+    /// The retry budget: the number of retries earned by each request, in
+    /// percents. The retries stop when the budget is spent, so that a failing
+    /// server is not flooded by the retries of all the requests.
+    pub budget_percent: u32,
+    /// The number of retries the budget can hold, and starts with
+    pub budget_reserve: u32,
+    /// Send the guarded writes with an `Idempotency-Key` header, so that they
+    /// can be retried whatever the failure. The server must support it. When
+    /// disabled, a guarded write is only retried when it could not be sent.
+    pub idempotent_writes: bool,
+    /// The number of consecutive failures of the server which opens the
+    /// circuit: the requests then fail immediately. `0` disables the circuit
+    /// breaker.
+    pub breaker_threshold: u32,
+    /// The time, in milliseconds, after which the requests are sent again to
+    /// a server whose circuit is open
+    pub breaker_cooldown_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 10_000,
+            budget_percent: 20,
+            budget_reserve: 10,
+            idempotent_writes: true,
+            breaker_threshold: 10,
+            breaker_cooldown_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the given retry, starting at 1.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let backoff = 1_u64
+            .checked_shl(retry.saturating_sub(1))
+            .map_or(u64::MAX, |factor| {
+                self.initial_backoff_ms.saturating_mul(factor)
+            })
+            .min(self.max_backoff_ms);
+        let half = backoff / 2;
+        Duration::from_millis(half + jitter(backoff - half))
+    }
+}
+
+/// A random number between `0` and `max`, included.
+fn jitter(max: u64) -> u64 {
+    // Each `RandomState` is randomly seeded, which is enough for a jitter.
+    RandomState::new().build_hasher().finish() % max.saturating_add(1)
+}
+
+/// How safe it is to retry a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RetrySafety {
+    /// The request reads and can always be retried.
+    Read,
+    /// The request writes and is sent with an idempotency key: its retries
+    /// get the response of the original request.
+    IdempotentWrite,
+    /// The request writes without an idempotency key: it can only be retried
+    /// when it did not reach the server.
+    Write,
+}
+
+impl RetrySafety {
+    pub(crate) fn retries_error(self, error: &Error) -> bool {
+        match self {
+            Self::Read | Self::IdempotentWrite => true,
+            Self::Write => error.is_connect(),
+        }
+    }
+
+    pub(crate) fn retries_status(self, status: StatusCode) -> bool {
+        let transient = (status.is_server_error()
+            && status != StatusCode::NOT_IMPLEMENTED
+            && status != StatusCode::HTTP_VERSION_NOT_SUPPORTED)
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS;
+        match self {
+            Self::Read => transient,
+            // The original request is still running
+            Self::IdempotentWrite => transient || status == StatusCode::CONFLICT,
+            Self::Write => false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The retry budget, in hundredths of a retry
+    budget: u64,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// The retry budget and circuit breaker, shared by the clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct RetryState(Mutex<State>);
+
+impl RetryState {
+    pub(crate) fn new(policy: Option<&RetryPolicy>) -> Self {
+        Self(Mutex::new(State {
+            budget: policy.map_or(0, |p| u64::from(p.budget_reserve) * 100),
+            ..State::default()
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is consistent between the statements holding the lock.
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Earn the retries of a new request.
+    pub(crate) fn deposit(&self, policy: &RetryPolicy) {
+        let mut state = self.lock();
+        state.budget = (state.budget + u64::from(policy.budget_percent))
+            .min(u64::from(policy.budget_reserve) * 100);
+    }
+
+    /// Spend a retry, returning whether the budget allowed it.
+    pub(crate) fn withdraw(&self) -> bool {
+        let mut state = self.lock();
+        state.budget.checked_sub(100).is_some_and(|budget| {
+            state.budget = budget;
+            true
+        })
+    }
+
+    /// Fail if the circuit is open. Once the cooldown is over, the requests
+    /// are sent again, the next failure opening the circuit again.
+    pub(crate) fn check_circuit(&self) -> ClientResult<()> {
+        let (open_until, failures) = {
+            let state = self.lock();
+            (state.open_until, state.consecutive_failures)
+        };
+        match open_until {
+            Some(open_until) if Instant::now() < open_until => {
+                Err(ClientError::ServerUnavailable(format!(
+                    "the server failed {failures} consecutive requests, the requests are sent \
+                     again in {} ms",
+                    open_until
+                        .saturating_duration_since(Instant::now())
+                        .as_millis()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record whether the server failed a request.
+    pub(crate) fn record(&self, policy: &RetryPolicy, failed: bool) {
+        let mut state = self.lock();
+        if failed {
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            if policy.breaker_threshold > 0
+                && state.consecutive_failures >= policy.breaker_threshold
+            {
+                state.open_until =
+                    Some(Instant::now() + Duration::from_millis(policy.breaker_cooldown_ms));
+            }
+        } else {
+            state.consecutive_failures = 0;
+            state.open_until = None;
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+
+    use super::{RetryPolicy, RetrySafety, RetryState};
+    use crate::ClientResult;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            ..RetryPolicy::default()
+        };
+        for (retry, backoff) in [
+            (1, 100),
+            (2, 200),
+            (3, 400),
+            (4, 800),
+            (5, 1_000),
+            (80, 1_000),
+        ] {
+            let delay = policy.backoff(retry);
+            assert!(delay >= Duration::from_millis(backoff / 2));
+            assert!(delay <= Duration::from_millis(backoff));
+        }
+    }
+
+    #[test]
+    fn test_retry_safety() {
+        for status in [StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE] {
+            assert!(RetrySafety::Read.retries_status(status));
+            assert!(RetrySafety::IdempotentWrite.retries_status(status));
+            assert!(!RetrySafety::Write.retries_status(status));
+        }
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::NOT_IMPLEMENTED] {
+            assert!(!RetrySafety::Read.retries_status(status));
+        }
+        assert!(RetrySafety::IdempotentWrite.retries_status(StatusCode::CONFLICT));
+        assert!(!RetrySafety::Read.retries_status(StatusCode::CONFLICT));
+    }
+
+    #[test]
+    fn test_retry_budget() {
+        let policy = RetryPolicy {
+            budget_percent: 50,
+            budget_reserve: 2,
+            ..RetryPolicy::default()
+        };
+        let state = RetryState::new(Some(&policy));
+        assert!(state.withdraw());
+        assert!(state.withdraw());
+        assert!(!state.withdraw());
+        // Two requests earn a retry
+        state.deposit(&policy);
+        assert!(!state.withdraw());
+        state.deposit(&policy);
+        assert!(state.withdraw());
+        // The budget is capped by the reserve
+        for _ in 0..10 {
+            state.deposit(&policy);
+        }
+        assert!(state.withdraw());
+        assert!(state.withdraw());
+        assert!(!state.withdraw());
+    }
+
+    #[test]
+    fn test_circuit_breaker() -> ClientResult<()> {
+        let policy = RetryPolicy {
+            breaker_threshold: 2,
+            breaker_cooldown_ms: 50,
+            ..RetryPolicy::default()
+        };
+        let state = RetryState::new(Some(&policy));
+        state.record(&policy, true);
+        state.check_circuit()?;
+        // A success resets the count of consecutive failures
+        state.record(&policy, false);
+        state.record(&policy, true);
+        state.check_circuit()?;
+        state.record(&policy, true);
+        assert!(state.check_circuit().is_err());
+
+        // The requests are sent again after the cooldown, and a single
+        // failure opens the circuit again
+        std::thread::sleep(Duration::from_millis(60));
+        state.check_circuit()?;
+        state.record(&policy, true);
+        assert!(state.check_circuit().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+//! Streaming variants of the batch read and of the dataset routes: the
+//! batches are sent and received as length-prefixed frames, so that neither
+//! the whole request nor the whole response has to be held in memory.
+//!
+//! The frames are sent and read uncompressed, whatever the configured
+//! compression.
+use cosmian_findex_structs::{
+    Addresses, EncryptedEntries, FRAMED_CONTENT_TYPE, FrameDecoder, OptionalWords, StructsError,
+    Uuids, encode_frame,
+};
+use cosmian_kms_cli::reexport::cosmian_kms_crypto::reexport::cosmian_crypto_core::bytes_ser_de::Serializable;
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+use reqwest::{Body, Response, header::CONTENT_TYPE};
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use crate::{
+    RestClient,
+    error::{ClientError, result::ClientResult},
+    rest_client::{SuccessResponse, handle_error, handle_status_code},
+};
+
+/// Read the frames of a response body as they are received.
+fn read_frames(response: Response) -> impl Stream<Item = ClientResult<Vec<u8>>> {
+    stream::try_unfold(
+        (response.bytes_stream(), FrameDecoder::new()),
+        |(mut chunks, mut decoder)| async move {
+            loop {
+                if let Some(frame) = decoder.next_frame()? {
+                    return Ok(Some((frame, (chunks, decoder))));
+                }
+                let Some(chunk) = chunks.next().await else {
+                    decoder.finish()?;
+                    return Ok(None);
+                };
+                decoder.push(&chunk?);
+            }
+        },
+    )
+}
+
+impl RestClient {
+    /// Send a framed request body to the endpoint.
+    async fn post_frames<S>(&self, endpoint: &str, frames: S) -> ClientResult<Response>
+    where
+        S: Stream<Item = Result<Vec<u8>, StructsError>> + Send + 'static,
+    {
+        let server_url = format!("{}{endpoint}", self.http_client.server_url);
+        trace!("POST: {server_url}");
+        Ok(self
+            .http_client
+            .client
+            .post(server_url)
+            .header(CONTENT_TYPE, FRAMED_CONTENT_TYPE)
+            .body(Body::wrap_stream(
+                frames.map_ok(|payload| encode_frame(&payload)),
+            ))
+            .send()
+            .await?)
+    }
+
+    /// Read the words stored at batches of addresses. The words of each
+    /// batch are returned as soon as they are read, in the order of the
+    /// batches.
+    /// # Errors
+    /// Fails if the request cannot be sent; each batch of words fails if it
+    /// cannot be read.
+    #[instrument(err, skip(self, batches), level = "trace")]
+    pub async fn batch_read_stream<const WORD_LENGTH: usize, S>(
+        &self,
+        index_id: &Uuid,
+        batches: S,
+    ) -> ClientResult<
+        impl Stream<Item = ClientResult<Vec<Option<[u8; WORD_LENGTH]>>>> + use<WORD_LENGTH, S>,
+    >
+    where
+        S: Stream<Item = Vec<Address<ADDRESS_LENGTH>>> + Send + 'static,
+    {
+        let endpoint = format!("/indexes/{index_id}/batch_read/stream");
+        let frames = batches.map(|addresses| Addresses::new(addresses).serialize());
+        let response = self.post_frames(&endpoint, frames).await?;
+        if !response.status().is_success() {
+            return Err(ClientError::RequestFailed(
+                handle_error(&endpoint, response).await?,
+            ));
+        }
+
+        Ok(read_frames(response).and_then(|frame| async move {
+            Ok(OptionalWords::<WORD_LENGTH>::deserialize(&frame)?.into_inner())
+        }))
+    }
+
+    /// Add encrypted entries to a dataset, sent in batches as they are
+    /// produced. The batches received by the server are added even if the
+    /// upload fails afterward.
+    /// # Errors
+    /// Fails if the entries cannot be added to the dataset.
+    #[instrument(ret(Display), err, skip(self, batches), level = "trace")]
+    pub async fn add_entries_stream<S>(
+        &self,
+        index_id: &Uuid,
+        batches: S,
+    ) -> ClientResult<SuccessResponse>
+    where
+        S: Stream<Item = EncryptedEntries> + Send + 'static,
+    {
+        let endpoint = format!("/datasets/{index_id}/add_entries/stream");
+        let frames = batches.map(|entries| entries.serialize().map(|bytes| bytes.to_vec()));
+        let response = self.post_frames(&endpoint, frames).await?;
+
+        handle_status_code(response, &endpoint).await
+    }
+
+    /// Get the entries of batches of UUIDs from a dataset. The entries of
+    /// each batch are returned as soon as they are read, in the order of the
+    /// batches.
+    /// # Errors
+    /// Fails if the request cannot be sent; each batch of entries fails if
+    /// it cannot be read.
+    #[instrument(err, skip(self, batches), level = "trace")]
+    pub async fn get_entries_stream<S>(
+        &self,
+        index_id: &Uuid,
+        batches: S,
+    ) -> ClientResult<impl Stream<Item = ClientResult<EncryptedEntries>> + use<S>>
+    where
+        S: Stream<Item = Vec<Uuid>> + Send + 'static,
+    {
+        let endpoint = format!("/datasets/{index_id}/get_entries/stream");
+        let frames =
+            batches.map(|uuids| Uuids::from(uuids).serialize().map(|bytes| bytes.to_vec()));
+        let response = self.post_frames(&endpoint, frames).await?;
+        if !response.status().is_success() {
+            return Err(ClientError::RequestFailed(
+                handle_error(&endpoint, response).await?,
+            ));
+        }
+
+        Ok(read_frames(response)
+            .and_then(|frame| async move { Ok(EncryptedEntries::deserialize(&frame)?) }))
+    }
+}
@@ -0,0 +1,725 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use cosmian_findex_structs::Keyword;
+use rust_stemmers::{Algorithm, Stemmer};
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{ClientError, ClientResult, KeywordExpansion, Query, Term};
+
+/// The name of the default tokenizer, which has no step
+const DEFAULT_TOKENIZER: &str = "ascii-lowercase";
+
+/// The language of the stop words or of the stemming of a tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    French,
+    German,
+}
+
+impl Language {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::English => "english",
+            Self::French => "french",
+            Self::German => "german",
+        }
+    }
+
+    const fn algorithm(self) -> Algorithm {
+        match self {
+            Self::English => Algorithm::English,
+            Self::French => Algorithm::French,
+            Self::German => Algorithm::German,
+        }
+    }
+
+    /// The common words of the language, in lowercase, which are not worth
+    /// indexing.
+    const fn stop_words(self) -> &'static [&'static str] {
+        match self {
+            Self::English => ENGLISH_STOP_WORDS,
+            Self::French => FRENCH_STOP_WORDS,
+            Self::German => GERMAN_STOP_WORDS,
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "english" | "en" => Ok(Self::English),
+            "french" | "fr" => Ok(Self::French),
+            "german" | "de" => Ok(Self::German),
+            _ => Err(ClientError::Default(format!(
+                "unknown language `{s}`, expected english, french or german"
+            ))),
+        }
+    }
+}
+
+/// How a tokenizer splits a text into words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Splitting {
+    /// The whole text is a single word
+    #[default]
+    None,
+    /// The words are separated by whitespaces
+    Whitespace,
+    /// The words are the Unicode words of the text (UAX #29), also split at
+    /// the apostrophes of the elisions, e.g. `l'école`
+    Words,
+}
+
+/// The pipeline turning the texts into the keywords indexing them, and the
+/// query terms into the keywords to search.
+///
+/// The steps are applied in order: normalization, splitting, stop-words
+/// removal, stemming, accent stripping and field scoping. An index must be searched with the
+/// tokenizer it was built with, which the [`FindexClient`] records in the
+/// index.
+///
+/// The default tokenizer indexes each text as a single keyword, in ASCII
+/// lowercase.
+///
+/// [`FindexClient`]: crate::FindexClient
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tokenizer {
+    splitting: Splitting,
+    /// Whether the texts are normalized to NFKC and case-folded, rather than
+    /// only lowercased in ASCII
+    normalization: bool,
+    accent_stripping: bool,
+    stop_words: Option<Language>,
+    stemming: Option<Language>,
+    field_scoping: bool,
+}
+
+impl Tokenizer {
+    #[must_use]
+    pub const fn with_splitting(mut self, splitting: Splitting) -> Self {
+        self.splitting = splitting;
+        self
+    }
+
+    /// Normalize the texts to their Unicode NFKC form and fold their case, so
+    /// that e.g. `ﬁ` matches `fi` and `STRASSE` matches `straße`.
+    #[must_use]
+    pub const fn with_normalization(mut self) -> Self {
+        self.normalization = true;
+        self
+    }
+
+    /// Strip the accents of the keywords, so that e.g. `ecole` matches
+    /// `école`.
+    #[must_use]
+    pub const fn with_accent_stripping(mut self) -> Self {
+        self.accent_stripping = true;
+        self
+    }
+
+    /// Do not index the common words of the language.
+    #[must_use]
+    pub const fn with_stop_words(mut self, language: Language) -> Self {
+        self.stop_words = Some(language);
+        self
+    }
+
+    /// Index the stems of the words, with the Snowball stemmer of the
+    /// language, so that e.g. `maisons` matches `maison`.
+    #[must_use]
+    pub const fn with_stemming(mut self, language: Language) -> Self {
+        self.stemming = Some(language);
+        self
+    }
+
+    /// Scope the keywords of the fields of the records to their field, e.g.
+    /// `city:paris`, so that a search for `city:paris` does not match the
+    /// other fields containing `paris`. The query terms must then name their
+    /// field.
+    #[must_use]
+    pub const fn with_field_scoping(mut self) -> Self {
+        self.field_scoping = true;
+        self
+    }
+
+    /// The keywords indexing the text of a field of a record, scoped to the
+    /// field if the tokenizer scopes the fields.
+    #[must_use]
+    pub fn field_keywords(
+        &self,
+        field: &str,
+        text: &str,
+        expansion: &KeywordExpansion,
+    ) -> Vec<Keyword> {
+        let keywords = self.keywords(text, expansion);
+        if self.field_scoping {
+            let field = field_name(field);
+            keywords
+                .iter()
+                .map(|keyword| scoped(&field, keyword))
+                .collect()
+        } else {
+            keywords
+        }
+    }
+
+    /// The keywords indexing a text: the keyword of each of its words, and
+    /// the synthetic keywords of these words.
+    #[must_use]
+    pub fn keywords(&self, text: &str, expansion: &KeywordExpansion) -> Vec<Keyword> {
+        let mut keywords = Vec::new();
+        for word in self.words(text) {
+            keywords.push(self.keyword(&word));
+            keywords.extend(expansion.synthetic_keywords(&self.unaccented(&word)));
+        }
+        keywords
+    }
+
+    /// The query searching a query term: the keywords of all its words, or
+    /// the synthetic keyword of a prefix or phonetic term, which must be a
+    /// single word. A literal term is never a prefix or phonetic term.
+    ///
+    /// With field scoping, the term `field:term` searches the keywords of
+    /// `term` scoped to `field`.
+    ///
+    /// # Errors
+    /// - If the term only contains stop words
+    /// - If a prefix or phonetic term is not a single word
+    /// - If the expansion rejects a prefix or phonetic term
+    /// - If the tokenizer scopes the fields and the term names no field
+    pub fn search_query(&self, term: &Term, expansion: &KeywordExpansion) -> ClientResult<Query> {
+        if self.field_scoping {
+            let Some((field, text)) = term.text.split_once(':') else {
+                return Err(ClientError::InvalidQuery(format!(
+                    "the term `{term}` must name its field, e.g. `field:{term}`"
+                )));
+            };
+            let field = field_name(field);
+            let unscoped = Self {
+                field_scoping: false,
+                ..*self
+            };
+            let term = Term {
+                text: text.to_owned(),
+                literal: term.literal,
+            };
+            return Ok(unscoped
+                .search_query(&term, expansion)?
+                .map(&mut |keyword| scoped(&field, &keyword)));
+        }
+        let (literal, term) = (term.literal, term.text.as_str());
+        let (before, word, after) = if literal {
+            ("", term, "")
+        } else {
+            term.strip_suffix('*').map_or_else(
+                || {
+                    term.strip_prefix('~')
+                        .map_or(("", term, ""), |word| ("~", word, ""))
+                },
+                |word| ("", word, "*"),
+            )
+        };
+        let words = self.words(word);
+        if before.is_empty() && after.is_empty() {
+            let keywords = words
+                .iter()
+                .map(|word| Query::Keyword(self.keyword(word)))
+                .collect::<Vec<_>>();
+            return match <[_; 1]>::try_from(keywords) {
+                Ok([keyword]) => Ok(keyword),
+                Err(keywords) if keywords.is_empty() => Err(ClientError::InvalidQuery(format!(
+                    "the term `{term}` only contains stop words"
+                ))),
+                Err(keywords) => Ok(Query::And(keywords)),
+            };
+        }
+        let Ok([word]) = <[_; 1]>::try_from(words) else {
+            return Err(ClientError::InvalidQuery(format!(
+                "the term `{term}` must be a single word"
+            )));
+        };
+        Ok(Query::Keyword(expansion.search_keyword(&format!(
+            "{before}{}{after}",
+            self.unaccented(&word)
+        ))?))
+    }
+
+    /// The words of a text, normalized, without the stop words.
+    fn words(self, text: &str) -> Vec<String> {
+        let text = if self.normalization {
+            text.nfkc()
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+                // The only common letter whose case folding differs from its
+                // lowercase
+                .replace('ß', "ss")
+        } else {
+            text.to_ascii_lowercase()
+        };
+        let words = match self.splitting {
+            Splitting::None => vec![text.as_str()],
+            Splitting::Whitespace => text.split_whitespace().collect(),
+            Splitting::Words => text
+                .unicode_words()
+                .flat_map(|word| word.split(['\'', '’']))
+                .filter(|word| !word.is_empty())
+                .collect(),
+        };
+        words
+            .into_iter()
+            .filter(|word| {
+                !self
+                    .stop_words
+                    .is_some_and(|language| language.stop_words().contains(word))
+            })
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// The keyword indexing a word: its stem, without accents.
+    fn keyword(self, word: &str) -> Keyword {
+        let stem = self.stemming.map_or_else(
+            || word.to_owned(),
+            |language| {
+                Stemmer::create(language.algorithm())
+                    .stem(word)
+                    .into_owned()
+            },
+        );
+        Keyword::from(self.unaccented(&stem).into_bytes())
+    }
+
+    fn unaccented(self, word: &str) -> String {
+        if self.accent_stripping {
+            word.nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect()
+        } else {
+            word.to_owned()
+        }
+    }
+}
+
+/// The name of a field, as it scopes its keywords
+fn field_name(field: &str) -> String {
+    field.trim().to_lowercase()
+}
+
+fn scoped(field: &str, keyword: &Keyword) -> Keyword {
+    Keyword::from([field.as_bytes(), b":", keyword.as_ref()].concat())
+}
+
+/// The steps of the tokenizer separated by commas, e.g.
+/// `split-words,nfkc-casefold,strip-accents,stop-words:french,stem:french`,
+/// or `ascii-lowercase` for the default tokenizer.
+impl Display for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut steps = Vec::new();
+        match self.splitting {
+            Splitting::None => {}
+            Splitting::Whitespace => steps.push("split-whitespace".to_owned()),
+            Splitting::Words => steps.push("split-words".to_owned()),
+        }
+        if self.normalization {
+            steps.push("nfkc-casefold".to_owned());
+        }
+        if self.accent_stripping {
+            steps.push("strip-accents".to_owned());
+        }
+        if let Some(language) = self.stop_words {
+            steps.push(format!("stop-words:{language}"));
+        }
+        if let Some(language) = self.stemming {
+            steps.push(format!("stem:{language}"));
+        }
+        if self.field_scoping {
+            steps.push("scope-fields".to_owned());
+        }
+        if steps.is_empty() {
+            f.write_str(DEFAULT_TOKENIZER)
+        } else {
+            f.write_str(&steps.join(","))
+        }
+    }
+}
+
+impl FromStr for Tokenizer {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokenizer = Self::default();
+        for step in s.split(',').map(str::trim) {
+            tokenizer = match step.split_once(':') {
+                None => match step {
+                    DEFAULT_TOKENIZER => tokenizer,
+                    "split-whitespace" => tokenizer.with_splitting(Splitting::Whitespace),
+                    "split-words" => tokenizer.with_splitting(Splitting::Words),
+                    "nfkc-casefold" => tokenizer.with_normalization(),
+                    "strip-accents" => tokenizer.with_accent_stripping(),
+                    "scope-fields" => tokenizer.with_field_scoping(),
+                    _ => {
+                        return Err(ClientError::Default(format!(
+                            "unknown tokenizer step `{step}`"
+                        )));
+                    }
+                },
+                Some(("stop-words", language)) => tokenizer.with_stop_words(language.parse()?),
+                Some(("stem", language)) => tokenizer.with_stemming(language.parse()?),
+                Some(_) => {
+                    return Err(ClientError::Default(format!(
+                        "unknown tokenizer step `{step}`"
+                    )));
+                }
+            };
+        }
+        Ok(tokenizer)
+    }
+}
+
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a",
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "could",
+    "did",
+    "do",
+    "does",
+    "doing",
+    "down",
+    "during",
+    "each",
+    "few",
+    "for",
+    "from",
+    "further",
+    "had",
+    "has",
+    "have",
+    "having",
+    "he",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "it",
+    "its",
+    "itself",
+    "me",
+    "more",
+    "most",
+    "my",
+    "myself",
+    "no",
+    "nor",
+    "not",
+    "of",
+    "off",
+    "on",
+    "once",
+    "only",
+    "or",
+    "other",
+    "ought",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "same",
+    "she",
+    "should",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "under",
+    "until",
+    "up",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "with",
+    "would",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+];
+
+const FRENCH_STOP_WORDS: &[&str] = &[
+    "a", "ai", "aie", "aient", "aies", "ait", "as", "au", "aura", "aurai", "auraient", "aurais",
+    "aurait", "aux", "avaient", "avais", "avait", "avec", "avez", "aviez", "avions", "avons",
+    "ayant", "c", "ce", "ceci", "cela", "ces", "cet", "cette", "d", "dans", "de", "des", "du",
+    "elle", "elles", "en", "es", "est", "et", "étaient", "étais", "était", "étant", "été", "êtes",
+    "étiez", "étions", "eu", "eux", "furent", "fut", "il", "ils", "j", "je", "l", "la", "le",
+    "les", "leur", "leurs", "lui", "m", "ma", "mais", "me", "même", "mes", "moi", "mon", "n", "ne",
+    "nos", "notre", "nous", "on", "ont", "ou", "par", "pas", "pour", "qu", "que", "qui", "s", "sa",
+    "se", "sera", "serai", "seraient", "serais", "serait", "ses", "soit", "sommes", "son", "sont",
+    "suis", "sur", "t", "ta", "te", "tes", "toi", "ton", "tu", "un", "une", "vos", "votre", "vous",
+    "y", "à",
+];
+
+/// In their case-folded form, `dass` standing for `daß`
+const GERMAN_STOP_WORDS: &[&str] = &[
+    "aber", "alle", "allem", "allen", "aller", "alles", "als", "also", "am", "an", "ander",
+    "andere", "anderem", "anderen", "anderer", "anderes", "auch", "auf", "aus", "bei", "bin",
+    "bis", "bist", "da", "damit", "dann", "das", "dass", "daß", "dein", "deine", "dem", "den",
+    "der", "des", "dich", "die", "dir", "doch", "dort", "du", "durch", "ein", "eine", "einem",
+    "einen", "einer", "eines", "er", "es", "etwas", "euch", "euer", "eure", "für", "gegen",
+    "gewesen", "hab", "habe", "haben", "hat", "hatte", "hatten", "hier", "hin", "hinter", "ich",
+    "ihm", "ihn", "ihnen", "ihr", "ihre", "im", "in", "indem", "ins", "ist", "jede", "jeder",
+    "jetzt", "kann", "kein", "keine", "man", "mein", "meine", "mich", "mir", "mit", "muss", "nach",
+    "nicht", "nichts", "noch", "nun", "nur", "ob", "oder", "ohne", "sehr", "sein", "seine", "sich",
+    "sie", "sind", "so", "soll", "sondern", "über", "um", "und", "uns", "unser", "unter", "viel",
+    "vom", "von", "vor", "war", "waren", "was", "weil", "welche", "wenn", "werden", "wie",
+    "wieder", "will", "wir", "wird", "wo", "zu", "zum", "zur", "zwischen",
+];
+
+#[cfg(test)]
+#[expect(clippy::panic_in_result_fn, clippy::unwrap_used)]
+mod tests {
+    use cosmian_findex_structs::Keyword;
+
+    use super::{Language, Splitting, Tokenizer};
+    use crate::{ClientResult, KeywordExpansion, Query, Term};
+
+    fn keywords(tokenizer: Tokenizer, text: &str) -> Vec<Keyword> {
+        tokenizer.keywords(text, &KeywordExpansion::default())
+    }
+
+    fn keyword(word: &str) -> Keyword {
+        Keyword::from(word.as_bytes())
+    }
+
+    #[test]
+    fn test_default_tokenizer() -> ClientResult<()> {
+        // Each text is a single keyword, in ASCII lowercase
+        let tokenizer = Tokenizer::default();
+        assert_eq!(
+            keywords(tokenizer, "Saint-Étienne Loire"),
+            [keyword("saint-Étienne loire")]
+        );
+        assert_eq!(keywords(tokenizer, ""), [keyword("")]);
+        assert_eq!(
+            tokenizer.search_query(
+                &Term::new("Saint-Étienne Loire"),
+                &KeywordExpansion::default()
+            )?,
+            Query::Keyword(keyword("saint-Étienne loire"))
+        );
+        // Without the prefixes, only the literal prefix term can be searched
+        assert_eq!(
+            tokenizer.search_query(&Term::literal("C*"), &KeywordExpansion::default())?,
+            Query::Keyword(keyword("c*"))
+        );
+        tokenizer
+            .search_query(&Term::new("C*"), &KeywordExpansion::default())
+            .unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenizer() -> ClientResult<()> {
+        let tokenizer = Tokenizer::default()
+            .with_splitting(Splitting::Words)
+            .with_normalization()
+            .with_accent_stripping()
+            .with_stop_words(Language::French)
+            .with_stemming(Language::French);
+        let tokenize = |text| keywords(tokenizer, text);
+        // The stop words are dropped, and the compatibility forms, the case,
+        // the inflections and the accents are ignored
+        assert_eq!(
+            tokenize("L'École des maisons, ﬁn."),
+            [tokenize("ecole"), tokenize("MAISON"), tokenize("fin")].concat()
+        );
+        assert_eq!(tokenize("ecole").len(), 1);
+
+        let tokenizer = Tokenizer::default()
+            .with_splitting(Splitting::Whitespace)
+            .with_normalization()
+            .with_stop_words(Language::German)
+            .with_stemming(Language::German);
+        let tokenize = |text| keywords(tokenizer, text);
+        assert_eq!(
+            tokenize("Die HÄUSER der Straße"),
+            [tokenize("haus"), tokenize("STRASSE")].concat()
+        );
+
+        // The terms are searched with the keywords of all their words
+        let expansion = KeywordExpansion::default().with_prefixes(3, 5)?;
+        let query = tokenizer.search_query(&Term::new("Häuser"), &expansion)?;
+        assert!(matches!(query, Query::Keyword(_)));
+        assert_eq!(
+            query.keywords(),
+            tokenize("haus").iter().collect::<Vec<_>>()
+        );
+        let query = tokenizer.search_query(&Term::new("die Häuser der STRASSE"), &expansion)?;
+        assert!(matches!(query, Query::And(_)));
+        assert_eq!(
+            query.keywords(),
+            [tokenize("haus"), tokenize("strasse")]
+                .concat()
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        tokenizer
+            .search_query(&Term::new("die"), &expansion)
+            .unwrap_err();
+
+        // The prefixes are those of the words, not of their stems
+        let indexed = tokenizer.keywords("Häuser", &expansion);
+        for term in ["HÄU*", "häuse*", "Häuserblock*"] {
+            assert!(
+                matches!(
+                    tokenizer.search_query(&Term::new(term), &expansion)?,
+                    Query::Keyword(keyword) if indexed.contains(&keyword)
+                ),
+                "{term}"
+            );
+        }
+        tokenizer
+            .search_query(&Term::new("die*"), &expansion)
+            .unwrap_err();
+        tokenizer
+            .search_query(&Term::new("alte häuser*"), &expansion)
+            .unwrap_err();
+
+        // The literal terms are searched as is
+        assert_eq!(
+            tokenizer.search_query(&Term::literal("HÄU*"), &expansion)?,
+            Query::Keyword(tokenizer.keyword("häu*"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_scoping() -> ClientResult<()> {
+        let tokenizer = Tokenizer::default()
+            .with_splitting(Splitting::Whitespace)
+            .with_field_scoping();
+        let expansion = KeywordExpansion::default().with_prefixes(3, 5)?;
+        let indexed = tokenizer.field_keywords(" City ", "Saint Paris", &expansion);
+        assert!(indexed.contains(&keyword("city:paris")));
+        assert!(!indexed.contains(&keyword("paris")));
+
+        // The terms search the keywords of their field only
+        assert_eq!(
+            tokenizer.search_query(&Term::new("CITY:Paris"), &expansion)?,
+            Query::Keyword(keyword("city:paris"))
+        );
+        assert_ne!(
+            tokenizer.search_query(&Term::new("name:paris"), &expansion)?,
+            Query::Keyword(keyword("city:paris"))
+        );
+        assert!(matches!(
+            tokenizer.search_query(&Term::new("city:par*"), &expansion)?,
+            Query::Keyword(prefix) if indexed.contains(&prefix)
+        ));
+        tokenizer
+            .search_query(&Term::new("paris"), &expansion)
+            .unwrap_err();
+
+        // Without field scoping, the fields are ignored
+        assert_eq!(
+            Tokenizer::default().field_keywords("city", "Paris", &expansion),
+            Tokenizer::default().keywords("Paris", &expansion)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tokenizer_names() -> ClientResult<()> {
+        for tokenizer in [
+            Tokenizer::default(),
+            Tokenizer::default().with_splitting(Splitting::Whitespace),
+            Tokenizer::default()
+                .with_splitting(Splitting::Words)
+                .with_normalization()
+                .with_accent_stripping()
+                .with_stop_words(Language::French)
+                .with_stemming(Language::German)
+                .with_field_scoping(),
+        ] {
+            assert_eq!(tokenizer.to_string().parse::<Tokenizer>()?, tokenizer);
+        }
+        assert_eq!(
+            "split-words, stem:fr".parse::<Tokenizer>()?.to_string(),
+            "split-words,stem:french"
+        );
+        for invalid in ["", "split", "stem:latin", "stem", "lowercase:english"] {
+            assert!(invalid.parse::<Tokenizer>().is_err(), "{invalid}");
+        }
+        Ok(())
+    }
+}
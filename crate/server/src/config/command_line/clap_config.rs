@@ -3,9 +3,12 @@ use std::fmt::{self};
 use clap::Parser;
 use serde::{Deserialize, Serialize};
 
-use super::{DBConfig, HttpConfig, JwtAuthConfig};
+use super::{DBConfig, HttpConfig, JwtAuthConfig, ServerCommand};
 
 const DEFAULT_USERNAME: &str = "admin";
+const DEFAULT_PENDING_ENTRIES_TTL: u64 = 3600;
+const DEFAULT_IDEMPOTENCY_WINDOW: u64 = 3600;
+const DEFAULT_MAX_ARCHIVE_ITEMS: usize = 1_000_000;
 
 impl Default for ClapConfig {
     fn default() -> Self {
@@ -15,12 +18,16 @@ impl Default for ClapConfig {
             auth: JwtAuthConfig::default(),
             default_username: DEFAULT_USERNAME.to_owned(),
             force_default_username: false,
+            pending_entries_ttl: DEFAULT_PENDING_ENTRIES_TTL,
+            idempotency_window: DEFAULT_IDEMPOTENCY_WINDOW,
+            max_archive_items: DEFAULT_MAX_ARCHIVE_ITEMS,
+            command: None,
         }
     }
 }
 
 #[derive(Parser, Serialize, Deserialize, PartialEq, Eq)]
-#[clap(version, about, long_about = None)]
+#[clap(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 #[serde(default)]
 pub struct ClapConfig {
     #[clap(flatten)]
@@ -41,6 +48,27 @@ pub struct ClapConfig {
     /// authentication method
     #[clap(long, env = "FINDEX_SERVER_FORCE_DEFAULT_USERNAME")]
     pub force_default_username: bool,
+
+    /// The time, in seconds, after which the dataset entries staged under a
+    /// transaction which has not been committed are discarded
+    #[clap(long, env = "FINDEX_SERVER_PENDING_ENTRIES_TTL", default_value_t = DEFAULT_PENDING_ENTRIES_TTL)]
+    pub pending_entries_ttl: u64,
+
+    /// The time, in seconds, during which the response to a write request
+    /// sent with an `Idempotency-Key` header is replayed to the retries of
+    /// the request
+    #[clap(long, env = "FINDEX_SERVER_IDEMPOTENCY_WINDOW", default_value_t = DEFAULT_IDEMPOTENCY_WINDOW)]
+    pub idempotency_window: u64,
+
+    /// The maximum number of words and entries of an index which can be
+    /// exported or cloned, as the archive is built in memory
+    #[clap(long, env = "FINDEX_SERVER_MAX_ARCHIVE_ITEMS", default_value_t = DEFAULT_MAX_ARCHIVE_ITEMS)]
+    pub max_archive_items: usize,
+
+    /// A command to run instead of starting the server
+    #[clap(subcommand)]
+    #[serde(skip)]
+    pub command: Option<ServerCommand>,
 }
 
 impl fmt::Debug for ClapConfig {
@@ -55,6 +83,10 @@ impl fmt::Debug for ClapConfig {
         let x = x.field("Findex server http", &self.http);
         let x = x.field("default username", &self.default_username);
         let x = x.field("force default username", &self.force_default_username);
+        let x = x.field("pending entries ttl", &self.pending_entries_ttl);
+        let x = x.field("idempotency window", &self.idempotency_window);
+        let x = x.field("max archive items", &self.max_archive_items);
+        let x = x.field("command", &self.command);
         x.finish()
     }
 }
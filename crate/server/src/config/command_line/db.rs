@@ -6,9 +6,11 @@ use url::Url;
 #[cfg(test)]
 use variant_count::VariantCount;
 
-use crate::{config::params::DbParams, error::result::FResult, findex_server_error};
-
-pub(crate) const DEFAULT_SQLITE_PATH: &str = "./sqlite-data.db";
+use crate::{
+    config::params::{DbParams, RedisParams, RedisTopology},
+    error::result::FResult,
+    findex_server_error, server_bail,
+};
 
 #[cfg_attr(test, derive(VariantCount))] // Used only in some tests to make sure they stay up to date after a new database type is added
 #[derive(ValueEnum, Clone, Deserialize, Serialize, PartialEq, Eq, Debug)]
@@ -17,6 +19,19 @@ pub enum DatabaseType {
     Sqlite,
 }
 
+/// The deployment topology of the Redis database
+#[derive(ValueEnum, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Debug, Default)]
+pub enum RedisMode {
+    /// A single Redis instance
+    #[default]
+    Standalone,
+    /// A Redis Cluster; the database url lists one or more seed nodes
+    Cluster,
+    /// A Redis deployment managed by Sentinel; the database url lists the
+    /// Sentinel nodes
+    Sentinel,
+}
+
 /// Configuration for the database
 #[derive(Args, Clone, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(default)]
@@ -46,7 +61,9 @@ pub struct DBConfig {
     pub database_url: String,
 
     /// Clear the database on start.
-    /// WARNING: This will delete ALL the data in the database
+    /// WARNING: This will delete ALL the data of the Findex server
+    /// (on Redis, only the keys of the server are deleted, never the whole
+    /// database)
     #[clap(
         long,
         env = "FINDEX_SERVER_CLEAR_DATABASE",
@@ -54,6 +71,39 @@ pub struct DBConfig {
         default_value = "false"
     )]
     pub clear_database: bool,
+
+    /// The Redis deployment topology
+    /// - standalone: a single Redis instance reachable at the database url
+    /// - cluster: a Redis Cluster; the database url is a comma-separated
+    ///   list of seed nodes
+    /// - sentinel: a Sentinel-managed deployment; the database url is a
+    ///   comma-separated list of Sentinel nodes and the master name must be
+    ///   provided
+    #[clap(
+        long,
+        env = "FINDEX_SERVER_REDIS_MODE",
+        default_value = "standalone",
+        value_enum,
+        verbatim_doc_comment
+    )]
+    pub redis_mode: RedisMode,
+
+    /// The name of the master monitored by the Sentinel nodes.
+    /// Required when the Redis mode is `sentinel`
+    #[clap(
+        long,
+        env = "FINDEX_SERVER_REDIS_SENTINEL_MASTER",
+        required_if_eq("redis_mode", "sentinel"),
+        verbatim_doc_comment
+    )]
+    pub redis_sentinel_master: Option<String>,
+
+    /// A prefix prepended to all the Redis keys written by the server, for
+    /// example `findex:`. When set, clearing the database only deletes the
+    /// keys starting with this prefix, so that the Redis instance can be
+    /// shared with other services. The prefix cannot contain `{` or `}`
+    #[clap(long, env = "FINDEX_SERVER_REDIS_KEY_PREFIX", verbatim_doc_comment)]
+    pub redis_key_prefix: Option<String>,
 }
 
 impl Default for DBConfig {
@@ -62,6 +112,9 @@ impl Default for DBConfig {
             database_type: DatabaseType::Redis,
             database_url: "redis://localhost:6379".to_owned(),
             clear_database: false,
+            redis_mode: RedisMode::Standalone,
+            redis_sentinel_master: None,
+            redis_key_prefix: None,
         }
     }
 }
@@ -69,7 +122,13 @@ impl Default for DBConfig {
 impl Display for DBConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.database_type {
-            DatabaseType::Redis => write!(f, "redis: {}", self.database_url),
+            DatabaseType::Redis => {
+                write!(f, "redis ({:?}): {}", self.redis_mode, self.database_url)?;
+                if let Some(prefix) = &self.redis_key_prefix {
+                    write!(f, ", key prefix: {prefix}")?;
+                }
+                Ok(())
+            }
             DatabaseType::Sqlite => write!(f, "sqlite: {}", self.database_url),
         }?;
         write!(f, ", clear_database?: {}", self.clear_database)
@@ -94,8 +153,34 @@ impl DBConfig {
     pub(crate) fn init(&self) -> FResult<DbParams> {
         match self.database_type {
             DatabaseType::Redis => {
-                let url = ensure_url(self.database_url.as_str(), "FINDEX_SERVER_REDIS_URL")?;
-                Ok(DbParams::Redis(url))
+                let topology = match self.redis_mode {
+                    RedisMode::Standalone => RedisTopology::Standalone(ensure_url(
+                        self.database_url.as_str(),
+                        "FINDEX_SERVER_REDIS_URL",
+                    )?),
+                    RedisMode::Cluster => RedisTopology::Cluster(ensure_urls(
+                        self.database_url.as_str(),
+                        "FINDEX_SERVER_REDIS_URL",
+                    )?),
+                    RedisMode::Sentinel => RedisTopology::Sentinel {
+                        sentinels: ensure_urls(
+                            self.database_url.as_str(),
+                            "FINDEX_SERVER_REDIS_URL",
+                        )?,
+                        master_name: self.redis_sentinel_master.clone().ok_or_else(|| {
+                            findex_server_error!(
+                                "The Redis Sentinel mode requires the master name to be set \
+                                 using the 'redis-sentinel-master' option."
+                            )
+                        })?,
+                    },
+                };
+                Ok(DbParams::Redis(RedisParams {
+                    topology,
+                    key_prefix: ensure_redis_key_prefix(self.redis_key_prefix.as_deref())?,
+                    legacy_layout: false,
+                    legacy_words_by_length: false,
+                }))
             }
             DatabaseType::Sqlite => {
                 let path =
@@ -126,6 +211,34 @@ fn ensure_url(database_url: &str, alternate_env_variable: &str) -> FResult<Url>
     )?)?)
 }
 
+// Parse a comma-separated list of URLs, as used to list the seed nodes of a
+// Redis Cluster or the nodes of a Sentinel deployment
+fn ensure_urls(database_url: &str, alternate_env_variable: &str) -> FResult<Vec<Url>> {
+    let urls = retrieve_database_location(database_url, alternate_env_variable)?
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(Url::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    if urls.is_empty() {
+        server_bail!("At least one Redis node URL must be supplied");
+    }
+    Ok(urls)
+}
+
+// The keys embed the index ID in a hash tag so that the keys of an index are
+// stored in the same slot of a Redis Cluster; a brace in the prefix would
+// change the hash tag.
+fn ensure_redis_key_prefix(prefix: Option<&str>) -> FResult<Option<String>> {
+    match prefix {
+        None | Some("") => Ok(None),
+        Some(prefix) if prefix.contains(['{', '}']) => {
+            server_bail!("The Redis key prefix cannot contain '{{' or '}}': {prefix}")
+        }
+        Some(prefix) => Ok(Some(prefix.to_owned())),
+    }
+}
+
 // Open and immediately close a connection from the provided path to check if it is valid
 // This creates the database if it does not exist, and tries to open it if it does
 fn ensure_sqlite_db(database_url: &str, alternate_env_variable: &str) -> FResult<PathBuf> {
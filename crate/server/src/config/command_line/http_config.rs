@@ -17,6 +17,12 @@ pub struct HttpConfig {
     #[clap(long, env = "FINDEX_SERVER_HOSTNAME", default_value = DEFAULT_HOSTNAME)]
     pub hostname: String,
 
+    /// The optional port of the gRPC API, served on the same hostname and with
+    /// the same TLS and authentication settings. If not provided, the gRPC API
+    /// is disabled.
+    #[clap(long, env = "FINDEX_SERVER_GRPC_PORT")]
+    pub grpc_port: Option<u16>,
+
     /// The Findex server optional PKCS#12 Certificates and Key file. If
     /// provided, this will start the server in HTTPS mode.
     #[clap(long, env = "FINDEX_SERVER_HTTPS_P12_FILE")]
@@ -47,10 +53,14 @@ impl Display for HttpConfig {
                 f,
                 "authority cert file: {:?}",
                 self.authority_cert_file.as_ref()
-            )
+            )?;
         } else {
-            write!(f, "http://{}:{}", self.hostname, self.port)
+            write!(f, "http://{}:{}", self.hostname, self.port)?;
+        }
+        if let Some(grpc_port) = self.grpc_port {
+            write!(f, ", gRPC port: {grpc_port}")?;
         }
+        Ok(())
     }
 }
 
@@ -65,6 +75,7 @@ impl Default for HttpConfig {
         Self {
             port: DEFAULT_PORT,
             hostname: DEFAULT_HOSTNAME.to_owned(),
+            grpc_port: None,
             https_p12_file: None,
             https_p12_password: None,
             authority_cert_file: None,
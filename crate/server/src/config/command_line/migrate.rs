@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Args, Subcommand, builder::RangedU64ValueParser};
+
+use super::{DBConfig, DatabaseType, RedisMode};
+use crate::{config::params::DbParams, error::result::FResult, server_bail};
+
+/// The commands run instead of starting the server
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum ServerCommand {
+    /// Copy all the memory words, permissions and dataset entries of a
+    /// database to another one, possibly of another type.
+    /// The data is encrypted: it is copied as is, without re-indexing
+    #[clap(verbatim_doc_comment)]
+    Migrate(MigrateConfig),
+}
+
+/// Configuration of a database migration
+#[derive(Args, Clone, Debug, PartialEq, Eq)]
+pub struct MigrateConfig {
+    /// The url of the database to copy
+    /// - redis: a `redis://` or `rediss://` url; a comma-separated list of
+    ///   urls for a Redis Cluster or a Sentinel deployment
+    /// - sqlite: the `SQLite` file path, optionally prefixed by `sqlite://`
+    #[clap(long, verbatim_doc_comment)]
+    pub from: String,
+
+    /// The url of the database to copy the data to, in the same format.
+    /// It must be empty, unless the migration is resumed
+    #[clap(long, verbatim_doc_comment)]
+    pub to: String,
+
+    /// The Redis deployment topology of the source database
+    #[clap(long, default_value = "standalone", value_enum)]
+    pub from_redis_mode: RedisMode,
+
+    /// The Sentinel master name of the source database
+    #[clap(long, required_if_eq("from_redis_mode", "sentinel"))]
+    pub from_redis_sentinel_master: Option<String>,
+
+    /// The Redis key prefix of the source database
+    #[clap(long)]
+    pub from_redis_key_prefix: Option<String>,
+
+    /// Read the source Redis database with the key layout of the server
+    /// versions prior to the Redis Cluster support, which stored the memory
+    /// words and the dataset entries under their raw `index_id || id` key.
+    /// The data is rewritten with the current layout of the target database
+    #[clap(long, conflicts_with = "from_redis_key_prefix", verbatim_doc_comment)]
+    pub from_redis_legacy_layout: bool,
+
+    /// Copy the values of the legacy layout of exactly the length of a
+    /// memory word as memory words, even though the source holds dataset
+    /// entries: the words and the entries are told apart by their length only,
+    /// hence an entry of that length is copied as a word and must be uploaded
+    /// again. Without it, the migration of such a database fails
+    #[clap(long, requires = "from_redis_legacy_layout", verbatim_doc_comment)]
+    pub from_redis_legacy_words_by_length: bool,
+
+    /// The Redis deployment topology of the target database
+    #[clap(long, default_value = "standalone", value_enum)]
+    pub to_redis_mode: RedisMode,
+
+    /// The Sentinel master name of the target database
+    #[clap(long, required_if_eq("to_redis_mode", "sentinel"))]
+    pub to_redis_sentinel_master: Option<String>,
+
+    /// The Redis key prefix of the target database
+    #[clap(long)]
+    pub to_redis_key_prefix: Option<String>,
+
+    /// A file recording the progress of the migration.
+    /// When it exists, an interrupted migration resumes from the recorded
+    /// position. It is deleted once the migration succeeds
+    #[clap(long, verbatim_doc_comment)]
+    pub checkpoint_file: Option<PathBuf>,
+
+    /// The number of items read and written per batch
+    #[clap(long, default_value = "1000", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub batch_size: usize,
+}
+
+impl MigrateConfig {
+    /// The parameters of the database to copy
+    pub(crate) fn source(&self) -> FResult<DbParams> {
+        let config = db_config(
+            &self.from,
+            self.from_redis_mode,
+            self.from_redis_sentinel_master.as_ref(),
+            self.from_redis_key_prefix.as_ref(),
+        );
+        // Opening a missing SQLite file would create an empty database.
+        if config.database_type == DatabaseType::Sqlite && !Path::new(&config.database_url).exists()
+        {
+            server_bail!(
+                "The SQLite database to migrate does not exist: {}",
+                config.database_url
+            );
+        }
+        let mut params = config.init()?;
+        if self.from_redis_legacy_layout {
+            let DbParams::Redis(redis_params) = &mut params else {
+                server_bail!("The legacy key layout only applies to a Redis database");
+            };
+            redis_params.legacy_layout = true;
+            redis_params.legacy_words_by_length = self.from_redis_legacy_words_by_length;
+        }
+        Ok(params)
+    }
+
+    /// The parameters of the database to copy the data to
+    pub(crate) fn target(&self) -> FResult<DbParams> {
+        db_config(
+            &self.to,
+            self.to_redis_mode,
+            self.to_redis_sentinel_master.as_ref(),
+            self.to_redis_key_prefix.as_ref(),
+        )
+        .init()
+    }
+}
+
+// The database type is inferred from the scheme of the url
+fn db_config(
+    url: &str,
+    redis_mode: RedisMode,
+    redis_sentinel_master: Option<&String>,
+    redis_key_prefix: Option<&String>,
+) -> DBConfig {
+    let (database_type, database_url) =
+        if url.starts_with("redis://") || url.starts_with("rediss://") {
+            (DatabaseType::Redis, url)
+        } else {
+            (
+                DatabaseType::Sqlite,
+                url.strip_prefix("sqlite://").unwrap_or(url),
+            )
+        };
+    DBConfig {
+        database_type,
+        database_url: database_url.to_owned(),
+        clear_database: false,
+        redis_mode,
+        redis_sentinel_master: redis_sentinel_master.cloned(),
+        redis_key_prefix: redis_key_prefix.cloned(),
+    }
+}
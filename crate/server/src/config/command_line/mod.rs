@@ -2,9 +2,10 @@ mod clap_config;
 mod db;
 mod http_config;
 mod jwt_auth_config;
+mod migrate;
 
 pub use clap_config::ClapConfig;
-pub(crate) use db::DEFAULT_SQLITE_PATH;
-pub use db::{DBConfig, DatabaseType};
+pub use db::{DBConfig, DatabaseType, RedisMode};
 pub use http_config::HttpConfig;
 pub use jwt_auth_config::JwtAuthConfig;
+pub use migrate::{MigrateConfig, ServerCommand};
@@ -2,7 +2,7 @@ mod command_line;
 mod params;
 
 pub use command_line::*;
-pub use params::{DbParams, HttpParams, ServerParams};
+pub use params::{DbParams, HttpParams, RedisParams, RedisTopology, ServerParams};
 
 #[derive(Debug, Clone)]
 pub struct IdpConfig {
@@ -6,10 +6,40 @@ use std::{
 use url::Url;
 
 pub enum DbParams {
-    Redis(Url),
+    Redis(RedisParams),
     Sqlite(PathBuf),
 }
 
+/// The parameters of the Redis database
+#[derive(Clone)]
+pub struct RedisParams {
+    pub topology: RedisTopology,
+    /// A prefix prepended to all the keys written by the server
+    pub key_prefix: Option<String>,
+    /// Whether the database holds the key layout of the versions prior to the
+    /// Redis Cluster support, which only `migrate` reads
+    pub legacy_layout: bool,
+    /// Whether the values of the legacy layout of exactly the length of a
+    /// memory word are read as memory words even though the database holds
+    /// dataset entries, which cannot be told apart from them
+    pub legacy_words_by_length: bool,
+}
+
+/// The way the server reaches the Redis database
+#[derive(Clone)]
+pub enum RedisTopology {
+    /// A single Redis instance
+    Standalone(Url),
+    /// A Redis Cluster, reached through one or more seed nodes
+    Cluster(Vec<Url>),
+    /// A deployment managed by Sentinel: the Sentinel nodes are queried for
+    /// the address of the current master of `master_name`
+    Sentinel {
+        sentinels: Vec<Url>,
+        master_name: String,
+    },
+}
+
 impl DbParams {
     /// Return the name of the database type
     #[must_use]
@@ -24,15 +54,22 @@ impl DbParams {
 impl Default for DbParams {
     #[allow(clippy::expect_used)] // Won't panic because the URL is valid
     fn default() -> Self {
-        Self::Redis(Url::parse("redis://localhost:6379").expect("Invalid default URL"))
+        Self::Redis(RedisParams {
+            topology: RedisTopology::Standalone(
+                Url::parse("redis://localhost:6379").expect("Invalid default URL"),
+            ),
+            key_prefix: None,
+            legacy_layout: false,
+            legacy_words_by_length: false,
+        })
     }
 }
 
 impl Display for DbParams {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Redis(url) => {
-                write!(f, "redis: {}", redact_url(url))
+            Self::Redis(params) => {
+                write!(f, "redis: {params}")
             }
             Self::Sqlite(path) => {
                 write!(f, "sqlite: {}", path.display())
@@ -41,6 +78,43 @@ impl Display for DbParams {
     }
 }
 
+impl Display for RedisParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.topology)?;
+        if let Some(prefix) = &self.key_prefix {
+            write!(f, ", key prefix: '{prefix}'")?;
+        }
+        if self.legacy_layout {
+            write!(f, ", legacy key layout")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for RedisTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Standalone(url) => write!(f, "{}", redact_url(url)),
+            Self::Cluster(nodes) => write!(f, "cluster [{}]", redact_urls(nodes)),
+            Self::Sentinel {
+                sentinels,
+                master_name,
+            } => write!(
+                f,
+                "sentinel master '{master_name}' [{}]",
+                redact_urls(sentinels)
+            ),
+        }
+    }
+}
+
+fn redact_urls(urls: &[Url]) -> String {
+    urls.iter()
+        .map(|url| redact_url(url).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Redact the username and password from the URL for logging purposes
 #[allow(clippy::expect_used)]
 fn redact_url(original: &Url) -> Url {
@@ -56,6 +130,12 @@ fn redact_url(original: &Url) -> Url {
     url
 }
 
+impl std::fmt::Debug for RedisTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}", &self))
+    }
+}
+
 impl std::fmt::Debug for DbParams {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("{}", &self))
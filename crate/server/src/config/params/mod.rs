@@ -2,6 +2,6 @@ mod db_params;
 mod http_params;
 mod server_params;
 
-pub use db_params::DbParams;
+pub use db_params::{DbParams, RedisParams, RedisTopology};
 pub use http_params::HttpParams;
 pub use server_params::ServerParams;
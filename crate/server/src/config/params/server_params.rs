@@ -1,4 +1,4 @@
-use std::{fmt, path::PathBuf};
+use std::{fmt, path::PathBuf, time::Duration};
 
 use openssl::x509::X509;
 use tracing::warn;
@@ -35,11 +35,26 @@ pub struct ServerParams {
 
     pub port: u16,
 
+    /// The port of the gRPC API, disabled if `None`
+    pub grpc_port: Option<u16>,
+
     pub http_params: HttpParams,
 
     /// The certificate used to verify the client TLS certificates
     /// used for authentication
     pub authority_cert_file: Option<X509>,
+
+    /// The time after which the dataset entries staged under a transaction
+    /// which has not been committed are discarded
+    pub pending_entries_ttl: Duration,
+
+    /// The time during which the response to a write request sent with an
+    /// idempotency key is replayed to its retries
+    pub idempotency_window: Duration,
+
+    /// The maximum number of words and entries of an exported or cloned
+    /// index
+    pub max_archive_items: usize,
 }
 
 /// Represents the server parameters.
@@ -85,10 +100,14 @@ impl ServerParams {
             clear_db_on_start: conf.db.clear_database,
             hostname: conf.http.hostname,
             port: conf.http.port,
+            grpc_port: conf.http.grpc_port,
             http_params,
             default_username: conf.default_username,
             force_default_username: conf.force_default_username,
             authority_cert_file,
+            pending_entries_ttl: Duration::from_secs(conf.pending_entries_ttl),
+            idempotency_window: Duration::from_secs(conf.idempotency_window),
+            max_archive_items: conf.max_archive_items,
         })
     }
 
@@ -134,6 +153,7 @@ impl fmt::Debug for ServerParams {
                     &self.port
                 ),
             )
+            .field("grpc_port", &self.grpc_port)
             .field("db_params", &self.db_params)
             .field("clear_db_on_start", &self.clear_db_on_start);
         let x = if let Some(identity_provider_configurations) =
@@ -153,7 +173,10 @@ impl fmt::Debug for ServerParams {
         };
         let x = x
             .field("default_username", &self.default_username)
-            .field("force_default_username", &self.force_default_username);
+            .field("force_default_username", &self.force_default_username)
+            .field("pending_entries_ttl", &self.pending_entries_ttl)
+            .field("idempotency_window", &self.idempotency_window)
+            .field("max_archive_items", &self.max_archive_items);
         let x = x.field("http_params", &self.http_params);
         x.finish()
     }
@@ -176,8 +199,12 @@ impl Clone for ServerParams {
             clear_db_on_start: self.clear_db_on_start,
             hostname: self.hostname.clone(),
             port: self.port,
+            grpc_port: self.grpc_port,
             http_params: HttpParams::Http,
             authority_cert_file: self.authority_cert_file.clone(),
+            pending_entries_ttl: self.pending_entries_ttl,
+            idempotency_window: self.idempotency_window,
+            max_archive_items: self.max_archive_items,
         }
     }
 }
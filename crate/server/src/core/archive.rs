@@ -0,0 +1,600 @@
+//! Export of an index to a portable archive, and import of an archive into
+//! an index.
+use std::collections::HashMap;
+
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, IndexArchive, Permission, SERVER_ADDRESS_LENGTH, UID_LENGTH, Uuids,
+    VersionedEntries,
+};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use super::{FindexServer, WrittenKeys, prepend_index_id};
+use crate::{
+    database::{
+        database_traits::{DatasetsTrait, MigrationTrait, PermissionsTrait, ScanPage},
+        findex_database::DatabaseResult,
+    },
+    error::{result::FResult, server::ServerError},
+    findex_server_error,
+};
+
+/// The number of items read or written per database request
+const ARCHIVE_BATCH_SIZE: usize = 1000;
+
+type Words = HashMap<Address<SERVER_ADDRESS_LENGTH>, [u8; CUSTOM_WORD_LENGTH]>;
+
+impl FindexServer {
+    /// Export the words, the entries and optionally the permissions of an
+    /// index.
+    ///
+    /// The index can be written to during the export: the archive holds the
+    /// state of the index at the end of the export.
+    ///
+    /// The archive is built in memory, hence the export fails as soon as the
+    /// index holds more words and entries than the configured maximum.
+    pub(crate) async fn export_index(
+        &self,
+        index_id: &Uuid,
+        with_permissions: bool,
+    ) -> FResult<IndexArchive> {
+        self.journals.open(index_id).await?;
+        let (words, entries) = match self.read_index(index_id).await {
+            Ok(data) => data,
+            Err(e) => {
+                self.journals.close(index_id, |_| async { Ok(()) }).await?;
+                return Err(e);
+            }
+        };
+        // Read again the keys written during the export, while the writes are
+        // blocked.
+        let (words, entries) = self
+            .journals
+            .close(index_id, |written| async move {
+                let (mut words, mut entries) = (words, entries);
+                let addresses = written.addresses.into_iter().collect::<Vec<_>>();
+                for addresses in addresses.chunks(ARCHIVE_BATCH_SIZE) {
+                    let current = self.db.batch_read(addresses.to_vec()).await?;
+                    for (address, word) in addresses.iter().zip(current) {
+                        match word {
+                            Some(word) => words.insert(*address, word),
+                            None => words.remove(address),
+                        };
+                    }
+                }
+                let uuids = written.entries.into_iter().collect::<Vec<_>>();
+                for uuids in uuids.chunks(ARCHIVE_BATCH_SIZE) {
+                    let mut current = self
+                        .db
+                        .dataset_get_entries(index_id, &Uuids::from(uuids))
+                        .await?;
+                    for uuid in uuids {
+                        match current.remove(uuid) {
+                            Some(entry) => entries.insert(*uuid, entry),
+                            None => entries.remove(uuid),
+                        };
+                    }
+                }
+                self.ensure_archive_size(index_id, words.len(), entries.len())?;
+                Ok((words, entries))
+            })
+            .await?;
+
+        let permissions = if with_permissions {
+            Some(self.read_permissions(index_id).await?)
+        } else {
+            None
+        };
+
+        let mut words = words
+            .into_iter()
+            .map(|(address, word)| Ok((strip_index_id(&address)?, word)))
+            .collect::<FResult<Vec<_>>>()?;
+        words.sort_unstable_by(|(a, _), (b, _)| (**a).cmp(&**b));
+        info!(
+            "Index {index_id} exported: {} words, {} entries",
+            words.len(),
+            entries.len()
+        );
+        Ok(IndexArchive {
+            index_id: *index_id,
+            words,
+            entries,
+            permissions,
+        })
+    }
+
+    /// Read all the words and the entries of an index.
+    async fn read_index(&self, index_id: &Uuid) -> FResult<(Words, VersionedEntries)> {
+        let mut words = Words::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .db
+                .memory_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                .await?;
+            words.extend(page.items);
+            self.ensure_archive_size(index_id, words.len(), 0)?;
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        let mut entries = VersionedEntries::default();
+        loop {
+            let page = self
+                .db
+                .datasets_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                .await?;
+            entries.extend(page.items.into_iter().map(|(_, uuid, entry)| (uuid, entry)));
+            self.ensure_archive_size(index_id, words.len(), entries.len())?;
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        debug!(
+            "read_index: {} words and {} entries read from index {index_id}",
+            words.len(),
+            entries.len()
+        );
+        Ok((words, entries))
+    }
+
+    /// Fail when an index holds more words and entries than an archive can.
+    fn ensure_archive_size(&self, index_id: &Uuid, words: usize, entries: usize) -> FResult<()> {
+        let max = self.params.max_archive_items;
+        if words + entries > max {
+            return Err(ServerError::InvalidRequest(format!(
+                "Index {index_id} holds more than {max} words and entries, the maximum size of \
+                 an archive"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read the permissions of all the users on an index.
+    async fn read_permissions(&self, index_id: &Uuid) -> FResult<Vec<(String, Permission)>> {
+        let mut permissions = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .db
+                .permissions_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                .await?;
+            permissions.extend(
+                page.items
+                    .into_iter()
+                    .map(|(user_id, _, permission)| (user_id, permission)),
+            );
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(permissions)
+    }
+
+    /// Import an archive into an index, and return the ID of this index.
+    ///
+    /// The entries are written with their archived versions.
+    ///
+    /// Without an index ID, a new index is created. Otherwise, the user must
+    /// be an administrator of the index, or the index must not be used yet.
+    /// The user is granted the admin permission on the index, and the
+    /// exported permissions are restored if requested.
+    pub(crate) async fn import_index(
+        &self,
+        user: &str,
+        archive: IndexArchive,
+        index_id: Option<Uuid>,
+        with_permissions: bool,
+    ) -> FResult<Uuid> {
+        let index_id = match index_id {
+            None => self.db.create_index_id(user).await?,
+            Some(index_id) => {
+                // A missing permission is reported as an error.
+                let is_admin = self
+                    .get_permission(user, &index_id.to_string())
+                    .await
+                    .is_ok_and(|permission| permission == Permission::Admin);
+                if !is_admin {
+                    if !self.is_unused(&index_id).await? {
+                        return Err(ServerError::Unauthorized(format!(
+                            "User {user} is not allowed to import into index {index_id}, which \
+                             is already in use"
+                        )));
+                    }
+                    self.db
+                        .set_permission(user, Permission::Admin, &index_id)
+                        .await?;
+                }
+                index_id
+            }
+        };
+
+        let IndexArchive {
+            words,
+            entries,
+            permissions,
+            ..
+        } = archive;
+        let words = words
+            .into_iter()
+            .map(|(address, word)| (prepend_index_id(&address, &index_id), word))
+            .collect::<Vec<_>>();
+        let entries = entries.entries.into_iter().collect::<Vec<_>>();
+        self.journals
+            .write(&index_id, async {
+                for words in words.chunks(ARCHIVE_BATCH_SIZE) {
+                    self.db.memory_restore(words.to_vec()).await?;
+                }
+                for entries in entries.chunks(ARCHIVE_BATCH_SIZE) {
+                    self.db
+                        .dataset_restore_entries(&index_id, &entries.iter().cloned().collect())
+                        .await?;
+                }
+                let mut written = WrittenKeys::addresses(words.iter().map(|(a, _)| *a));
+                written.entries = entries.iter().map(|(uuid, _)| *uuid).collect();
+                Ok(((), written))
+            })
+            .await?;
+
+        if with_permissions {
+            for (user_id, permission) in permissions.unwrap_or_default() {
+                // The importing user keeps the admin permission.
+                if user_id != user {
+                    self.db
+                        .set_permission(&user_id, permission, &index_id)
+                        .await?;
+                }
+            }
+        }
+        info!(
+            "Index {index_id} imported by {user}: {} words, {} entries",
+            words.len(),
+            entries.len()
+        );
+        Ok(index_id)
+    }
+
+    /// Copy the words and the entries of an index into a new index
+    /// administered by the user, and return the ID of this new index.
+    ///
+    /// The Findex addresses are relative to the index, hence the clone can be
+    /// read and written to with the keys of the original index.
+    pub(crate) async fn clone_index(&self, user: &str, index_id: &Uuid) -> FResult<Uuid> {
+        let archive = self.export_index(index_id, false).await?;
+        self.import_index(user, archive, None, false).await
+    }
+
+    /// Tell whether an index holds no word, no entry and no permission.
+    async fn is_unused(&self, index_id: &Uuid) -> FResult<bool> {
+        Ok(is_empty(|cursor| async move {
+            self.db
+                .permissions_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                .await
+        })
+        .await?
+            && self.is_index_empty(index_id).await?)
+    }
+
+    /// Tell whether an index holds no word and no entry.
+    pub(crate) async fn is_index_empty(&self, index_id: &Uuid) -> FResult<bool> {
+        Ok(is_empty(|cursor| async move {
+            self.db
+                .memory_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                .await
+        })
+        .await?
+            && is_empty(|cursor| async move {
+                self.db
+                    .datasets_scan(Some(index_id), cursor.as_deref(), ARCHIVE_BATCH_SIZE)
+                    .await
+            })
+            .await?)
+    }
+}
+
+/// Tell whether a scan finds no item. A page may be empty while the scan is
+/// not over.
+async fn is_empty<T, F>(scan: impl Fn(Option<String>) -> F) -> FResult<bool>
+where
+    F: Future<Output = DatabaseResult<ScanPage<T>>>,
+{
+    let mut cursor = None;
+    loop {
+        let page = scan(cursor).await?;
+        if !page.items.is_empty() {
+            return Ok(false);
+        }
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            return Ok(true);
+        }
+    }
+}
+
+/// Remove the index ID prepended to a Findex address.
+fn strip_index_id(address: &Address<SERVER_ADDRESS_LENGTH>) -> FResult<Address<ADDRESS_LENGTH>> {
+    address
+        .get(UID_LENGTH..)
+        .and_then(|address| <[u8; ADDRESS_LENGTH]>::try_from(address).ok())
+        .map(Address::from)
+        .ok_or_else(|| findex_server_error!("Invalid memory address of {} bytes", address.len()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::time::Duration;
+
+    use cosmian_crypto_core::bytes_ser_de::Serializable;
+    use cosmian_findex_structs::EncryptedEntries;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{ClapConfig, DBConfig, DatabaseType, ServerParams};
+
+    const N_WORDS: u8 = 5;
+
+    async fn instantiate_server(dir: &TempDir) -> FindexServer {
+        let conf = ClapConfig {
+            db: DBConfig {
+                database_type: DatabaseType::Sqlite,
+                database_url: dir.path().join("findex.db").to_str().unwrap().to_owned(),
+                ..DBConfig::default()
+            },
+            ..ClapConfig::default()
+        };
+        FindexServer::instantiate(ServerParams::try_from(conf).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Fill an index and return its ID.
+    async fn populate(server: &FindexServer) -> Uuid {
+        let index_id = server.db.create_index_id("alice").await.unwrap();
+        server
+            .db
+            .set_permission("bob", Permission::Read, &index_id)
+            .await
+            .unwrap();
+        let bindings = (0..N_WORDS)
+            .map(|n| {
+                (
+                    prepend_index_id(&Address::from([n; ADDRESS_LENGTH]), &index_id),
+                    [n; CUSTOM_WORD_LENGTH],
+                )
+            })
+            .collect::<Vec<_>>();
+        server
+            .db
+            .guarded_write((bindings[0].0, None), bindings)
+            .await
+            .unwrap();
+        // The entry is written twice, hence at version 2.
+        let entries = EncryptedEntries::from_iter([(Uuid::new_v4(), vec![1, 2, 3])]);
+        for _ in 0..2 {
+            server
+                .db
+                .dataset_add_entries(&index_id, &entries)
+                .await
+                .unwrap();
+        }
+        index_id
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_index() {
+        let dir = TempDir::new().unwrap();
+        let server = instantiate_server(&dir).await;
+        let index_id = populate(&server).await;
+        // Another index must not be exported.
+        populate(&server).await;
+
+        let archive = server.export_index(&index_id, true).await.unwrap();
+        assert_eq!(archive.index_id, index_id);
+        assert_eq!(
+            archive.words,
+            (0..N_WORDS)
+                .map(|n| (Address::from([n; ADDRESS_LENGTH]), [n; CUSTOM_WORD_LENGTH]))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(archive.entries.len(), 1);
+        assert!(archive.entries.values().all(|entry| entry.version == 2));
+        let mut permissions = archive.permissions.clone().unwrap();
+        permissions.sort();
+        assert_eq!(
+            permissions,
+            vec![
+                ("alice".to_owned(), Permission::Admin),
+                ("bob".to_owned(), Permission::Read)
+            ]
+        );
+        let archive = IndexArchive::deserialize(&archive.serialize().unwrap()).unwrap();
+
+        // The archive is imported into a new index administered by the
+        // importing user.
+        let new_index_id = server
+            .import_index("carol", archive, None, true)
+            .await
+            .unwrap();
+        assert_ne!(new_index_id, index_id);
+        let imported = server.export_index(&new_index_id, true).await.unwrap();
+        let exported = server.export_index(&index_id, false).await.unwrap();
+        assert_eq!(imported.words, exported.words);
+        assert_eq!(imported.entries, exported.entries);
+        let mut permissions = imported.permissions.unwrap();
+        permissions.sort();
+        assert_eq!(
+            permissions,
+            vec![
+                ("alice".to_owned(), Permission::Admin),
+                ("bob".to_owned(), Permission::Read),
+                ("carol".to_owned(), Permission::Admin),
+            ]
+        );
+
+        // Importing into an index in use requires the admin permission.
+        let archive = server.export_index(&index_id, false).await.unwrap();
+        server
+            .import_index("bob", archive, Some(index_id), false)
+            .await
+            .unwrap_err();
+        let archive = server.export_index(&index_id, false).await.unwrap();
+        let unused_index_id = Uuid::new_v4();
+        assert_eq!(
+            server
+                .import_index("bob", archive, Some(unused_index_id), false)
+                .await
+                .unwrap(),
+            unused_index_id
+        );
+        assert_eq!(
+            server
+                .get_permission("bob", &unused_index_id.to_string())
+                .await
+                .unwrap(),
+            Permission::Admin
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clone_index() {
+        let dir = TempDir::new().unwrap();
+        let server = instantiate_server(&dir).await;
+        let index_id = populate(&server).await;
+
+        let clone_id = server.clone_index("carol", &index_id).await.unwrap();
+        assert_ne!(clone_id, index_id);
+        let original = server.export_index(&index_id, false).await.unwrap();
+        let clone = server.export_index(&clone_id, true).await.unwrap();
+        assert_eq!(clone.words, original.words);
+        assert_eq!(clone.entries, original.entries);
+        // Only the caller is granted a permission on the clone.
+        assert_eq!(
+            clone.permissions.unwrap(),
+            vec![("carol".to_owned(), Permission::Admin)]
+        );
+
+        // The clone is independent from the original index.
+        let address = prepend_index_id(&Address::from([0; ADDRESS_LENGTH]), &clone_id);
+        server
+            .db
+            .guarded_write(
+                (address, Some([0; CUSTOM_WORD_LENGTH])),
+                vec![(address, [9; CUSTOM_WORD_LENGTH])],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            server.export_index(&index_id, false).await.unwrap().words,
+            original.words
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_index_empty() {
+        let dir = TempDir::new().unwrap();
+        let server = instantiate_server(&dir).await;
+        let index_id = populate(&server).await;
+        assert!(!server.is_index_empty(&index_id).await.unwrap());
+
+        // The permissions are not data of the index.
+        let empty_id = server.db.create_index_id("alice").await.unwrap();
+        assert!(server.is_index_empty(&empty_id).await.unwrap());
+        assert!(!server.is_unused(&empty_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_export_sees_concurrent_writes() {
+        let dir = TempDir::new().unwrap();
+        let server = instantiate_server(&dir).await;
+        let index_id = populate(&server).await;
+
+        // A write made once the index has been read is recorded and read
+        // again at the end of the export.
+        let (words, entries) = server.read_index(&index_id).await.unwrap();
+        server.journals.open(&index_id).await.unwrap();
+        let address = prepend_index_id(&Address::from([0; ADDRESS_LENGTH]), &index_id);
+        server
+            .journals
+            .write(&index_id, async {
+                server
+                    .db
+                    .guarded_write(
+                        (address, Some([0; CUSTOM_WORD_LENGTH])),
+                        vec![(address, [9; CUSTOM_WORD_LENGTH])],
+                    )
+                    .await?;
+                Ok(((), WrittenKeys::addresses([address])))
+            })
+            .await
+            .unwrap();
+        let written = server
+            .journals
+            .close(&index_id, |written| async { Ok(written) })
+            .await
+            .unwrap();
+        assert_eq!(written.addresses.len(), 1);
+        assert_eq!(words[&address], [0; CUSTOM_WORD_LENGTH]);
+        assert_eq!(entries.len(), 1);
+
+        // Writes made during an export are included in the archive.
+        let archive = server.export_index(&index_id, false).await.unwrap();
+        assert_eq!(archive.words[0].1, [9; CUSTOM_WORD_LENGTH]);
+
+        // An index cannot be exported twice at the same time.
+        server.journals.open(&index_id).await.unwrap();
+        server.export_index(&index_id, false).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_export_size_limit() {
+        let dir = TempDir::new().unwrap();
+        let mut server = instantiate_server(&dir).await;
+        let index_id = populate(&server).await;
+
+        // The index holds as many words and entries as an archive can.
+        server.params.max_archive_items = usize::from(N_WORDS) + 1;
+        server.export_index(&index_id, false).await.unwrap();
+
+        // A larger index is refused, and can be exported again afterwards.
+        server.params.max_archive_items = usize::from(N_WORDS);
+        server.export_index(&index_id, false).await.unwrap_err();
+        server.clone_index("carol", &index_id).await.unwrap_err();
+        assert!(server.journals.is_empty());
+        server.params.max_archive_items = usize::from(N_WORDS) + 1;
+        server.export_index(&index_id, false).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_only_blocks_the_writes_to_its_index() {
+        let dir = TempDir::new().unwrap();
+        let server = instantiate_server(&dir).await;
+        let (exported, other) = (populate(&server).await, populate(&server).await);
+
+        // While the keys written to an index are read again, the other indexes
+        // can be written to.
+        server.journals.open(&exported).await.unwrap();
+        server
+            .journals
+            .close(&exported, |_| async {
+                let write = server
+                    .journals
+                    .write(&other, async { Ok(((), WrittenKeys::default())) });
+                tokio::time::timeout(Duration::from_secs(5), write)
+                    .await
+                    .map_err(|e| {
+                        findex_server_error!("the write to the other index is blocked: {e}")
+                    })?
+            })
+            .await
+            .unwrap();
+
+        // The journals are forgotten once the export is done.
+        assert!(server.journals.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+//! The successful responses of the write requests sent with an
+//! `Idempotency-Key` header are kept during a configurable window, so that a
+//! retried request is answered with the original response instead of being
+//! run again.
+//!
+//! The keys are scoped to the user sending them, and bound to the request
+//! they were first sent with: its method, its path and the hash of its body. They are held in memory: a retry reaching
+//! another instance of the server, or sent after a restart, is run again.
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::header::HeaderValue, web::Bytes};
+
+/// The response to replay to the retries of a request.
+#[derive(Debug, Clone)]
+pub(crate) struct StoredResponse {
+    pub(crate) status: u16,
+    pub(crate) content_type: Option<HeaderValue>,
+    pub(crate) body: Bytes,
+}
+
+/// An idempotency key, scoped to the user sending it.
+type CacheKey = (String, String);
+
+/// The SHA-256 hash of the body of a request.
+pub(crate) type BodyHash = [u8; 32];
+
+enum Slot {
+    /// The request is being run.
+    InProgress,
+    Done {
+        /// The method and path of the request
+        request: String,
+        body_hash: BodyHash,
+        response: StoredResponse,
+        expires_at: Instant,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    slots: HashMap<CacheKey, Slot>,
+    /// The keys of the stored responses, in the order they expire in
+    expirations: VecDeque<(Instant, CacheKey)>,
+}
+
+impl State {
+    fn purge_expired(&mut self, now: Instant) {
+        while let Some((expires_at, _)) = self.expirations.front() {
+            if *expires_at > now {
+                break;
+            }
+            if let Some((expires_at, key)) = self.expirations.pop_front() {
+                if matches!(
+                    self.slots.get(&key),
+                    Some(Slot::Done { expires_at: e, .. }) if *e == expires_at
+                ) {
+                    self.slots.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// What to do with a request sent with an idempotency key.
+pub(crate) enum Lookup {
+    /// Run the request, then complete the reservation with its response.
+    Run(Reservation),
+    /// Answer with the response of the original request.
+    Replay(StoredResponse),
+    /// The original request is still being run.
+    InProgress,
+    /// The key was first sent with another request, described here.
+    Mismatch(String),
+}
+
+pub(crate) struct IdempotencyCache {
+    window: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    // The state is consistent between the statements holding the lock.
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl IdempotencyCache {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            state: Arc::default(),
+        }
+    }
+
+    /// Look up the key sent by the user with the request, given by its method
+    /// and path and the hash of its body, reserving the key if it is unknown.
+    pub(crate) fn begin(
+        &self,
+        user: &str,
+        key: &str,
+        request: &str,
+        body_hash: BodyHash,
+    ) -> Lookup {
+        let mut state = lock(&self.state);
+        state.purge_expired(Instant::now());
+
+        let cache_key = (user.to_owned(), key.to_owned());
+        match state.slots.get(&cache_key) {
+            None => {
+                state.slots.insert(cache_key.clone(), Slot::InProgress);
+                Lookup::Run(Reservation {
+                    state: self.state.clone(),
+                    window: self.window,
+                    key: Some(cache_key),
+                    request: request.to_owned(),
+                    body_hash,
+                })
+            }
+            Some(Slot::InProgress) => Lookup::InProgress,
+            Some(Slot::Done {
+                request: original, ..
+            }) if original != request => Lookup::Mismatch(original.clone()),
+            Some(Slot::Done {
+                request: original,
+                body_hash: original_hash,
+                ..
+            }) if *original_hash != body_hash => {
+                Lookup::Mismatch(format!("{original} with another body"))
+            }
+            Some(Slot::Done { response, .. }) => Lookup::Replay(response.clone()),
+        }
+    }
+}
+
+/// A key reserved for a request being run. The key is released if the
+/// reservation is dropped without being completed, e.g. if the request fails.
+pub(crate) struct Reservation {
+    state: Arc<Mutex<State>>,
+    window: Duration,
+    key: Option<CacheKey>,
+    request: String,
+    body_hash: BodyHash,
+}
+
+impl Reservation {
+    /// Store the response of the request, for its retries.
+    pub(crate) fn complete(mut self, response: StoredResponse) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        let expires_at = Instant::now() + self.window;
+        let mut state = lock(&self.state);
+        state.expirations.push_back((expires_at, key.clone()));
+        state.slots.insert(
+            key,
+            Slot::Done {
+                request: std::mem::take(&mut self.request),
+                body_hash: self.body_hash,
+                response,
+                expires_at,
+            },
+        );
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            lock(&self.state).slots.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_web::web::Bytes;
+
+    use super::{BodyHash, IdempotencyCache, Lookup, StoredResponse};
+
+    const BODY: BodyHash = [0; 32];
+
+    fn response(body: &'static str) -> StoredResponse {
+        StoredResponse {
+            status: 200,
+            content_type: None,
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_cache() {
+        let cache = IdempotencyCache::new(Duration::from_secs(3600));
+
+        let Lookup::Run(reservation) = cache.begin("alice", "key", "POST /create/index", BODY)
+        else {
+            panic!("an unknown key must be run");
+        };
+        assert!(matches!(
+            cache.begin("alice", "key", "POST /create/index", BODY),
+            Lookup::InProgress
+        ));
+        // The keys are scoped to the users
+        assert!(matches!(
+            cache.begin("bob", "key", "POST /create/index", BODY),
+            Lookup::Run(_)
+        ));
+        reservation.complete(response("created"));
+
+        let Lookup::Replay(replayed) = cache.begin("alice", "key", "POST /create/index", BODY)
+        else {
+            panic!("a completed key must be replayed");
+        };
+        assert_eq!(replayed.body, "created");
+        assert!(matches!(
+            cache.begin("alice", "key", "POST /permission/revoke/bob/index", BODY),
+            Lookup::Mismatch(request) if request == "POST /create/index"
+        ));
+        // The key is bound to the body of the request too
+        assert!(matches!(
+            cache.begin("alice", "key", "POST /create/index", [1; 32]),
+            Lookup::Mismatch(request) if request == "POST /create/index with another body"
+        ));
+
+        // A dropped reservation releases the key
+        let Lookup::Run(reservation) = cache.begin("alice", "other", "POST /create/index", BODY)
+        else {
+            panic!("an unknown key must be run");
+        };
+        drop(reservation);
+        assert!(matches!(
+            cache.begin("alice", "other", "POST /create/index", BODY),
+            Lookup::Run(_)
+        ));
+    }
+
+    #[test]
+    fn test_idempotency_cache_expiration() {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+
+        let Lookup::Run(reservation) = cache.begin("alice", "key", "POST /create/index", BODY)
+        else {
+            panic!("an unknown key must be run");
+        };
+        reservation.complete(response("created"));
+        assert!(matches!(
+            cache.begin("alice", "key", "POST /create/index", BODY),
+            Lookup::Run(_)
+        ));
+    }
+}
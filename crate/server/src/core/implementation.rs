@@ -1,50 +1,61 @@
+use std::time::Duration;
+
 use actix_web::{HttpMessage, HttpRequest};
 use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Permission};
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 use uuid::Uuid;
 
 use crate::{
-    config::{DEFAULT_SQLITE_PATH, DatabaseType, DbParams, ServerParams},
+    config::ServerParams,
+    core::{IdempotencyCache, WriteJournals},
     database::{
         FindexDatabase,
-        database_traits::{InstantiationTrait, PermissionsTrait},
+        database_traits::{DatasetsTrait, PermissionsTrait},
     },
     error::{result::FResult, server::ServerError},
     middlewares::{JwtAuthClaim, PeerCommonName},
 };
 
+/// The period at which the expired staged entries are purged
+const EXPIRED_ENTRIES_PURGE_PERIOD: Duration = Duration::from_secs(60);
+
 pub(crate) struct FindexServer {
     pub(crate) params: ServerParams,
     pub(crate) db: FindexDatabase<CUSTOM_WORD_LENGTH>,
+    pub(crate) journals: WriteJournals,
+    pub(crate) idempotency: IdempotencyCache,
 }
 
 impl FindexServer {
-    pub(crate) async fn instantiate(mut shared_config: ServerParams) -> FResult<Self> {
-        let db = match &mut shared_config.db_params {
-            DbParams::Redis(url) => {
-                FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate(
-                    DatabaseType::Redis,
-                    url.as_str(),
-                    shared_config.clear_db_on_start,
-                )
-                .await?
-            }
-            DbParams::Sqlite(path) => {
-                FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate(
-                    DatabaseType::Sqlite,
-                    path.to_str().unwrap_or(DEFAULT_SQLITE_PATH),
-                    shared_config.clear_db_on_start,
-                )
-                .await?
-            }
-        };
+    pub(crate) async fn instantiate(shared_config: ServerParams) -> FResult<Self> {
+        let db = FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate_from_params(
+            &shared_config.db_params,
+            shared_config.clear_db_on_start,
+        )
+        .await?;
 
         Ok(Self {
+            idempotency: IdempotencyCache::new(shared_config.idempotency_window),
             params: shared_config,
             db,
+            journals: WriteJournals::default(),
         })
     }
 
+    /// Purge the expired staged entries of all the indexes periodically, so
+    /// that the entries of the indexes which are no longer written to are
+    /// discarded too. Never returns.
+    pub(crate) async fn purge_expired_entries(&self) {
+        let mut interval = tokio::time::interval(EXPIRED_ENTRIES_PURGE_PERIOD);
+        loop {
+            interval.tick().await;
+            match self.db.dataset_purge_expired_entries().await {
+                Ok(purged) => trace!("{purged} expired staged entries purged"),
+                Err(e) => warn!("Failed to purge the expired staged entries: {e}"),
+            }
+        }
+    }
+
     /// Get the user from the request depending on the authentication method
     /// The user is encoded in the JWT `Authorization` header
     /// If the header is not present, the user is extracted from the client
@@ -52,6 +63,24 @@ impl FindexServer {
     /// If the client certificate is not present, the user is
     /// extracted from the configuration file
     pub(crate) fn get_user(&self, req_http: &HttpRequest) -> String {
+        let extensions = req_http.extensions();
+        self.resolve_user(
+            extensions
+                .get::<JwtAuthClaim>()
+                .map(|claim| claim.email.as_str()),
+            extensions
+                .get::<PeerCommonName>()
+                .map(|claim| claim.common_name.as_str()),
+        )
+    }
+
+    /// Resolve the user authenticated by the email of a JWT, or else by the
+    /// common name of a client certificate, or else the default user.
+    pub(crate) fn resolve_user(
+        &self,
+        jwt_email: Option<&str>,
+        common_name: Option<&str>,
+    ) -> String {
         let default_username = self.params.default_username.clone();
 
         if self.params.force_default_username {
@@ -62,15 +91,9 @@ impl FindexServer {
             return default_username;
         }
         // if there is a JWT token, use it in priority
-        let user = req_http.extensions().get::<JwtAuthClaim>().map_or_else(
-            || {
-                req_http
-                    .extensions()
-                    .get::<PeerCommonName>()
-                    .map_or(default_username, |claim| claim.common_name.clone())
-            },
-            |claim| claim.email.clone(),
-        );
+        let user = jwt_email
+            .or(common_name)
+            .map_or(default_username, ToOwned::to_owned);
         trace!("Authenticated user: {}", user);
         user
     }
@@ -1,3 +1,25 @@
+mod archive;
+mod idempotency;
 pub(crate) mod implementation;
+mod operations;
+mod write_journal;
 
+use cosmian_findex_structs::{SERVER_ADDRESS_LENGTH, UID_LENGTH};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+pub(crate) use idempotency::{IdempotencyCache, Lookup, StoredResponse};
 pub(crate) use implementation::FindexServer;
+use uuid::Uuid;
+pub(crate) use write_journal::{WriteJournals, WrittenKeys};
+
+/// Prepend the index ID to a Findex address, so that the indexes sharing the
+/// memory do not overlap.
+#[allow(clippy::indexing_slicing)]
+pub(crate) fn prepend_index_id(
+    address: &Address<ADDRESS_LENGTH>,
+    index_id: &Uuid,
+) -> Address<SERVER_ADDRESS_LENGTH> {
+    let mut server_address = Address::<{ SERVER_ADDRESS_LENGTH }>::from([0; SERVER_ADDRESS_LENGTH]);
+    server_address[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
+    server_address[UID_LENGTH..].copy_from_slice(&**address);
+    server_address
+}
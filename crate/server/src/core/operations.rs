@@ -0,0 +1,237 @@
+//! The operations offered by both the REST and the gRPC APIs, permission checks
+//! included.
+
+use std::collections::HashMap;
+
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, EncryptedEntries, Permission, Permissions, Uuids, VersionedEntries,
+};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use tracing::trace;
+use uuid::Uuid;
+
+use crate::{
+    core::{FindexServer, WrittenKeys, prepend_index_id},
+    database::database_traits::{DatasetsTrait, PermissionsTrait},
+    error::{result::FResult, server::ServerError},
+};
+
+type Word = [u8; CUSTOM_WORD_LENGTH];
+
+impl FindexServer {
+    pub(crate) async fn batch_read(
+        &self,
+        user: &str,
+        index_id: &str,
+        addresses: Vec<Address<ADDRESS_LENGTH>>,
+    ) -> FResult<Vec<Option<Word>>> {
+        self.ensure_minimum_permission(user, index_id, Permission::Read)
+            .await?;
+
+        let index_id = Uuid::parse_str(index_id)?;
+        let addresses = addresses
+            .into_iter()
+            .map(|a| prepend_index_id(&a, &index_id))
+            .collect::<Vec<_>>();
+
+        trace!("batch_read: number of addresses {}:", addresses.len());
+
+        let words = self.db.batch_read(addresses).await?;
+
+        trace!(
+            "batch_read successful. Number of non null words: {}.",
+            words.iter().filter(|w| w.is_some()).count()
+        );
+        Ok(words)
+    }
+
+    /// Read the words of several indexes in a single database call, the read
+    /// permission being required on each of them.
+    pub(crate) async fn batch_read_indexes(
+        &self,
+        user: &str,
+        addresses: HashMap<Uuid, Vec<Address<ADDRESS_LENGTH>>>,
+    ) -> FResult<HashMap<Uuid, Vec<Option<Word>>>> {
+        for index_id in addresses.keys() {
+            self.ensure_minimum_permission(user, &index_id.to_string(), Permission::Read)
+                .await?;
+        }
+
+        let addresses = addresses.into_iter().collect::<Vec<_>>();
+        let all_addresses = addresses
+            .iter()
+            .flat_map(|(index_id, addresses)| {
+                addresses.iter().map(|a| prepend_index_id(a, index_id))
+            })
+            .collect::<Vec<_>>();
+
+        trace!(
+            "batch_read_indexes: number of indexes {}, number of addresses {}",
+            addresses.len(),
+            all_addresses.len()
+        );
+
+        let mut words = self.db.batch_read(all_addresses).await?.into_iter();
+        Ok(addresses
+            .into_iter()
+            .map(|(index_id, addresses)| (index_id, words.by_ref().take(addresses.len()).collect()))
+            .collect())
+    }
+
+    pub(crate) async fn guarded_write(
+        &self,
+        user: &str,
+        index_id: &str,
+        (a_g, w_g): (Address<ADDRESS_LENGTH>, Option<Word>),
+        bindings: Vec<(Address<ADDRESS_LENGTH>, Word)>,
+    ) -> FResult<Option<Word>> {
+        self.ensure_minimum_permission(user, index_id, Permission::Write)
+            .await?;
+
+        let index_id = Uuid::parse_str(index_id)?;
+        let bindings = bindings
+            .into_iter()
+            .map(|(a, w)| (prepend_index_id(&a, &index_id), w))
+            .collect::<Vec<_>>();
+
+        self.journals
+            .write(&index_id, async {
+                let written = WrittenKeys::addresses(bindings.iter().map(|(a, _)| *a));
+                let result_word = self
+                    .db
+                    .guarded_write((prepend_index_id(&a_g, &index_id), w_g), bindings)
+                    .await?;
+                // The bindings are written only if the guard holds.
+                if result_word == w_g {
+                    Ok((result_word, written))
+                } else {
+                    Ok((result_word, WrittenKeys::default()))
+                }
+            })
+            .await
+    }
+
+    pub(crate) async fn add_entries(
+        &self,
+        user: &str,
+        index_id: &str,
+        encrypted_entries: &EncryptedEntries,
+    ) -> FResult<Uuid> {
+        self.ensure_minimum_permission(user, index_id, Permission::Write)
+            .await?;
+
+        let index_id = Uuid::parse_str(index_id)?;
+
+        trace!(
+            "add_entries: number of encrypted entries: {}:",
+            encrypted_entries.len()
+        );
+
+        self.journals
+            .write(&index_id, async {
+                self.db
+                    .dataset_add_entries(&index_id, encrypted_entries)
+                    .await?;
+                Ok(((), WrittenKeys::entries(encrypted_entries.keys().copied())))
+            })
+            .await?;
+        Ok(index_id)
+    }
+
+    pub(crate) async fn delete_entries(
+        &self,
+        user: &str,
+        index_id: &str,
+        uuids: &Uuids,
+    ) -> FResult<Uuid> {
+        self.ensure_minimum_permission(user, index_id, Permission::Write)
+            .await?;
+
+        let index_id = Uuid::parse_str(index_id)?;
+
+        trace!(
+            "delete_entries: number of UUIDs: {} from index {}:",
+            uuids.len(),
+            index_id
+        );
+
+        self.journals
+            .write(&index_id, async {
+                self.db.dataset_delete_entries(&index_id, uuids).await?;
+                Ok(((), WrittenKeys::entries(uuids.iter().copied())))
+            })
+            .await?;
+        Ok(index_id)
+    }
+
+    pub(crate) async fn get_entries(
+        &self,
+        user: &str,
+        index_id: &str,
+        uuids: &Uuids,
+    ) -> FResult<VersionedEntries> {
+        self.ensure_minimum_permission(user, index_id, Permission::Read)
+            .await?;
+
+        let index_id = Uuid::parse_str(index_id)?;
+
+        trace!("get_entries: number of UUIDs: {}:", uuids.len());
+
+        Ok(self.db.dataset_get_entries(&index_id, uuids).await?)
+    }
+
+    /// Set the permission of a user on an index: only its admins can do that.
+    pub(crate) async fn set_permission(
+        &self,
+        user: &str,
+        user_id: &str,
+        permission: Permission,
+        index_id: &str,
+    ) -> FResult<Uuid> {
+        let user_permission = self.get_permission(user, index_id).await?;
+        if Permission::Admin != user_permission {
+            return Err(ServerError::Unauthorized(format!(
+                "Delegating permission to an index requires an admin permission. User {user} \
+                 with permission {user_permission} does not allow setting permission to index \
+                 {index_id} with permission {permission}",
+            )));
+        }
+
+        let index_id = Uuid::parse_str(index_id)?;
+        self.db
+            .set_permission(user_id, permission, &index_id)
+            .await?;
+        Ok(index_id)
+    }
+
+    /// List the permissions of a user, the ones the requesting user also has.
+    pub(crate) async fn list_permissions(&self, user: &str, user_id: &str) -> FResult<Permissions> {
+        let request_user_permissions = self.db.get_permissions(user).await?;
+        let requested_user_permissions = self.db.get_permissions(user_id).await?;
+
+        // To avoid a user to lookup who are the more powerful users only display the
+        // minimum permission between the two users
+        Ok(requested_user_permissions.min(&request_user_permissions))
+    }
+
+    /// Revoke the permission of a user on an index: only its admins can do that.
+    pub(crate) async fn revoke_permission(
+        &self,
+        user: &str,
+        user_id: &str,
+        index_id: &str,
+    ) -> FResult<Uuid> {
+        let user_permission = self.get_permission(user, index_id).await?;
+        if Permission::Admin != user_permission {
+            return Err(ServerError::Unauthorized(format!(
+                "Revoking permission to an index requires an admin permission. User {user} with \
+                 permission {user_permission} does not allow revoking permission to index \
+                 {index_id}",
+            )));
+        }
+
+        let index_id = Uuid::parse_str(index_id)?;
+        self.db.revoke_permission(user_id, &index_id).await?;
+        Ok(index_id)
+    }
+}
@@ -0,0 +1,156 @@
+//! The words and the entries of an index are exported in several batches
+//! while the index keeps being written to. The keys written during the export
+//! of an index are recorded in a journal and read again once the writes are
+//! blocked, so that the archive holds the state of the index at the end of
+//! the export.
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    sync::{Arc, MutexGuard, PoisonError},
+};
+
+use cosmian_findex_structs::SERVER_ADDRESS_LENGTH;
+use cosmian_sse_memories::Address;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::error::{result::FResult, server::ServerError};
+
+/// The memory addresses and the dataset entries written to an index.
+#[derive(Default)]
+pub(crate) struct WrittenKeys {
+    pub(crate) addresses: HashSet<Address<SERVER_ADDRESS_LENGTH>>,
+    pub(crate) entries: HashSet<Uuid>,
+}
+
+impl WrittenKeys {
+    pub(crate) fn addresses(
+        addresses: impl IntoIterator<Item = Address<SERVER_ADDRESS_LENGTH>>,
+    ) -> Self {
+        Self {
+            addresses: addresses.into_iter().collect(),
+            entries: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn entries(entries: impl IntoIterator<Item = Uuid>) -> Self {
+        Self {
+            addresses: HashSet::new(),
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    fn extend(&mut self, other: Self) {
+        self.addresses.extend(other.addresses);
+        self.entries.extend(other.entries);
+    }
+}
+
+/// The keys written to an index while it is being exported, `None` when it is
+/// not.
+type Journal = Arc<RwLock<Option<Mutex<WrittenKeys>>>>;
+
+#[derive(Default)]
+pub(crate) struct WriteJournals {
+    // The writes to an index hold the read lock of its journal until they are
+    // recorded, so that taking the write lock to open or close the journal
+    // waits for the writes in progress to this index and blocks the following
+    // ones, without blocking the writes to the other indexes. The journal of
+    // an index is only kept while it is in use or open.
+    journals: std::sync::Mutex<HashMap<Uuid, Journal>>,
+}
+
+/// A journal in use, forgotten on drop once it is neither in use nor open.
+struct JournalRef<'a> {
+    journals: &'a WriteJournals,
+    index_id: Uuid,
+    journal: Journal,
+}
+
+impl Deref for JournalRef<'_> {
+    type Target = RwLock<Option<Mutex<WrittenKeys>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.journal
+    }
+}
+
+impl Drop for JournalRef<'_> {
+    fn drop(&mut self) {
+        let mut journals = self.journals.lock();
+        // The journals are only shared under the lock of the map: a journal
+        // referenced by the map and this reference only is not in use.
+        let unused = Arc::strong_count(&self.journal) == 2
+            && self
+                .journal
+                .try_read()
+                .is_ok_and(|journal| journal.is_none());
+        if unused {
+            journals.remove(&self.index_id);
+        }
+    }
+}
+
+impl WriteJournals {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, Journal>> {
+        self.journals.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn journal(&self, index_id: &Uuid) -> JournalRef<'_> {
+        JournalRef {
+            journals: self,
+            index_id: *index_id,
+            journal: self.lock().entry(*index_id).or_default().clone(),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Run a write to an index, and record the keys it wrote if the index is
+    /// being exported.
+    pub(crate) async fn write<T>(
+        &self,
+        index_id: &Uuid,
+        write: impl Future<Output = FResult<(T, WrittenKeys)>>,
+    ) -> FResult<T> {
+        let journal = self.journal(index_id);
+        let recording = journal.read().await;
+        let (result, written) = write.await?;
+        if let Some(keys) = recording.as_ref() {
+            keys.lock().await.extend(written);
+        }
+        Ok(result)
+    }
+
+    /// Start recording the keys written to an index.
+    pub(crate) async fn open(&self, index_id: &Uuid) -> FResult<()> {
+        let journal = self.journal(index_id);
+        let mut recording = journal.write().await;
+        if recording.is_some() {
+            return Err(ServerError::InvalidRequest(format!(
+                "Index {index_id} is already being exported"
+            )));
+        }
+        *recording = Some(Mutex::default());
+        Ok(())
+    }
+
+    /// Stop recording the keys written to an index, and pass them to `read`
+    /// which runs while the writes to this index are blocked.
+    pub(crate) async fn close<T, F>(
+        &self,
+        index_id: &Uuid,
+        read: impl FnOnce(WrittenKeys) -> F,
+    ) -> FResult<T>
+    where
+        F: Future<Output = FResult<T>>,
+    {
+        let journal = self.journal(index_id);
+        let mut recording = journal.write().await;
+        let written = recording.take().map(Mutex::into_inner).unwrap_or_default();
+        read(written).await
+    }
+}
@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
-use cosmian_findex_structs::{EncryptedEntries, Permission, Permissions, Uuids};
-use cosmian_sse_memories::MemoryADT;
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, EncryptedEntries, Permission, Permissions, SERVER_ADDRESS_LENGTH, Uuids,
+    VersionedEntries, VersionedEntry,
+};
+use cosmian_sse_memories::{Address, MemoryADT};
 use uuid::Uuid;
 
 use super::findex_database::DatabaseResult;
@@ -34,11 +39,120 @@ pub(crate) trait DatasetsTrait: Sync + Send {
         entries: &EncryptedEntries,
     ) -> DatabaseResult<()>;
     async fn dataset_delete_entries(&self, index_id: &Uuid, uuids: &Uuids) -> DatabaseResult<()>;
+    /// Read entries along with their versions. The missing entries are
+    /// omitted.
     async fn dataset_get_entries(
         &self,
         index_id: &Uuid,
         uuids: &Uuids,
-    ) -> DatabaseResult<EncryptedEntries>;
+    ) -> DatabaseResult<VersionedEntries>;
+    /// Write entries if their current versions are the given ones, a missing
+    /// entry having the version 0, and increment these versions.
+    ///
+    /// No entry is written if one of the versions differs: a
+    /// `VersionConflict` error holding the current versions of the
+    /// conflicting entries is returned instead.
+    async fn dataset_update_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()>;
+    /// List the IDs and the sizes of at most `limit` entries of an index,
+    /// starting at `cursor` or at the first entry when `None`. A page may be
+    /// empty while the listing is not over: it is over once no cursor is
+    /// returned.
+    async fn dataset_list_entries(
+        &self,
+        index_id: &Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> DatabaseResult<ScanPage<EntrySize>>;
+    /// Stage entries under a transaction. The staged entries are not part
+    /// of the dataset until the transaction is committed, and are discarded
+    /// once `ttl` has elapsed without a commit.
+    async fn dataset_stage_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+        entries: &EncryptedEntries,
+        ttl: Duration,
+    ) -> DatabaseResult<()>;
+    /// Add the entries staged under a transaction to the dataset, as
+    /// `dataset_add_entries` does, and return their IDs. An
+    /// `UnknownTransaction` error is returned if the transaction does not
+    /// exist or has expired.
+    async fn dataset_commit_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<Uuids>;
+    /// Discard the entries staged under a transaction, if any.
+    async fn dataset_abort_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<()>;
+    /// Discard the staged entries of all the indexes whose transaction has
+    /// expired, and return their number.
+    async fn dataset_purge_expired_entries(&self) -> DatabaseResult<usize>;
+}
+
+/// A page of items read from a database.
+#[derive(Debug)]
+pub(crate) struct ScanPage<T> {
+    pub(crate) items: Vec<T>,
+    /// The cursor to pass to read the next page, `None` once the scan is
+    /// over. Its format is specific to each backend.
+    pub(crate) next_cursor: Option<String>,
+}
+
+/// A memory word and the server address it is stored at.
+pub(crate) type MemoryWord = (Address<SERVER_ADDRESS_LENGTH>, [u8; CUSTOM_WORD_LENGTH]);
+
+/// The permission granted to a user on an index.
+pub(crate) type UserPermission = (String, Uuid, Permission);
+
+/// The ID of a dataset entry and the size of its ciphertext, in bytes.
+pub(crate) type EntrySize = (Uuid, usize);
+
+/// An encrypted dataset entry: the index ID, the entry ID and the ciphertext
+/// with its version.
+pub(crate) type DatasetEntry = (Uuid, Uuid, VersionedEntry);
+
+#[async_trait]
+pub(crate) trait MigrationTrait: Sync + Send {
+    //
+    // Bulk access used to copy a database or an index
+    //
+    /// Read a page of about `count` memory words, starting at `cursor` or at
+    /// the beginning of the memory when `None`. The scans read the items of
+    /// all the indexes, or of the given one.
+    async fn memory_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<MemoryWord>>;
+    /// Write memory words unconditionally.
+    async fn memory_restore(&self, words: Vec<MemoryWord>) -> DatabaseResult<()>;
+    async fn permissions_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<UserPermission>>;
+    async fn datasets_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<DatasetEntry>>;
+    /// Write dataset entries unconditionally, with their versions.
+    async fn dataset_restore_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()>;
 }
 
 #[async_trait]
@@ -52,6 +166,6 @@ pub(crate) trait InstantiationTrait: Sync + Send + Sized {
 #[allow(dead_code)] // false positive, used in crate/server/src/database/redis/mod.rs
 #[async_trait]
 pub(crate) trait DatabaseTraits:
-    PermissionsTrait + DatasetsTrait + InstantiationTrait + MemoryADT
+    PermissionsTrait + DatasetsTrait + MigrationTrait + InstantiationTrait + MemoryADT
 {
 }
@@ -1,7 +1,7 @@
-use cosmian_sse_memories::RedisMemoryError;
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::database::sqlite::SqliteMemoryError;
+use crate::database::{redis::RedisMemoryError, sqlite::SqliteMemoryError};
 
 /// Wraps memory errors from different findex memories
 #[derive(Error, Debug)]
@@ -21,8 +21,28 @@ pub(crate) enum DatabaseError {
     // something else that's not convertible to the expected type
     #[error("Database returned invalid data : {0}")]
     InvalidDatabaseResponse(String),
+    #[error("Version conflict, the current versions are: {}", format_versions(.0))]
+    VersionConflict(Vec<(Uuid, u64)>),
+    #[error("Unknown or expired transaction: {0}")]
+    UnknownTransaction(Uuid),
+    #[error("Invalid scan cursor: {0}")]
+    InvalidCursor(String),
+    #[error("Redis key layout: {0}")]
+    LegacyLayout(String),
+    #[error("Invalid Redis key prefix, which cannot contain '{{' or '}}': {0}")]
+    InvalidKeyPrefix(String),
+    #[error("Invalid SQLite database path, which is not valid UTF-8: {0}")]
+    InvalidSqlitePath(String),
     #[error("Invalid database type: {0} expected, {1} passed")]
     InvalidDatabaseType(String, String),
     #[error("Invalid database url: {0}")]
     StdIoError(#[from] std::io::Error),
 }
+
+fn format_versions(versions: &[(Uuid, u64)]) -> String {
+    versions
+        .iter()
+        .map(|(uid, version)| format!("{uid}: {version}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
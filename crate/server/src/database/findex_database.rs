@@ -3,20 +3,26 @@
 //! This module provides a unified interface to different database backends for the Findex server.
 //! It implements an abstraction layer that allows the application to work with any implemented DB backend.
 //! and use databases interchangeably through a common API defined by various traits.
+use std::time::Duration;
+
 use async_trait::async_trait;
 use cosmian_findex_structs::{
     CUSTOM_WORD_LENGTH, EncryptedEntries, Permission, Permissions, SERVER_ADDRESS_LENGTH, Uuids,
+    VersionedEntries,
 };
 use cosmian_sse_memories::{Address, MemoryADT};
 use uuid::Uuid;
 
 use super::{
-    database_traits::{DatabaseTraits, DatasetsTrait, InstantiationTrait, PermissionsTrait},
+    database_traits::{
+        DatabaseTraits, DatasetEntry, DatasetsTrait, EntrySize, InstantiationTrait, MemoryWord,
+        MigrationTrait, PermissionsTrait, ScanPage, UserPermission,
+    },
     error::DatabaseError,
     redis::Redis,
     sqlite::Sqlite,
 };
-use crate::config::DatabaseType;
+use crate::config::{DatabaseType, DbParams, RedisParams};
 
 pub(crate) type DatabaseResult<R> = Result<R, DatabaseError>;
 
@@ -35,6 +41,37 @@ macro_rules! delegate_to_db {
         };
     }
 
+impl<const WORD_LENGTH: usize> FindexDatabase<WORD_LENGTH> {
+    /// Instantiate a Redis database of any supported topology.
+    pub(crate) async fn instantiate_redis(
+        params: &RedisParams,
+        clear_database: bool,
+    ) -> DatabaseResult<Self> {
+        Ok(Self::Redis(
+            Redis::instantiate_with_params(params, clear_database).await?,
+        ))
+    }
+
+    /// Instantiate the database described by the parameters.
+    pub(crate) async fn instantiate_from_params(
+        params: &DbParams,
+        clear_database: bool,
+    ) -> DatabaseResult<Self> {
+        match params {
+            DbParams::Redis(redis_params) => {
+                Self::instantiate_redis(redis_params, clear_database).await
+            }
+            DbParams::Sqlite(path) => {
+                // Falling back to another path would open another database.
+                let path = path
+                    .to_str()
+                    .ok_or_else(|| DatabaseError::InvalidSqlitePath(path.display().to_string()))?;
+                Self::instantiate(DatabaseType::Sqlite, path, clear_database).await
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl DatabaseTraits for FindexDatabase<CUSTOM_WORD_LENGTH> {}
 
@@ -84,9 +121,104 @@ impl DatasetsTrait for FindexDatabase<CUSTOM_WORD_LENGTH> {
         &self,
         index_id: &Uuid,
         uuids: &Uuids,
-    ) -> DatabaseResult<EncryptedEntries> {
+    ) -> DatabaseResult<VersionedEntries> {
         delegate_to_db!(self, dataset_get_entries, index_id, uuids)
     }
+
+    async fn dataset_update_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        delegate_to_db!(self, dataset_update_entries, index_id, entries)
+    }
+
+    async fn dataset_list_entries(
+        &self,
+        index_id: &Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> DatabaseResult<ScanPage<EntrySize>> {
+        delegate_to_db!(self, dataset_list_entries, index_id, cursor, limit)
+    }
+
+    async fn dataset_stage_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+        entries: &EncryptedEntries,
+        ttl: Duration,
+    ) -> DatabaseResult<()> {
+        delegate_to_db!(
+            self,
+            dataset_stage_entries,
+            index_id,
+            transaction_id,
+            entries,
+            ttl
+        )
+    }
+
+    async fn dataset_commit_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<Uuids> {
+        delegate_to_db!(self, dataset_commit_entries, index_id, transaction_id)
+    }
+
+    async fn dataset_abort_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<()> {
+        delegate_to_db!(self, dataset_abort_entries, index_id, transaction_id)
+    }
+    async fn dataset_purge_expired_entries(&self) -> DatabaseResult<usize> {
+        delegate_to_db!(self, dataset_purge_expired_entries)
+    }
+}
+
+#[async_trait]
+impl MigrationTrait for FindexDatabase<CUSTOM_WORD_LENGTH> {
+    async fn memory_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<MemoryWord>> {
+        delegate_to_db!(self, memory_scan, index_id, cursor, count)
+    }
+
+    async fn memory_restore(&self, words: Vec<MemoryWord>) -> DatabaseResult<()> {
+        delegate_to_db!(self, memory_restore, words)
+    }
+
+    async fn permissions_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<UserPermission>> {
+        delegate_to_db!(self, permissions_scan, index_id, cursor, count)
+    }
+
+    async fn datasets_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<DatasetEntry>> {
+        delegate_to_db!(self, datasets_scan, index_id, cursor, count)
+    }
+
+    async fn dataset_restore_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        delegate_to_db!(self, dataset_restore_entries, index_id, entries)
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,303 @@
+//! Connections to the Redis deployments supported by the Findex server:
+//! standalone instances, Redis Cluster and Sentinel-managed deployments.
+use std::sync::Arc;
+
+use redis::{
+    Cmd, ErrorKind, IntoConnectionInfo, Pipeline, RedisError, RedisFuture, RedisResult, Value,
+    aio::{ConnectionLike, ConnectionManager},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    cluster_routing::{
+        MultipleNodeRoutingInfo, Route, RoutingInfo, SingleNodeRoutingInfo, SlotAddr,
+    },
+    sentinel::{SentinelClient, SentinelNodeConnectionInfo, SentinelServerType},
+};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::config::RedisTopology;
+
+/// A connection to a Redis deployment, whatever its topology.
+///
+/// Cloning is cheap: the clones share the underlying connections.
+#[derive(Clone)]
+pub(crate) enum RedisConnection {
+    Standalone(ConnectionManager),
+    Cluster(ClusterConnection),
+    Sentinel(SentinelConnection),
+}
+
+impl RedisConnection {
+    pub(crate) async fn connect(topology: &RedisTopology) -> RedisResult<Self> {
+        match topology {
+            RedisTopology::Standalone(url) => {
+                let client = redis::Client::open(url.as_str())?;
+                Ok(Self::Standalone(client.get_connection_manager().await?))
+            }
+            RedisTopology::Cluster(nodes) => {
+                let client = ClusterClient::new(nodes.clone())?;
+                Ok(Self::Cluster(client.get_async_connection().await?))
+            }
+            RedisTopology::Sentinel {
+                sentinels,
+                master_name,
+            } => Ok(Self::Sentinel(
+                SentinelConnection::connect(sentinels, master_name).await?,
+            )),
+        }
+    }
+}
+
+/// A node whose keys can be scanned: the only node of a standalone or
+/// Sentinel-managed deployment, or one of the primaries of a Redis Cluster.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ScanNode {
+    Single,
+    Primary {
+        host: String,
+        port: u16,
+    },
+    /// The primary of a Redis Cluster owning a hash slot
+    SlotOwner(u16),
+}
+
+/// The number of hash slots of a Redis Cluster
+const CLUSTER_SLOTS: u16 = 16384;
+
+/// The hash slot of the keys of a hash tag: the CRC16 (XMODEM) of the tag,
+/// modulo the number of slots.
+fn hash_slot(hash_tag: &str) -> u16 {
+    let crc = hash_tag.bytes().fold(0_u16, |crc, byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    });
+    crc % CLUSTER_SLOTS
+}
+
+impl RedisConnection {
+    /// List the nodes holding the keys of the deployment, sorted so that a
+    /// node can be designated by its position in the list.
+    ///
+    /// The keys of a hash tag are all held by the node owning its slot, which
+    /// is the only node listed when a hash tag is given.
+    pub(crate) async fn scan_nodes(&self, hash_tag: Option<&str>) -> RedisResult<Vec<ScanNode>> {
+        let Self::Cluster(connection) = self else {
+            return Ok(vec![ScanNode::Single]);
+        };
+        if let Some(hash_tag) = hash_tag {
+            return Ok(vec![ScanNode::SlotOwner(hash_slot(hash_tag))]);
+        }
+        // Without a response policy, the replies are mapped to the address of
+        // the primary that sent them.
+        let replies = connection
+            .clone()
+            .route_command(
+                &redis::cmd("PING"),
+                RoutingInfo::MultiNode((MultipleNodeRoutingInfo::AllMasters, None)),
+            )
+            .await?;
+        let Value::Map(replies) = replies else {
+            return Err(RedisError::from((
+                ErrorKind::TypeError,
+                "Unexpected reply when listing the cluster primaries",
+            )));
+        };
+        let mut nodes = replies
+            .into_iter()
+            .map(|(address, _)| {
+                let address: String = redis::from_owned_redis_value(address)?;
+                address
+                    .rsplit_once(':')
+                    .and_then(|(host, port)| {
+                        Some(ScanNode::Primary {
+                            host: host.to_owned(),
+                            port: port.parse().ok()?,
+                        })
+                    })
+                    .ok_or_else(|| {
+                        RedisError::from((
+                            ErrorKind::TypeError,
+                            "Invalid address of a cluster primary",
+                            address,
+                        ))
+                    })
+            })
+            .collect::<RedisResult<Vec<_>>>()?;
+        nodes.sort();
+        Ok(nodes)
+    }
+
+    /// Run one iteration of `SCAN` on a node.
+    ///
+    /// Returns the cursor of the next iteration, which is 0 when the scan is
+    /// over, and the keys matching the pattern.
+    pub(crate) async fn scan(
+        &self,
+        node: &ScanNode,
+        cursor: u64,
+        pattern: &str,
+        count: usize,
+    ) -> RedisResult<(u64, Vec<Vec<u8>>)> {
+        let mut cmd = redis::cmd("SCAN");
+        cmd.arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(count);
+        let routing = match node {
+            ScanNode::Single => None,
+            ScanNode::Primary { host, port } => Some(SingleNodeRoutingInfo::ByAddress {
+                host: host.clone(),
+                port: *port,
+            }),
+            ScanNode::SlotOwner(slot) => Some(SingleNodeRoutingInfo::SpecificNode(Route::new(
+                *slot,
+                SlotAddr::Master,
+            ))),
+        };
+        match (self, routing) {
+            (Self::Cluster(connection), Some(routing)) => {
+                let reply = connection
+                    .clone()
+                    .route_command(&cmd, RoutingInfo::SingleNode(routing))
+                    .await?;
+                redis::from_owned_redis_value(reply)
+            }
+            _ => cmd.query_async(&mut self.clone()).await,
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Self::Standalone(manager) => manager.req_packed_command(cmd),
+            Self::Cluster(connection) => connection.req_packed_command(cmd),
+            Self::Sentinel(sentinel) => Box::pin(async move {
+                let mut manager = sentinel.manager().await;
+                let result = manager.req_packed_command(cmd).await;
+                if let Err(e) = &result {
+                    sentinel.handle_error(e).await;
+                }
+                result
+            }),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::Standalone(manager) => manager.req_packed_commands(cmd, offset, count),
+            Self::Cluster(connection) => connection.req_packed_commands(cmd, offset, count),
+            Self::Sentinel(sentinel) => Box::pin(async move {
+                let mut manager = sentinel.manager().await;
+                let result = manager.req_packed_commands(cmd, offset, count).await;
+                if let Err(e) = &result {
+                    sentinel.handle_error(e).await;
+                }
+                result
+            }),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::Standalone(manager) => manager.get_db(),
+            Self::Cluster(connection) => connection.get_db(),
+            Self::Sentinel(sentinel) => sentinel.db,
+        }
+    }
+}
+
+/// A connection to the master of a Sentinel-managed deployment.
+///
+/// The address of the master is resolved through the Sentinel nodes. When a
+/// command fails because the master is unreachable or has been demoted to a
+/// replica, the address is resolved again so that the following commands
+/// reach the newly promoted master.
+#[derive(Clone)]
+pub(crate) struct SentinelConnection {
+    sentinel: Arc<Mutex<SentinelClient>>,
+    manager: Arc<RwLock<ConnectionManager>>,
+    db: i64,
+}
+
+impl SentinelConnection {
+    async fn connect(sentinels: &[Url], master_name: &str) -> RedisResult<Self> {
+        // The credentials and the database number of the first Sentinel URL
+        // are also used to connect to the master.
+        let redis_connection_info = sentinels
+            .first()
+            .map(|url| url.clone().into_connection_info())
+            .transpose()?
+            .map(|info| info.redis);
+        let db = redis_connection_info.as_ref().map_or(0, |info| info.db);
+        let mut sentinel = SentinelClient::build(
+            sentinels.to_vec(),
+            master_name.to_owned(),
+            Some(SentinelNodeConnectionInfo {
+                tls_mode: None,
+                redis_connection_info,
+            }),
+            SentinelServerType::Master,
+        )?;
+        let manager = sentinel
+            .async_get_client()
+            .await?
+            .get_connection_manager()
+            .await?;
+        Ok(Self {
+            sentinel: Arc::new(Mutex::new(sentinel)),
+            manager: Arc::new(RwLock::new(manager)),
+            db,
+        })
+    }
+
+    async fn manager(&self) -> ConnectionManager {
+        self.manager.read().await.clone()
+    }
+
+    /// Resolve the master again when the error may be caused by a failover.
+    async fn handle_error(&self, error: &RedisError) {
+        if error.kind() == ErrorKind::ReadOnly
+            || error.is_io_error()
+            || error.is_connection_dropped()
+        {
+            if let Err(e) = self.refresh_master().await {
+                warn!("Failed to resolve the Redis master through Sentinel: {e}");
+            }
+        }
+    }
+
+    async fn refresh_master(&self) -> RedisResult<()> {
+        let client = self.sentinel.lock().await.async_get_client().await?;
+        let manager = client.get_connection_manager().await?;
+        *self.manager.write().await = manager;
+        info!("Reconnected to the Redis master resolved through Sentinel");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_slot() {
+        // The test vector of the Redis Cluster specification, and the slots
+        // returned by `CLUSTER KEYSLOT`.
+        assert_eq!(hash_slot("123456789"), 0x31C3);
+        assert_eq!(hash_slot("foo"), 12182);
+        assert_eq!(hash_slot(""), 0);
+    }
+}
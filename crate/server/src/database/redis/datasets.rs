@@ -1,20 +1,117 @@
-use std::collections::HashMap;
+//! The version of a dataset entry is stored under its own key, next to the
+//! entry. Entries written before the versions were introduced have no version
+//! key and are considered at version 1. The version key of a deleted entry is
+//! kept, so that the entry resumes from this version if it is written again.
+//!
+//! The entries staged under a transaction are stored in a hash which expires
+//! unless the transaction is committed.
+use std::{sync::LazyLock, time::Duration};
 
 use async_trait::async_trait;
-use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, EncryptedEntries, Uuids};
-use redis::pipe;
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, EncryptedEntries, Uuids, VersionedEntries, VersionedEntry,
+};
+use redis::{Script, pipe};
 use tracing::{instrument, trace};
 use uuid::Uuid;
 
-use super::Redis;
-use crate::database::{database_traits::DatasetsTrait, findex_database::DatabaseResult};
+use super::{Redis, migration::parsed};
+use crate::database::{
+    DatabaseError,
+    database_traits::{DatasetsTrait, EntrySize, ScanPage},
+    findex_database::DatabaseResult,
+};
 
-/// Generate a Redis-key for the dataset table
-fn build_redis_key(index_id: &Uuid, uid: &Uuid) -> Vec<u8> {
-    let mut key = Vec::with_capacity(32);
-    key.extend_from_slice(index_id.as_bytes());
-    key.extend_from_slice(uid.as_bytes());
-    key
+/// Write the entries and increment their versions.
+///
+/// `KEYS[2i-1]` and `KEYS[2i]` are the keys of the i-th entry and of its
+/// version, `ARGV[i]` holds the i-th entry.
+static DATASET_ADD_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+for i = 1, #ARGV do
+    local version = tonumber(redis.call('GET', KEYS[2 * i]))
+        or redis.call('EXISTS', KEYS[2 * i - 1])
+    redis.call('SET', KEYS[2 * i - 1], ARGV[i])
+    redis.call('SET', KEYS[2 * i], version + 1)
+end
+return 0
+",
+    )
+});
+
+/// Write the entries if they all have the expected versions, atomically.
+///
+/// `KEYS` are laid out as for [`DATASET_ADD_SCRIPT`], `ARGV[2i-1]` holds the
+/// expected version of the i-th entry and `ARGV[2i]` the entry, a missing
+/// entry being at version 0. The position and the current version of each
+/// entry whose version differs are returned, nothing being written if any.
+static DATASET_UPDATE_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+local conflicts = {}
+for i = 1, #KEYS / 2 do
+    local version = 0
+    if redis.call('EXISTS', KEYS[2 * i - 1]) == 1 then
+        version = tonumber(redis.call('GET', KEYS[2 * i])) or 1
+    end
+    if version ~= tonumber(ARGV[2 * i - 1]) then
+        table.insert(conflicts, i)
+        table.insert(conflicts, version)
+    end
+end
+if #conflicts == 0 then
+    for i = 1, #KEYS / 2 do
+        local version = tonumber(redis.call('GET', KEYS[2 * i]))
+            or redis.call('EXISTS', KEYS[2 * i - 1])
+        redis.call('SET', KEYS[2 * i - 1], ARGV[2 * i])
+        redis.call('SET', KEYS[2 * i], version + 1)
+    end
+end
+return conflicts
+",
+    )
+});
+
+/// Move the entries staged under a transaction to the dataset, and increment
+/// their versions.
+///
+/// `KEYS[1]` is the hash of the staged entries, `KEYS[2i]` and `KEYS[2i+1]`
+/// are the keys of the i-th entry and of its version, `ARGV[i]` holds the ID
+/// of the i-th entry, under which it is staged. Returns 0 if the transaction
+/// expired in the meantime, nothing being written.
+static DATASET_COMMIT_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+for i = 1, #ARGV do
+    local entry = redis.call('HGET', KEYS[1], ARGV[i])
+    if entry then
+        local version = tonumber(redis.call('GET', KEYS[2 * i + 1]))
+            or redis.call('EXISTS', KEYS[2 * i])
+        redis.call('SET', KEYS[2 * i], entry)
+        redis.call('SET', KEYS[2 * i + 1], version + 1)
+    end
+end
+redis.call('DEL', KEYS[1])
+return 1
+",
+    )
+});
+
+/// Parse the value of the version key of an existing entry, which is at
+/// version 1 without such key.
+pub(super) fn parse_version(id: &Uuid, version: Option<Vec<u8>>) -> DatabaseResult<u64> {
+    version.map_or(Ok(1), |version| {
+        std::str::from_utf8(&version)
+            .ok()
+            .and_then(|version| version.parse().ok())
+            .ok_or_else(|| {
+                DatabaseError::InvalidDatabaseResponse(format!("invalid version of the entry {id}"))
+            })
+    })
 }
 
 #[async_trait]
@@ -28,23 +125,35 @@ impl DatasetsTrait for Redis<CUSTOM_WORD_LENGTH> {
         index_id: &Uuid,
         entries: &EncryptedEntries,
     ) -> DatabaseResult<()> {
-        Ok(entries
-            .iter()
-            .map(|(id, data)| (build_redis_key(index_id, id), data))
-            .fold(&mut pipe(), |pipe, (key, data)| pipe.set(key, data))
-            .atomic()
-            .query_async(&mut self.manager.clone())
-            .await?)
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut invocation = DATASET_ADD_SCRIPT.prepare_invoke();
+        for (id, data) in entries.iter() {
+            invocation
+                .key(self.keys.dataset(index_id, id))
+                .key(self.keys.dataset_version(index_id, id))
+                .arg(data.as_slice());
+        }
+        invocation
+            .invoke_async::<()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
     }
 
     #[instrument(ret, err, skip(self), level = "trace")]
     async fn dataset_delete_entries(&self, index_id: &Uuid, ids: &Uuids) -> DatabaseResult<()> {
-        Ok(ids
-            .iter()
-            .map(|id| build_redis_key(index_id, id))
-            .fold(&mut pipe(), |pipe, key| pipe.del(key))
-            .atomic()
-            .query_async(&mut self.manager.clone())
+        if ids.is_empty() {
+            return Ok(());
+        }
+        // The version keys are kept.
+        Ok(redis::cmd("DEL")
+            .arg(
+                ids.iter()
+                    .map(|id| self.keys.dataset(index_id, id))
+                    .collect::<Vec<_>>(),
+            )
+            .query_async(&mut self.connection.clone())
             .await?)
     }
 
@@ -53,24 +162,241 @@ impl DatasetsTrait for Redis<CUSTOM_WORD_LENGTH> {
         &self,
         index_id: &Uuid,
         ids: &Uuids,
-    ) -> DatabaseResult<EncryptedEntries> {
+    ) -> DatabaseResult<VersionedEntries> {
+        if ids.is_empty() {
+            return Ok(VersionedEntries::default());
+        }
         let values = ids
             .iter()
-            .map(|id| build_redis_key(index_id, id))
-            .fold(&mut pipe(), |pipe, key| pipe.get(key))
+            .fold(&mut pipe(), |pipe, id| {
+                pipe.get(self.keys.dataset(index_id, id))
+                    .get(self.keys.dataset_version(index_id, id))
+            })
             .atomic()
-            .query_async::<Vec<Vec<u8>>>(&mut self.manager.clone())
+            .query_async::<Vec<Option<Vec<u8>>>>(&mut self.connection.clone())
             .await?;
 
         trace!("dataset_get_entries: values len: {}", values.len());
 
-        // Filter empty values out.
-        let values = ids
+        // Filter missing values out.
+        let mut values = values.into_iter();
+        let mut entries = VersionedEntries::default();
+        for id in ids.iter() {
+            let (Some(entry), version) = (values.next().flatten(), values.next().flatten()) else {
+                continue;
+            };
+            let version = parse_version(id, version)?;
+            entries.insert(*id, VersionedEntry { version, entry });
+        }
+        Ok(entries)
+    }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn dataset_update_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let entries = entries.iter().collect::<Vec<_>>();
+        let mut invocation = DATASET_UPDATE_SCRIPT.prepare_invoke();
+        for (id, versioned) in &entries {
+            invocation
+                .key(self.keys.dataset(index_id, id))
+                .key(self.keys.dataset_version(index_id, id))
+                .arg(versioned.version)
+                .arg(versioned.entry.as_slice());
+        }
+        let mut reply = invocation
+            .invoke_async::<Vec<u64>>(&mut self.connection.clone())
+            .await?
+            .into_iter();
+        let mut conflicts = Vec::new();
+        while let (Some(position), Some(version)) = (reply.next(), reply.next()) {
+            let (id, _) = usize::try_from(position)
+                .ok()
+                .and_then(|position| entries.get(position.checked_sub(1)?))
+                .ok_or_else(|| {
+                    DatabaseError::InvalidDatabaseResponse(format!(
+                        "invalid position {position} of a conflicting entry"
+                    ))
+                })?;
+            conflicts.push((**id, version));
+        }
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(DatabaseError::VersionConflict(conflicts))
+        }
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn dataset_list_entries(
+        &self,
+        index_id: &Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> DatabaseResult<ScanPage<EntrySize>> {
+        let (keys, next_cursor) = self
+            .scan_page(
+                Some(index_id),
+                cursor,
+                &self.keys.datasets_pattern(Some(index_id)),
+                limit,
+            )
+            .await?;
+        let (keys, uids): (Vec<_>, Vec<_>) = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (_, uid) = parsed(&key, self.keys.parse_dataset(&key))?;
+                Some((key, uid))
+            })
+            .unzip();
+        let sizes = if keys.is_empty() {
+            Vec::new()
+        } else {
+            keys.iter()
+                .fold(&mut pipe(), |pipe, key| pipe.strlen(key))
+                .query_async::<Vec<usize>>(&mut self.connection.clone())
+                .await?
+        };
+        // An entry deleted since the scan has a null size.
+        let items = uids
+            .into_iter()
+            .zip(sizes)
+            .filter(|(_, size)| *size > 0)
+            .collect::<Vec<_>>();
+        trace!("dataset_list_entries: {} entries listed", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(ret, err, skip(self, entries), level = "trace")]
+    async fn dataset_stage_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+        entries: &EncryptedEntries,
+        ttl: Duration,
+    ) -> DatabaseResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let key = self.keys.pending_entries(index_id, transaction_id);
+        let ttl = i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX);
+        Ok(entries
             .iter()
-            .zip(values)
-            .filter_map(|(k, v)| if v.is_empty() { None } else { Some((k, v)) })
-            .collect::<HashMap<_, _>>();
+            .fold(&mut pipe(), |pipe, (id, data)| {
+                pipe.hset(&key, id.as_bytes(), data)
+            })
+            .pexpire(&key, ttl)
+            .atomic()
+            .query_async(&mut self.connection.clone())
+            .await?)
+    }
+
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    async fn dataset_commit_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<Uuids> {
+        let key = self.keys.pending_entries(index_id, transaction_id);
+        let uids = redis::cmd("HKEYS")
+            .arg(&key)
+            .query_async::<Vec<Vec<u8>>>(&mut self.connection.clone())
+            .await?
+            .iter()
+            .map(|uid| {
+                Uuid::from_slice(uid).map_err(|e| {
+                    DatabaseError::InvalidDatabaseResponse(format!("invalid staged entry ID: {e}"))
+                })
+            })
+            .collect::<DatabaseResult<Vec<_>>>()?;
+        if uids.is_empty() {
+            return Err(DatabaseError::UnknownTransaction(*transaction_id));
+        }
+
+        let mut invocation = DATASET_COMMIT_SCRIPT.key(key);
+        for id in &uids {
+            invocation
+                .key(self.keys.dataset(index_id, id))
+                .key(self.keys.dataset_version(index_id, id))
+                .arg(id.as_bytes());
+        }
+        let committed = invocation
+            .invoke_async::<bool>(&mut self.connection.clone())
+            .await?;
+        if !committed {
+            return Err(DatabaseError::UnknownTransaction(*transaction_id));
+        }
+        Ok(Uuids::from(uids))
+    }
+
+    #[instrument(ret, err, skip(self), level = "trace")]
+    async fn dataset_abort_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<()> {
+        Ok(redis::cmd("DEL")
+            .arg(self.keys.pending_entries(index_id, transaction_id))
+            .query_async(&mut self.connection.clone())
+            .await?)
+    }
+    #[instrument(ret, err, skip(self), level = "trace")]
+    async fn dataset_purge_expired_entries(&self) -> DatabaseResult<usize> {
+        // The hashes of the staged entries expire by themselves.
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{
+        config::DatabaseType,
+        database::{
+            database_traits::InstantiationTrait,
+            test_utils::dataset_tests::{list_entries, staged_entries, versioned_entries},
+        },
+    };
+
+    async fn setup_test_db() -> Redis<CUSTOM_WORD_LENGTH> {
+        let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_owned());
+        Redis::instantiate(DatabaseType::Redis, url.as_str(), false)
+            .await
+            .expect("Test failed to instantiate Redis")
+    }
+
+    #[ignore = "Redis tests require a running Redis instance"]
+    #[tokio::test]
+    async fn list_entries_test() {
+        let db = setup_test_db().await;
+        list_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test list_entries failed: {e:?}"));
+    }
+
+    #[ignore = "Redis tests require a running Redis instance"]
+    #[tokio::test]
+    async fn versioned_entries_test() {
+        let db = setup_test_db().await;
+        versioned_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test versioned_entries failed: {e:?}"));
+    }
 
-        Ok(values.into())
+    #[ignore = "Redis tests require a running Redis instance"]
+    #[tokio::test]
+    async fn staged_entries_test() {
+        let db = setup_test_db().await;
+        staged_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test staged_entries failed: {e:?}"));
     }
 }
@@ -1,7 +1,7 @@
 use cosmian_findex_structs::SERVER_ADDRESS_LENGTH;
-use cosmian_sse_memories::{Address, MemoryADT, RedisMemoryError};
+use cosmian_sse_memories::{Address, MemoryADT};
 
-use super::Redis;
+use super::{Redis, RedisMemoryError};
 
 impl<const WORD_LENGTH: usize> MemoryADT for Redis<WORD_LENGTH> {
     type Address = Address<SERVER_ADDRESS_LENGTH>;
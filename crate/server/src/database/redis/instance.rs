@@ -1,21 +1,190 @@
 use async_trait::async_trait;
 use cosmian_findex_structs::SERVER_ADDRESS_LENGTH;
-use cosmian_sse_memories::{Address, RedisMemory};
-use redis::aio::ConnectionManager;
+use cosmian_sse_memories::Address;
+use redis::pipe;
 use tracing::info;
+use url::Url;
 
+use super::{connection::RedisConnection, keys::RedisKeys, memory::RedisMemory};
 use crate::{
-    config::DatabaseType,
-    database::{database_traits::InstantiationTrait, findex_database::DatabaseResult},
+    config::{DatabaseType, RedisParams, RedisTopology},
+    database::{
+        DatabaseError, database_traits::InstantiationTrait, findex_database::DatabaseResult,
+    },
 };
 
 pub(crate) struct Redis<const WORD_LENGTH: usize> {
     pub(crate) memory: RedisMemory<Address<SERVER_ADDRESS_LENGTH>, [u8; WORD_LENGTH]>,
-    pub(crate) manager: ConnectionManager,
+    pub(crate) connection: RedisConnection,
+    pub(crate) keys: RedisKeys,
+}
+
+/// The number of keys requested per `SCAN` iteration
+const SCAN_COUNT: usize = 1000;
+
+impl<const WORD_LENGTH: usize> Redis<WORD_LENGTH> {
+    /// Connect to a Redis deployment of any supported topology.
+    pub(crate) async fn instantiate_with_params(
+        params: &RedisParams,
+        clear_database: bool,
+    ) -> DatabaseResult<Self> {
+        let connection = RedisConnection::connect(&params.topology).await?;
+        let keys = if params.legacy_layout {
+            RedisKeys::legacy()
+        } else {
+            RedisKeys::new(params.key_prefix.as_deref())?
+        };
+        info!("Connected to Redis: {params}");
+
+        // The memory words and the dataset entries of the legacy layout are
+        // stored under keys of the same form, and told apart by their length:
+        // a dataset entry of exactly the length of a word cannot be copied as
+        // such.
+        if params.legacy_layout && !params.legacy_words_by_length {
+            let content = scan_legacy_content(&connection, WORD_LENGTH, |content| {
+                content.words && content.entries
+            })
+            .await?;
+            if content.words && content.entries {
+                return Err(DatabaseError::LegacyLayout(format!(
+                    "the memory words and the dataset entries of the legacy layout cannot be told \
+                     apart when an entry is exactly as long as a word ({WORD_LENGTH} bytes): \
+                     migrate with `--from-redis-legacy-words-by-length` to copy such entries as \
+                     memory words, after which they must be uploaded again"
+                )));
+            }
+        }
+
+        // The legacy layout predates the Redis Cluster support: a cluster
+        // never holds it.
+        if !params.legacy_layout && !matches!(params.topology, RedisTopology::Cluster(_)) {
+            let content =
+                scan_legacy_content(&connection, WORD_LENGTH, |content| content.words).await?;
+            if content.words {
+                return Err(DatabaseError::LegacyLayout(
+                    "the database holds memory words stored under the raw `index_id || address` \
+                     keys of the server versions prior to the Redis Cluster support, which this \
+                     version no longer reads: upgrade it with `cosmian_findex_server migrate \
+                     --from-redis-legacy-layout` before starting the server"
+                        .to_owned(),
+                ));
+            }
+        }
+
+        if clear_database {
+            // The database may be shared with other applications: only the
+            // keys of the server are deleted, never the whole database.
+            info!(
+                "Warning: proceeding to delete the keys of the Findex server, this operation is \
+                 irreversible."
+            );
+            let mut deleted = 0;
+            for pattern in keys.patterns() {
+                deleted += delete_keys(&connection, &pattern).await?;
+            }
+            info!("Database cleared: {deleted} keys deleted");
+        }
+
+        let memory = RedisMemory::new_with_connection(connection.clone(), keys.clone());
+
+        Ok(Self {
+            memory,
+            connection,
+            keys,
+        })
+    }
+}
+
+/// What the raw `index_id || id` keys of the legacy layout hold: the memory
+/// words and the dataset entries, told apart by their length.
+#[derive(Default)]
+struct LegacyContent {
+    words: bool,
+    entries: bool,
+}
+
+/// Scan the raw keys of the legacy layout on all the nodes of the deployment,
+/// until `found` holds for their content.
+async fn scan_legacy_content(
+    connection: &RedisConnection,
+    word_length: usize,
+    found: impl Fn(&LegacyContent) -> bool + Send,
+) -> DatabaseResult<LegacyContent> {
+    // The legacy keys are exactly as long as a server address, while the
+    // keys of the current layout are longer, or hold hashes.
+    let pattern = "?".repeat(SERVER_ADDRESS_LENGTH);
+    let mut content = LegacyContent::default();
+    for node in connection.scan_nodes(None).await? {
+        let mut cursor = 0;
+        loop {
+            let (next_cursor, keys) = connection.scan(&node, cursor, &pattern, SCAN_COUNT).await?;
+            // The other applications sharing the database may store keys of
+            // this length, of any type.
+            if !keys.is_empty() {
+                let types: Vec<String> = keys
+                    .iter()
+                    .fold(&mut pipe(), |pipe, key| pipe.key_type(key))
+                    .query_async(&mut connection.clone())
+                    .await?;
+                let strings = keys
+                    .iter()
+                    .zip(types)
+                    .filter_map(|(key, key_type)| (key_type == "string").then_some(key))
+                    .collect::<Vec<_>>();
+                if !strings.is_empty() {
+                    let lengths: Vec<usize> = strings
+                        .iter()
+                        .fold(&mut pipe(), |pipe, key| pipe.strlen(key))
+                        .query_async(&mut connection.clone())
+                        .await?;
+                    // A key deleted since the scan has no length.
+                    for length in lengths.into_iter().filter(|length| *length != 0) {
+                        if length == word_length {
+                            content.words = true;
+                        } else {
+                            content.entries = true;
+                        }
+                    }
+                    if found(&content) {
+                        return Ok(content);
+                    }
+                }
+            }
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+    }
+    Ok(content)
+}
+
+/// Delete the keys matching the pattern on all the nodes of the deployment,
+/// and return the number of deleted keys.
+async fn delete_keys(connection: &RedisConnection, pattern: &str) -> DatabaseResult<usize> {
+    let mut deleted = 0;
+    for node in connection.scan_nodes(None).await? {
+        let mut cursor = 0;
+        loop {
+            let (next_cursor, keys) = connection.scan(&node, cursor, pattern, SCAN_COUNT).await?;
+            if !keys.is_empty() {
+                deleted += redis::cmd("UNLINK")
+                    .arg(keys)
+                    .query_async::<usize>(&mut connection.clone())
+                    .await?;
+            }
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+    }
+    Ok(deleted)
 }
 
 #[async_trait]
 impl<const WORD_LENGTH: usize> InstantiationTrait for Redis<WORD_LENGTH> {
+    /// Connect to a standalone Redis instance.
     async fn instantiate(
         db_type: DatabaseType,
         db_url: &str,
@@ -27,27 +196,22 @@ impl<const WORD_LENGTH: usize> InstantiationTrait for Redis<WORD_LENGTH> {
                 format!("{db_type:?}"),
             ));
         }
-        let client = redis::Client::open(db_url)?;
-        let mut manager = client.get_connection_manager().await?;
-
-        if clear_database {
-            info!("Warning: proceeding to clear the database, this operation is irreversible.");
-            let deletion_result: String = redis::cmd("FLUSHDB").query_async(&mut manager).await?;
-            if deletion_result.as_str() == "OK" {
-                info!("Database cleared");
-            } else {
-                return Err(crate::database::DatabaseError::RedisCoreError(
-                    redis::RedisError::from((
-                        redis::ErrorKind::ResponseError,
-                        "Failed to clear the database",
-                        deletion_result,
-                    )),
-                ));
-            }
-        }
-
-        let memory = RedisMemory::new_with_manager(manager.clone()).await?;
-
-        Ok(Self { memory, manager })
+        let url = Url::parse(db_url).map_err(|e| {
+            redis::RedisError::from((
+                redis::ErrorKind::InvalidClientConfig,
+                "Invalid Redis URL",
+                e.to_string(),
+            ))
+        })?;
+        Self::instantiate_with_params(
+            &RedisParams {
+                topology: RedisTopology::Standalone(url),
+                key_prefix: None,
+                legacy_layout: false,
+                legacy_words_by_length: false,
+            },
+            clear_database,
+        )
+        .await
     }
 }
@@ -0,0 +1,338 @@
+//! Layout of the keys stored by the Redis backend.
+//!
+//! Memory words and dataset entries are stored under keys embedding the
+//! index ID in a hash tag (`{...}`): on a Redis Cluster all the keys of an
+//! index hence map to the same slot, so a guarded write or a dataset
+//! transaction never spans several nodes. The version of a dataset entry is
+//! stored next to it, under a key of the same hash tag, and so are the entries
+//! staged under a transaction, in a hash per transaction. The permissions of a
+//! user are stored in a single hash.
+//!
+//! All the keys start with the configured prefix, if any.
+//!
+//! The server versions prior to the Redis Cluster support stored the memory
+//! words and the dataset entries under their raw `index_id || id` key. This
+//! legacy layout is only read by `migrate`, to rewrite such a database with the
+//! current layout: the server refuses to start on it.
+use cosmian_findex_structs::{SERVER_ADDRESS_LENGTH, UID_LENGTH};
+use uuid::Uuid;
+
+use crate::database::{DatabaseError, findex_database::DatabaseResult};
+
+const MEMORY_PREFIX: &str = "memory";
+const DATASETS_PREFIX: &str = "datasets";
+const DATASET_VERSIONS_PREFIX: &str = "dataset_versions";
+const PENDING_ENTRIES_PREFIX: &str = "pending_entries";
+const PERMISSIONS_PREFIX: &str = "permissions";
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RedisKeys {
+    prefix: String,
+    legacy: bool,
+}
+
+impl RedisKeys {
+    /// The keys under the given prefix. A brace in the prefix would change
+    /// the hash tag of the keys, which could then be spread over several
+    /// slots of a Redis Cluster.
+    ///
+    /// # Errors
+    /// - If the prefix contains a brace
+    pub(crate) fn new(prefix: Option<&str>) -> DatabaseResult<Self> {
+        let prefix = prefix.unwrap_or_default();
+        if prefix.contains(['{', '}']) {
+            return Err(DatabaseError::InvalidKeyPrefix(prefix.to_owned()));
+        }
+        Ok(Self {
+            prefix: prefix.to_owned(),
+            legacy: false,
+        })
+    }
+
+    /// The keys of a database of the legacy layout, which had no prefix.
+    pub(crate) const fn legacy() -> Self {
+        Self {
+            prefix: String::new(),
+            legacy: true,
+        }
+    }
+
+    /// Whether the database holds the legacy layout.
+    pub(crate) const fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    /// Whether the keys are namespaced by a prefix.
+    pub(crate) const fn has_prefix(&self) -> bool {
+        !self.prefix.is_empty()
+    }
+
+    /// `SCAN` patterns matching all the keys of the server: the keys under
+    /// the prefix if there is one, or else the keys of each kind.
+    pub(crate) fn patterns(&self) -> Vec<String> {
+        if self.has_prefix() {
+            vec![self.pattern_of("")]
+        } else {
+            [
+                MEMORY_PREFIX,
+                DATASETS_PREFIX,
+                DATASET_VERSIONS_PREFIX,
+                PENDING_ENTRIES_PREFIX,
+                PERMISSIONS_PREFIX,
+            ]
+            .iter()
+            .map(|kind| self.pattern_of(&format!("{kind}:")))
+            .collect()
+        }
+    }
+
+    /// `SCAN` patterns matching the keys of the memory words and of the
+    /// dataset entries, of all the indexes or of a single one.
+    pub(crate) fn memory_pattern(&self, index_id: Option<&Uuid>) -> String {
+        self.pattern_of(&Self::kind_prefix(MEMORY_PREFIX, index_id))
+    }
+
+    pub(crate) fn datasets_pattern(&self, index_id: Option<&Uuid>) -> String {
+        self.pattern_of(&Self::kind_prefix(DATASETS_PREFIX, index_id))
+    }
+
+    /// A `SCAN` pattern matching the keys of the permissions.
+    pub(crate) fn permissions_pattern(&self) -> String {
+        self.pattern_of(&format!("{PERMISSIONS_PREFIX}:"))
+    }
+
+    fn kind_prefix(kind: &str, index_id: Option<&Uuid>) -> String {
+        index_id.map_or_else(
+            || format!("{kind}:"),
+            |index_id| format!("{kind}:{{{}}}:", Self::hash_tag(index_id)),
+        )
+    }
+
+    /// The hash tag of the keys of an index, its hex-encoded ID.
+    pub(crate) fn hash_tag(index_id: &Uuid) -> String {
+        index_id.simple().to_string()
+    }
+
+    fn pattern_of(&self, kind: &str) -> String {
+        let mut pattern = String::with_capacity(self.prefix.len() + kind.len() + 1);
+        for c in self.prefix.chars().chain(kind.chars()) {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+        pattern
+    }
+
+    /// Build the key `<prefix><kind>:{<index id>}:<suffix>`, the index ID
+    /// being hex-encoded and the suffix kept as raw bytes.
+    fn index_key(&self, kind: &str, index_id: &[u8], suffix: &[u8]) -> Vec<u8> {
+        let mut key = format!("{}{kind}:{{{}}}:", self.prefix, hex::encode(index_id)).into_bytes();
+        key.extend_from_slice(suffix);
+        key
+    }
+
+    /// Key of a memory word. The server address is the index ID followed by
+    /// the Findex address, which is the whole key in the legacy layout.
+    pub(crate) fn memory(&self, address: &[u8; SERVER_ADDRESS_LENGTH]) -> Vec<u8> {
+        if self.legacy {
+            return address.to_vec();
+        }
+        let (index_id, findex_address) = address.split_at(UID_LENGTH);
+        self.index_key(MEMORY_PREFIX, index_id, findex_address)
+    }
+
+    /// Key of a dataset entry, the raw `index_id || uid` in the legacy
+    /// layout.
+    pub(crate) fn dataset(&self, index_id: &Uuid, uid: &Uuid) -> Vec<u8> {
+        if self.legacy {
+            return [index_id.as_bytes().as_slice(), uid.as_bytes()].concat();
+        }
+        self.index_key(DATASETS_PREFIX, index_id.as_bytes(), uid.as_bytes())
+    }
+
+    /// Key of the version of a dataset entry.
+    pub(crate) fn dataset_version(&self, index_id: &Uuid, uid: &Uuid) -> Vec<u8> {
+        self.index_key(DATASET_VERSIONS_PREFIX, index_id.as_bytes(), uid.as_bytes())
+    }
+
+    /// Key of the hash holding the entries staged under a transaction.
+    pub(crate) fn pending_entries(&self, index_id: &Uuid, transaction_id: &Uuid) -> Vec<u8> {
+        self.index_key(
+            PENDING_ENTRIES_PREFIX,
+            index_id.as_bytes(),
+            transaction_id.as_bytes(),
+        )
+    }
+
+    /// Key of the hash holding the permissions of a user.
+    pub(crate) fn permissions(&self, user_id: &str) -> String {
+        format!("{}{PERMISSIONS_PREFIX}:{user_id}", self.prefix)
+    }
+
+    /// Split a key built by [`Self::index_key`] into the index ID and the
+    /// suffix, if it is of the given kind.
+    fn parse_index_key<'a>(&self, kind: &str, key: &'a [u8]) -> Option<(Uuid, &'a [u8])> {
+        let key = key
+            .strip_prefix(self.prefix.as_bytes())?
+            .strip_prefix(kind.as_bytes())?
+            .strip_prefix(b":{")?;
+        let (index_id, suffix) = key.split_at_checked(2 * UID_LENGTH)?;
+        let index_id = Uuid::from_slice(&hex::decode(index_id).ok()?).ok()?;
+        Some((index_id, suffix.strip_prefix(b"}:")?))
+    }
+
+    /// The server address stored under a memory key.
+    pub(crate) fn parse_memory(&self, key: &[u8]) -> Option<[u8; SERVER_ADDRESS_LENGTH]> {
+        let (index_id, findex_address) = self.parse_index_key(MEMORY_PREFIX, key)?;
+        let mut address = [0; SERVER_ADDRESS_LENGTH];
+        let (index_id_part, findex_address_part) = address.split_at_mut(UID_LENGTH);
+        index_id_part.copy_from_slice(index_id.as_bytes());
+        if findex_address.len() != findex_address_part.len() {
+            return None;
+        }
+        findex_address_part.copy_from_slice(findex_address);
+        Some(address)
+    }
+
+    /// The index ID and the entry ID stored under a dataset key.
+    pub(crate) fn parse_dataset(&self, key: &[u8]) -> Option<(Uuid, Uuid)> {
+        let (index_id, uid) = self.parse_index_key(DATASETS_PREFIX, key)?;
+        Some((index_id, Uuid::from_slice(uid).ok()?))
+    }
+
+    /// Split a raw `index_id || id` key of the legacy layout into the index
+    /// ID and the ID.
+    pub(crate) fn parse_legacy(key: &[u8]) -> Option<(Uuid, Uuid)> {
+        if key.len() != SERVER_ADDRESS_LENGTH {
+            return None;
+        }
+        let (index_id, id) = key.split_at_checked(UID_LENGTH)?;
+        Some((Uuid::from_slice(index_id).ok()?, Uuid::from_slice(id).ok()?))
+    }
+
+    /// The user ID whose permissions are stored under a key.
+    pub(crate) fn parse_permissions<'a>(&self, key: &'a [u8]) -> Option<&'a str> {
+        let user_id = key
+            .strip_prefix(self.prefix.as_bytes())?
+            .strip_prefix(PERMISSIONS_PREFIX.as_bytes())?
+            .strip_prefix(b":")?;
+        std::str::from_utf8(user_id).ok()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_of_an_index_share_a_hash_tag() {
+        let keys = RedisKeys::default();
+        let index_id = Uuid::new_v4();
+        let mut address = [7_u8; SERVER_ADDRESS_LENGTH];
+        address[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
+
+        let expected_tag = format!("{{{}}}", index_id.simple()).into_bytes();
+        let memory_key = keys.memory(&address);
+        let dataset_key = keys.dataset(&index_id, &Uuid::new_v4());
+        let version_key = keys.dataset_version(&index_id, &Uuid::new_v4());
+        let pending_key = keys.pending_entries(&index_id, &Uuid::new_v4());
+
+        for key in [&memory_key, &dataset_key, &version_key, &pending_key] {
+            let tag_start = key.iter().position(|b| *b == b'{').unwrap();
+            assert_eq!(
+                &key[tag_start..tag_start + expected_tag.len()],
+                expected_tag.as_slice()
+            );
+        }
+        assert!(memory_key.starts_with(b"memory:"));
+        assert!(memory_key.ends_with(&[7_u8; SERVER_ADDRESS_LENGTH - UID_LENGTH]));
+        assert!(dataset_key.starts_with(b"datasets:"));
+        assert!(version_key.starts_with(b"dataset_versions:"));
+        assert_eq!(keys.permissions("alice"), "permissions:alice");
+    }
+
+    #[test]
+    fn test_prefixed_keys() {
+        let keys = RedisKeys::new(Some("findex[1]:")).unwrap();
+        assert!(keys.has_prefix());
+        assert!(!RedisKeys::new(None).unwrap().has_prefix());
+        RedisKeys::new(Some("{findex}:")).unwrap_err();
+        RedisKeys::new(Some("findex}")).unwrap_err();
+
+        let index_id = Uuid::new_v4();
+        assert!(
+            keys.dataset(&index_id, &Uuid::new_v4())
+                .starts_with(b"findex[1]:datasets:{")
+        );
+        assert!(
+            keys.memory(&[0; SERVER_ADDRESS_LENGTH])
+                .starts_with(b"findex[1]:memory:{")
+        );
+        assert_eq!(keys.permissions("alice"), "findex[1]:permissions:alice");
+        assert_eq!(keys.patterns(), ["findex\\[1\\]:*"]);
+        assert_eq!(
+            RedisKeys::default().patterns(),
+            [
+                "memory:*",
+                "datasets:*",
+                "dataset_versions:*",
+                "pending_entries:*",
+                "permissions:*"
+            ]
+        );
+        assert_eq!(keys.datasets_pattern(None), "findex\\[1\\]:datasets:*");
+        assert_eq!(
+            keys.memory_pattern(Some(&index_id)),
+            format!("findex\\[1\\]:memory:{{{}}}:*", index_id.simple())
+        );
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let keys = RedisKeys::new(Some("findex:")).unwrap();
+        let (index_id, uid) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut address = [7_u8; SERVER_ADDRESS_LENGTH];
+        address[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
+
+        assert_eq!(keys.parse_memory(&keys.memory(&address)), Some(address));
+        assert_eq!(
+            keys.parse_dataset(&keys.dataset(&index_id, &uid)),
+            Some((index_id, uid))
+        );
+        assert_eq!(
+            keys.parse_permissions(keys.permissions("alice").as_bytes()),
+            Some("alice")
+        );
+
+        // Keys of another kind or without the prefix are rejected.
+        assert_eq!(keys.parse_memory(&keys.dataset(&index_id, &uid)), None);
+        assert_eq!(
+            keys.parse_dataset(&keys.dataset_version(&index_id, &uid)),
+            None
+        );
+        assert_eq!(
+            keys.parse_dataset(&RedisKeys::default().dataset(&index_id, &uid)),
+            None
+        );
+        assert_eq!(keys.parse_memory(b"findex:memory:{00}:"), None);
+    }
+
+    #[test]
+    fn test_parse_legacy_keys() {
+        let (index_id, uid) = (Uuid::new_v4(), Uuid::new_v4());
+        let key = [index_id.as_bytes().as_slice(), uid.as_bytes()].concat();
+        assert_eq!(RedisKeys::parse_legacy(&key), Some((index_id, uid)));
+        assert_eq!(RedisKeys::parse_legacy(&key[1..]), None);
+        assert_eq!(RedisKeys::parse_legacy(b"permissions:alice"), None);
+        assert_eq!(RedisKeys::legacy().dataset(&index_id, &uid), key);
+        assert_eq!(
+            RedisKeys::legacy().memory(&key.clone().try_into().unwrap()),
+            key
+        );
+        assert!(RedisKeys::legacy().is_legacy());
+        assert!(!RedisKeys::default().is_legacy());
+    }
+}
@@ -0,0 +1,195 @@
+//! Findex server implements its own Redis memory so that the keys are laid out
+//! for Redis Cluster: the words of an index share a hash tag and are stored in
+//! the same slot, hence a guarded write is performed by a single Lua script
+//! whose keys are all declared.
+use std::marker::PhantomData;
+
+use cosmian_findex_structs::SERVER_ADDRESS_LENGTH;
+use cosmian_sse_memories::{Address, MemoryADT};
+use redis::Script;
+use thiserror::Error;
+
+use super::{connection::RedisConnection, keys::RedisKeys};
+
+/// Check the guard and write the bindings if it holds, atomically.
+///
+/// `KEYS[1]` is the guard address and `KEYS[2..]` are the binding addresses.
+/// `ARGV[1]` tells whether a word is expected at the guard address,
+/// `ARGV[2]` holds this word and `ARGV[3..]` hold the words to bind.
+/// The word currently stored at the guard address is returned.
+const GUARDED_WRITE_SCRIPT: &str = r"
+local current = redis.call('GET', KEYS[1])
+local guard_holds
+if ARGV[1] == '1' then
+    guard_holds = current == ARGV[2]
+else
+    guard_holds = current == false
+end
+if guard_holds then
+    for i = 2, #KEYS do
+        redis.call('SET', KEYS[i], ARGV[i + 1])
+    end
+end
+return current
+";
+
+#[derive(Error, Debug)]
+pub(crate) enum RedisMemoryError {
+    #[error("redis error: {0}")]
+    RedisError(#[from] redis::RedisError),
+    #[error("invalid word of {0} bytes read from Redis, {1} bytes expected")]
+    InvalidWordLength(usize, usize),
+}
+
+#[derive(Clone)]
+pub(crate) struct RedisMemory<Address, Word> {
+    connection: RedisConnection,
+    keys: RedisKeys,
+    guarded_write_script: Script,
+    _marker: PhantomData<(Address, Word)>,
+}
+
+impl<Address, Word> RedisMemory<Address, Word> {
+    /// Returns a new memory instance using the given Redis connection.
+    pub(crate) fn new_with_connection(connection: RedisConnection, keys: RedisKeys) -> Self {
+        Self {
+            connection,
+            keys,
+            guarded_write_script: Script::new(GUARDED_WRITE_SCRIPT),
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn into_word<const WORD_LENGTH: usize>(
+    bytes: &[u8],
+) -> Result<[u8; WORD_LENGTH], RedisMemoryError> {
+    <[u8; WORD_LENGTH]>::try_from(bytes)
+        .map_err(|_e| RedisMemoryError::InvalidWordLength(bytes.len(), WORD_LENGTH))
+}
+
+impl<const WORD_LENGTH: usize> MemoryADT
+    for RedisMemory<Address<SERVER_ADDRESS_LENGTH>, [u8; WORD_LENGTH]>
+{
+    type Address = Address<SERVER_ADDRESS_LENGTH>;
+    type Error = RedisMemoryError;
+    type Word = [u8; WORD_LENGTH];
+
+    async fn batch_read(
+        &self,
+        addresses: Vec<Self::Address>,
+    ) -> Result<Vec<Option<Self::Word>>, Self::Error> {
+        if addresses.is_empty() {
+            return Ok(Vec::new());
+        }
+        let keys = addresses
+            .iter()
+            .map(|address| self.keys.memory(address))
+            .collect::<Vec<_>>();
+        let words: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        words
+            .into_iter()
+            .map(|word| word.as_deref().map(into_word).transpose())
+            .collect()
+    }
+
+    async fn guarded_write(
+        &self,
+        guard: (Self::Address, Option<Self::Word>),
+        bindings: Vec<(Self::Address, Self::Word)>,
+    ) -> Result<Option<Self::Word>, Self::Error> {
+        let (guard_address, guard_word) = guard;
+
+        let mut invocation = self
+            .guarded_write_script
+            .key(self.keys.memory(&guard_address));
+        match guard_word {
+            Some(word) => invocation.arg("1").arg(word.as_slice()),
+            None => invocation.arg("0").arg(""),
+        };
+        for (address, word) in &bindings {
+            invocation
+                .key(self.keys.memory(address))
+                .arg(word.as_slice());
+        }
+
+        let current: Option<Vec<u8>> = invocation
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        current.as_deref().map(into_word).transpose()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::env;
+
+    use cosmian_findex_structs::UID_LENGTH;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::config::RedisTopology;
+
+    async fn setup_test_memory() -> RedisMemory<Address<SERVER_ADDRESS_LENGTH>, [u8; 8]> {
+        let url = env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_owned());
+        let connection = RedisConnection::connect(&RedisTopology::Standalone(url.parse().unwrap()))
+            .await
+            .unwrap();
+        RedisMemory::new_with_connection(connection, RedisKeys::new(Some("findex-test:")).unwrap())
+    }
+
+    fn address(index_id: &Uuid, n: u8) -> Address<SERVER_ADDRESS_LENGTH> {
+        let mut address = [n; SERVER_ADDRESS_LENGTH];
+        address[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
+        Address::from(address)
+    }
+
+    #[ignore = "Redis tests require a running Redis instance"]
+    #[tokio::test]
+    async fn test_guarded_write_and_batch_read() {
+        let memory = setup_test_memory().await;
+        let index_id = Uuid::new_v4();
+        let (a0, a1, a2) = (
+            address(&index_id, 0),
+            address(&index_id, 1),
+            address(&index_id, 2),
+        );
+
+        // The guard holds on an empty address.
+        let current = memory
+            .guarded_write((a0, None), vec![(a0, [1; 8]), (a1, [2; 8])])
+            .await
+            .unwrap();
+        assert_eq!(current, None);
+        assert_eq!(
+            memory.batch_read(vec![a0, a1, a2]).await.unwrap(),
+            vec![Some([1; 8]), Some([2; 8]), None]
+        );
+
+        // A wrong guard prevents the write and the current word is returned.
+        let current = memory
+            .guarded_write((a0, Some([9; 8])), vec![(a1, [3; 8])])
+            .await
+            .unwrap();
+        assert_eq!(current, Some([1; 8]));
+        assert_eq!(
+            memory.batch_read(vec![a1]).await.unwrap(),
+            vec![Some([2; 8])]
+        );
+
+        // The right guard allows the write.
+        let current = memory
+            .guarded_write((a0, Some([1; 8])), vec![(a1, [3; 8])])
+            .await
+            .unwrap();
+        assert_eq!(current, Some([1; 8]));
+        assert_eq!(
+            memory.batch_read(vec![a1]).await.unwrap(),
+            vec![Some([3; 8])]
+        );
+    }
+}
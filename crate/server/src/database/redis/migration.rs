@@ -0,0 +1,374 @@
+use async_trait::async_trait;
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, Permission, SERVER_ADDRESS_LENGTH, VersionedEntries, VersionedEntry,
+};
+use cosmian_sse_memories::{Address, MemoryADT};
+use redis::AsyncCommands;
+use tracing::{instrument, trace, warn};
+use uuid::Uuid;
+
+use super::{Redis, datasets::parse_version, keys::RedisKeys};
+use crate::database::{
+    DatabaseError,
+    database_traits::{DatasetEntry, MemoryWord, MigrationTrait, ScanPage, UserPermission},
+    findex_database::DatabaseResult,
+};
+
+/// The position in a scan: the position of the node, the `SCAN` cursor on
+/// this node and, if the keys of this `SCAN` iteration are partially read,
+/// the last key read.
+type ScanPosition = (usize, u64, Option<Vec<u8>>);
+
+/// Parse a cursor of the form `<node position>:<SCAN cursor>`, followed by
+/// `:<hex-encoded last key>` if the keys of the iteration are partially read.
+fn parse_cursor(cursor: &str) -> DatabaseResult<ScanPosition> {
+    let mut parts = cursor.splitn(3, ':');
+    let position = (|| {
+        let node = parts.next()?.parse().ok()?;
+        let node_cursor = parts.next()?.parse().ok()?;
+        let last_key = match parts.next() {
+            Some(last_key) => Some(hex::decode(last_key).ok()?),
+            None => None,
+        };
+        Some((node, node_cursor, last_key))
+    })();
+    position.ok_or_else(|| DatabaseError::InvalidCursor(cursor.to_owned()))
+}
+
+impl Redis<CUSTOM_WORD_LENGTH> {
+    /// Read a page of at most `count` keys matching the pattern, which only
+    /// matches the keys of the given index, if any.
+    ///
+    /// Each page runs one `SCAN` iteration. The nodes of a Redis Cluster are
+    /// scanned one after the other, hence the cursor designates a node and the
+    /// `SCAN` cursor on this node; the keys of an index are all held by the
+    /// node owning its hash slot, the only one scanned. An iteration may return
+    /// more keys than requested: they are read in order, the cursor then
+    /// designating the same iteration and the last key read. A page may be
+    /// empty while the scan is not over.
+    pub(super) async fn scan_page(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        pattern: &str,
+        count: usize,
+    ) -> DatabaseResult<(Vec<Vec<u8>>, Option<String>)> {
+        let count = count.max(1);
+        let (position, node_cursor, last_key) = cursor.map_or(Ok((0, 0, None)), parse_cursor)?;
+        let hash_tag = index_id.map(RedisKeys::hash_tag);
+        let nodes = self.connection.scan_nodes(hash_tag.as_deref()).await?;
+        let node = nodes.get(position).ok_or_else(|| {
+            DatabaseError::InvalidCursor(format!(
+                "{} (the deployment has {} nodes)",
+                cursor.unwrap_or_default(),
+                nodes.len()
+            ))
+        })?;
+        let (next, mut keys) = self
+            .connection
+            .scan(node, node_cursor, pattern, count)
+            .await?;
+        // An iteration may also return a key more than once.
+        keys.sort_unstable();
+        keys.dedup();
+        if let Some(last_key) = &last_key {
+            keys.retain(|key| key > last_key);
+        }
+        let next_cursor = if keys.len() > count {
+            keys.truncate(count);
+            keys.last()
+                .map(|last_key| format!("{position}:{node_cursor}:{}", hex::encode(last_key)))
+        } else if next != 0 {
+            Some(format!("{position}:{next}"))
+        } else if position + 1 < nodes.len() {
+            Some(format!("{}:0", position + 1))
+        } else {
+            None
+        };
+        Ok((keys, next_cursor))
+    }
+}
+
+impl Redis<CUSTOM_WORD_LENGTH> {
+    /// Read a page of the values stored under the raw `index_id || id` keys
+    /// of the legacy layout, of all the indexes or of the given one.
+    async fn legacy_scan_page(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<(Vec<(Vec<u8>, Vec<u8>)>, Option<String>)> {
+        // The raw keys have no kind to match on.
+        let (keys, next_cursor) = self.scan_page(None, cursor, "*", count).await?;
+        let keys = keys
+            .into_iter()
+            .filter(|key| {
+                RedisKeys::parse_legacy(key)
+                    .is_some_and(|(id, _)| index_id.is_none_or(|index_id| *index_id == id))
+            })
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok((Vec::new(), next_cursor));
+        }
+        // The keys which do not hold a string, such as the hashes of the
+        // permissions, are read as nil.
+        let values: Vec<Option<Vec<u8>>> = redis::cmd("MGET")
+            .arg(&keys)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        let items = keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect();
+        Ok((items, next_cursor))
+    }
+
+    /// Read a page of the memory words of the legacy layout. The words and
+    /// the dataset entries are stored under keys of the same form: they are
+    /// told apart by their length, hence an entry of exactly the length of a
+    /// word is taken for a word. A database holding both words and entries is
+    /// only opened if the operator accepts it.
+    async fn legacy_memory_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<MemoryWord>> {
+        let (values, next_cursor) = self.legacy_scan_page(index_id, cursor, count).await?;
+        let items = values
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let address = <[u8; SERVER_ADDRESS_LENGTH]>::try_from(key).ok()?;
+                Some((Address::from(address), value.try_into().ok()?))
+            })
+            .collect::<Vec<_>>();
+        trace!("legacy_memory_scan: {} words read", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    /// Read a page of the dataset entries of the legacy layout: the values
+    /// which are not of the length of a memory word. They are at version 1.
+    async fn legacy_datasets_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<DatasetEntry>> {
+        let (values, next_cursor) = self.legacy_scan_page(index_id, cursor, count).await?;
+        let items = values
+            .into_iter()
+            .filter(|(_, value)| value.len() != CUSTOM_WORD_LENGTH)
+            .filter_map(|(key, value)| {
+                let (index_id, uid) = RedisKeys::parse_legacy(&key)?;
+                Some((
+                    index_id,
+                    uid,
+                    VersionedEntry {
+                        version: 1,
+                        entry: value,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        trace!("legacy_datasets_scan: {} entries read", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+}
+
+/// Keep the keys that were parsed, warning about the others.
+pub(super) fn parsed<T>(key: &[u8], parsed: Option<T>) -> Option<T> {
+    if parsed.is_none() {
+        warn!(
+            "Skipping the unexpected key {}",
+            String::from_utf8_lossy(key)
+        );
+    }
+    parsed
+}
+
+#[async_trait]
+impl MigrationTrait for Redis<CUSTOM_WORD_LENGTH> {
+    #[instrument(err, skip(self), level = "trace")]
+    async fn memory_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<MemoryWord>> {
+        if self.keys.is_legacy() {
+            return self.legacy_memory_scan(index_id, cursor, count).await;
+        }
+        let (keys, next_cursor) = self
+            .scan_page(index_id, cursor, &self.keys.memory_pattern(index_id), count)
+            .await?;
+        let addresses = keys
+            .iter()
+            .filter_map(|key| parsed(key, self.keys.parse_memory(key)))
+            .map(Address::from)
+            .collect::<Vec<_>>();
+        // A word deleted since the scan is skipped.
+        let words = self.memory.batch_read(addresses.clone()).await?;
+        let items = addresses
+            .into_iter()
+            .zip(words)
+            .filter_map(|(address, word)| Some((address, word?)))
+            .collect::<Vec<_>>();
+        trace!("memory_scan: {} words read", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn memory_restore(&self, words: Vec<MemoryWord>) -> DatabaseResult<()> {
+        if words.is_empty() {
+            return Ok(());
+        }
+        // On a Redis Cluster, the command is split by slot.
+        let mut cmd = redis::cmd("MSET");
+        for (address, word) in &words {
+            cmd.arg(self.keys.memory(address)).arg(word.as_slice());
+        }
+        cmd.query_async::<()>(&mut self.connection.clone()).await?;
+        trace!("memory_restore: {} words written", words.len());
+        Ok(())
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn permissions_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<UserPermission>> {
+        let (keys, next_cursor) = self
+            .scan_page(None, cursor, &self.keys.permissions_pattern(), count)
+            .await?;
+        let mut items = Vec::new();
+        for key in &keys {
+            let Some(user_id) = parsed(key, self.keys.parse_permissions(key)) else {
+                continue;
+            };
+            // The permissions of a user are a hash mapping index IDs to
+            // permissions.
+            let mut connection = self.connection.clone();
+            let permissions = match index_id {
+                Some(index_id) => connection
+                    .hget::<_, _, Option<u8>>(key, index_id.to_string())
+                    .await?
+                    .map(|permission| (index_id.to_string(), permission))
+                    .into_iter()
+                    .collect(),
+                None => connection.hgetall::<_, Vec<(String, u8)>>(key).await?,
+            };
+            for (index_id, permission) in permissions {
+                items.push((
+                    user_id.to_owned(),
+                    Uuid::parse_str(&index_id).map_err(|e| {
+                        DatabaseError::InvalidDatabaseResponse(format!("Invalid index ID. {e}"))
+                    })?,
+                    Permission::try_from(permission).map_err(|e| {
+                        DatabaseError::InvalidDatabaseResponse(format!(
+                            "An invalid permission value was returned by the database. {e}"
+                        ))
+                    })?,
+                ));
+            }
+        }
+        trace!("permissions_scan: {} permissions read", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn datasets_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<DatasetEntry>> {
+        if self.keys.is_legacy() {
+            return self.legacy_datasets_scan(index_id, cursor, count).await;
+        }
+        let (keys, next_cursor) = self
+            .scan_page(
+                index_id,
+                cursor,
+                &self.keys.datasets_pattern(index_id),
+                count,
+            )
+            .await?;
+        let (keys, ids): (Vec<_>, Vec<_>) = keys
+            .into_iter()
+            .filter_map(|key| {
+                let ids = parsed(&key, self.keys.parse_dataset(&key))?;
+                Some((key, ids))
+            })
+            .unzip();
+        let mut items = Vec::with_capacity(ids.len());
+        if !keys.is_empty() {
+            // The entries are read with their versions.
+            let mut values = redis::cmd("MGET")
+                .arg(
+                    keys.into_iter()
+                        .zip(&ids)
+                        .flat_map(|(key, (index_id, uid))| {
+                            [key, self.keys.dataset_version(index_id, uid)]
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .query_async::<Vec<Option<Vec<u8>>>>(&mut self.connection.clone())
+                .await?
+                .into_iter();
+            for (index_id, uid) in ids {
+                // An entry deleted since the scan is skipped.
+                let (Some(entry), version) = (values.next().flatten(), values.next().flatten())
+                else {
+                    continue;
+                };
+                let version = parse_version(&uid, version)?;
+                items.push((index_id, uid, VersionedEntry { version, entry }));
+            }
+        }
+        trace!("datasets_scan: {} entries read", items.len());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn dataset_restore_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        // The keys of an index share a hash slot: the command is atomic.
+        let mut cmd = redis::cmd("MSET");
+        for (uid, versioned) in entries.iter() {
+            cmd.arg(self.keys.dataset(index_id, uid))
+                .arg(versioned.entry.as_slice())
+                .arg(self.keys.dataset_version(index_id, uid))
+                .arg(versioned.version);
+        }
+        cmd.query_async::<()>(&mut self.connection.clone()).await?;
+        trace!("dataset_restore_entries: {} entries written", entries.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cursor() {
+        assert_eq!(parse_cursor("0:42").unwrap(), (0, 42, None));
+        assert_eq!(
+            parse_cursor("2:42:6b6579").unwrap(),
+            (2, 42, Some(b"key".to_vec()))
+        );
+        for cursor in ["", "0", "a:42", "0:42:key"] {
+            parse_cursor(cursor).unwrap_err();
+        }
+    }
+}
@@ -1,10 +1,15 @@
+mod connection;
 mod datasets;
 mod findex;
 mod instance;
+mod keys;
+mod memory;
+mod migration;
 mod permissions;
 
 use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
 pub(crate) use instance::Redis;
+pub(crate) use memory::RedisMemoryError;
 
 use crate::database::database_traits::DatabaseTraits;
 impl DatabaseTraits for Redis<CUSTOM_WORD_LENGTH> {}
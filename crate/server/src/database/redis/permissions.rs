@@ -1,27 +1,28 @@
 use async_trait::async_trait;
 use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Permission, Permissions};
-use redis::{AsyncCommands, RedisError, aio::ConnectionManager};
+use redis::{AsyncCommands, RedisError};
 use tracing::{instrument, trace};
 use uuid::Uuid;
 
-use super::Redis;
+use super::{Redis, connection::RedisConnection, keys::RedisKeys};
 use crate::database::{
     DatabaseError, database_traits::PermissionsTrait, findex_database::DatabaseResult,
 };
 
-const PERMISSIONS_PREFIX: &str = "permissions";
-
 async fn hset_redis_permission(
-    manager: &ConnectionManager,
+    connection: &RedisConnection,
+    keys: &RedisKeys,
     user_id: &str,
     index_id: &Uuid,
     permission: Permission,
 ) -> Result<(), RedisError> {
-    let user_redis_key = format!("{PERMISSIONS_PREFIX}:{user_id}");
-
-    manager
+    connection
         .clone()
-        .hset::<_, _, u8, _>(&user_redis_key, index_id.to_string(), u8::from(permission))
+        .hset::<_, _, u8, _>(
+            keys.permissions(user_id),
+            index_id.to_string(),
+            u8::from(permission),
+        )
         .await
 }
 
@@ -31,7 +32,14 @@ impl PermissionsTrait for Redis<CUSTOM_WORD_LENGTH> {
     #[instrument(ret(Display), err, skip(self), level = "trace")]
     async fn create_index_id(&self, user_id: &str) -> DatabaseResult<Uuid> {
         let index_id = Uuid::new_v4();
-        hset_redis_permission(&self.manager, user_id, &index_id, Permission::Admin).await?;
+        hset_redis_permission(
+            &self.connection,
+            &self.keys,
+            user_id,
+            &index_id,
+            Permission::Admin,
+        )
+        .await?;
         trace!("New index with id {index_id} created for user  {user_id}");
         Ok(index_id)
     }
@@ -43,17 +51,17 @@ impl PermissionsTrait for Redis<CUSTOM_WORD_LENGTH> {
         permission: Permission,
         index_id: &Uuid,
     ) -> DatabaseResult<()> {
-        hset_redis_permission(&self.manager, user_id, index_id, permission).await?;
+        hset_redis_permission(&self.connection, &self.keys, user_id, index_id, permission).await?;
         trace!("Set {permission:?} permission to {user_id} for index {index_id}");
         Ok(())
     }
 
     #[instrument(ret(Display), err, skip(self), level = "trace")]
     async fn get_permissions(&self, user_id: &str) -> DatabaseResult<Permissions> {
-        let user_redis_key = format!("{PERMISSIONS_PREFIX}:{user_id}");
+        let user_redis_key = self.keys.permissions(user_id);
 
         let permissions: Permissions = self
-            .manager
+            .connection
             .clone()
             .hgetall::<_, Vec<(String, u8)>>(user_redis_key)
             .await?
@@ -76,10 +84,10 @@ impl PermissionsTrait for Redis<CUSTOM_WORD_LENGTH> {
 
     #[instrument(ret(Display), err, skip(self), level = "trace")]
     async fn get_permission(&self, user_id: &str, index_id: &Uuid) -> DatabaseResult<Permission> {
-        let user_key = format!("{PERMISSIONS_PREFIX}:{user_id}");
+        let user_key = self.keys.permissions(user_id);
 
         let permission = self
-            .manager
+            .connection
             .clone()
             .hget::<_, _, Option<u8>>(&user_key, index_id.to_string())
             .await?
@@ -102,11 +110,11 @@ impl PermissionsTrait for Redis<CUSTOM_WORD_LENGTH> {
 
     #[instrument(ret, err, skip(self), level = "trace")]
     async fn revoke_permission(&self, user_id: &str, index_id: &Uuid) -> DatabaseResult<()> {
-        let user_key = format!("{PERMISSIONS_PREFIX}:{user_id}");
+        let user_key = self.keys.permissions(user_id);
 
         // never type fallbacks will be deprecated in future Rust releases, hence this explicit typing
         let _: () = self
-            .manager
+            .connection
             .clone()
             .hdel(&user_key, index_id.to_string())
             .await
@@ -1,13 +1,81 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
-use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, EncryptedEntries, Uuids};
-use rusqlite::params_from_iter;
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, EncryptedEntries, Uuids, VersionedEntries, VersionedEntry,
+};
+use rusqlite::{params_from_iter, types::Value};
 use tracing::{instrument, trace};
 use uuid::Uuid;
 
-use super::{FINDEX_DATASETS_TABLE_NAME, Sqlite};
-use crate::database::{database_traits::DatasetsTrait, findex_database::DatabaseResult};
+use super::{
+    FINDEX_DATASETS_TABLE_NAME, FINDEX_DELETED_ENTRIES_TABLE_NAME,
+    FINDEX_PENDING_ENTRIES_TABLE_NAME, Sqlite,
+    migration::{decode_cursor, next_cursor, query_page},
+};
+use crate::database::{
+    DatabaseError,
+    database_traits::{DatasetsTrait, EntrySize, ScanPage},
+    findex_database::DatabaseResult,
+};
+
+/// The current time, in milliseconds since the Unix epoch.
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| i64::try_from(now.as_millis()).unwrap_or(i64::MAX))
+}
+
+/// Delete the staged entries whose transaction has expired.
+fn purge_expired_entries(conn: &rusqlite::Connection) -> Result<usize, rusqlite::Error> {
+    conn.execute(
+        &format!("DELETE FROM {FINDEX_PENDING_ENTRIES_TABLE_NAME} WHERE expires_at <= ?"),
+        [now_millis()],
+    )
+}
+
+/// Resume the versions of the entries written again after their deletion
+/// from the version they were deleted at, so that an entry never gets a
+/// version twice.
+///
+/// The entries must have just been inserted at their initial version.
+fn resume_versions(
+    tx: &rusqlite::Transaction<'_>,
+    index_id: &Uuid,
+    uids: &[Uuid],
+) -> Result<(), rusqlite::Error> {
+    if uids.is_empty() {
+        return Ok(());
+    }
+    let params = std::iter::once(index_id)
+        .chain(uids)
+        .map(|uid| uid.as_bytes().to_vec())
+        .collect::<Vec<_>>();
+    let placeholders = vec!["?"; uids.len()].join(",");
+    tx.execute(
+        &format!(
+            "UPDATE {FINDEX_DATASETS_TABLE_NAME} SET version = \
+             {FINDEX_DATASETS_TABLE_NAME}.version + deleted.version FROM \
+             {FINDEX_DELETED_ENTRIES_TABLE_NAME} AS deleted WHERE \
+             {FINDEX_DATASETS_TABLE_NAME}.index_id = deleted.index_id AND \
+             {FINDEX_DATASETS_TABLE_NAME}.user_id = deleted.user_id AND deleted.index_id = ? AND \
+             deleted.user_id IN ({placeholders})"
+        ),
+        params_from_iter(&params),
+    )?;
+    tx.execute(
+        &format!(
+            "DELETE FROM {FINDEX_DELETED_ENTRIES_TABLE_NAME} WHERE index_id = ? AND user_id IN \
+             ({placeholders})"
+        ),
+        params_from_iter(&params),
+    )?;
+    Ok(())
+}
 
 #[async_trait]
 impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
@@ -26,16 +94,20 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
         // the borrow checker refuses to move the shared reference in the async block
         // as it might outlive this function. Cloning the values seems inevitable
         let index_id_bytes = Arc::new(index_id.as_bytes().to_vec());
+        let index_id = *index_id;
         let entries = entries.entries.clone();
 
         self.pool
             .conn_mut(move |conn| {
                 let tx = conn.transaction()?;
                 let n = entries.len(); // for logging purposes
+                let uids = entries.keys().copied().collect::<Vec<_>>();
 
                 tx.execute(
                     &format!(
-                        "INSERT OR REPLACE INTO {} (index_id, user_id, encrypted_entry) VALUES {}",
+                        "INSERT INTO {} (index_id, user_id, encrypted_entry) VALUES {} ON \
+                         CONFLICT (index_id, user_id) DO UPDATE SET encrypted_entry = \
+                         excluded.encrypted_entry, version = version + 1",
                         FINDEX_DATASETS_TABLE_NAME,
                         vec!["(?,?,?)"; entries.len()].join(",")
                     ),
@@ -47,6 +119,7 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
                         ]
                     })),
                 )?;
+                resume_versions(&tx, &index_id, &uids)?;
                 tx.commit()?;
                 trace!("dataset_add_entries: {} entries added", n);
                 Ok(())
@@ -73,17 +146,29 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
                 }
 
                 // Build a query with placeholders for each ID
+                let placeholders = vec!["(?,?)"; ids_owned.len()].join(",");
+                let params = ids_owned
+                    .iter()
+                    .flat_map(|id| [index_id.into_bytes(), id.into_bytes()])
+                    .collect::<Vec<_>>();
+                // Keep the versions of the deleted entries, from which they
+                // resume if they are written again.
                 tx.execute(
                     &format!(
-                        "DELETE FROM {} WHERE (index_id, user_id) IN ({})",
-                        FINDEX_DATASETS_TABLE_NAME,
-                        vec!["(?,?)"; ids_owned.len()].join(",")
+                        "INSERT INTO {FINDEX_DELETED_ENTRIES_TABLE_NAME} (index_id, user_id, \
+                         version) SELECT index_id, user_id, version FROM \
+                         {FINDEX_DATASETS_TABLE_NAME} WHERE (index_id, user_id) IN \
+                         ({placeholders}) ON CONFLICT (index_id, user_id) DO UPDATE SET version \
+                         = excluded.version"
                     ),
-                    params_from_iter(
-                        ids_owned
-                            .iter()
-                            .flat_map(|id| [index_id.into_bytes(), id.into_bytes()]),
+                    params_from_iter(&params),
+                )?;
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {FINDEX_DATASETS_TABLE_NAME} WHERE (index_id, user_id) IN \
+                         ({placeholders})"
                     ),
+                    params_from_iter(&params),
                 )?;
 
                 tx.commit()?;
@@ -104,10 +189,10 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
         &self,
         index_id: &Uuid,
         ids: &Uuids,
-    ) -> DatabaseResult<EncryptedEntries> {
+    ) -> DatabaseResult<VersionedEntries> {
         // Early return for empty IDs
         if ids.is_empty() {
-            return Ok(EncryptedEntries::from(HashMap::<Uuid, Vec<u8>>::new()));
+            return Ok(VersionedEntries::default());
         }
 
         let index_id = *index_id;
@@ -116,8 +201,8 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
             .pool
             .conn(move |conn| {
                 let query = format!(
-                    "SELECT user_id, encrypted_entry FROM {} WHERE index_id = ? AND user_id IN \
-                     ({})",
+                    "SELECT user_id, encrypted_entry, version FROM {} WHERE index_id = ? AND \
+                     user_id IN ({})",
                     FINDEX_DATASETS_TABLE_NAME,
                     vec!["?"; ids.len()].join(",")
                 );
@@ -128,15 +213,344 @@ impl DatasetsTrait for Sqlite<CUSTOM_WORD_LENGTH> {
                 let rows = stmt
                     .query_map(params_from_iter(params), |row| {
                         let user_id = Uuid::from_bytes(row.get::<_, [u8; 16]>(0)?);
-                        let encrypted_entry: Vec<u8> = row.get(1)?;
-                        Ok((user_id, encrypted_entry))
+                        let entry: Vec<u8> = row.get(1)?;
+                        let version = row.get(2)?;
+                        Ok((user_id, VersionedEntry { version, entry }))
                     })?
-                    .collect::<Result<HashMap<_, _>, _>>()?;
+                    .collect::<Result<VersionedEntries, _>>()?;
 
                 trace!("dataset_get_entries: {} entries retrieved", rows.len());
 
-                Ok(EncryptedEntries::from(rows))
+                Ok(rows)
             })
             .await?)
     }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn dataset_update_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let index_id = *index_id;
+        let entries = entries
+            .iter()
+            .map(|(uid, versioned)| (*uid, versioned.clone()))
+            .collect::<Vec<_>>();
+
+        let conflicts =
+            self.pool
+                .conn_mut(move |conn| {
+                    let tx = conn.transaction()?;
+                    let current = tx
+                    .prepare(&format!(
+                        "SELECT user_id, version FROM {} WHERE index_id = ? AND user_id IN ({})",
+                        FINDEX_DATASETS_TABLE_NAME,
+                        vec!["?"; entries.len()].join(",")
+                    ))?
+                    .query_map(
+                        params_from_iter(
+                            std::iter::once(index_id.into_bytes())
+                                .chain(entries.iter().map(|(uid, _)| uid.into_bytes())),
+                        ),
+                        |row| {
+                            Ok((
+                                Uuid::from_bytes(row.get::<_, [u8; 16]>(0)?),
+                                row.get::<_, u64>(1)?,
+                            ))
+                        },
+                    )?
+                    .collect::<Result<HashMap<_, _>, _>>()?;
+                    let conflicts = entries
+                        .iter()
+                        .filter_map(|(uid, versioned)| {
+                            let version = current.get(uid).copied().unwrap_or_default();
+                            (version != versioned.version).then_some((*uid, version))
+                        })
+                        .collect::<Vec<_>>();
+                    if !conflicts.is_empty() {
+                        // Dropping the transaction rolls it back.
+                        return Ok(conflicts);
+                    }
+
+                    let uids = entries.iter().map(|(uid, _)| *uid).collect::<Vec<_>>();
+                    let mut params = Vec::with_capacity(4 * entries.len());
+                    for (uid, versioned) in entries {
+                        let version = versioned
+                            .version
+                            .checked_add(1)
+                            .ok_or_else(|| rusqlite::Error::IntegralValueOutOfRange(3, i64::MAX))?;
+                        params.extend([
+                            Value::Blob(index_id.into_bytes().to_vec()),
+                            Value::Blob(uid.into_bytes().to_vec()),
+                            Value::Blob(versioned.entry),
+                            Value::Integer(i64::try_from(version).map_err(|_e| {
+                                rusqlite::Error::IntegralValueOutOfRange(3, i64::MAX)
+                            })?),
+                        ]);
+                    }
+                    tx.execute(
+                    &format!(
+                        "INSERT INTO {} (index_id, user_id, encrypted_entry, version) VALUES {} \
+                         ON CONFLICT (index_id, user_id) DO UPDATE SET encrypted_entry = \
+                         excluded.encrypted_entry, version = excluded.version",
+                        FINDEX_DATASETS_TABLE_NAME,
+                        vec!["(?,?,?,?)"; params.len() / 4].join(",")
+                    ),
+                    params_from_iter(params),
+                )?;
+                    resume_versions(&tx, &index_id, &uids)?;
+                    tx.commit()?;
+                    Ok(Vec::new())
+                })
+                .await?;
+
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(DatabaseError::VersionConflict(conflicts))
+        }
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn dataset_list_entries(
+        &self,
+        index_id: &Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> DatabaseResult<ScanPage<EntrySize>> {
+        let mut conditions = vec![(
+            "index_id = ?",
+            vec![Value::Blob(index_id.as_bytes().to_vec())],
+        )];
+        if let Some(cursor) = cursor {
+            conditions.push(("user_id > ?", vec![Value::Blob(decode_cursor(cursor)?)]));
+        }
+        let items = self
+            .pool
+            .conn(move |conn| {
+                query_page(
+                    conn,
+                    &format!(
+                        "SELECT user_id, length(encrypted_entry) FROM {FINDEX_DATASETS_TABLE_NAME}"
+                    ),
+                    conditions,
+                    "user_id",
+                    limit,
+                    |row| Ok((Uuid::from_bytes(row.get::<_, [u8; 16]>(0)?), row.get(1)?)),
+                )
+            })
+            .await?;
+        trace!("dataset_list_entries: {} entries listed", items.len());
+        let next_cursor = next_cursor(&items, limit, |(uid, _)| uid.simple().to_string());
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(ret, err, skip(self, entries), level = "trace")]
+    async fn dataset_stage_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+        entries: &EncryptedEntries,
+        ttl: Duration,
+    ) -> DatabaseResult<()> {
+        let expires_at =
+            now_millis().saturating_add(i64::try_from(ttl.as_millis()).unwrap_or(i64::MAX));
+        let mut params = Vec::with_capacity(5 * entries.len());
+        for (uid, entry) in entries.iter() {
+            params.extend([
+                Value::Blob(transaction_id.as_bytes().to_vec()),
+                Value::Blob(index_id.as_bytes().to_vec()),
+                Value::Blob(uid.as_bytes().to_vec()),
+                Value::Blob(entry.clone()),
+                Value::Integer(expires_at),
+            ]);
+        }
+
+        self.pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                let purged = purge_expired_entries(&tx)?;
+                trace!("dataset_stage_entries: {purged} expired staged entries discarded");
+                if !params.is_empty() {
+                    tx.execute(
+                        &format!(
+                            "INSERT OR REPLACE INTO {} (transaction_id, index_id, user_id, \
+                             encrypted_entry, expires_at) VALUES {}",
+                            FINDEX_PENDING_ENTRIES_TABLE_NAME,
+                            vec!["(?,?,?,?,?)"; params.len() / 5].join(",")
+                        ),
+                        params_from_iter(params),
+                    )?;
+                }
+                tx.commit()
+            })
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(ret(Display), err, skip(self), level = "trace")]
+    async fn dataset_commit_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<Uuids> {
+        let (index_id, transaction_id) = (*index_id, *transaction_id);
+        let uids = self
+            .pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                purge_expired_entries(&tx)?;
+                let keys = [
+                    transaction_id.as_bytes().to_vec(),
+                    index_id.as_bytes().to_vec(),
+                ];
+                let uids = tx
+                    .prepare(&format!(
+                        "SELECT user_id FROM {FINDEX_PENDING_ENTRIES_TABLE_NAME} WHERE \
+                         transaction_id = ? AND index_id = ?"
+                    ))?
+                    .query_map(params_from_iter(&keys), |row| {
+                        Ok(Uuid::from_bytes(row.get::<_, [u8; 16]>(0)?))
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                tx.execute(
+                    &format!(
+                        "INSERT INTO {FINDEX_DATASETS_TABLE_NAME} (index_id, user_id, \
+                         encrypted_entry) SELECT index_id, user_id, encrypted_entry FROM \
+                         {FINDEX_PENDING_ENTRIES_TABLE_NAME} WHERE transaction_id = ? AND \
+                         index_id = ? ON CONFLICT (index_id, user_id) DO UPDATE SET \
+                         encrypted_entry = excluded.encrypted_entry, version = version + 1"
+                    ),
+                    params_from_iter(&keys),
+                )?;
+                resume_versions(&tx, &index_id, &uids)?;
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {FINDEX_PENDING_ENTRIES_TABLE_NAME} WHERE transaction_id = ? \
+                         AND index_id = ?"
+                    ),
+                    params_from_iter(&keys),
+                )?;
+                tx.commit()?;
+                Ok(uids)
+            })
+            .await?;
+
+        if uids.is_empty() {
+            return Err(DatabaseError::UnknownTransaction(transaction_id));
+        }
+        Ok(Uuids::from(uids))
+    }
+
+    #[instrument(ret, err, skip(self), level = "trace")]
+    async fn dataset_abort_entries(
+        &self,
+        index_id: &Uuid,
+        transaction_id: &Uuid,
+    ) -> DatabaseResult<()> {
+        let keys = [
+            transaction_id.as_bytes().to_vec(),
+            index_id.as_bytes().to_vec(),
+        ];
+        self.pool
+            .conn_mut(move |conn| {
+                conn.execute(
+                    &format!(
+                        "DELETE FROM {FINDEX_PENDING_ENTRIES_TABLE_NAME} WHERE transaction_id = ? \
+                         AND index_id = ?"
+                    ),
+                    params_from_iter(keys),
+                )
+            })
+            .await?;
+        Ok(())
+    }
+    #[instrument(ret, err, skip(self), level = "trace")]
+    async fn dataset_purge_expired_entries(&self) -> DatabaseResult<usize> {
+        Ok(self
+            .pool
+            .conn_mut(|conn| purge_expired_entries(conn))
+            .await?)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::expect_used, clippy::panic)]
+mod tests {
+    use cosmian_crypto_core::{
+        CsRng,
+        reexport::rand_core::{RngCore, SeedableRng},
+    };
+
+    use super::*;
+    use crate::{
+        config::DatabaseType,
+        database::{
+            database_traits::InstantiationTrait,
+            test_utils::dataset_tests::{list_entries, staged_entries, versioned_entries},
+        },
+    };
+
+    const SQLITE_TEST_DB_URL: &str = "sqlite-test";
+
+    async fn setup_a_random_test_db() -> Sqlite<CUSTOM_WORD_LENGTH> {
+        let random_db = std::env::temp_dir().join(format!(
+            "{}-{}.db",
+            SQLITE_TEST_DB_URL,
+            CsRng::from_entropy().next_u64()
+        ));
+        Sqlite::instantiate(DatabaseType::Sqlite, &random_db.to_string_lossy(), false)
+            .await
+            .expect("Test failed to instantiate Sqlite")
+    }
+
+    #[tokio::test]
+    async fn list_entries_test() {
+        let db = setup_a_random_test_db().await;
+        list_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test list_entries failed: {e:?}"));
+    }
+
+    #[tokio::test]
+    async fn versioned_entries_test() {
+        let db = setup_a_random_test_db().await;
+        versioned_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test versioned_entries failed: {e:?}"));
+    }
+
+    #[tokio::test]
+    async fn staged_entries_test() {
+        let db = setup_a_random_test_db().await;
+        staged_entries(db)
+            .await
+            .unwrap_or_else(|e| panic!("Test staged_entries failed: {e:?}"));
+    }
+
+    #[tokio::test]
+    async fn purge_expired_entries_test() {
+        let db = setup_a_random_test_db().await;
+        let entries = EncryptedEntries::from_iter([(Uuid::new_v4(), vec![1])]);
+        // Staging purges the entries expired at that time.
+        for ttl in [
+            Duration::from_secs(60),
+            Duration::from_millis(500),
+            Duration::from_millis(500),
+        ] {
+            db.dataset_stage_entries(&Uuid::new_v4(), &Uuid::new_v4(), &entries, ttl)
+                .await
+                .expect("Test failed to stage entries");
+        }
+        tokio::time::sleep(Duration::from_millis(600)).await;
+
+        // The expired entries of all the indexes are purged, whether or not
+        // these indexes are written again.
+        assert_eq!(db.dataset_purge_expired_entries().await.ok(), Some(2));
+        assert_eq!(db.dataset_purge_expired_entries().await.ok(), Some(0));
+    }
 }
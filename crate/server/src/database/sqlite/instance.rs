@@ -60,6 +60,8 @@ impl SqlitePool {
 pub const FINDEX_MEMORY_TABLE_NAME: &str = "findex_server_memory";
 pub const FINDEX_PERMISSIONS_TABLE_NAME: &str = "findex_server_permissions";
 pub const FINDEX_DATASETS_TABLE_NAME: &str = "findex_server_datasets";
+pub(crate) const FINDEX_PENDING_ENTRIES_TABLE_NAME: &str = "findex_server_pending_entries";
+pub(crate) const FINDEX_DELETED_ENTRIES_TABLE_NAME: &str = "findex_server_deleted_entries";
 
 #[async_trait]
 #[allow(clippy::expect_used)]
@@ -85,6 +87,8 @@ impl<const WORD_LENGTH: usize> InstantiationTrait for Sqlite<WORD_LENGTH> {
                     DROP TABLE IF EXISTS {FINDEX_MEMORY_TABLE_NAME};
                     DROP TABLE IF EXISTS {FINDEX_PERMISSIONS_TABLE_NAME};
                     DROP TABLE IF EXISTS {FINDEX_DATASETS_TABLE_NAME};
+                    DROP TABLE IF EXISTS {FINDEX_PENDING_ENTRIES_TABLE_NAME};
+                    DROP TABLE IF EXISTS {FINDEX_DELETED_ENTRIES_TABLE_NAME};
                     ",
                 ))
             })
@@ -113,10 +117,39 @@ impl<const WORD_LENGTH: usize> InstantiationTrait for Sqlite<WORD_LENGTH> {
                     index_id BLOB NOT NULL,
                     user_id      BLOB NOT NULL,
                     encrypted_entry     BLOB NOT NULL,
+                    version INTEGER NOT NULL DEFAULT 1,
+                    PRIMARY KEY (index_id, user_id)
+                );
+                CREATE TABLE IF NOT EXISTS {FINDEX_PENDING_ENTRIES_TABLE_NAME} (
+                    transaction_id BLOB NOT NULL,
+                    index_id BLOB NOT NULL,
+                    user_id BLOB NOT NULL,
+                    encrypted_entry BLOB NOT NULL,
+                    expires_at INTEGER NOT NULL,
+                    PRIMARY KEY (transaction_id, user_id)
+                );
+                CREATE TABLE IF NOT EXISTS {FINDEX_DELETED_ENTRIES_TABLE_NAME} (
+                    index_id BLOB NOT NULL,
+                    user_id BLOB NOT NULL,
+                    version INTEGER NOT NULL,
                     PRIMARY KEY (index_id, user_id)
                 );
                 ",
-            ))
+            ))?;
+            // The entries stored before the versioning get the version 1.
+            let is_versioned = conn
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{FINDEX_DATASETS_TABLE_NAME}') WHERE name = \
+                     'version'"
+                ))?
+                .exists([])?;
+            if !is_versioned {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {FINDEX_DATASETS_TABLE_NAME} ADD COLUMN version INTEGER NOT NULL \
+                     DEFAULT 1;"
+                ))?;
+            }
+            Ok(())
         })
         .await?;
 
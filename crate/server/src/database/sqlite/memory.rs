@@ -138,6 +138,12 @@ mod tests {
     const DB_PATH: &str = "sqlite-test.sqlite.db";
     const TABLE_NAME: &str = "findex_memory";
 
+    /// The path of a test database, in the temporary directory rather than in
+    /// the crate.
+    fn db_path(prefix: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{prefix}{DB_PATH}"))
+    }
+
     impl<Address, Word> SqliteMemory<Address, Word> {
         async fn new_with_path(
             path: impl AsRef<std::path::Path>,
@@ -171,7 +177,7 @@ mod tests {
     // note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
     // #[tokio::test]
     // async fn test_sequential_read_write() {
-    //     let m = SqliteMemory::<_, [u8; 52]>::new_with_path(DB_PATH, TABLE_NAME.to_owned())
+    //     let m = SqliteMemory::<_, [u8; 52]>::new_with_path(db_path(""), TABLE_NAME.to_owned())
     //         .await
     //         .unwrap();
     //     test_single_write_and_read(&m, gen_seed()).await;
@@ -179,7 +185,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_sequential_wrong_guard() {
-        let m = SqliteMemory::<_, [u8; 999]>::new_with_path(DB_PATH, TABLE_NAME.to_owned())
+        let m = SqliteMemory::<_, [u8; 999]>::new_with_path(db_path(""), TABLE_NAME.to_owned())
             .await
             .unwrap();
         test_wrong_guard(&m, gen_seed()).await;
@@ -188,7 +194,7 @@ mod tests {
     #[tokio::test]
     async fn test_sequential_same_address() {
         let m = SqliteMemory::<_, [u8; 87]>::new_with_path(
-            format!("test_sequential_same_address{DB_PATH}"),
+            db_path("test_sequential_same_address"),
             TABLE_NAME.to_owned(),
         )
         .await
@@ -202,7 +208,7 @@ mod tests {
     async fn test_concurrent_read_write() {
         const WORD_LENGTH: usize = 129;
         let m = SqliteMemory::<_, [u8; WORD_LENGTH]>::new_with_path(
-            format!("test_concurrent_read_write{DB_PATH}"),
+            db_path("test_concurrent_read_write"),
             TABLE_NAME.to_owned(),
         )
         .await
@@ -0,0 +1,309 @@
+use async_trait::async_trait;
+use cosmian_findex_structs::{
+    CUSTOM_WORD_LENGTH, Permission, SERVER_ADDRESS_LENGTH, UID_LENGTH, VersionedEntries,
+    VersionedEntry,
+};
+use cosmian_sse_memories::Address;
+use rusqlite::{params_from_iter, types::Value};
+use tracing::{instrument, trace};
+use uuid::Uuid;
+
+use super::{
+    FINDEX_DATASETS_TABLE_NAME, FINDEX_DELETED_ENTRIES_TABLE_NAME, FINDEX_MEMORY_TABLE_NAME,
+    FINDEX_PERMISSIONS_TABLE_NAME, Sqlite,
+};
+use crate::database::{
+    DatabaseError,
+    database_traits::{DatasetEntry, MemoryWord, MigrationTrait, ScanPage, UserPermission},
+    findex_database::DatabaseResult,
+};
+
+// The rows are read in primary key order and the cursor holds the key of the
+// last row read (keyset pagination): the hex-encoded address of a memory
+// word, `<hex index id>:<user id>` for a permission and
+// `<hex index id>:<hex entry id>` for a dataset entry.
+
+pub(super) fn decode_cursor(cursor: &str) -> DatabaseResult<Vec<u8>> {
+    hex::decode(cursor).map_err(|_e| DatabaseError::InvalidCursor(cursor.to_owned()))
+}
+
+fn decode_pair_cursor(cursor: &str) -> DatabaseResult<(Vec<u8>, &str)> {
+    let (first, second) = cursor
+        .split_once(':')
+        .ok_or_else(|| DatabaseError::InvalidCursor(cursor.to_owned()))?;
+    Ok((decode_cursor(first)?, second))
+}
+
+/// The cursor of the next page: a full page may not be the last one.
+pub(super) fn next_cursor<T>(
+    items: &[T],
+    count: usize,
+    cursor_of: impl Fn(&T) -> String,
+) -> Option<String> {
+    if items.len() < count {
+        None
+    } else {
+        items.last().map(cursor_of)
+    }
+}
+
+fn into_permission(value: u8) -> Result<Permission, rusqlite::Error> {
+    Permission::try_from(value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Integer, Box::new(e))
+    })
+}
+
+/// Read at most `count` rows of a query, keeping the rows matching all the
+/// conditions.
+pub(super) fn query_page<T>(
+    conn: &rusqlite::Connection,
+    select: &str,
+    conditions: Vec<(&str, Vec<Value>)>,
+    order_by: &str,
+    count: usize,
+    map_row: impl FnMut(&rusqlite::Row<'_>) -> Result<T, rusqlite::Error>,
+) -> Result<Vec<T>, rusqlite::Error> {
+    let (conditions, params): (Vec<_>, Vec<_>) = conditions.into_iter().unzip();
+    let mut params = params.concat();
+    let condition = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    params.push(Value::Integer(i64::try_from(count).unwrap_or(i64::MAX)));
+    conn.prepare(&format!("{select} {condition} ORDER BY {order_by} LIMIT ?"))?
+        .query_map(params_from_iter(params), map_row)?
+        .collect()
+}
+
+#[async_trait]
+impl MigrationTrait for Sqlite<CUSTOM_WORD_LENGTH> {
+    #[instrument(err, skip(self), level = "trace")]
+    async fn memory_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<MemoryWord>> {
+        let mut conditions = Vec::new();
+        if let Some(index_id) = index_id {
+            // The addresses of an index start with its ID.
+            let bounds = [0_u8, u8::MAX].map(|byte| {
+                let mut bound = [byte; SERVER_ADDRESS_LENGTH];
+                bound[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
+                Value::Blob(bound.to_vec())
+            });
+            conditions.push(("a BETWEEN ? AND ?", bounds.to_vec()));
+        }
+        if let Some(cursor) = cursor {
+            conditions.push(("a > ?", vec![Value::Blob(decode_cursor(cursor)?)]));
+        }
+        let items = self
+            .pool
+            .conn(move |conn| {
+                query_page(
+                    conn,
+                    &format!("SELECT a, w FROM {FINDEX_MEMORY_TABLE_NAME}"),
+                    conditions,
+                    "a",
+                    count,
+                    |row| {
+                        let address: [u8; SERVER_ADDRESS_LENGTH] = row.get(0)?;
+                        Ok((Address::from(address), row.get(1)?))
+                    },
+                )
+            })
+            .await?;
+        trace!("memory_scan: {} words read", items.len());
+        let next_cursor = next_cursor(&items, count, |(address, _)| hex::encode(**address));
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn memory_restore(&self, words: Vec<MemoryWord>) -> DatabaseResult<()> {
+        if words.is_empty() {
+            return Ok(());
+        }
+        let n = words.len(); // for logging purposes
+        self.pool
+            .conn_mut(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO {} (a, w) VALUES {}",
+                        FINDEX_MEMORY_TABLE_NAME,
+                        vec!["(?,?)"; words.len()].join(",")
+                    ),
+                    params_from_iter(
+                        words
+                            .iter()
+                            .flat_map(|(address, word)| [address.to_vec(), word.to_vec()]),
+                    ),
+                )?;
+                tx.commit()
+            })
+            .await?;
+        trace!("memory_restore: {n} words written");
+        Ok(())
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn permissions_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<UserPermission>> {
+        let mut conditions = Vec::new();
+        if let Some(index_id) = index_id {
+            conditions.push((
+                "index_id = ?",
+                vec![Value::Blob(index_id.as_bytes().to_vec())],
+            ));
+        }
+        if let Some(cursor) = cursor {
+            let (index_id, user_id) = decode_pair_cursor(cursor)?;
+            conditions.push((
+                "(user_id, index_id) > (?, ?)",
+                vec![Value::Text(user_id.to_owned()), Value::Blob(index_id)],
+            ));
+        }
+        let items = self
+            .pool
+            .conn(move |conn| {
+                query_page(
+                    conn,
+                    &format!(
+                        "SELECT user_id, index_id, permission FROM {FINDEX_PERMISSIONS_TABLE_NAME}"
+                    ),
+                    conditions,
+                    "user_id, index_id",
+                    count,
+                    |row| {
+                        Ok((
+                            row.get(0)?,
+                            Uuid::from_bytes(row.get::<_, [u8; 16]>(1)?),
+                            into_permission(row.get(2)?)?,
+                        ))
+                    },
+                )
+            })
+            .await?;
+        trace!("permissions_scan: {} permissions read", items.len());
+        let next_cursor = next_cursor(&items, count, |(user_id, index_id, _)| {
+            format!("{}:{user_id}", index_id.simple())
+        });
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip(self), level = "trace")]
+    async fn datasets_scan(
+        &self,
+        index_id: Option<&Uuid>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<ScanPage<DatasetEntry>> {
+        let mut conditions = Vec::new();
+        if let Some(index_id) = index_id {
+            conditions.push((
+                "index_id = ?",
+                vec![Value::Blob(index_id.as_bytes().to_vec())],
+            ));
+        }
+        if let Some(cursor) = cursor {
+            let (index_id, uid) = decode_pair_cursor(cursor)?;
+            conditions.push((
+                "(index_id, user_id) > (?, ?)",
+                vec![Value::Blob(index_id), Value::Blob(decode_cursor(uid)?)],
+            ));
+        }
+        let items = self
+            .pool
+            .conn(move |conn| {
+                query_page(
+                    conn,
+                    &format!(
+                        "SELECT index_id, user_id, encrypted_entry, version FROM \
+                         {FINDEX_DATASETS_TABLE_NAME}"
+                    ),
+                    conditions,
+                    "index_id, user_id",
+                    count,
+                    |row| {
+                        Ok((
+                            Uuid::from_bytes(row.get::<_, [u8; 16]>(0)?),
+                            Uuid::from_bytes(row.get::<_, [u8; 16]>(1)?),
+                            VersionedEntry {
+                                version: row.get(3)?,
+                                entry: row.get(2)?,
+                            },
+                        ))
+                    },
+                )
+            })
+            .await?;
+        trace!("datasets_scan: {} entries read", items.len());
+        let next_cursor = next_cursor(&items, count, |(index_id, uid, _)| {
+            format!("{}:{}", index_id.simple(), uid.simple())
+        });
+        Ok(ScanPage { items, next_cursor })
+    }
+
+    #[instrument(err, skip_all, level = "trace")]
+    async fn dataset_restore_entries(
+        &self,
+        index_id: &Uuid,
+        entries: &VersionedEntries,
+    ) -> DatabaseResult<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let n = entries.len(); // for logging purposes
+        let keys = std::iter::once(index_id)
+            .chain(entries.keys())
+            .map(|uid| uid.as_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let index_id = *index_id;
+        let entries = entries.entries.clone();
+        self.pool
+            .conn_mut(move |conn| {
+                let mut params = Vec::with_capacity(4 * entries.len());
+                for (uid, versioned) in entries {
+                    params.extend([
+                        Value::Blob(index_id.into_bytes().to_vec()),
+                        Value::Blob(uid.into_bytes().to_vec()),
+                        Value::Blob(versioned.entry),
+                        Value::Integer(
+                            i64::try_from(versioned.version).map_err(|_e| {
+                                rusqlite::Error::IntegralValueOutOfRange(3, i64::MAX)
+                            })?,
+                        ),
+                    ]);
+                }
+                let tx = conn.transaction()?;
+                tx.execute(
+                    &format!(
+                        "INSERT INTO {} (index_id, user_id, encrypted_entry, version) VALUES {} \
+                         ON CONFLICT (index_id, user_id) DO UPDATE SET encrypted_entry = \
+                         excluded.encrypted_entry, version = excluded.version",
+                        FINDEX_DATASETS_TABLE_NAME,
+                        vec!["(?,?,?,?)"; n].join(",")
+                    ),
+                    params_from_iter(params),
+                )?;
+                // The restored versions supersede those of deleted entries.
+                tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE index_id = ? AND user_id IN ({})",
+                        FINDEX_DELETED_ENTRIES_TABLE_NAME,
+                        vec!["?"; n].join(",")
+                    ),
+                    params_from_iter(keys),
+                )?;
+                tx.commit()
+            })
+            .await?;
+        trace!("dataset_restore_entries: {n} entries written");
+        Ok(())
+    }
+}
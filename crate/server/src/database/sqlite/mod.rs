@@ -2,6 +2,7 @@ mod datasets;
 mod findex;
 mod instance;
 mod memory;
+mod migration;
 mod permissions;
 use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
 
@@ -12,4 +13,5 @@ pub(crate) use instance::Sqlite;
 pub use instance::{
     FINDEX_DATASETS_TABLE_NAME, FINDEX_MEMORY_TABLE_NAME, FINDEX_PERMISSIONS_TABLE_NAME,
 };
+pub(crate) use instance::{FINDEX_DELETED_ENTRIES_TABLE_NAME, FINDEX_PENDING_ENTRIES_TABLE_NAME};
 pub(crate) use memory::SqliteMemoryError;
@@ -195,12 +195,12 @@ mod tests {
     // starvation issues. The starving test will consume all of its body timeout time
     // and throw a `DatabaseBusy` error.
     async fn setup_a_random_test_db() -> Sqlite<CUSTOM_WORD_LENGTH> {
-        let random_db = format!(
+        let random_db = std::env::temp_dir().join(format!(
             "{}-{}.db",
             SQLITE_TEST_DB_URL,
             CsRng::from_entropy().next_u64()
-        );
-        Sqlite::instantiate(DatabaseType::Sqlite, &random_db, false)
+        ));
+        Sqlite::instantiate(DatabaseType::Sqlite, &random_db.to_string_lossy(), false)
             .await
             .expect("Test failed to instantiate Sqlite")
     }
@@ -0,0 +1,244 @@
+#[allow(
+    clippy::unwrap_used,
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_in_result,
+    clippy::items_after_test_module
+)] // The below module is only compiled for tests, and those lints are not useful in tests
+#[cfg(test)]
+pub(crate) mod tests_mod {
+    use std::{collections::HashMap, time::Duration};
+
+    use cosmian_findex_structs::{EncryptedEntries, Uuids, VersionedEntries, VersionedEntry};
+    use uuid::Uuid;
+
+    use crate::database::{
+        DatabaseError, database_traits::DatasetsTrait, findex_database::DatabaseResult,
+    };
+
+    async fn list_all<T: DatasetsTrait>(
+        db: &T,
+        index_id: &Uuid,
+    ) -> DatabaseResult<HashMap<Uuid, usize>> {
+        let mut listed = HashMap::new();
+        let mut cursor = None;
+        loop {
+            let page = db
+                .dataset_list_entries(index_id, cursor.as_deref(), 10)
+                .await?;
+            assert!(page.items.len() <= 10, "a page exceeds the limit");
+            listed.extend(page.items);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                return Ok(listed);
+            }
+        }
+    }
+
+    /// Test listing the entries of an index page by page
+    pub(crate) async fn list_entries<T: DatasetsTrait>(db: T) -> DatabaseResult<()> {
+        let index_id = Uuid::new_v4();
+        let entries = (1..=25_usize)
+            .map(|size| (Uuid::new_v4(), vec![1; size]))
+            .collect::<HashMap<_, _>>();
+        db.dataset_add_entries(&index_id, &EncryptedEntries::from(entries.clone()))
+            .await?;
+        // The entries of another index are not listed.
+        db.dataset_add_entries(
+            &Uuid::new_v4(),
+            &EncryptedEntries::from_iter([(Uuid::new_v4(), vec![1])]),
+        )
+        .await?;
+
+        let expected = entries
+            .iter()
+            .map(|(uid, entry)| (*uid, entry.len()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(list_all(&db, &index_id).await?, expected);
+
+        // Deleted entries are no longer listed.
+        let deleted = entries.keys().take(5).copied().collect::<Vec<_>>();
+        db.dataset_delete_entries(&index_id, &Uuids::from(deleted.clone()))
+            .await?;
+        let listed = list_all(&db, &index_id).await?;
+        assert_eq!(listed.len(), 20);
+        assert!(deleted.iter().all(|uid| !listed.contains_key(uid)));
+
+        // An invalid cursor is rejected.
+        db.dataset_list_entries(&index_id, Some("not a cursor"), 10)
+            .await
+            .unwrap_err();
+        Ok(())
+    }
+
+    fn versioned(version: u64, entry: &[u8]) -> VersionedEntry {
+        VersionedEntry {
+            version,
+            entry: entry.to_vec(),
+        }
+    }
+
+    /// Test the versions of the entries and the conditional updates
+    pub(crate) async fn versioned_entries<T: DatasetsTrait>(db: T) -> DatabaseResult<()> {
+        let index_id = Uuid::new_v4();
+        let (added, created, missing) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let ids = Uuids::from(vec![added, created, missing]);
+
+        // Each write increments the version of an entry.
+        db.dataset_add_entries(&index_id, &EncryptedEntries::from_iter([(added, vec![1])]))
+            .await?;
+        db.dataset_add_entries(&index_id, &EncryptedEntries::from_iter([(added, vec![2])]))
+            .await?;
+        assert_eq!(
+            db.dataset_get_entries(&index_id, &ids).await?,
+            VersionedEntries::from_iter([(added, versioned(2, &[2]))])
+        );
+
+        // An update with the current versions succeeds, a missing entry
+        // being at version 0.
+        db.dataset_update_entries(
+            &index_id,
+            &VersionedEntries::from_iter([
+                (added, versioned(2, &[3])),
+                (created, versioned(0, &[4])),
+            ]),
+        )
+        .await?;
+        let expected = VersionedEntries::from_iter([
+            (added, versioned(3, &[3])),
+            (created, versioned(1, &[4])),
+        ]);
+        assert_eq!(db.dataset_get_entries(&index_id, &ids).await?, expected);
+
+        // An update with a stale version writes nothing and reports the
+        // current versions of the conflicting entries.
+        let error = db
+            .dataset_update_entries(
+                &index_id,
+                &VersionedEntries::from_iter([
+                    (added, versioned(2, &[5])),
+                    (created, versioned(1, &[6])),
+                    (missing, versioned(1, &[7])),
+                ]),
+            )
+            .await
+            .unwrap_err();
+        let DatabaseError::VersionConflict(mut conflicts) = error else {
+            panic!("expected a version conflict, found {error:?}");
+        };
+        conflicts.sort_unstable();
+        let mut expected_conflicts = vec![(added, 3), (missing, 0)];
+        expected_conflicts.sort_unstable();
+        assert_eq!(conflicts, expected_conflicts);
+        assert_eq!(db.dataset_get_entries(&index_id, &ids).await?, expected);
+
+        // A deleted entry is missing, and resumes from the version it was
+        // deleted at when it is written again.
+        let added_id = Uuids::from(vec![added]);
+        db.dataset_delete_entries(&index_id, &added_id).await?;
+        let error = db
+            .dataset_update_entries(
+                &index_id,
+                &VersionedEntries::from_iter([(added, versioned(3, &[8]))]),
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, DatabaseError::VersionConflict(conflicts) if conflicts == &[(added, 0)]),
+            "expected a version conflict, found {error:?}"
+        );
+        db.dataset_update_entries(
+            &index_id,
+            &VersionedEntries::from_iter([(added, versioned(0, &[8]))]),
+        )
+        .await?;
+        assert_eq!(
+            db.dataset_get_entries(&index_id, &added_id).await?,
+            VersionedEntries::from_iter([(added, versioned(4, &[8]))])
+        );
+        db.dataset_delete_entries(&index_id, &added_id).await?;
+        db.dataset_add_entries(&index_id, &EncryptedEntries::from_iter([(added, vec![9])]))
+            .await?;
+        assert_eq!(
+            db.dataset_get_entries(&index_id, &added_id).await?,
+            VersionedEntries::from_iter([(added, versioned(5, &[9]))])
+        );
+        Ok(())
+    }
+
+    /// Test staging entries under transactions, then committing or aborting
+    /// them, or letting them expire
+    pub(crate) async fn staged_entries<T: DatasetsTrait>(db: T) -> DatabaseResult<()> {
+        const TTL: Duration = Duration::from_secs(60);
+        let index_id = Uuid::new_v4();
+        let entries = (0..10_u8)
+            .map(|i| (Uuid::new_v4(), vec![i; 8]))
+            .collect::<HashMap<_, _>>();
+        let uuids = Uuids::from(entries.keys().copied().collect::<Vec<_>>());
+
+        // Staged entries are not part of the dataset until committed.
+        let transaction_id = Uuid::new_v4();
+        db.dataset_stage_entries(
+            &index_id,
+            &transaction_id,
+            &EncryptedEntries::from(entries.clone()),
+            TTL,
+        )
+        .await?;
+        assert!(db.dataset_get_entries(&index_id, &uuids).await?.is_empty());
+        // A transaction is committed to its own index only.
+        let error = db
+            .dataset_commit_entries(&Uuid::new_v4(), &transaction_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DatabaseError::UnknownTransaction(_)));
+
+        let mut committed = db
+            .dataset_commit_entries(&index_id, &transaction_id)
+            .await?
+            .to_vec();
+        committed.sort_unstable();
+        let mut expected = uuids.to_vec();
+        expected.sort_unstable();
+        assert_eq!(committed, expected);
+        let stored = db.dataset_get_entries(&index_id, &uuids).await?;
+        assert_eq!(
+            EncryptedEntries::from(stored),
+            EncryptedEntries::from(entries)
+        );
+
+        // A transaction is committed once.
+        let error = db
+            .dataset_commit_entries(&index_id, &transaction_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, DatabaseError::UnknownTransaction(_)));
+
+        // Aborted and expired transactions leave the dataset unchanged.
+        let staged = EncryptedEntries::from_iter([(Uuid::new_v4(), vec![1])]);
+        let aborted = Uuid::new_v4();
+        let expired = Uuid::new_v4();
+        db.dataset_stage_entries(&index_id, &aborted, &staged, TTL)
+            .await?;
+        db.dataset_abort_entries(&index_id, &aborted).await?;
+        db.dataset_stage_entries(&index_id, &expired, &staged, Duration::from_millis(1))
+            .await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        for transaction_id in [aborted, expired] {
+            let error = db
+                .dataset_commit_entries(&index_id, &transaction_id)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, DatabaseError::UnknownTransaction(_)));
+        }
+        assert!(
+            db.dataset_get_entries(&index_id, &staged.get_uuids())
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) use tests_mod::*;
@@ -1 +1,2 @@
+pub(crate) mod dataset_tests;
 pub(crate) mod permission_tests;
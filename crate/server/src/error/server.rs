@@ -25,6 +25,9 @@ pub enum ServerError {
     // Any actions of the user which is not allowed
     #[error("Permission denied: {0}")]
     Unauthorized(String),
+    // A write based on an outdated state of the data
+    #[error("Conflict: {0}")]
+    Conflict(String),
     // A failure originating from one of the cryptographic algorithms
     #[error("Cryptographic error: {0}")]
     CryptographicError(String),
@@ -54,7 +57,13 @@ impl From<std::io::Error> for ServerError {
 // Actual database error conversion is handled in the database module
 impl From<crate::database::DatabaseError> for ServerError {
     fn from(e: crate::database::DatabaseError) -> Self {
-        Self::DatabaseError(format!("Database error : {e}"))
+        match e {
+            crate::database::DatabaseError::VersionConflict(_) => Self::Conflict(e.to_string()),
+            crate::database::DatabaseError::UnknownTransaction(_) => {
+                Self::InvalidRequest(e.to_string())
+            }
+            _ => Self::DatabaseError(format!("Database error : {e}")),
+        }
     }
 }
 
@@ -5,8 +5,8 @@ use actix_identity::IdentityMiddleware;
 use actix_web::{
     App, HttpServer,
     dev::ServerHandle,
-    middleware::Condition,
-    web::{self, Data, JsonConfig, PayloadConfig},
+    middleware::{Compress, Condition},
+    web::{self, Data, JsonConfig, PayloadConfig, ServiceConfig},
 };
 use openssl::{
     ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod, SslVerifyMode},
@@ -18,10 +18,15 @@ use crate::{
     config::{self, JwtAuthConfig, ServerParams},
     core::FindexServer,
     error::result::FResult,
+    grpc::start_grpc_server,
     middlewares::{AuthTransformer, JwksManager, JwtConfig, SslAuth, extract_peer_certificate},
     routes::{
-        create_index_id, datasets_add_entries, datasets_del_entries, datasets_get_entries,
-        findex_batch_read, findex_guarded_write, get_version, list_permission, revoke_permission,
+        clone_index, create_index_id, datasets_abort_entries, datasets_add_entries,
+        datasets_add_entries_stream, datasets_commit_entries, datasets_del_entries,
+        datasets_get_entries, datasets_get_entries_stream, datasets_list_entries,
+        datasets_stage_entries, datasets_update_entries, export_index, findex_batch_read,
+        findex_batch_read_indexes, findex_batch_read_stream, findex_guarded_write, get_openapi,
+        get_version, import_index, index_is_empty, list_permission, revoke_permission,
         set_permission,
     },
     server_bail,
@@ -33,6 +38,8 @@ use crate::{
 /// 1. Plain HTTP,
 /// 2. HTTPS with PKCS#12,
 ///
+/// The gRPC API is served alongside, on its own port, if one is configured.
+///
 /// The method used depends on the server settings specified in the
 /// `ServerParams` instance provided.
 ///
@@ -88,7 +95,8 @@ async fn start_plain_http_findex_server(
     let findex_server = Arc::new(FindexServer::instantiate(server_params).await?);
 
     // Prepare the server
-    let server = prepare_findex_server(findex_server, None).await?;
+    let jwt_configurations = prepare_jwt_configurations(&findex_server.params).await?;
+    let server = prepare_findex_server(findex_server.clone(), None, jwt_configurations.clone())?;
 
     // send the server handle to the caller
     if let Some(tx) = &server_handle_transmitter {
@@ -97,7 +105,7 @@ async fn start_plain_http_findex_server(
 
     info!("Starting the HTTP Findex server...");
     // Run the server and return the result
-    server.await.map_err(Into::into)
+    run_findex_server(server, findex_server, jwt_configurations).await
 }
 
 /// Start an HTTPS Findex server using a PKCS#12 certificate file
@@ -151,7 +159,12 @@ async fn start_https_findex_server(
 
     // Instantiate and prepare the Findex server
     let findex_server = Arc::new(FindexServer::instantiate(server_params).await?);
-    let server = prepare_findex_server(findex_server, Some(builder)).await?;
+    let jwt_configurations = prepare_jwt_configurations(&findex_server.params).await?;
+    let server = prepare_findex_server(
+        findex_server.clone(),
+        Some(builder),
+        jwt_configurations.clone(),
+    )?;
 
     // send the server handle to the caller
     if let Some(tx) = &server_handle_transmitter {
@@ -161,7 +174,57 @@ async fn start_https_findex_server(
     info!("Starting the HTTPS Findex server...");
 
     // Run the server and return the result
-    server.await.map_err(Into::into)
+    run_findex_server(server, findex_server, jwt_configurations).await
+}
+
+/// Run the HTTP server, and the gRPC server alongside it if a gRPC port is
+/// configured. The gRPC server and the purge of the expired staged entries
+/// are stopped with the HTTP server.
+async fn run_findex_server(
+    server: actix_web::dev::Server,
+    findex_server: Arc<FindexServer>,
+    jwt_configurations: Option<Arc<Vec<JwtConfig>>>,
+) -> FResult<()> {
+    let purge = findex_server.clone();
+    let purge = tokio::spawn(async move { purge.purge_expired_entries().await });
+    let res = match findex_server.params.grpc_port {
+        None => server.await.map_err(Into::into),
+        Some(grpc_port) => tokio::select! {
+            res = server => res.map_err(Into::into),
+            res = start_grpc_server(findex_server, jwt_configurations, grpc_port) => res,
+        },
+    };
+    purge.abort();
+    res
+}
+
+/// Prepare the JWT configurations, sharing a JWKS manager, if the server is
+/// using JWT for authentication.
+async fn prepare_jwt_configurations(params: &ServerParams) -> FResult<Option<Arc<Vec<JwtConfig>>>> {
+    let Some(identity_provider_configurations) = &params.identity_provider_configurations else {
+        return Ok(None);
+    };
+
+    // Prepare all the needed URIs from all the configured Identity Providers
+    let all_jwks_uris: Vec<_> = identity_provider_configurations
+        .iter()
+        .map(|idp_config| {
+            JwtAuthConfig::uri(&idp_config.jwt_issuer_uri, idp_config.jwks_uri.as_deref())
+        })
+        .collect();
+
+    let jwks_manager = Arc::new(JwksManager::new(all_jwks_uris).await?);
+
+    let built_jwt_configurations = identity_provider_configurations
+        .iter()
+        .map(|idp_config| JwtConfig {
+            jwt_issuer_uri: idp_config.jwt_issuer_uri.clone(),
+            jwks: jwks_manager.clone(),
+            jwt_audience: idp_config.jwt_audience.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Some(Arc::new(built_jwt_configurations)))
 }
 
 /**
@@ -176,6 +239,8 @@ async fn start_https_findex_server(
  *   be used by the application.
  * * `builder`: An optional `SslAcceptorBuilder` to configure the SSL
  *   encryption for the server.
+ * * `jwt_configurations`: The JWT configurations, if the server is using JWT
+ *   for authentication.
  *
  * # Returns
  *
@@ -188,39 +253,11 @@ async fn start_https_findex_server(
  * - `FindexServerError::ServerError` - If there is an error in the server
  *   configuration or preparation.
  */
-pub(crate) async fn prepare_findex_server(
+pub(crate) fn prepare_findex_server(
     findex_server: Arc<FindexServer>,
     builder: Option<SslAcceptorBuilder>,
+    jwt_configurations: Option<Arc<Vec<JwtConfig>>>,
 ) -> FResult<actix_web::dev::Server> {
-    // Prepare the JWT configurations and the JWKS manager if the server is using
-    // JWT for authentication.
-    let (jwt_configurations, _jwks_manager) = if let Some(identity_provider_configurations) =
-        &findex_server.params.identity_provider_configurations
-    {
-        // Prepare all the needed URIs from all the configured Identity Providers
-        let all_jwks_uris: Vec<_> = identity_provider_configurations
-            .iter()
-            .map(|idp_config| {
-                JwtAuthConfig::uri(&idp_config.jwt_issuer_uri, idp_config.jwks_uri.as_deref())
-            })
-            .collect();
-
-        let jwks_manager = Arc::new(JwksManager::new(all_jwks_uris).await?);
-
-        let built_jwt_configurations = identity_provider_configurations
-            .iter()
-            .map(|idp_config| JwtConfig {
-                jwt_issuer_uri: idp_config.jwt_issuer_uri.clone(),
-                jwks: jwks_manager.clone(),
-                jwt_audience: idp_config.jwt_audience.clone(),
-            })
-            .collect::<Vec<_>>();
-
-        (Some(Arc::new(built_jwt_configurations)), Some(jwks_manager))
-    } else {
-        (None, None)
-    };
-
     // Determine if Client Cert Auth should be used for authentication.
     let use_cert_auth = findex_server.params.authority_cert_file.is_some();
 
@@ -239,31 +276,28 @@ pub(crate) async fn prepare_findex_server(
             .app_data(PayloadConfig::new(10_000_000_000)) // Set the maximum size of the request payload.
             .app_data(JsonConfig::default().limit(10_000_000_000)); // Set the maximum size of the JSON request payload.
 
-        // The default scope serves from the root
-        let default_scope = web::scope("")
-            .wrap(AuthTransformer::new(jwt_configurations.clone())) // Use JWT for authentication if necessary.
-            .wrap(Condition::new(use_cert_auth, SslAuth)) // Use certificates for authentication if necessary.
-            // Enable CORS for the application.
-            // Since Actix is running the middlewares in reverse order, it's important that the
-            // CORS middleware is the last one so that the auth middlewares do not run on
-            // preflight (OPTION) requests.
-            .wrap(Cors::permissive())
-            // Findex endpoints
-            .service(findex_batch_read)
-            .service(findex_guarded_write)
-            // Permissions management endpoints
-            .service(create_index_id)
-            .service(list_permission)
-            .service(set_permission)
-            .service(revoke_permission)
-            // Dataset management
-            .service(datasets_add_entries)
-            .service(datasets_del_entries)
-            .service(datasets_get_entries)
-            // Version endpoint
-            .service(get_version);
+        // The routes are served under the `/v1` prefix, and from the root for the clients
+        // predating it.
+        let scope = |path| {
+            web::scope(path)
+                // Compress the responses of the clients accepting it; the compressed request
+                // bodies are decompressed by the extractors.
+                .wrap(Compress::default())
+                .wrap(AuthTransformer::new(jwt_configurations.clone())) // Use JWT for authentication if necessary.
+                .wrap(Condition::new(use_cert_auth, SslAuth)) // Use certificates for authentication if necessary.
+                // Enable CORS for the application.
+                // Since Actix is running the middlewares in reverse order, it's important that the
+                // CORS middleware is the last one so that the auth middlewares do not run on
+                // preflight (OPTION) requests.
+                .wrap(Cors::permissive())
+                .configure(configure_routes)
+        };
 
-        app.service(default_scope)
+        app
+            // The OpenAPI document is served without authentication
+            .service(get_openapi)
+            .service(scope("/v1"))
+            .service(scope(""))
     })
     .client_disconnect_timeout(std::time::Duration::from_secs(30)) // default: 5s
     .tls_handshake_timeout(std::time::Duration::from_secs(18)) // default: 3s
@@ -287,3 +321,36 @@ pub(crate) async fn prepare_findex_server(
         _ => server.bind(address)?.run(),
     })
 }
+
+/// Register the routes of the API.
+fn configure_routes(cfg: &mut ServiceConfig) {
+    cfg
+        // Findex endpoints
+        .service(findex_batch_read)
+        .service(findex_batch_read_indexes)
+        .service(findex_batch_read_stream)
+        .service(findex_guarded_write)
+        // Permissions management endpoints
+        .service(create_index_id)
+        .service(list_permission)
+        .service(set_permission)
+        .service(revoke_permission)
+        // Dataset management
+        .service(datasets_add_entries)
+        .service(datasets_add_entries_stream)
+        .service(datasets_del_entries)
+        .service(datasets_get_entries)
+        .service(datasets_get_entries_stream)
+        .service(datasets_list_entries)
+        .service(datasets_update_entries)
+        .service(datasets_stage_entries)
+        .service(datasets_commit_entries)
+        .service(datasets_abort_entries)
+        // Index backup, restore and copy
+        .service(export_index)
+        .service(import_index)
+        .service(clone_index)
+        .service(index_is_empty)
+        // Version endpoint
+        .service(get_version);
+}
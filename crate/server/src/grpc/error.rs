@@ -0,0 +1,39 @@
+use tonic::Status;
+use tracing::{error, warn};
+
+use crate::error::server::ServerError;
+
+pub(crate) type GrpcResult<T> = Result<tonic::Response<T>, Status>;
+
+impl From<ServerError> for Status {
+    fn from(e: ServerError) -> Self {
+        let message = e.to_string();
+        let status = match e {
+            ServerError::Unauthorized(_) => Self::permission_denied(message),
+
+            ServerError::Conflict(_) => Self::aborted(message),
+
+            // Over gRPC, these errors come from the decoding of the request messages
+            ServerError::InvalidRequest(_)
+            | ServerError::ClientConnectionError(_)
+            | ServerError::UrlParseError(_)
+            | ServerError::StructsError(_)
+            | ServerError::UuidError(_) => Self::invalid_argument(message),
+
+            ServerError::DatabaseError(_)
+            | ServerError::ConversionError(_)
+            | ServerError::CryptographicError(_)
+            | ServerError::Findex(_)
+            | ServerError::SendError(_)
+            | ServerError::Certificate(_)
+            | ServerError::OpenSslError(_)
+            | ServerError::ServerError(_) => Self::internal(message),
+        };
+        if status.code() == tonic::Code::Internal {
+            error!("gRPC {:?} - {}", status.code(), status.message());
+        } else {
+            warn!("gRPC {:?} - {}", status.code(), status.message());
+        }
+        status
+    }
+}
@@ -0,0 +1,91 @@
+//! The gRPC API, offering the Findex, dataset and permission operations of the
+//! REST API on a separate port, with the same TLS and authentication settings.
+
+mod error;
+mod service;
+
+use std::sync::Arc;
+
+use cosmian_findex_structs::grpc::findex_server::FindexServer as FindexGrpcServer;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tracing::info;
+
+use crate::{
+    config::HttpParams,
+    core::FindexServer,
+    error::{result::FResult, server::ServerError},
+    grpc::service::FindexService,
+    middlewares::JwtConfig,
+    server_bail,
+};
+
+/// The maximum size of the messages, the default 4 MiB being too small for
+/// large batches
+const MAX_MESSAGE_SIZE: usize = 1 << 30;
+
+/// Serve the gRPC API on the given port, until the returned future is dropped.
+///
+/// # Errors
+///
+/// This function returns an error if:
+/// - The TLS configuration cannot be built from the PKCS#12 file
+/// - The server cannot bind to the port or fails to run
+pub(crate) async fn start_grpc_server(
+    findex_server: Arc<FindexServer>,
+    jwt_configurations: Option<Arc<Vec<JwtConfig>>>,
+    grpc_port: u16,
+) -> FResult<()> {
+    let address = tokio::net::lookup_host((findex_server.params.hostname.as_str(), grpc_port))
+        .await?
+        .next()
+        .ok_or_else(|| {
+            ServerError::ServerError(format!(
+                "gRPC: cannot resolve {}",
+                findex_server.params.hostname
+            ))
+        })?;
+
+    let mut builder = Server::builder();
+    if let Some(tls_config) = tls_config(&findex_server)? {
+        builder = builder
+            .tls_config(tls_config)
+            .map_err(|e| ServerError::ServerError(format!("gRPC TLS configuration: {e}")))?;
+    }
+
+    let service = FindexGrpcServer::new(FindexService::new(findex_server, jwt_configurations))
+        .max_decoding_message_size(MAX_MESSAGE_SIZE)
+        .max_encoding_message_size(MAX_MESSAGE_SIZE);
+
+    info!("Starting the gRPC Findex server on {address}...");
+    builder
+        .add_service(service)
+        .serve(address)
+        .await
+        .map_err(|e| ServerError::ServerError(format!("gRPC server: {e}")))
+}
+
+/// Build the TLS configuration of the gRPC server from the PKCS#12 file of the
+/// HTTPS server, verifying the client certificates against the same authority.
+fn tls_config(findex_server: &FindexServer) -> FResult<Option<ServerTlsConfig>> {
+    let HttpParams::Https(p12) = &findex_server.params.http_params else {
+        return Ok(None);
+    };
+    let (Some(pkey), Some(cert)) = (&p12.pkey, &p12.cert) else {
+        server_bail!("gRPC: the PKCS#12 file must hold a key and a certificate")
+    };
+
+    let mut cert_chain = cert.to_pem()?;
+    if let Some(chain) = &p12.ca {
+        for x in chain {
+            cert_chain.extend(x.to_pem()?);
+        }
+    }
+    let mut tls_config = ServerTlsConfig::new().identity(Identity::from_pem(
+        cert_chain,
+        pkey.private_key_to_pem_pkcs8()?,
+    ));
+    if let Some(verify_cert) = &findex_server.params.authority_cert_file {
+        tls_config = tls_config.client_ca_root(Certificate::from_pem(verify_cert.to_pem()?));
+    }
+    Ok(Some(tls_config))
+}
@@ -0,0 +1,338 @@
+use std::{pin::Pin, sync::Arc};
+
+use cosmian_findex_structs::{
+    EncryptedEntries, Uuids,
+    grpc::{
+        AddEntriesRequest, BatchReadRequest, BatchReadResponse, CreateIndexRequest,
+        DeleteEntriesRequest, GetEntriesRequest, GetEntriesResponse, GuardedWriteRequest,
+        GuardedWriteResponse, ListPermissionsRequest, ListPermissionsResponse, OperationResponse,
+        RevokePermissionRequest, SetPermissionRequest, entries_from_grpc, entries_to_grpc,
+        findex_server::Findex, uuids_from_grpc,
+    },
+};
+use futures::{Stream, TryStreamExt};
+use openssl::x509::X509;
+use tonic::{Request, Response, Status, Streaming};
+use tracing::{info, trace};
+use uuid::Uuid;
+
+use crate::{
+    core::FindexServer,
+    database::database_traits::PermissionsTrait,
+    error::server::ServerError,
+    grpc::error::GrpcResult,
+    middlewares::{JwtConfig, authenticate_jwt, extract_common_name},
+};
+
+/// The credentials presented with a gRPC call
+struct Credentials {
+    /// The common name of the client certificate, verified by the TLS layer
+    common_name: Option<String>,
+    /// The `authorization` metadata, holding a JWT
+    authorization: Option<String>,
+}
+
+impl Credentials {
+    fn new<T>(request: &Request<T>) -> Result<Self, ServerError> {
+        let common_name = request
+            .peer_certs()
+            .and_then(|certs| certs.first().cloned())
+            .map(|cert| extract_common_name(&X509::from_der(&cert)?))
+            .transpose()?;
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned);
+        Ok(Self {
+            common_name,
+            authorization,
+        })
+    }
+}
+
+/// The gRPC service, sharing the Findex server with the REST API.
+pub(crate) struct FindexService {
+    findex_server: Arc<FindexServer>,
+    jwt_configurations: Option<Arc<Vec<JwtConfig>>>,
+}
+
+impl FindexService {
+    pub(crate) const fn new(
+        findex_server: Arc<FindexServer>,
+        jwt_configurations: Option<Arc<Vec<JwtConfig>>>,
+    ) -> Self {
+        Self {
+            findex_server,
+            jwt_configurations,
+        }
+    }
+
+    /// Authenticate the caller as the REST API does: a client certificate is
+    /// required if the server verifies them, else a JWT is required if
+    /// identity providers are configured, else the caller is the default user.
+    async fn authenticate(&self, credentials: Credentials) -> Result<String, Status> {
+        if self.findex_server.params.authority_cert_file.is_some() {
+            let common_name = credentials
+                .common_name
+                .ok_or_else(|| Status::unauthenticated("A client certificate is required"))?;
+            return Ok(self.findex_server.resolve_user(None, Some(&common_name)));
+        }
+        if let Some(configurations) = &self.jwt_configurations {
+            let email = authenticate_jwt(
+                configurations,
+                credentials.authorization.as_deref().unwrap_or_default(),
+            )
+            .await
+            .map_err(|e| Status::unauthenticated(e.to_string()))?;
+            return Ok(self.findex_server.resolve_user(Some(&email), None));
+        }
+        Ok(self.findex_server.resolve_user(None, None))
+    }
+
+    async fn guarded_write_one(
+        findex_server: &FindexServer,
+        user: &str,
+        request: &GuardedWriteRequest,
+    ) -> Result<GuardedWriteResponse, ServerError> {
+        let result_word = findex_server
+            .guarded_write(
+                user,
+                &request.index_id,
+                request.guard()?,
+                request.bindings()?,
+            )
+            .await?;
+        Ok(GuardedWriteResponse::new(result_word))
+    }
+}
+
+fn operation_response(success: String, index_id: Uuid) -> Response<OperationResponse> {
+    Response::new(OperationResponse {
+        success,
+        index_id: index_id.to_string(),
+    })
+}
+
+#[tonic::async_trait]
+impl Findex for FindexService {
+    type GuardedWriteStreamStream =
+        Pin<Box<dyn Stream<Item = Result<GuardedWriteResponse, Status>> + Send>>;
+
+    async fn batch_read(
+        &self,
+        request: Request<BatchReadRequest>,
+    ) -> GrpcResult<BatchReadResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        trace!("user {user}: gRPC BatchRead on index {}", request.index_id);
+
+        let addresses = request.addresses().map_err(ServerError::from)?;
+        let words = self
+            .findex_server
+            .batch_read(&user, &request.index_id, addresses)
+            .await?;
+        Ok(Response::new(BatchReadResponse::new(&words)))
+    }
+
+    async fn guarded_write(
+        &self,
+        request: Request<GuardedWriteRequest>,
+    ) -> GrpcResult<GuardedWriteResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        trace!(
+            "user {user}: gRPC GuardedWrite on index {}",
+            request.index_id
+        );
+
+        Ok(Response::new(
+            Self::guarded_write_one(&self.findex_server, &user, &request).await?,
+        ))
+    }
+
+    /// Each request is answered in order, as soon as it is written.
+    async fn guarded_write_stream(
+        &self,
+        request: Request<Streaming<GuardedWriteRequest>>,
+    ) -> GrpcResult<Self::GuardedWriteStreamStream> {
+        let credentials = Credentials::new(&request)?;
+        let user = Arc::new(self.authenticate(credentials).await?);
+
+        trace!("user {user}: gRPC GuardedWriteStream");
+
+        let findex_server = self.findex_server.clone();
+        let responses = request.into_inner().and_then(move |request| {
+            let findex_server = findex_server.clone();
+            let user = user.clone();
+            async move { Ok(Self::guarded_write_one(&findex_server, &user, &request).await?) }
+        });
+        Ok(Response::new(Box::pin(responses)))
+    }
+
+    async fn add_entries(
+        &self,
+        request: Request<AddEntriesRequest>,
+    ) -> GrpcResult<OperationResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        info!("user {user}: gRPC AddEntries on index {}", request.index_id);
+
+        let encrypted_entries: EncryptedEntries =
+            entries_from_grpc(request.entries).map_err(ServerError::from)?;
+        let index_id = self
+            .findex_server
+            .add_entries(&user, &request.index_id, &encrypted_entries)
+            .await?;
+        Ok(operation_response(
+            format!(
+                "{} entries successfully added to index {index_id}",
+                encrypted_entries.len()
+            ),
+            index_id,
+        ))
+    }
+
+    async fn delete_entries(
+        &self,
+        request: Request<DeleteEntriesRequest>,
+    ) -> GrpcResult<OperationResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        info!(
+            "user {user}: gRPC DeleteEntries on index {}",
+            request.index_id
+        );
+
+        let uuids = Uuids::from(uuids_from_grpc(&request.uuids).map_err(ServerError::from)?);
+        let index_id = self
+            .findex_server
+            .delete_entries(&user, &request.index_id, &uuids)
+            .await?;
+        Ok(operation_response(
+            format!(
+                "Encrypted entries successfully deleted from index {index_id}. UUIDs were {uuids}",
+            ),
+            index_id,
+        ))
+    }
+
+    async fn get_entries(
+        &self,
+        request: Request<GetEntriesRequest>,
+    ) -> GrpcResult<GetEntriesResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        info!("user {user}: gRPC GetEntries on index {}", request.index_id);
+
+        let uuids = Uuids::from(uuids_from_grpc(&request.uuids).map_err(ServerError::from)?);
+        let versioned_entries = self
+            .findex_server
+            .get_entries(&user, &request.index_id, &uuids)
+            .await?;
+        Ok(Response::new(GetEntriesResponse {
+            entries: entries_to_grpc(EncryptedEntries::from(versioned_entries)),
+        }))
+    }
+
+    async fn create_index(
+        &self,
+        request: Request<CreateIndexRequest>,
+    ) -> GrpcResult<OperationResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+
+        trace!("user {user}: gRPC CreateIndex");
+
+        let index_id = self
+            .findex_server
+            .db
+            .create_index_id(&user)
+            .await
+            .map_err(ServerError::from)?;
+        Ok(operation_response(
+            format!("[{user}] New admin permission successfully created on index: {index_id}"),
+            index_id,
+        ))
+    }
+
+    async fn set_permission(
+        &self,
+        request: Request<SetPermissionRequest>,
+    ) -> GrpcResult<OperationResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+        let permission = request.parsed_permission().map_err(ServerError::from)?;
+
+        trace!(
+            "user {user}: gRPC SetPermission {permission} on index {} to {}",
+            request.index_id, request.user_id
+        );
+
+        let index_id = self
+            .findex_server
+            .set_permission(&user, &request.user_id, permission, &request.index_id)
+            .await?;
+        Ok(operation_response(
+            format!(
+                "[{}] permission {permission} on index {index_id} successfully added",
+                request.user_id
+            ),
+            index_id,
+        ))
+    }
+
+    async fn list_permissions(
+        &self,
+        request: Request<ListPermissionsRequest>,
+    ) -> GrpcResult<ListPermissionsResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        trace!("user {user}: gRPC ListPermissions of {}", request.user_id);
+
+        let permissions = self
+            .findex_server
+            .list_permissions(&user, &request.user_id)
+            .await?;
+        Ok(Response::new(ListPermissionsResponse::from(permissions)))
+    }
+
+    async fn revoke_permission(
+        &self,
+        request: Request<RevokePermissionRequest>,
+    ) -> GrpcResult<OperationResponse> {
+        let credentials = Credentials::new(&request)?;
+        let user = self.authenticate(credentials).await?;
+        let request = request.into_inner();
+
+        trace!(
+            "user {user}: gRPC RevokePermission on index {} of {}",
+            request.index_id, request.user_id
+        );
+
+        let index_id = self
+            .findex_server
+            .revoke_permission(&user, &request.user_id, &request.index_id)
+            .await?;
+        Ok(operation_response(
+            format!(
+                "Permission for {} on index {index_id} successfully revoked",
+                request.user_id
+            ),
+            index_id,
+        ))
+    }
+}
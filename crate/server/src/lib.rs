@@ -57,7 +57,9 @@ pub mod core;
 pub mod database;
 pub mod error;
 pub mod findex_server;
+pub mod grpc;
 pub mod middlewares;
+pub mod migration;
 pub mod routes;
 
 #[allow(clippy::panic, clippy::unwrap_used, clippy::expect_used)]
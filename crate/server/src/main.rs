@@ -2,9 +2,10 @@ use std::path::PathBuf;
 
 use clap::Parser;
 use cosmian_findex_server::{
-    config::{ClapConfig, ServerParams},
+    config::{ClapConfig, ServerCommand, ServerParams},
     error::{result::FResult, server::ServerError},
     findex_server::start_findex_server,
+    migration::migrate,
     server_bail,
 };
 use cosmian_logger::log_init;
@@ -39,6 +40,13 @@ async fn main() -> FResult<()> {
     // Load variable from a .env file
     dotenv().ok();
 
+    // Do that first to catch --help or --version even if we use a conf file
+    let cli_config = ClapConfig::parse();
+    if let Some(ServerCommand::Migrate(migrate_config)) = &cli_config.command {
+        migrate(migrate_config).await?;
+        return Ok(());
+    }
+
     let conf = if let Ok(conf_path) = std::env::var("COSMIAN_FINDEX_SERVER_CONF") {
         let conf_path = PathBuf::from(conf_path);
         if !conf_path.exists() {
@@ -53,8 +61,6 @@ async fn main() -> FResult<()> {
     };
 
     let clap_config = if conf.exists() {
-        ClapConfig::parse(); // Do that do catch --help or --version even if we use a conf file
-
         info!(
             "Configuration file {conf:?} found. Command line arguments and env variables are \
              ignored."
@@ -71,7 +77,7 @@ async fn main() -> FResult<()> {
             ))
         })?
     } else {
-        ClapConfig::parse()
+        cli_config
     };
 
     // Instantiate a config object using the env variables and the args of the
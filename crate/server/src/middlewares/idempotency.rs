@@ -0,0 +1,330 @@
+use std::{
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use actix_service::{Service, Transform};
+use actix_web::{
+    Error, HttpResponse, ResponseError,
+    body::{BoxBody, MessageBody, to_bytes},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::ErrorInternalServerError,
+    http::{
+        StatusCode,
+        header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, HeaderName, HeaderValue},
+    },
+    web::{Bytes, Data},
+};
+use futures::{
+    Future,
+    future::{Ready, ok},
+};
+use openssl::sha::sha256;
+use tracing::{debug, trace};
+
+use crate::{
+    core::{FindexServer, Lookup, StoredResponse},
+    error::server::ServerError,
+};
+
+/// The header holding the idempotency key chosen by the client
+pub(crate) const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// The header set on the responses replayed to a retried request
+pub(crate) const IDEMPOTENCY_REPLAYED: HeaderName = HeaderName::from_static("idempotency-replayed");
+
+/// The maximum length of an idempotency key
+const MAX_KEY_LENGTH: usize = 255;
+
+/// The middleware answering the retries of a request sent with an
+/// `Idempotency-Key` header with the response to the original request, see
+/// [`crate::core::IdempotencyCache`]. The requests without the header are run
+/// as usual.
+pub(crate) struct Idempotency;
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Error = Error;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+    type InitError = ();
+    type Response = ServiceResponse<BoxBody>;
+    type Transform = IdempotencyMiddleware<S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub(crate) struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Response = ServiceResponse<BoxBody>;
+
+    fn poll_ready(&self, ctx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY)
+            .map(|key| key.to_str().map(ToOwned::to_owned));
+        let findex_server = req.app_data::<Data<Arc<FindexServer>>>().cloned();
+        let (Some(key), Some(findex_server)) = (key, findex_server) else {
+            let fut = self.service.call(req);
+            return Box::pin(async move { Ok(fut.await?.map_into_boxed_body()) });
+        };
+
+        let key = match key {
+            Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key,
+            _ => {
+                let response = rejected(
+                    req,
+                    &ServerError::InvalidRequest(format!(
+                        "The idempotency key must be made of 1 to {MAX_KEY_LENGTH} visible ASCII \
+                         characters"
+                    )),
+                );
+                return Box::pin(async move { Ok(response) });
+            }
+        };
+        let service = self.service.clone();
+        Box::pin(async move {
+            // The body is read to bind the key to it, then handed back to the
+            // route. It is read decompressed, hence handed back as such.
+            let body = match req.extract::<Bytes>().await {
+                Ok(body) => body,
+                Err(e) => return Ok(req.error_response(e)),
+            };
+            let body_hash = sha256(&body);
+            req.headers_mut().remove(CONTENT_ENCODING);
+            req.headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+            req.set_payload(Payload::from(body));
+
+            let user = findex_server.get_user(req.request());
+            // The routes served with and without the `/v1` prefix are the same
+            let path = req.path();
+            let path = path.strip_prefix("/v1").unwrap_or(path);
+            let request = format!("{} {path}", req.method());
+
+            match findex_server
+                .idempotency
+                .begin(&user, &key, &request, body_hash)
+            {
+                Lookup::Replay(response) => {
+                    debug!("user {user}: replaying the response to {request} for key {key}");
+                    let mut replayed = HttpResponse::build(
+                        StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK),
+                    );
+                    if let Some(content_type) = response.content_type {
+                        replayed.insert_header((CONTENT_TYPE, content_type));
+                    }
+                    replayed.insert_header((IDEMPOTENCY_REPLAYED, "true"));
+                    Ok(req.into_response(replayed.body(response.body)))
+                }
+                Lookup::InProgress => Ok(rejected(
+                    req,
+                    &ServerError::Conflict(format!(
+                        "A request with the idempotency key {key} is in progress"
+                    )),
+                )),
+                Lookup::Mismatch(original) => Ok(rejected(
+                    req,
+                    &ServerError::InvalidRequest(format!(
+                        "The idempotency key {key} was used for another request: {original}"
+                    )),
+                )),
+                Lookup::Run(reservation) => {
+                    let (req, response) = service.call(req).await?.into_parts();
+                    // Only the successful responses are stored: a failed
+                    // request wrote nothing and may be retried.
+                    if !response.status().is_success() {
+                        return Ok(ServiceResponse::new(req, response.map_into_boxed_body()));
+                    }
+                    let (response, body) = response.into_parts();
+                    let body = to_bytes(body).await.map_err(|e| {
+                        let e: Box<dyn std::error::Error> = e.into();
+                        ErrorInternalServerError(e.to_string())
+                    })?;
+                    trace!("storing the response to {request} for key {key}");
+                    reservation.complete(StoredResponse {
+                        status: response.status().as_u16(),
+                        content_type: response.headers().get(CONTENT_TYPE).cloned(),
+                        body: body.clone(),
+                    });
+                    Ok(ServiceResponse::new(
+                        req,
+                        response.set_body(BoxBody::new(body)),
+                    ))
+                }
+            }
+        })
+    }
+}
+
+fn rejected(req: ServiceRequest, error: &ServerError) -> ServiceResponse<BoxBody> {
+    let response = error.error_response();
+    req.into_response(response)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use actix_web::{
+        App,
+        http::{
+            StatusCode,
+            header::{CONTENT_ENCODING, CONTENT_TYPE},
+        },
+        test::{self, TestRequest},
+        web::Data,
+    };
+    use base64::{Engine, engine::general_purpose};
+    use cosmian_findex_structs::CUSTOM_WORD_LENGTH;
+    use cosmian_sse_memories::ADDRESS_LENGTH;
+    use flate2::{Compression, write::GzEncoder};
+    use tempfile::TempDir;
+
+    use super::{IDEMPOTENCY_KEY, IDEMPOTENCY_REPLAYED};
+    use crate::{
+        config::{ClapConfig, DBConfig, DatabaseType, ServerParams},
+        core::FindexServer,
+        database::database_traits::PermissionsTrait,
+        routes::{create_index_id, findex_guarded_write},
+    };
+
+    async fn instantiate_server(dir: &TempDir) -> FindexServer {
+        let conf = ClapConfig {
+            db: DBConfig {
+                database_type: DatabaseType::Sqlite,
+                database_url: dir.path().join("findex.db").to_str().unwrap().to_owned(),
+                ..DBConfig::default()
+            },
+            ..ClapConfig::default()
+        };
+        FindexServer::instantiate(ServerParams::try_from(conf).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_idempotent_create_index() {
+        let dir = TempDir::new().unwrap();
+        let findex_server = instantiate_server(&dir).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(Arc::new(findex_server)))
+                .service(create_index_id),
+        )
+        .await;
+
+        let create = |key: Option<&'static str>| {
+            let request = TestRequest::post().uri("/create/index");
+            match key {
+                Some(key) => request.insert_header((IDEMPOTENCY_KEY, key)),
+                None => request,
+            }
+            .to_request()
+        };
+
+        // A retried request gets the original response
+        let first = test::call_service(&app, create(Some("key"))).await;
+        assert!(first.status().is_success());
+        assert!(!first.headers().contains_key(IDEMPOTENCY_REPLAYED));
+        let first = test::read_body(first).await;
+        let retried = test::call_service(&app, create(Some("key"))).await;
+        assert!(retried.headers().contains_key(IDEMPOTENCY_REPLAYED));
+        assert_eq!(test::read_body(retried).await, first);
+
+        // The key is bound to the body of the request
+        let other_body = TestRequest::post()
+            .uri("/create/index")
+            .insert_header((IDEMPOTENCY_KEY, "key"))
+            .set_payload("body")
+            .to_request();
+        let other_body = test::call_service(&app, other_body).await;
+        assert_eq!(other_body.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Another key, or no key, runs the request again
+        let other = test::read_body(test::call_service(&app, create(Some("other"))).await).await;
+        assert_ne!(other, first);
+        let no_key = test::read_body(test::call_service(&app, create(None)).await).await;
+        assert_ne!(no_key, first);
+
+        // An empty key is rejected
+        let empty = test::call_service(&app, create(Some(""))).await;
+        assert!(empty.status().is_client_error());
+    }
+
+    #[actix_web::test]
+    async fn test_idempotent_compressed_guarded_write() {
+        let dir = TempDir::new().unwrap();
+        let findex_server = instantiate_server(&dir).await;
+        let index_id = findex_server
+            .db
+            .create_index_id(&findex_server.params.default_username)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(Arc::new(findex_server)))
+                .service(findex_guarded_write),
+        )
+        .await;
+
+        let address = general_purpose::STANDARD.encode([1; ADDRESS_LENGTH]);
+        let word = general_purpose::STANDARD.encode([2; CUSTOM_WORD_LENGTH]);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(
+                format!(
+                    r#"{{"guard":{{"address":"{address}","word":null}},
+                    "bindings":[{{"address":"{address}","word":"{word}"}}]}}"#
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let body = encoder.finish().unwrap();
+        let write = || {
+            TestRequest::post()
+                .uri(&format!("/indexes/{index_id}/guarded_write"))
+                .insert_header((CONTENT_TYPE, "application/json"))
+                .insert_header((CONTENT_ENCODING, "gzip"))
+                .insert_header((IDEMPOTENCY_KEY, "key"))
+                .set_payload(body.clone())
+                .to_request()
+        };
+
+        // The compressed body read by the middleware is handed to the route
+        // decompressed.
+        let first = test::call_service(&app, write()).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let first = test::read_body(first).await;
+        assert_eq!(first, r#"{"words":[null]}"#);
+
+        // Had the write been run again, the guard would have failed.
+        let retried = test::call_service(&app, write()).await;
+        assert!(retried.headers().contains_key(IDEMPOTENCY_REPLAYED));
+        assert_eq!(test::read_body(retried).await, first);
+    }
+}
@@ -55,7 +55,6 @@ fn extract_user_claim(
     Err(jwt_log_errors)
 }
 
-#[allow(clippy::cognitive_complexity)]
 pub(crate) async fn manage_jwt(
     configs: Arc<Vec<JwtConfig>>,
     req: &ServiceRequest,
@@ -76,7 +75,17 @@ pub(crate) async fn manage_jwt(
 
     trace!("Checking JWT identity: {identity}");
 
-    let mut private_claim = extract_user_claim(&configs, &identity);
+    authenticate_jwt(&configs, &identity)
+        .await
+        .map(JwtAuthClaim::new)
+        .inspect_err(|e| error!("{:?} {} 401 unauthorized: {e}", req.method(), req.path()))
+}
+
+/// Authenticate the bearer of a JWT, `Bearer` prefix included, against the
+/// configured identity providers, and return the email it claims.
+#[allow(clippy::cognitive_complexity)]
+pub(crate) async fn authenticate_jwt(configs: &[JwtConfig], identity: &str) -> FResult<String> {
+    let mut private_claim = extract_user_claim(configs, identity);
     // If no configuration could get the claim, try refreshing them and extract user
     // claim again
     if private_claim.is_err() {
@@ -86,31 +95,19 @@ pub(crate) async fn manage_jwt(
             .jwks
             .refresh()
             .await?;
-        private_claim = extract_user_claim(&configs, &identity);
+        private_claim = extract_user_claim(configs, identity);
     }
 
     match private_claim.map(|user_claim| user_claim.email) {
         Ok(Some(email)) => {
             debug!("JWT Access granted to {email}!");
-            Ok(JwtAuthClaim::new(email))
-        }
-        Ok(None) => {
-            error!(
-                "{:?} {} 401 unauthorized, no email in JWT",
-                req.method(),
-                req.path()
-            );
-            Err(ServerError::InvalidRequest("No email in JWT".to_owned()))
+            Ok(email)
         }
+        Ok(None) => Err(ServerError::InvalidRequest("No email in JWT".to_owned())),
         Err(jwt_log_errors) => {
             for error in &jwt_log_errors {
                 tracing::error!("{error:?}");
             }
-            error!(
-                "{:?} {} 401 unauthorized: bad JWT",
-                req.method(),
-                req.path(),
-            );
             Err(ServerError::InvalidRequest("bad JWT".to_owned()))
         }
     }
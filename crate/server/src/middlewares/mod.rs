@@ -2,13 +2,16 @@ mod main;
 pub(crate) use main::AuthTransformer;
 
 mod jwt_token_auth;
-pub(crate) use jwt_token_auth::{JwtAuthClaim, manage_jwt_request};
+pub(crate) use jwt_token_auth::{JwtAuthClaim, authenticate_jwt, manage_jwt_request};
 
 mod ssl_auth;
-pub(crate) use ssl_auth::{PeerCommonName, SslAuth, extract_peer_certificate};
+pub(crate) use ssl_auth::{PeerCommonName, SslAuth, extract_common_name, extract_peer_certificate};
 
 mod jwt;
 pub(crate) use jwt::{JwtConfig, UserClaim};
 
 mod jwks;
 pub(crate) use jwks::JwksManager;
+
+mod idempotency;
+pub(crate) use idempotency::Idempotency;
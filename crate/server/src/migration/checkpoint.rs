@@ -0,0 +1,60 @@
+use std::{fmt, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{MigrationCounts, Stage};
+use crate::error::result::{FResult, FResultHelper};
+
+/// The progress of a migration, saved after each batch so that an interrupted
+/// migration can be resumed.
+///
+/// A batch is saved once written to the target database: when resuming, the
+/// batch that was being copied is copied again, which is harmless since the
+/// writes overwrite the existing values.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub(super) struct Checkpoint {
+    /// The stage in progress, `None` once all the data is copied
+    pub(super) stage: Option<Stage>,
+    /// The cursor of the next batch of the stage in progress
+    pub(super) cursor: Option<String>,
+    /// The number of items copied so far, only reported as the progress: an
+    /// item is counted again when its batch is copied again or when a Redis
+    /// `SCAN` returns it twice
+    pub(super) copied: MigrationCounts,
+}
+
+impl Checkpoint {
+    pub(super) fn new() -> Self {
+        Self {
+            stage: Some(Stage::Memory),
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn load(path: &Path) -> FResult<Self> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Cannot read the checkpoint file {}", path.display()))?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Cannot parse the checkpoint file {}", path.display()))
+    }
+
+    /// Save the checkpoint. The file is replaced atomically so that an
+    /// interruption never leaves a truncated checkpoint.
+    pub(super) fn save(&self, path: &Path) -> FResult<()> {
+        let content = serde_json::to_vec_pretty(self).context("Cannot serialize the checkpoint")?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, content)
+            .and_then(|()| std::fs::rename(&tmp_path, path))
+            .with_context(|| format!("Cannot write the checkpoint file {}", path.display()))
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.stage {
+            Some(stage) => write!(f, "copying the {stage}")?,
+            None => write!(f, "copy done")?,
+        }
+        write!(f, ", {} copied", self.copied)
+    }
+}
@@ -0,0 +1,561 @@
+//! Copy of the whole content of a database to another one.
+//!
+//! The server only stores encrypted data: the memory words, the permissions
+//! and the dataset entries are copied as is, batch by batch, which allows
+//! changing of database backend without re-indexing. The source database
+//! should not be written during the migration.
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use cosmian_findex_structs::{CUSTOM_WORD_LENGTH, Uuids, VersionedEntries};
+use cosmian_sse_memories::MemoryADT;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::MigrateConfig,
+    database::{
+        FindexDatabase,
+        database_traits::{
+            DatasetEntry, DatasetsTrait, MemoryWord, MigrationTrait, PermissionsTrait,
+            UserPermission,
+        },
+        findex_database::DatabaseResult,
+    },
+    error::result::FResult,
+    server_bail,
+};
+
+mod checkpoint;
+
+use checkpoint::Checkpoint;
+
+/// The minimum delay between two progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The number of items of each kind copied by a migration
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationCounts {
+    pub memory_words: usize,
+    pub permissions: usize,
+    pub dataset_entries: usize,
+}
+
+impl MigrationCounts {
+    const fn get_mut(&mut self, stage: Stage) -> &mut usize {
+        match stage {
+            Stage::Memory => &mut self.memory_words,
+            Stage::Permissions => &mut self.permissions,
+            Stage::Datasets => &mut self.dataset_entries,
+        }
+    }
+}
+
+impl fmt::Display for MigrationCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} memory words, {} permissions and {} dataset entries",
+            self.memory_words, self.permissions, self.dataset_entries
+        )
+    }
+}
+
+/// The kinds of data, copied one after the other
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Memory,
+    Permissions,
+    Datasets,
+}
+
+impl Stage {
+    const ALL: [Self; 3] = [Self::Memory, Self::Permissions, Self::Datasets];
+
+    const fn next(self) -> Option<Self> {
+        match self {
+            Self::Memory => Some(Self::Permissions),
+            Self::Permissions => Some(Self::Datasets),
+            Self::Datasets => None,
+        }
+    }
+
+    /// Read a batch of items of this kind.
+    async fn scan(
+        self,
+        db: &FindexDatabase<CUSTOM_WORD_LENGTH>,
+        cursor: Option<&str>,
+        count: usize,
+    ) -> DatabaseResult<(Batch, Option<String>)> {
+        Ok(match self {
+            Self::Memory => {
+                let page = db.memory_scan(None, cursor, count).await?;
+                (Batch::Memory(page.items), page.next_cursor)
+            }
+            Self::Permissions => {
+                let page = db.permissions_scan(None, cursor, count).await?;
+                (Batch::Permissions(page.items), page.next_cursor)
+            }
+            Self::Datasets => {
+                let page = db.datasets_scan(None, cursor, count).await?;
+                (Batch::Datasets(page.items), page.next_cursor)
+            }
+        })
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory => write!(f, "memory words"),
+            Self::Permissions => write!(f, "permissions"),
+            Self::Datasets => write!(f, "dataset entries"),
+        }
+    }
+}
+
+enum Batch {
+    Memory(Vec<MemoryWord>),
+    Permissions(Vec<UserPermission>),
+    Datasets(Vec<DatasetEntry>),
+}
+
+impl Batch {
+    /// Look the items up in a database, and describe the first one it does
+    /// not hold with the same value, if any.
+    async fn first_missing(
+        &self,
+        db: &FindexDatabase<CUSTOM_WORD_LENGTH>,
+    ) -> DatabaseResult<Option<String>> {
+        match self {
+            Self::Memory(words) => {
+                let addresses = words.iter().map(|(address, _)| *address).collect();
+                let held = db.batch_read(addresses).await?;
+                Ok(words
+                    .iter()
+                    .zip(held)
+                    .find(|((_, word), held)| held.as_ref() != Some(word))
+                    .map(|((address, _), _)| {
+                        format!("the memory word {}", hex::encode(address.as_slice()))
+                    }))
+            }
+            Self::Permissions(permissions) => {
+                let mut held = HashMap::new();
+                for (user_id, index_id, permission) in permissions {
+                    if !held.contains_key(user_id) {
+                        held.insert(user_id, db.get_permissions(user_id).await?);
+                    }
+                    if held
+                        .get(user_id)
+                        .and_then(|permissions| permissions.get_permission(index_id))
+                        != Some(permission)
+                    {
+                        return Ok(Some(format!(
+                            "the permission of {user_id} on the index {index_id}"
+                        )));
+                    }
+                }
+                Ok(None)
+            }
+            Self::Datasets(entries) => {
+                let mut uids_by_index = HashMap::<Uuid, Vec<Uuid>>::new();
+                for (index_id, uid, _) in entries {
+                    uids_by_index.entry(*index_id).or_default().push(*uid);
+                }
+                let mut held = HashMap::new();
+                for (index_id, uids) in uids_by_index {
+                    held.insert(
+                        index_id,
+                        db.dataset_get_entries(&index_id, &Uuids::from(uids))
+                            .await?,
+                    );
+                }
+                Ok(entries
+                    .iter()
+                    .find(|(index_id, uid, entry)| {
+                        held.get(index_id).and_then(|held| held.get(uid)) != Some(entry)
+                    })
+                    .map(|(index_id, uid, _)| {
+                        format!("the dataset entry {uid} of the index {index_id}")
+                    }))
+            }
+        }
+    }
+
+    const fn len(&self) -> usize {
+        match self {
+            Self::Memory(items) => items.len(),
+            Self::Permissions(items) => items.len(),
+            Self::Datasets(items) => items.len(),
+        }
+    }
+
+    async fn write(self, db: &FindexDatabase<CUSTOM_WORD_LENGTH>) -> DatabaseResult<()> {
+        match self {
+            Self::Memory(words) => db.memory_restore(words).await,
+            Self::Permissions(permissions) => {
+                for (user_id, index_id, permission) in permissions {
+                    db.set_permission(&user_id, permission, &index_id).await?;
+                }
+                Ok(())
+            }
+            Self::Datasets(entries) => {
+                let mut entries_by_index = HashMap::<Uuid, VersionedEntries>::new();
+                for (index_id, uid, entry) in entries {
+                    entries_by_index
+                        .entry(index_id)
+                        .or_default()
+                        .insert(uid, entry);
+                }
+                for (index_id, entries) in entries_by_index {
+                    db.dataset_restore_entries(&index_id, &entries).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Copy all the data of the source database to the target database, then
+/// check that both databases hold the same items.
+///
+/// When a checkpoint file is configured, the progress is saved to it after
+/// each batch and an interrupted migration resumes from it.
+///
+/// # Errors
+///
+/// Returns an error if a database cannot be reached, if the target database
+/// is not empty when the migration starts, or if the verification fails.
+pub async fn migrate(config: &MigrateConfig) -> FResult<MigrationCounts> {
+    let source_params = config.source()?;
+    let target_params = config.target()?;
+    info!("Migrating the data of {source_params} to {target_params}");
+    let source =
+        FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate_from_params(&source_params, false)
+            .await?;
+    let target =
+        FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate_from_params(&target_params, false)
+            .await?;
+
+    let mut checkpoint = match &config.checkpoint_file {
+        Some(path) if path.exists() => {
+            let checkpoint = Checkpoint::load(path)?;
+            info!(
+                "Resuming the migration from {}: {checkpoint}",
+                path.display()
+            );
+            checkpoint
+        }
+        _ => {
+            ensure_empty(&target, config.batch_size).await?;
+            Checkpoint::new()
+        }
+    };
+
+    let mut last_report = Instant::now();
+    while let Some(stage) = checkpoint.stage {
+        let (batch, next_cursor) = stage
+            .scan(&source, checkpoint.cursor.as_deref(), config.batch_size)
+            .await?;
+        let n = batch.len();
+        batch.write(&target).await?;
+
+        let copied = checkpoint.copied.get_mut(stage);
+        *copied += n;
+        let copied = *copied;
+        checkpoint.cursor = next_cursor;
+        if checkpoint.cursor.is_none() {
+            info!("All the {stage} are copied: {copied}");
+            checkpoint.stage = stage.next();
+        } else if last_report.elapsed() >= PROGRESS_INTERVAL {
+            info!("Copying the {stage}: {} copied so far", checkpoint.copied);
+            last_report = Instant::now();
+        }
+        if let Some(path) = &config.checkpoint_file {
+            checkpoint.save(path)?;
+        }
+    }
+
+    info!("Verifying the target database");
+    let verified = verify(&source, &target, config.batch_size, "target").await?;
+    verify(&target, &source, config.batch_size, "source").await?;
+    if let Some(path) = &config.checkpoint_file {
+        std::fs::remove_file(path)?;
+    }
+    info!("Migration complete: {verified} copied and verified");
+    Ok(verified)
+}
+
+/// Refuse to start a migration to a database holding data, since the
+/// verification could not tell it apart from the copied data.
+async fn ensure_empty(db: &FindexDatabase<CUSTOM_WORD_LENGTH>, count: usize) -> FResult<()> {
+    for stage in Stage::ALL {
+        let mut cursor = None;
+        loop {
+            let (batch, next_cursor) = stage.scan(db, cursor.as_deref(), count).await?;
+            if batch.len() > 0 {
+                server_bail!(
+                    "The target database already holds {stage}: the data can only be migrated \
+                     to an empty database"
+                );
+            }
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that every item of a database is held, with the same value, by
+/// another one, named in the errors, and count the items checked.
+///
+/// The items are read and looked up batch by batch, so that the memory used
+/// does not grow with the size of the databases. Checking both ways verifies
+/// that the databases hold the same items; an item returned twice by a Redis
+/// `SCAN` is counted twice.
+async fn verify(
+    db: &FindexDatabase<CUSTOM_WORD_LENGTH>,
+    other: &FindexDatabase<CUSTOM_WORD_LENGTH>,
+    batch_size: usize,
+    other_name: &str,
+) -> FResult<MigrationCounts> {
+    let mut counts = MigrationCounts::default();
+    for stage in Stage::ALL {
+        let mut cursor = None;
+        loop {
+            let (batch, next_cursor) = stage.scan(db, cursor.as_deref(), batch_size).await?;
+            *counts.get_mut(stage) += batch.len();
+            if let Some(item) = batch.first_missing(other).await? {
+                server_bail!(
+                    "The migration verification failed: {item} differs or is missing in the \
+                     {other_name} database"
+                );
+            }
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::path::Path;
+
+    use cosmian_findex_structs::{EncryptedEntries, Permission, SERVER_ADDRESS_LENGTH};
+    use cosmian_sse_memories::Address;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{DbParams, RedisMode};
+
+    const N_WORDS: u8 = 5;
+    const N_ENTRIES: usize = 3;
+
+    fn migrate_config(from: &Path, to: &Path, checkpoint_file: &Path) -> MigrateConfig {
+        MigrateConfig {
+            from: from.to_str().unwrap().to_owned(),
+            to: format!("sqlite://{}", to.to_str().unwrap()),
+            from_redis_mode: RedisMode::Standalone,
+            from_redis_sentinel_master: None,
+            from_redis_key_prefix: None,
+            from_redis_legacy_layout: false,
+            from_redis_legacy_words_by_length: false,
+            to_redis_mode: RedisMode::Standalone,
+            to_redis_sentinel_master: None,
+            to_redis_key_prefix: None,
+            checkpoint_file: Some(checkpoint_file.to_path_buf()),
+            // Smaller than the number of items to copy several batches
+            batch_size: 2,
+        }
+    }
+
+    async fn open(path: &Path) -> FindexDatabase<CUSTOM_WORD_LENGTH> {
+        FindexDatabase::instantiate_from_params(&DbParams::Sqlite(path.to_path_buf()), false)
+            .await
+            .unwrap()
+    }
+
+    fn addresses(index_id: &Uuid) -> Vec<Address<SERVER_ADDRESS_LENGTH>> {
+        (0..N_WORDS)
+            .map(|n| {
+                let mut address = [n; SERVER_ADDRESS_LENGTH];
+                address[..16].copy_from_slice(index_id.as_bytes());
+                Address::from(address)
+            })
+            .collect()
+    }
+
+    /// Fill a database and return the index ID and the dataset entries IDs.
+    async fn populate(db: &FindexDatabase<CUSTOM_WORD_LENGTH>) -> (Uuid, Uuids) {
+        let index_id = db.create_index_id("alice").await.unwrap();
+        db.set_permission("bob", Permission::Read, &index_id)
+            .await
+            .unwrap();
+
+        let addresses = addresses(&index_id);
+        let bindings = addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| (*address, [u8::try_from(i).unwrap(); CUSTOM_WORD_LENGTH]))
+            .collect();
+        db.guarded_write((addresses[0], None), bindings)
+            .await
+            .unwrap();
+
+        let entries = (0..N_ENTRIES)
+            .map(|i| (Uuid::new_v4(), vec![u8::try_from(i).unwrap(); 10]))
+            .collect::<HashMap<_, _>>();
+        let uids = Uuids::from(entries.keys().copied().collect::<Vec<_>>());
+        // The entries are written twice, hence at version 2.
+        let entries = EncryptedEntries::from(entries);
+        for _ in 0..2 {
+            db.dataset_add_entries(&index_id, &entries).await.unwrap();
+        }
+        (index_id, uids)
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        let dir = TempDir::new().unwrap();
+        let (source_path, target_path) =
+            (dir.path().join("source.db"), dir.path().join("target.db"));
+        let checkpoint_file = dir.path().join("checkpoint.json");
+
+        let source = open(&source_path).await;
+        let (index_id, uids) = populate(&source).await;
+
+        let config = migrate_config(&source_path, &target_path, &checkpoint_file);
+        let counts = migrate(&config).await.unwrap();
+        assert_eq!(
+            counts,
+            MigrationCounts {
+                memory_words: usize::from(N_WORDS),
+                permissions: 2,
+                dataset_entries: N_ENTRIES,
+            }
+        );
+        assert!(!checkpoint_file.exists());
+
+        let target = open(&target_path).await;
+        assert_eq!(
+            target.batch_read(addresses(&index_id)).await.unwrap(),
+            source.batch_read(addresses(&index_id)).await.unwrap()
+        );
+        assert_eq!(
+            target.get_permission("bob", &index_id).await.unwrap(),
+            Permission::Read
+        );
+        assert_eq!(
+            target.get_permission("alice", &index_id).await.unwrap(),
+            Permission::Admin
+        );
+        // The entries keep their versions.
+        let entries = target.dataset_get_entries(&index_id, &uids).await.unwrap();
+        assert_eq!(entries.len(), N_ENTRIES);
+        assert!(entries.values().all(|entry| entry.version == 2));
+        assert_eq!(
+            entries,
+            source.dataset_get_entries(&index_id, &uids).await.unwrap()
+        );
+
+        // The target database is no longer empty.
+        migrate(&config).await.unwrap_err();
+
+        // A word differing or missing in either database fails the
+        // verification.
+        verify(&source, &target, 2, "target").await.unwrap();
+        let address = addresses(&index_id)[1];
+        target
+            .memory_restore(vec![(address, [42; CUSTOM_WORD_LENGTH])])
+            .await
+            .unwrap();
+        verify(&source, &target, 2, "target").await.unwrap_err();
+        verify(&target, &source, 2, "source").await.unwrap_err();
+        target
+            .dataset_delete_entries(&index_id, &uids)
+            .await
+            .unwrap();
+        target
+            .memory_restore(source.memory_scan(None, None, 100).await.unwrap().items)
+            .await
+            .unwrap();
+        verify(&target, &source, 2, "source").await.unwrap();
+        verify(&source, &target, 2, "target").await.unwrap_err();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_non_utf8_sqlite_path() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        use crate::database::DatabaseError;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(OsStr::from_bytes(b"\xff.db"));
+        assert!(matches!(
+            FindexDatabase::<CUSTOM_WORD_LENGTH>::instantiate_from_params(
+                &DbParams::Sqlite(path),
+                false
+            )
+            .await,
+            Err(DatabaseError::InvalidSqlitePath(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resume_migration() {
+        let dir = TempDir::new().unwrap();
+        let (source_path, target_path) =
+            (dir.path().join("source.db"), dir.path().join("target.db"));
+        let checkpoint_file = dir.path().join("checkpoint.json");
+
+        let source = open(&source_path).await;
+        let (index_id, _) = populate(&source).await;
+
+        // Resume a migration interrupted while the permissions were being
+        // copied, after their batch was written: the memory words are skipped
+        // and the permissions copied again.
+        let target = open(&target_path).await;
+        for stage in [Stage::Memory, Stage::Permissions] {
+            let (batch, _) = stage.scan(&source, None, 100).await.unwrap();
+            batch.write(&target).await.unwrap();
+        }
+        Checkpoint {
+            stage: Some(Stage::Permissions),
+            cursor: None,
+            copied: MigrationCounts {
+                memory_words: usize::from(N_WORDS),
+                permissions: 2,
+                dataset_entries: 0,
+            },
+        }
+        .save(&checkpoint_file)
+        .unwrap();
+
+        // The permissions copied twice are counted once.
+        let config = migrate_config(&source_path, &target_path, &checkpoint_file);
+        let counts = migrate(&config).await.unwrap();
+        assert_eq!(
+            counts,
+            MigrationCounts {
+                memory_words: usize::from(N_WORDS),
+                permissions: 2,
+                dataset_entries: N_ENTRIES,
+            }
+        );
+        assert_eq!(
+            target.batch_read(addresses(&index_id)).await.unwrap(),
+            source.batch_read(addresses(&index_id)).await.unwrap()
+        );
+    }
+}
@@ -1,22 +1,29 @@
-use std::sync::Arc;
+use std::{pin::pin, sync::Arc};
 
 use actix_web::{
-    HttpRequest, HttpResponse, post,
+    HttpRequest, get, post,
     web::{self, Bytes, Data, Json},
 };
 use cosmian_crypto_core::bytes_ser_de::Serializable;
-use cosmian_findex_structs::{EncryptedEntries, Permission, Uuids};
+use cosmian_findex_structs::{EncryptedEntries, Permission, Uuids, VersionedEntries};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
 use tracing::{info, trace};
 use uuid::Uuid;
 
 use crate::{
-    core::FindexServer,
+    core::{FindexServer, WrittenKeys},
     database::database_traits::DatasetsTrait,
-    error::result::FResult,
-    routes::error::{ResponseBytes, SuccessResponse},
+    error::{result::FResult, server::ServerError},
+    middlewares::Idempotency,
+    routes::{
+        error::{ResponseBytes, SuccessResponse},
+        frames::{framed_response, read_frames},
+        json::{body_response, read_body},
+    },
 };
 
-#[post("/datasets/{index_id}/add_entries")]
+#[post("/datasets/{index_id}/add_entries", wrap = "Idempotency")]
 pub(crate) async fn datasets_add_entries(
     req: HttpRequest,
     index_id: web::Path<String>,
@@ -27,95 +34,389 @@ pub(crate) async fn datasets_add_entries(
 
     info!("user {user}: POST /datasets/{index_id}/add_entries");
 
+    let encrypted_entries: EncryptedEntries = read_body(&req, &bytes)?;
+    let index_id = findex_server
+        .add_entries(&user, &index_id, &encrypted_entries)
+        .await?;
+
+    Ok(Json(SuccessResponse {
+        success: format!(
+            "{} entries successfully added to index {index_id}",
+            encrypted_entries.len()
+        ),
+        index_id,
+    }))
+}
+
+/// Add entries in batches, each frame of the request holding a batch. The
+/// batches are added as they are received: if the upload fails, the batches
+/// received so far are added.
+#[post("/datasets/{index_id}/add_entries/stream")]
+pub(crate) async fn datasets_add_entries_stream(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    payload: web::Payload,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /datasets/{index_id}/add_entries/stream");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Write)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    let mut frames = pin!(read_frames(&req, payload));
+    let mut count = 0;
+    while let Some(frame) = frames.try_next().await? {
+        let encrypted_entries = EncryptedEntries::deserialize(&frame)?;
+        trace!(
+            "add_entries/stream: number of encrypted entries: {}",
+            encrypted_entries.len()
+        );
+        findex_server
+            .journals
+            .write(&index_id, async {
+                findex_server
+                    .db
+                    .dataset_add_entries(&index_id, &encrypted_entries)
+                    .await?;
+                Ok(((), WrittenKeys::entries(encrypted_entries.keys().copied())))
+            })
+            .await?;
+        count += encrypted_entries.len();
+    }
+
+    Ok(Json(SuccessResponse {
+        success: format!("{count} entries successfully added to index {index_id}"),
+        index_id,
+    }))
+}
+
+#[post("/datasets/{index_id}/delete_entries", wrap = "Idempotency")]
+pub(crate) async fn datasets_del_entries(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    bytes: Bytes,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /datasets/{index_id}/delete_entries");
+
+    let uuids: Uuids = read_body(&req, &bytes)?;
+    let index_id = findex_server
+        .delete_entries(&user, &index_id, &uuids)
+        .await?;
+
+    Ok(Json(SuccessResponse {
+        success: format!(
+            "Encrypted entries successfully deleted from index {index_id}. UUIDs were {uuids}",
+        ),
+        index_id,
+    }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetParams {
+    /// Whether the versions of the entries are returned
+    #[serde(default)]
+    versions: bool,
+}
+
+#[post("/datasets/{index_id}/get_entries")]
+pub(crate) async fn datasets_get_entries(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    params: web::Query<GetParams>,
+    bytes: Bytes,
+    findex_server: Data<Arc<FindexServer>>,
+) -> ResponseBytes {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /datasets/{index_id}/get_entries",);
+
+    let uuids: Uuids = read_body(&req, &bytes)?;
+    let versioned_entries = findex_server.get_entries(&user, &index_id, &uuids).await?;
+
+    if params.versions {
+        body_response(&req, versioned_entries)
+    } else {
+        body_response(&req, EncryptedEntries::from(versioned_entries))
+    }
+}
+
+/// Get entries in batches: each frame of the request holds a batch of UUIDs,
+/// and the matching frame of the response holds their entries.
+#[post("/datasets/{index_id}/get_entries/stream")]
+pub(crate) async fn datasets_get_entries_stream(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    payload: web::Payload,
+    findex_server: Data<Arc<FindexServer>>,
+) -> ResponseBytes {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /datasets/{index_id}/get_entries/stream");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Read)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    let entries = read_frames(&req, payload).and_then(move |frame| {
+        let findex_server = findex_server.clone();
+        async move {
+            let uuids = Uuids::deserialize(&frame)?;
+            trace!("get_entries/stream: number of UUIDs: {}", uuids.len());
+            let versioned_entries = findex_server
+                .db
+                .dataset_get_entries(&index_id, &uuids)
+                .await?;
+            Ok(EncryptedEntries::from(versioned_entries)
+                .serialize()?
+                .to_vec())
+        }
+    });
+
+    Ok(framed_response(entries))
+}
+
+/// Write the entries only if they all still have the versions they were read
+/// with, the versions of the missing entries being 0. Otherwise nothing is
+/// written and a `409 Conflict` lists the current versions of the conflicting
+/// entries.
+#[post("/datasets/{index_id}/update_entries")]
+pub(crate) async fn datasets_update_entries(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    bytes: Bytes,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /datasets/{index_id}/update_entries");
+
     findex_server
         .ensure_minimum_permission(&user, &index_id, Permission::Write)
         .await?;
 
     let index_id = Uuid::parse_str(&index_id)?;
-    let encrypted_entries = EncryptedEntries::deserialize(&bytes)?;
+    let versioned_entries: VersionedEntries = read_body(&req, &bytes)?;
 
     trace!(
-        "add_entries: number of encrypted entries: {}:",
-        encrypted_entries.len()
+        "update_entries: number of versioned entries: {}",
+        versioned_entries.len()
     );
 
     findex_server
-        .db
-        .dataset_add_entries(&index_id, &encrypted_entries)
+        .journals
+        .write(&index_id, async {
+            findex_server
+                .db
+                .dataset_update_entries(&index_id, &versioned_entries)
+                .await?;
+            Ok(((), WrittenKeys::entries(versioned_entries.keys().copied())))
+        })
         .await?;
 
     Ok(Json(SuccessResponse {
         success: format!(
-            "{} entries successfully added to index {index_id}",
-            encrypted_entries.len()
+            "{} entries successfully updated in index {index_id}",
+            versioned_entries.len()
         ),
         index_id,
     }))
 }
 
-#[post("/datasets/{index_id}/delete_entries")]
-pub(crate) async fn datasets_del_entries(
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct StagedEntries {
+    pub success: String,
+    pub index_id: Uuid,
+    /// The ID of the transaction to commit the entries with
+    pub transaction_id: Uuid,
+}
+
+/// Stage entries under a new transaction. They are added to the dataset once
+/// the transaction is committed, which should be done after indexing them, and
+/// are discarded if it is not committed in time.
+#[post("/datasets/{index_id}/stage_entries")]
+pub(crate) async fn datasets_stage_entries(
     req: HttpRequest,
     index_id: web::Path<String>,
     bytes: Bytes,
     findex_server: Data<Arc<FindexServer>>,
-) -> FResult<Json<SuccessResponse>> {
+) -> FResult<Json<StagedEntries>> {
     let user = findex_server.get_user(&req);
 
-    info!("user {user}: POST /datasets/{index_id}/delete_entries");
+    info!("user {user}: POST /datasets/{index_id}/stage_entries");
 
     findex_server
         .ensure_minimum_permission(&user, &index_id, Permission::Write)
         .await?;
 
     let index_id = Uuid::parse_str(&index_id)?;
-    let uuids = Uuids::deserialize(&bytes)?;
+    let encrypted_entries: EncryptedEntries = read_body(&req, &bytes)?;
+    if encrypted_entries.is_empty() {
+        return Err(ServerError::InvalidRequest(
+            "No entries to stage".to_owned(),
+        ));
+    }
 
     trace!(
-        "delete_entries: number of UUIDs: {} from index {}:",
-        uuids.len(),
-        index_id
+        "stage_entries: number of encrypted entries: {}",
+        encrypted_entries.len()
     );
 
+    let transaction_id = Uuid::new_v4();
     findex_server
         .db
-        .dataset_delete_entries(&index_id, &uuids)
+        .dataset_stage_entries(
+            &index_id,
+            &transaction_id,
+            &encrypted_entries,
+            findex_server.params.pending_entries_ttl,
+        )
+        .await?;
+
+    Ok(Json(StagedEntries {
+        success: format!(
+            "{} entries successfully staged in index {index_id}",
+            encrypted_entries.len()
+        ),
+        index_id,
+        transaction_id,
+    }))
+}
+
+#[post("/datasets/{index_id}/commit_entries/{transaction_id}")]
+pub(crate) async fn datasets_commit_entries(
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+    let (index_id, transaction_id) = path.into_inner();
+
+    info!("user {user}: POST /datasets/{index_id}/commit_entries/{transaction_id}");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Write)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+
+    let uuids = findex_server
+        .journals
+        .write(&index_id, async {
+            let uuids = findex_server
+                .db
+                .dataset_commit_entries(&index_id, &transaction_id)
+                .await?;
+            let written = WrittenKeys::entries(uuids.iter().copied());
+            Ok((uuids, written))
+        })
         .await?;
 
     Ok(Json(SuccessResponse {
         success: format!(
-            "Encrypted entries successfully deleted from index {index_id}. UUIDs were {uuids}",
+            "{} entries of transaction {transaction_id} successfully committed to index {index_id}",
+            uuids.len()
         ),
         index_id,
     }))
 }
 
-#[post("/datasets/{index_id}/get_entries")]
-pub(crate) async fn datasets_get_entries(
+#[post("/datasets/{index_id}/abort_entries/{transaction_id}")]
+pub(crate) async fn datasets_abort_entries(
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+    let (index_id, transaction_id) = path.into_inner();
+
+    info!("user {user}: POST /datasets/{index_id}/abort_entries/{transaction_id}");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Write)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    findex_server
+        .db
+        .dataset_abort_entries(&index_id, &transaction_id)
+        .await?;
+
+    Ok(Json(SuccessResponse {
+        success: format!("Transaction {transaction_id} successfully aborted on index {index_id}"),
+        index_id,
+    }))
+}
+
+/// The number of entries listed per page by default
+const DEFAULT_LIST_LIMIT: usize = 100;
+/// The maximum number of entries listed per page
+const MAX_LIST_LIMIT: usize = 10_000;
+
+#[derive(Deserialize)]
+pub(crate) struct ListParams {
+    /// The cursor returned with the previous page, if any
+    cursor: Option<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct EntryInfo {
+    pub uuid: Uuid,
+    /// The size of the encrypted entry, in bytes
+    pub size: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct EntriesPage {
+    pub entries: Vec<EntryInfo>,
+    /// The cursor of the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[get("/datasets/{index_id}/entries")]
+pub(crate) async fn datasets_list_entries(
     req: HttpRequest,
     index_id: web::Path<String>,
-    bytes: Bytes,
+    params: web::Query<ListParams>,
     findex_server: Data<Arc<FindexServer>>,
-) -> ResponseBytes {
+) -> FResult<Json<EntriesPage>> {
     let user = findex_server.get_user(&req);
 
-    info!("user {user}: POST /datasets/{index_id}/get_entries",);
+    info!("user {user}: GET /datasets/{index_id}/entries");
 
     findex_server
         .ensure_minimum_permission(&user, &index_id, Permission::Read)
         .await?;
 
     let index_id = Uuid::parse_str(&index_id)?;
-    let uuids = Uuids::deserialize(&bytes)?;
-
-    trace!("get_entries: number of UUIDs: {}:", uuids.len());
+    let limit = params.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    if limit == 0 || limit > MAX_LIST_LIMIT {
+        return Err(ServerError::InvalidRequest(format!(
+            "The limit must be between 1 and {MAX_LIST_LIMIT}, found {limit}"
+        )));
+    }
 
-    let encrypted_entries = findex_server
+    let page = findex_server
         .db
-        .dataset_get_entries(&index_id, &uuids)
+        .dataset_list_entries(&index_id, params.cursor.as_deref(), limit)
         .await?;
 
-    Ok(HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .body(encrypted_entries.serialize()?.to_vec()))
+    trace!("list_entries: number of entries: {}", page.items.len());
+
+    Ok(Json(EntriesPage {
+        entries: page
+            .items
+            .into_iter()
+            .map(|(uuid, size)| EntryInfo { uuid, size })
+            .collect(),
+        next_cursor: page.next_cursor,
+    }))
 }
@@ -15,6 +15,8 @@ impl actix_web::error::ResponseError for ServerError {
         match self {
             Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
 
+            Self::Conflict(_) => StatusCode::CONFLICT,
+
             Self::DatabaseError(_)
             | Self::ConversionError(_)
             | Self::CryptographicError(_)
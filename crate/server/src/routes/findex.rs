@@ -1,29 +1,28 @@
 use std::sync::Arc;
 
 use actix_web::{
-    HttpRequest, HttpResponse, post,
+    HttpRequest, post,
     web::{self, Bytes, Data},
 };
 use cosmian_findex_structs::{
-    Addresses, Bindings, CUSTOM_WORD_LENGTH, Guard, OptionalWords, Permission,
-    SERVER_ADDRESS_LENGTH, UID_LENGTH,
+    Addresses, Bindings, CUSTOM_WORD_LENGTH, Guard, IndexedAddresses, IndexedWords, OptionalWords,
+    Permission,
 };
-use cosmian_sse_memories::{ADDRESS_LENGTH, Address, MemoryADT};
+use cosmian_sse_memories::{ADDRESS_LENGTH, MemoryADT};
+use futures::TryStreamExt;
 use tracing::trace;
 use uuid::Uuid;
 
-use crate::{core::FindexServer, error::server::ServerError, routes::error::ResponseBytes};
-
-#[allow(clippy::indexing_slicing)]
-fn prepend_index_id(
-    address: &Address<ADDRESS_LENGTH>,
-    index_id: &Uuid,
-) -> Address<SERVER_ADDRESS_LENGTH> {
-    let mut server_address = Address::<{ SERVER_ADDRESS_LENGTH }>::from([0; SERVER_ADDRESS_LENGTH]);
-    server_address[..UID_LENGTH].copy_from_slice(index_id.as_bytes());
-    server_address[UID_LENGTH..].copy_from_slice(&**address);
-    server_address
-}
+use crate::{
+    core::{FindexServer, prepend_index_id},
+    error::{result::FResult, server::ServerError},
+    middlewares::Idempotency,
+    routes::{
+        error::ResponseBytes,
+        frames::{framed_response, read_frames},
+        json::{GuardedWriteJson, body_response, is_json, parse_json, read_body},
+    },
+};
 
 #[post("/indexes/{index_id}/batch_read")]
 pub(crate) async fn findex_batch_read(
@@ -36,52 +35,100 @@ pub(crate) async fn findex_batch_read(
 
     trace!("user {user}: POST /indexes/{index_id}/batch_read");
 
-    findex_server
-        .ensure_minimum_permission(&user, &index_id, Permission::Read)
+    let addresses = read_body::<Addresses>(&req, &bytes)?.into_inner();
+    let words = findex_server
+        .batch_read(&user, &index_id, addresses)
         .await?;
 
-    let index_id = Uuid::parse_str(&index_id)?;
-    let addresses = Addresses::deserialize(&bytes)?
-        .into_inner()
-        .into_iter()
-        .map(|a| prepend_index_id(&a, &index_id))
-        .collect::<Vec<_>>();
+    body_response(&req, OptionalWords::new(words))
+}
+
+/// Read the words of several indexes at once, the read permission being
+/// required on each of them.
+#[post("/indexes/batch_read")]
+pub(crate) async fn findex_batch_read_indexes(
+    req: HttpRequest,
+    bytes: Bytes,
+    findex_server: Data<Arc<FindexServer>>,
+) -> ResponseBytes {
+    let user = findex_server.get_user(&req);
+
+    trace!("user {user}: POST /indexes/batch_read");
+
+    let addresses = read_body::<IndexedAddresses>(&req, &bytes)?.into_inner();
+    let words = findex_server.batch_read_indexes(&user, addresses).await?;
+
+    body_response(&req, IndexedWords::new(words))
+}
 
-    trace!("batch_read: number of addresses {}:", addresses.len());
+/// Read words in batches: each frame of the request holds a batch of
+/// addresses, and the matching frame of the response holds their words.
+#[post("/indexes/{index_id}/batch_read/stream")]
+pub(crate) async fn findex_batch_read_stream(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    payload: web::Payload,
+    findex_server: Data<Arc<FindexServer>>,
+) -> ResponseBytes {
+    let user = findex_server.get_user(&req);
 
-    let words = findex_server.db.batch_read(addresses).await?;
+    trace!("user {user}: POST /indexes/{index_id}/batch_read/stream");
 
-    trace!(
-        "batch_read successful. Number of non null words: {}.",
-        words.iter().filter(|w| w.is_some()).count()
-    );
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Read)
+        .await?;
 
-    // Convert Vec<Option<[u8; WORD_LENGTH]>> to Vec<u8>
-    let response_bytes = Bytes::from(OptionalWords::new(words).serialize()?);
+    let index_id = Uuid::parse_str(&index_id)?;
+    let words = read_frames(&req, payload).and_then(move |frame| {
+        let findex_server = findex_server.clone();
+        async move {
+            let addresses = Addresses::deserialize(&frame)?
+                .into_inner()
+                .into_iter()
+                .map(|a| prepend_index_id(&a, &index_id))
+                .collect::<Vec<_>>();
+            trace!("batch_read/stream: number of addresses {}", addresses.len());
+            let words = findex_server.db.batch_read(addresses).await?;
+            Ok(OptionalWords::new(words).serialize()?)
+        }
+    });
 
-    Ok(HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .body(response_bytes))
+    Ok(framed_response(words))
 }
 
-#[post("/indexes/{index_id}/guarded_write")]
+#[post("/indexes/{index_id}/guarded_write", wrap = "Idempotency")]
 pub(crate) async fn findex_guarded_write(
     req: HttpRequest,
     index_id: web::Path<String>,
     bytes: Bytes,
     findex_server: Data<Arc<FindexServer>>,
 ) -> ResponseBytes {
-    const OPERATION_NAME: &str = "guarded_write";
     let user = findex_server.get_user(&req);
 
     trace!("user {user}: POST /indexes/{index_id}/guarded_write");
 
-    findex_server
-        .ensure_minimum_permission(&user, &index_id, Permission::Write)
+    let (guard, bindings) = if is_json(&req) {
+        let guarded_write: GuardedWriteJson = parse_json(&bytes)?;
+        (guarded_write.guard()?, guarded_write.bindings()?)
+    } else {
+        let (guard, bindings) = parse_guarded_write(&bytes, &user, &index_id)?;
+        (guard.into_inner(), bindings.into_inner())
+    };
+
+    let result_word = findex_server
+        .guarded_write(&user, &index_id, guard, bindings)
         .await?;
 
-    let index_id = Uuid::parse_str(&index_id)?;
+    body_response(&req, OptionalWords::new(vec![result_word]))
+}
 
+/// Parse the binary body of a guarded write: a `Guard` followed by `Bindings`.
+fn parse_guarded_write(
+    bytes: &[u8],
+    user: &str,
+    index_id: &str,
+) -> FResult<(Guard<CUSTOM_WORD_LENGTH>, Bindings<CUSTOM_WORD_LENGTH>)> {
+    const OPERATION_NAME: &str = "guarded_write";
     let error_prefix = format!("Invalid {OPERATION_NAME} request by {user} on index {index_id}.");
 
     // 0 or 1. 0 means None, 1 means Some. Assumes the first ADDRESS_LENGTH
@@ -112,21 +159,5 @@ pub(crate) async fn findex_guarded_write(
         ))
     })?)?;
 
-    let (a_g, w_g) = guard.into_inner();
-    let bindings = bindings
-        .into_inner()
-        .into_iter()
-        .map(|(a, w)| (prepend_index_id(&a, &index_id), w))
-        .collect::<Vec<_>>();
-
-    let result_word = findex_server
-        .db
-        .guarded_write((prepend_index_id(&a_g, &index_id), w_g), bindings)
-        .await?;
-
-    let response_bytes = Bytes::from(OptionalWords::new(vec![result_word]).serialize()?);
-
-    Ok(HttpResponse::Ok()
-        .content_type("application/octet-stream")
-        .body(response_bytes))
+    Ok((guard, bindings))
 }
@@ -0,0 +1,153 @@
+//! Helpers of the routes streaming length-prefixed frames, see
+//! [`cosmian_findex_structs::FrameDecoder`].
+use actix_web::{
+    HttpRequest, HttpResponse,
+    dev::Decompress,
+    web::{Bytes, Payload},
+};
+use cosmian_findex_structs::{FRAMED_CONTENT_TYPE, FrameDecoder, encode_frame};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+
+use crate::error::{result::FResult, server::ServerError};
+
+/// Read the frames of a request body as they are received, decompressing
+/// the body according to its `Content-Encoding`.
+pub(crate) fn read_frames(
+    req: &HttpRequest,
+    payload: Payload,
+) -> impl Stream<Item = FResult<Vec<u8>>> + use<> {
+    stream::try_unfold(
+        (
+            Decompress::from_headers(payload, req.headers()),
+            FrameDecoder::new(),
+        ),
+        |(mut payload, mut decoder)| async move {
+            loop {
+                if let Some(frame) = decoder.next_frame()? {
+                    return Ok(Some((frame, (payload, decoder))));
+                }
+                let Some(chunk) = payload.next().await else {
+                    decoder.finish()?;
+                    return Ok(None);
+                };
+                decoder.push(&chunk.map_err(|e| {
+                    ServerError::InvalidRequest(format!("failed reading the body: {e}"))
+                })?);
+            }
+        },
+    )
+}
+
+/// Stream the frames as the body of the response.
+pub(crate) fn framed_response(
+    frames: impl Stream<Item = FResult<Vec<u8>>> + 'static,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(FRAMED_CONTENT_TYPE)
+        .streaming(frames.map_ok(|frame| Bytes::from(encode_frame(&frame))))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::{io::Write, sync::Arc};
+
+    use actix_web::{
+        App,
+        http::header::{CONTENT_ENCODING, CONTENT_TYPE},
+        test::{self, TestRequest},
+        web::Data,
+    };
+    use cosmian_crypto_core::bytes_ser_de::Serializable;
+    use cosmian_findex_structs::{
+        EncryptedEntries, FRAMED_CONTENT_TYPE, FrameDecoder, Uuids, encode_frame,
+    };
+    use flate2::{Compression, write::GzEncoder};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    use crate::{
+        config::{ClapConfig, DBConfig, DatabaseType, ServerParams},
+        core::FindexServer,
+        database::database_traits::PermissionsTrait,
+        routes::{datasets_add_entries_stream, datasets_get_entries_stream},
+    };
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_compressed_frames() {
+        let dir = TempDir::new().unwrap();
+        let conf = ClapConfig {
+            db: DBConfig {
+                database_type: DatabaseType::Sqlite,
+                database_url: dir.path().join("findex.db").to_str().unwrap().to_owned(),
+                ..DBConfig::default()
+            },
+            ..ClapConfig::default()
+        };
+        let findex_server = FindexServer::instantiate(ServerParams::try_from(conf).unwrap())
+            .await
+            .unwrap();
+        let index_id = findex_server
+            .db
+            .create_index_id(&findex_server.params.default_username)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(Data::new(Arc::new(findex_server)))
+                .service(datasets_add_entries_stream)
+                .service(datasets_get_entries_stream),
+        )
+        .await;
+
+        // The frames of a compressed request body are read once decompressed.
+        let batches = [
+            EncryptedEntries::from_iter([(Uuid::new_v4(), vec![1; 100])]),
+            EncryptedEntries::from_iter([(Uuid::new_v4(), vec![2; 100])]),
+        ];
+        let body = batches
+            .iter()
+            .flat_map(|entries| encode_frame(&entries.serialize().unwrap()))
+            .collect::<Vec<_>>();
+        let add = TestRequest::post()
+            .uri(&format!("/datasets/{index_id}/add_entries/stream"))
+            .insert_header((CONTENT_TYPE, FRAMED_CONTENT_TYPE))
+            .insert_header((CONTENT_ENCODING, "gzip"))
+            .set_payload(gzip(&body))
+            .to_request();
+        let response = test::call_service(&app, add).await;
+        assert!(response.status().is_success());
+
+        let uuids = batches
+            .iter()
+            .flat_map(|entries| entries.keys().copied())
+            .collect::<Vec<_>>();
+        let get = TestRequest::post()
+            .uri(&format!("/datasets/{index_id}/get_entries/stream"))
+            .insert_header((CONTENT_TYPE, FRAMED_CONTENT_TYPE))
+            .insert_header((CONTENT_ENCODING, "gzip"))
+            .set_payload(gzip(&encode_frame(
+                &Uuids::from(uuids).serialize().unwrap(),
+            )))
+            .to_request();
+        let response = test::call_service(&app, get).await;
+        assert!(response.status().is_success());
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&test::read_body(response).await);
+        let entries =
+            EncryptedEntries::deserialize(&decoder.next_frame().unwrap().unwrap()).unwrap();
+        assert_eq!(entries.len(), 2);
+        for batch in &batches {
+            for (uuid, entry) in batch.iter() {
+                assert_eq!(&entries[uuid], entry);
+            }
+        }
+        decoder.finish().unwrap();
+    }
+}
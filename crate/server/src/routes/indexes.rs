@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use actix_web::{
+    HttpRequest, HttpResponse, get, post,
+    web::{self, Bytes, Data, Json},
+};
+use cosmian_crypto_core::bytes_ser_de::Serializable;
+use cosmian_findex_structs::{IndexArchive, Permission};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    core::FindexServer,
+    error::result::FResult,
+    routes::error::{ResponseBytes, SuccessResponse},
+};
+
+#[derive(Deserialize)]
+pub(crate) struct ExportParams {
+    /// Whether the permissions on the index are exported
+    #[serde(default)]
+    permissions: bool,
+}
+
+#[post("/indexes/{index_id}/export")]
+pub(crate) async fn export_index(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    params: web::Query<ExportParams>,
+    findex_server: Data<Arc<FindexServer>>,
+) -> ResponseBytes {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /indexes/{index_id}/export");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Admin)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    let archive = findex_server
+        .export_index(&index_id, params.permissions)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .body(archive.serialize()?.to_vec()))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ImportParams {
+    /// The index to import into, a new index is created when missing
+    index_id: Option<Uuid>,
+    /// Whether the permissions held by the archive are restored
+    #[serde(default)]
+    permissions: bool,
+}
+
+#[post("/indexes/import")]
+pub(crate) async fn import_index(
+    req: HttpRequest,
+    params: web::Query<ImportParams>,
+    bytes: Bytes,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /indexes/import");
+
+    let archive = IndexArchive::deserialize(&bytes)?;
+    let (words, entries) = (archive.words.len(), archive.entries.len());
+    let index_id = findex_server
+        .import_index(&user, archive, params.index_id, params.permissions)
+        .await?;
+
+    Ok(Json(SuccessResponse {
+        success: format!(
+            "{words} words and {entries} entries successfully imported into index {index_id}"
+        ),
+        index_id,
+    }))
+}
+
+#[post("/indexes/{index_id}/clone")]
+pub(crate) async fn clone_index(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<SuccessResponse>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: POST /indexes/{index_id}/clone");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Admin)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    let clone_id = findex_server.clone_index(&user, &index_id).await?;
+
+    Ok(Json(SuccessResponse {
+        success: format!("Index {index_id} successfully cloned into index {clone_id}"),
+        index_id: clone_id,
+    }))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct IndexEmptiness {
+    /// Whether the index holds no word and no dataset entry
+    pub empty: bool,
+}
+
+#[get("/indexes/{index_id}/empty")]
+pub(crate) async fn index_is_empty(
+    req: HttpRequest,
+    index_id: web::Path<String>,
+    findex_server: Data<Arc<FindexServer>>,
+) -> FResult<Json<IndexEmptiness>> {
+    let user = findex_server.get_user(&req);
+
+    info!("user {user}: GET /indexes/{index_id}/empty");
+
+    findex_server
+        .ensure_minimum_permission(&user, &index_id, Permission::Read)
+        .await?;
+
+    let index_id = Uuid::parse_str(&index_id)?;
+    Ok(Json(IndexEmptiness {
+        empty: findex_server.is_index_empty(&index_id).await?,
+    }))
+}
@@ -0,0 +1,526 @@
+//! The JSON form of the binary bodies of the findex and dataset routes.
+//!
+//! A request sent with `Content-Type: application/json` has a JSON body, the
+//! addresses, words and entries being base64 encoded, and gets a JSON response.
+//! Any other request is read and answered in the binary LEB128 format.
+use std::collections::HashMap;
+
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, mime::APPLICATION_JSON};
+use base64::{Engine, engine::general_purpose};
+use cosmian_crypto_core::bytes_ser_de::Serializable;
+use cosmian_findex_structs::{
+    Addresses, CUSTOM_WORD_LENGTH, EncryptedEntries, IndexedAddresses, IndexedWords, OptionalWords,
+    Uuids, VersionedEntries, VersionedEntry,
+};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use uuid::Uuid;
+
+use crate::{
+    error::{result::FResult, server::ServerError},
+    routes::error::ResponseBytes,
+};
+
+type Word = [u8; CUSTOM_WORD_LENGTH];
+
+/// Whether the body of the request, and so of its response, is in JSON.
+pub(crate) fn is_json(req: &HttpRequest) -> bool {
+    req.content_type() == APPLICATION_JSON.essence_str()
+}
+
+/// Bytes serialized as a standard base64 string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Base64(pub Vec<u8>);
+
+impl Serialize for Base64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&general_purpose::STANDARD.encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Base64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        general_purpose::STANDARD
+            .decode(encoded)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Base64 {
+    fn to_array<const LENGTH: usize>(&self, name: &str) -> FResult<[u8; LENGTH]> {
+        self.0.as_slice().try_into().map_err(|_e| {
+            ServerError::InvalidRequest(format!(
+                "invalid {name} length: expected {LENGTH} bytes, found {}",
+                self.0.len()
+            ))
+        })
+    }
+
+    fn to_address(&self) -> FResult<Address<ADDRESS_LENGTH>> {
+        self.to_array("address").map(Address::from)
+    }
+}
+
+/// A body exchanged in binary, or in JSON if the request is.
+pub(crate) trait Negotiated: Sized {
+    type Json: Serialize + DeserializeOwned;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self>;
+
+    fn to_binary(&self) -> FResult<Vec<u8>>;
+
+    fn from_json(json: Self::Json) -> FResult<Self>;
+
+    fn to_json(self) -> Self::Json;
+}
+
+/// Parse a JSON request body, a malformed one being an invalid request.
+pub(crate) fn parse_json<T: DeserializeOwned>(bytes: &[u8]) -> FResult<T> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| ServerError::InvalidRequest(format!("invalid JSON body: {e}")))
+}
+
+/// Read the body of the request in the format it was sent in.
+pub(crate) fn read_body<T: Negotiated>(req: &HttpRequest, bytes: &[u8]) -> FResult<T> {
+    if is_json(req) {
+        T::from_json(parse_json(bytes)?)
+    } else {
+        T::from_binary(bytes)
+    }
+}
+
+/// Answer the request in the format it was sent in.
+pub(crate) fn body_response<T: Negotiated>(req: &HttpRequest, body: T) -> ResponseBytes {
+    if is_json(req) {
+        Ok(HttpResponse::Ok().json(body.to_json()))
+    } else {
+        Ok(HttpResponse::Ok()
+            .content_type("application/octet-stream")
+            .body(body.to_binary()?))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct AddressesJson {
+    pub addresses: Vec<Base64>,
+}
+
+impl Negotiated for Addresses {
+    type Json = AddressesJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?)
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        json.addresses
+            .iter()
+            .map(Base64::to_address)
+            .collect::<FResult<_>>()
+            .map(Self::new)
+    }
+
+    fn to_json(self) -> Self::Json {
+        AddressesJson {
+            addresses: self
+                .into_inner()
+                .into_iter()
+                .map(|a| Base64(a.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct WordsJson {
+    /// The words, `null` where there is none
+    pub words: Vec<Option<Base64>>,
+}
+
+impl Negotiated for OptionalWords<CUSTOM_WORD_LENGTH> {
+    type Json = WordsJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?)
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        json.words
+            .iter()
+            .map(|word| word.as_ref().map(|w| w.to_array("word")).transpose())
+            .collect::<FResult<_>>()
+            .map(Self::new)
+    }
+
+    fn to_json(self) -> Self::Json {
+        WordsJson {
+            words: self
+                .into_inner()
+                .into_iter()
+                .map(|word| word.map(|w| Base64(w.to_vec())))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct IndexedAddressesJson {
+    /// The addresses to read, by index ID
+    pub addresses: HashMap<Uuid, Vec<Base64>>,
+}
+
+impl Negotiated for IndexedAddresses {
+    type Json = IndexedAddressesJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?)
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        json.addresses
+            .into_iter()
+            .map(|(index_id, addresses)| {
+                let addresses = addresses
+                    .iter()
+                    .map(Base64::to_address)
+                    .collect::<FResult<_>>()?;
+                Ok((index_id, addresses))
+            })
+            .collect::<FResult<_>>()
+            .map(Self::new)
+    }
+
+    fn to_json(self) -> Self::Json {
+        IndexedAddressesJson {
+            addresses: self
+                .into_inner()
+                .into_iter()
+                .map(|(index_id, addresses)| {
+                    (
+                        index_id,
+                        addresses.into_iter().map(|a| Base64(a.to_vec())).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct IndexedWordsJson {
+    /// The words of each index, `null` where there is none
+    pub words: HashMap<Uuid, Vec<Option<Base64>>>,
+}
+
+impl Negotiated for IndexedWords<CUSTOM_WORD_LENGTH> {
+    type Json = IndexedWordsJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?)
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        json.words
+            .into_iter()
+            .map(|(index_id, words)| {
+                let words = words
+                    .iter()
+                    .map(|word| word.as_ref().map(|w| w.to_array("word")).transpose())
+                    .collect::<FResult<_>>()?;
+                Ok((index_id, words))
+            })
+            .collect::<FResult<_>>()
+            .map(Self::new)
+    }
+
+    fn to_json(self) -> Self::Json {
+        IndexedWordsJson {
+            words: self
+                .into_inner()
+                .into_iter()
+                .map(|(index_id, words)| {
+                    (
+                        index_id,
+                        words
+                            .into_iter()
+                            .map(|word| word.map(|w| Base64(w.to_vec())))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct GuardJson {
+    pub address: Base64,
+    /// The word the address is expected to hold, `null` if none
+    pub word: Option<Base64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct BindingJson {
+    pub address: Base64,
+    pub word: Base64,
+}
+
+/// The JSON body of a guarded write, the binary one being a `Guard` followed
+/// by `Bindings`.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct GuardedWriteJson {
+    pub guard: GuardJson,
+    pub bindings: Vec<BindingJson>,
+}
+
+impl GuardedWriteJson {
+    pub(crate) fn guard(&self) -> FResult<(Address<ADDRESS_LENGTH>, Option<Word>)> {
+        Ok((
+            self.guard.address.to_address()?,
+            self.guard
+                .word
+                .as_ref()
+                .map(|w| w.to_array("word"))
+                .transpose()?,
+        ))
+    }
+
+    pub(crate) fn bindings(&self) -> FResult<Vec<(Address<ADDRESS_LENGTH>, Word)>> {
+        self.bindings
+            .iter()
+            .map(|binding| {
+                Ok((
+                    binding.address.to_address()?,
+                    binding.word.to_array("word")?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct EntriesJson {
+    /// The encrypted entries, by UUID
+    pub entries: HashMap<Uuid, Base64>,
+}
+
+impl Negotiated for EncryptedEntries {
+    type Json = EntriesJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?.to_vec())
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        Ok(json
+            .entries
+            .into_iter()
+            .map(|(uuid, entry)| (uuid, entry.0))
+            .collect())
+    }
+
+    fn to_json(self) -> Self::Json {
+        EntriesJson {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|(uuid, entry)| (uuid, Base64(entry)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct VersionedEntryJson {
+    pub version: u64,
+    pub entry: Base64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct VersionedEntriesJson {
+    /// The encrypted entries and their versions, by UUID
+    pub entries: HashMap<Uuid, VersionedEntryJson>,
+}
+
+impl Negotiated for VersionedEntries {
+    type Json = VersionedEntriesJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?.to_vec())
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        Ok(json
+            .entries
+            .into_iter()
+            .map(|(uuid, versioned)| {
+                (
+                    uuid,
+                    VersionedEntry {
+                        version: versioned.version,
+                        entry: versioned.entry.0,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn to_json(self) -> Self::Json {
+        VersionedEntriesJson {
+            entries: self
+                .entries
+                .into_iter()
+                .map(|(uuid, versioned)| {
+                    (
+                        uuid,
+                        VersionedEntryJson {
+                            version: versioned.version,
+                            entry: Base64(versioned.entry),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct UuidsJson {
+    pub uuids: Vec<Uuid>,
+}
+
+impl Negotiated for Uuids {
+    type Json = UuidsJson;
+
+    fn from_binary(bytes: &[u8]) -> FResult<Self> {
+        Ok(Self::deserialize(bytes)?)
+    }
+
+    fn to_binary(&self) -> FResult<Vec<u8>> {
+        Ok(self.serialize()?.to_vec())
+    }
+
+    fn from_json(json: Self::Json) -> FResult<Self> {
+        Ok(Self::from(json.uuids))
+    }
+
+    fn to_json(self) -> Self::Json {
+        UuidsJson { uuids: self.uuids }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use base64::{Engine, engine::general_purpose};
+    use cosmian_findex_structs::{
+        Addresses, CUSTOM_WORD_LENGTH, EncryptedEntries, OptionalWords, Uuids,
+    };
+    use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+    use uuid::Uuid;
+
+    use super::{GuardedWriteJson, Negotiated, is_json, parse_json, read_body};
+
+    #[test]
+    fn test_content_negotiation() {
+        assert!(is_json(
+            &TestRequest::default()
+                .insert_header(("content-type", "application/json; charset=utf-8"))
+                .to_http_request()
+        ));
+        assert!(!is_json(
+            &TestRequest::default()
+                .insert_header(("content-type", "application/octet-stream"))
+                .to_http_request()
+        ));
+        assert!(!is_json(&TestRequest::default().to_http_request()));
+    }
+
+    #[test]
+    fn test_json_bodies() {
+        let json_request = TestRequest::default()
+            .insert_header(("content-type", "application/json"))
+            .to_http_request();
+        let binary_request = TestRequest::default().to_http_request();
+        let address = general_purpose::STANDARD.encode([1; ADDRESS_LENGTH]);
+        let word = general_purpose::STANDARD.encode([2; CUSTOM_WORD_LENGTH]);
+
+        // The JSON and binary forms of the same addresses are read the same.
+        let addresses = vec![Address::<ADDRESS_LENGTH>::from([1; ADDRESS_LENGTH])];
+        let from_json: Addresses = read_body(
+            &json_request,
+            format!(r#"{{"addresses":["{address}"]}}"#).as_bytes(),
+        )
+        .unwrap();
+        let from_binary: Addresses = read_body(
+            &binary_request,
+            &Addresses::new(addresses.clone()).serialize().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(from_json.into_inner(), addresses);
+        assert_eq!(from_binary.into_inner(), addresses);
+
+        // Malformed bodies and addresses of the wrong length are rejected.
+        read_body::<Addresses>(&json_request, br#"{"addresses":["AQE="]}"#).unwrap_err();
+        read_body::<Addresses>(&json_request, b"not json").unwrap_err();
+
+        let words = OptionalWords::new(vec![None, Some([2; CUSTOM_WORD_LENGTH])]).to_json();
+        assert_eq!(
+            serde_json::to_string(&words).unwrap(),
+            format!(r#"{{"words":[null,"{word}"]}}"#)
+        );
+
+        let uuid = Uuid::new_v4();
+        let entries = EncryptedEntries::from_json(
+            parse_json(format!(r#"{{"entries":{{"{uuid}":"aGVsbG8="}}}}"#).as_bytes()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(entries[&uuid], b"hello");
+
+        let uuids =
+            Uuids::from_json(parse_json(format!(r#"{{"uuids":["{uuid}"]}}"#).as_bytes()).unwrap())
+                .unwrap();
+        assert_eq!(uuids.to_json().uuids, vec![uuid]);
+
+        let guarded_write: GuardedWriteJson = parse_json(
+            format!(
+                r#"{{"guard":{{"address":"{address}","word":null}},
+                "bindings":[{{"address":"{address}","word":"{word}"}}]}}"#
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(guarded_write.guard().unwrap(), (addresses[0], None));
+        assert_eq!(
+            guarded_write.bindings().unwrap(),
+            vec![(addresses[0], [2; CUSTOM_WORD_LENGTH])]
+        );
+    }
+}
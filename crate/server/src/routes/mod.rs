@@ -1,10 +1,23 @@
 mod datasets;
 mod error;
 mod findex;
+mod frames;
+mod indexes;
+mod json;
+mod openapi;
 mod permissions;
 mod version;
 
-pub(crate) use datasets::{datasets_add_entries, datasets_del_entries, datasets_get_entries};
-pub(crate) use findex::{findex_batch_read, findex_guarded_write};
+pub(crate) use datasets::{
+    datasets_abort_entries, datasets_add_entries, datasets_add_entries_stream,
+    datasets_commit_entries, datasets_del_entries, datasets_get_entries,
+    datasets_get_entries_stream, datasets_list_entries, datasets_stage_entries,
+    datasets_update_entries,
+};
+pub(crate) use findex::{
+    findex_batch_read, findex_batch_read_indexes, findex_batch_read_stream, findex_guarded_write,
+};
+pub(crate) use indexes::{clone_index, export_index, import_index, index_is_empty};
+pub(crate) use openapi::get_openapi;
 pub(crate) use permissions::{create_index_id, list_permission, revoke_permission, set_permission};
 pub(crate) use version::get_version;
@@ -0,0 +1,65 @@
+use actix_web::{HttpResponse, get};
+
+/// The `OpenAPI` description of the `/v1` routes, binary bodies included
+const OPENAPI_DOCUMENT: &str = include_str!("openapi.json");
+
+/// Serve the `OpenAPI` document, without authentication so that clients can be
+/// generated from it.
+#[get("/v1/openapi.json")]
+pub(crate) async fn get_openapi() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(OPENAPI_DOCUMENT)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::OPENAPI_DOCUMENT;
+
+    #[test]
+    fn test_openapi_document_describes_every_route() {
+        let document: serde_json::Value = serde_json::from_str(OPENAPI_DOCUMENT).unwrap();
+        let described = document["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .flat_map(|(path, item)| {
+                item.as_object()
+                    .unwrap()
+                    .keys()
+                    .map(move |method| (method.clone(), path.clone()))
+            })
+            .collect::<BTreeSet<_>>();
+
+        // The routes declared by the `#[get(...)]` and `#[post(...)]` attributes
+        let declared = [
+            include_str!("datasets.rs"),
+            include_str!("findex.rs"),
+            include_str!("indexes.rs"),
+            include_str!("openapi.rs"),
+            include_str!("permissions.rs"),
+            include_str!("version.rs"),
+        ]
+        .iter()
+        .flat_map(|source| source.split("\n#[").skip(1))
+        .filter_map(|attribute| {
+            // The arguments may be split over several lines, and the path
+            // followed by other arguments, e.g. `wrap = "..."`
+            let (method, arguments) = attribute.split_once('(')?;
+            if !method.chars().all(char::is_alphanumeric) {
+                return None;
+            }
+            let (path, _) = arguments.trim_start().strip_prefix('"')?.split_once('"')?;
+            Some((
+                method.to_owned(),
+                path.strip_prefix("/v1").unwrap_or(path).to_owned(),
+            ))
+        })
+        .collect::<BTreeSet<_>>();
+
+        assert_eq!(described, declared);
+    }
+}
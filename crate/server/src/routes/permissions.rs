@@ -7,16 +7,16 @@ use actix_web::{
 use cosmian_crypto_core::bytes_ser_de::Serializable;
 use cosmian_findex_structs::Permission;
 use tracing::trace;
-use uuid::Uuid;
 
 use crate::{
     core::FindexServer,
     database::database_traits::PermissionsTrait,
-    error::{result::FResult, server::ServerError},
+    error::result::FResult,
+    middlewares::Idempotency,
     routes::error::{ResponseBytes, SuccessResponse},
 };
 
-#[post("/create/index")]
+#[post("/create/index", wrap = "Idempotency")]
 pub(crate) async fn create_index_id(
     req: HttpRequest,
     findex_server: Data<Arc<FindexServer>>,
@@ -32,7 +32,10 @@ pub(crate) async fn create_index_id(
     }))
 }
 
-#[post("/permission/set/{user_id}/{permission}/{index_id}")]
+#[post(
+    "/permission/set/{user_id}/{permission}/{index_id}",
+    wrap = "Idempotency"
+)]
 pub(crate) async fn set_permission(
     req: HttpRequest,
     params: web::Path<(String, String, String)>,
@@ -42,22 +45,9 @@ pub(crate) async fn set_permission(
     let (user_id, permission, index_id) = params.into_inner();
     trace!("user {user}: POST /permission/set/{user_id}/{permission}/{index_id}");
 
-    // Check if the user has the right to set permission: only admins can do that
-    let user_permission = findex_server.get_permission(&user, &index_id).await?;
-    if Permission::Admin != user_permission {
-        return Err(ServerError::Unauthorized(format!(
-            "Delegating permission to an index requires an admin permission. User {user} with \
-             permission {user_permission} does not allow setting permission to index {index_id} \
-             with permission {permission}",
-        )));
-    }
-
-    // Parse index_id
-    let index_id = Uuid::parse_str(&index_id)?;
-
-    findex_server
-        .db
+    let index_id = findex_server
         .set_permission(
+            &user,
             &user_id,
             Permission::from_str(permission.as_str())?,
             &index_id,
@@ -83,12 +73,9 @@ pub(crate) async fn list_permission(
 
     trace!("user {request_user}: POST /permission/list/{requested_user_id}");
 
-    let request_user_permissions = findex_server.db.get_permissions(&request_user).await?;
-    let requested_user_permissions = findex_server.db.get_permissions(&requested_user_id).await?;
-
-    // To avoid a user to lookup who are the more powerful users only display the
-    // minimum permission between the two users
-    let min_permissions = requested_user_permissions.min(&request_user_permissions);
+    let min_permissions = findex_server
+        .list_permissions(&request_user, &requested_user_id)
+        .await?;
 
     let bytes = min_permissions.serialize()?;
     Ok(HttpResponse::Ok()
@@ -96,7 +83,7 @@ pub(crate) async fn list_permission(
         .body(bytes.to_vec()))
 }
 
-#[post("/permission/revoke/{user_id}/{index_id}")]
+#[post("/permission/revoke/{user_id}/{index_id}", wrap = "Idempotency")]
 pub(crate) async fn revoke_permission(
     req: HttpRequest,
     params: web::Path<(String, String)>,
@@ -107,21 +94,8 @@ pub(crate) async fn revoke_permission(
 
     trace!("user {user}: POST /permission/revoke/{user_id}/{index_id}");
 
-    // Check if the user has the right to revoke permission: only admins can do that
-    let user_permission = findex_server.get_permission(&user, &index_id).await?;
-
-    if Permission::Admin != user_permission {
-        return Err(ServerError::Unauthorized(format!(
-            "Revoking permission to an index requires an admin permission. User {user} with \
-             permission {user_permission} does not allow revoking permission to index {index_id}",
-        )));
-    }
-
-    let index_id = Uuid::parse_str(&index_id)?;
-
-    findex_server
-        .db
-        .revoke_permission(&user_id, &index_id)
+    let index_id = findex_server
+        .revoke_permission(&user, &user_id, &index_id)
         .await?;
 
     Ok(Json(SuccessResponse {
@@ -5,7 +5,10 @@ mod tests_inner {
 
     use tempfile::TempDir;
 
-    use crate::config::{ClapConfig, DBConfig, DatabaseType, HttpConfig, JwtAuthConfig};
+    use crate::config::{
+        ClapConfig, DBConfig, DatabaseType, DbParams, HttpConfig, JwtAuthConfig, RedisMode,
+        RedisParams, RedisTopology,
+    };
 
     fn get_database_configurations() -> (Vec<String>, Vec<DBConfig>) {
         assert_eq!(
@@ -22,11 +25,17 @@ mod tests_inner {
                 database_type: DatabaseType::Redis,
                 database_url: "[some urls]".to_owned(),
                 clear_database: false,
+                redis_mode: RedisMode::Standalone,
+                redis_sentinel_master: None,
+                redis_key_prefix: None,
             },
             DBConfig {
                 database_type: DatabaseType::Sqlite,
                 database_url: "[some urls]".to_owned(),
                 clear_database: false,
+                redis_mode: RedisMode::Standalone,
+                redis_sentinel_master: None,
+                redis_key_prefix: None,
             },
         ];
 
@@ -43,6 +52,7 @@ mod tests_inner {
                 http: HttpConfig {
                     port: 443,
                     hostname: "[hostname]".to_owned(),
+                    grpc_port: Some(6669),
                     https_p12_file: Some(PathBuf::from("[https p12 file]")),
                     https_p12_password: Some("[https p12 password]".to_owned()),
                     authority_cert_file: Some(PathBuf::from("[authority cert file]")),
@@ -60,20 +70,29 @@ mod tests_inner {
                 },
                 default_username: "[default username]".to_owned(),
                 force_default_username: false,
+                pending_entries_ttl: 3600,
+                idempotency_window: 600,
+                max_archive_items: 1000,
+                command: None,
             };
             let expected_toml = format!(
                 r#"
 default_username = "[default username]"
 force_default_username = false
+pending_entries_ttl = 3600
+idempotency_window = 600
+max_archive_items = 1000
 
 [db]
 database_type = "{}"
 database_url = "[some urls]"
 clear_database = false
+redis_mode = "Standalone"
 
 [http]
 port = 443
 hostname = "[hostname]"
+grpc_port = 6669
 https_p12_file = "[https p12 file]"
 https_p12_password = "[https p12 password]"
 authority_cert_file = "[authority cert file]"
@@ -102,6 +121,7 @@ jwt_audience = ["[jwt audience 1]", "[jwt audience 2]"]
                 http: HttpConfig {
                     port: 443,
                     hostname: "[hostname]".to_owned(),
+                    grpc_port: Some(6669),
                     https_p12_file: Some(PathBuf::from("[https p12 file]")),
                     https_p12_password: Some("[https p12 password]".to_owned()),
                     authority_cert_file: Some(PathBuf::from("[authority cert file]")),
@@ -119,6 +139,10 @@ jwt_audience = ["[jwt audience 1]", "[jwt audience 2]"]
                 },
                 default_username: "[default username]".to_owned(),
                 force_default_username: false,
+                pending_entries_ttl: 3600,
+                idempotency_window: 600,
+                max_archive_items: 1000,
+                command: None,
             };
 
             // create a temp dir
@@ -136,4 +160,97 @@ jwt_audience = ["[jwt audience 1]", "[jwt audience 2]"]
             assert_eq!(config, read_config);
         }
     }
+
+    #[test]
+    fn test_redis_topologies() {
+        let redis_config =
+            |redis_mode, database_url: &str, redis_sentinel_master: Option<&str>| DBConfig {
+                database_type: DatabaseType::Redis,
+                database_url: database_url.to_owned(),
+                clear_database: false,
+                redis_mode,
+                redis_sentinel_master: redis_sentinel_master.map(ToOwned::to_owned),
+                redis_key_prefix: None,
+            };
+
+        let db_params = redis_config(RedisMode::Standalone, "redis://localhost:6379", None)
+            .init()
+            .unwrap();
+        assert!(matches!(
+            db_params,
+            DbParams::Redis(RedisParams { topology: RedisTopology::Standalone(url), key_prefix: None, legacy_layout: false, legacy_words_by_length: false }) if url.as_str() == "redis://localhost:6379"
+        ));
+
+        let db_params = redis_config(
+            RedisMode::Cluster,
+            "redis://node1:7000, redis://node2:7000,redis://node3:7000",
+            None,
+        )
+        .init()
+        .unwrap();
+        let DbParams::Redis(RedisParams {
+            topology: RedisTopology::Cluster(nodes),
+            ..
+        }) = db_params
+        else {
+            panic!("a Redis Cluster topology was expected");
+        };
+        assert_eq!(
+            nodes.iter().map(url::Url::as_str).collect::<Vec<_>>(),
+            vec![
+                "redis://node1:7000",
+                "redis://node2:7000",
+                "redis://node3:7000"
+            ]
+        );
+
+        let db_params = redis_config(
+            RedisMode::Sentinel,
+            "redis://sentinel1:26379,redis://sentinel2:26379",
+            Some("mymaster"),
+        )
+        .init()
+        .unwrap();
+        let DbParams::Redis(RedisParams {
+            topology:
+                RedisTopology::Sentinel {
+                    sentinels,
+                    master_name,
+                },
+            ..
+        }) = db_params
+        else {
+            panic!("a Redis Sentinel topology was expected");
+        };
+        assert_eq!(sentinels.len(), 2);
+        assert_eq!(master_name, "mymaster");
+
+        // The master name is mandatory in Sentinel mode
+        redis_config(RedisMode::Sentinel, "redis://sentinel1:26379", None)
+            .init()
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_redis_key_prefix() {
+        let redis_config = |redis_key_prefix: Option<&str>| DBConfig {
+            redis_key_prefix: redis_key_prefix.map(ToOwned::to_owned),
+            ..DBConfig::default()
+        };
+
+        let DbParams::Redis(redis_params) = redis_config(Some("findex:")).init().unwrap() else {
+            panic!("Redis parameters were expected");
+        };
+        assert_eq!(redis_params.key_prefix.as_deref(), Some("findex:"));
+
+        // An empty prefix is no prefix
+        let DbParams::Redis(redis_params) = redis_config(Some("")).init().unwrap() else {
+            panic!("Redis parameters were expected");
+        };
+        assert_eq!(redis_params.key_prefix, None);
+
+        // Braces would break the hash tags of the keys
+        redis_config(Some("{findex}:")).init().unwrap_err();
+        redis_config(Some("findex}")).init().unwrap_err();
+    }
 }
@@ -0,0 +1,110 @@
+//! Generate the gRPC client and server of the `findex.v1.Findex` service,
+//! whose messages are written by hand in `src/grpc.rs` so that no `protoc`
+//! is needed to build the crate. `proto/findex.proto` describes the same API
+//! for the other stacks.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    /// The methods: name, input and output types, and whether the requests
+    /// and responses are streamed.
+    const METHODS: [(&str, &str, &str, bool); 10] = [
+        ("BatchRead", "BatchReadRequest", "BatchReadResponse", false),
+        (
+            "GuardedWrite",
+            "GuardedWriteRequest",
+            "GuardedWriteResponse",
+            false,
+        ),
+        (
+            "GuardedWriteStream",
+            "GuardedWriteRequest",
+            "GuardedWriteResponse",
+            true,
+        ),
+        (
+            "AddEntries",
+            "AddEntriesRequest",
+            "OperationResponse",
+            false,
+        ),
+        (
+            "DeleteEntries",
+            "DeleteEntriesRequest",
+            "OperationResponse",
+            false,
+        ),
+        (
+            "GetEntries",
+            "GetEntriesRequest",
+            "GetEntriesResponse",
+            false,
+        ),
+        (
+            "CreateIndex",
+            "CreateIndexRequest",
+            "OperationResponse",
+            false,
+        ),
+        (
+            "SetPermission",
+            "SetPermissionRequest",
+            "OperationResponse",
+            false,
+        ),
+        (
+            "ListPermissions",
+            "ListPermissionsRequest",
+            "ListPermissionsResponse",
+            false,
+        ),
+        (
+            "RevokePermission",
+            "RevokePermissionRequest",
+            "OperationResponse",
+            false,
+        ),
+    ];
+
+    pub(super) fn compile() {
+        println!("cargo:rerun-if-changed=build.rs");
+        let service = METHODS
+            .iter()
+            .fold(
+                Service::builder().name("Findex").package("findex.v1"),
+                |service, &(name, input, output, streaming)| {
+                    let method = Method::builder()
+                        .name(to_snake_case(name))
+                        .route_name(name)
+                        .input_type(format!("crate::grpc::{input}"))
+                        .output_type(format!("crate::grpc::{output}"))
+                        .codec_path("tonic_prost::ProstCodec");
+                    let method = if streaming {
+                        method.client_streaming().server_streaming()
+                    } else {
+                        method
+                    };
+                    service.method(method.build())
+                },
+            )
+            .build();
+        Builder::new().build_transport(false).compile(&[service]);
+    }
+
+    fn to_snake_case(name: &str) -> String {
+        let mut snake_case = String::new();
+        for c in name.chars() {
+            if c.is_ascii_uppercase() && !snake_case.is_empty() {
+                snake_case.push('_');
+            }
+            snake_case.push(c.to_ascii_lowercase());
+        }
+        snake_case
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use cosmian_crypto_core::bytes_ser_de::{Deserializer, Serializer};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{
+    SerializationResult,
+    words::{deser_optional_word, ser_optional_word},
+};
+use crate::StructsError;
+
+/// Addresses to read from several indexes at once, grouped by index ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexedAddresses(pub HashMap<Uuid, Vec<Address<ADDRESS_LENGTH>>>);
+
+impl IndexedAddresses {
+    #[must_use]
+    pub const fn new(addresses: HashMap<Uuid, Vec<Address<ADDRESS_LENGTH>>>) -> Self {
+        Self(addresses)
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> HashMap<Uuid, Vec<Address<ADDRESS_LENGTH>>> {
+        self.0
+    }
+
+    /// Serializes the `IndexedAddresses` instance into a vector of bytes.
+    ///
+    /// Serialization format: the LEB128-encoded number of indexes, followed
+    /// by each 16-byte index ID, the LEB128-encoded number of its addresses
+    /// and the addresses.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the serialization process fails.
+    pub fn serialize(&self) -> SerializationResult<Vec<u8>> {
+        let mut ser = Serializer::new();
+        ser.write_leb128_u64(self.0.len().try_into()?)?;
+        for (index_id, addresses) in &self.0 {
+            ser.write_array(index_id.as_bytes())?;
+            ser.write_leb128_u64(addresses.len().try_into()?)?;
+            for adr in addresses {
+                ser.write_array(adr.as_ref())?;
+            }
+        }
+        Ok(ser.finalize().to_vec())
+    }
+
+    /// Deserializes a vector of bytes into an `IndexedAddresses` instance.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the deserialization process
+    /// fails or if an index is listed twice.
+    pub fn deserialize(data: &[u8]) -> SerializationResult<Self> {
+        let mut de = Deserializer::new(data);
+        let length = <usize>::try_from(de.read_leb128_u64()?)?;
+        let mut items = HashMap::with_capacity(length.min(1_000));
+        for _ in 0..length {
+            let index_id = Uuid::from_bytes(de.read_array()?);
+            let count = <usize>::try_from(de.read_leb128_u64()?)?;
+            if count > 1_000_000 {
+                debug!("IndexedAddresses: deserialize: allocating {count}");
+            }
+            let mut addresses = Vec::with_capacity(count);
+            for _ in 0..count {
+                let address: Address<ADDRESS_LENGTH> = de.read_array()?.into();
+                addresses.push(address);
+            }
+            if items.insert(index_id, addresses).is_some() {
+                return Err(StructsError::DeserializationError(format!(
+                    "index {index_id} is listed twice"
+                )));
+            }
+        }
+        Ok(Self(items))
+    }
+}
+
+/// The words read from several indexes at once, grouped by index ID, in the
+/// order of the addresses they were read at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexedWords<const WORD_LENGTH: usize>(
+    pub HashMap<Uuid, Vec<Option<[u8; WORD_LENGTH]>>>,
+);
+
+impl<const WORD_LENGTH: usize> IndexedWords<WORD_LENGTH> {
+    #[must_use]
+    pub const fn new(words: HashMap<Uuid, Vec<Option<[u8; WORD_LENGTH]>>>) -> Self {
+        Self(words)
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> HashMap<Uuid, Vec<Option<[u8; WORD_LENGTH]>>> {
+        self.0
+    }
+
+    /// Serializes the `IndexedWords` instance into a vector of bytes.
+    ///
+    /// Serialization format: the LEB128-encoded number of indexes, followed
+    /// by each 16-byte index ID and its words, serialized as `OptionalWords`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the serialization process fails.
+    pub fn serialize(&self) -> SerializationResult<Vec<u8>> {
+        let mut ser = Serializer::new();
+        ser.write_leb128_u64(self.0.len().try_into()?)?;
+        for (index_id, words) in &self.0 {
+            ser.write_array(index_id.as_bytes())?;
+            ser.write_leb128_u64(words.len().try_into()?)?;
+            for word in words {
+                ser_optional_word(&mut ser, word.as_ref())?;
+            }
+        }
+        Ok(ser.finalize().to_vec())
+    }
+
+    /// Deserializes a vector of bytes into an `IndexedWords` instance.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the deserialization process fails.
+    pub fn deserialize(data: &[u8]) -> SerializationResult<Self> {
+        let mut de = Deserializer::new(data);
+        let length = <usize>::try_from(de.read_leb128_u64()?)?;
+        let mut items = HashMap::with_capacity(length.min(1_000));
+        for _ in 0..length {
+            let index_id = Uuid::from_bytes(de.read_array()?);
+            let count = <usize>::try_from(de.read_leb128_u64()?)?;
+            if count > 1_000_000 {
+                debug!("IndexedWords: deserialize: allocating {count}");
+            }
+            let mut words = Vec::with_capacity(count);
+            for _ in 0..count {
+                words.push(deser_optional_word::<WORD_LENGTH>(&mut de)?);
+            }
+            items.insert(index_id, words);
+        }
+        Ok(Self(items))
+    }
+}
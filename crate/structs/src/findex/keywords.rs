@@ -10,6 +10,12 @@ use super::Value;
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Keyword(Vec<u8>);
 
+impl AsRef<[u8]> for Keyword {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl<'a> From<&'a [u8]> for Keyword {
     fn from(bytes: &'a [u8]) -> Self {
         Self(bytes.to_vec())
@@ -5,6 +5,7 @@ pub type SerializationResult<R> = Result<R, StructsError>;
 mod addresses;
 mod bindings;
 mod guard;
+mod indexed;
 mod keywords;
 mod search_results;
 mod tests;
@@ -14,6 +15,7 @@ mod words;
 pub use addresses::Addresses;
 pub use bindings::Bindings;
 pub use guard::Guard;
+pub use indexed::{IndexedAddresses, IndexedWords};
 pub use keywords::{Keyword, KeywordToDataSetsMap, Keywords};
 pub use search_results::SearchResults;
 pub use value::Value;
@@ -1,6 +1,7 @@
 #[allow(clippy::expect_used)]
 #[cfg(test)]
 mod findex_tests {
+    use std::collections::HashMap;
 
     use cosmian_crypto_core::{
         CsRng, Sampling,
@@ -8,9 +9,14 @@ mod findex_tests {
     };
     use cosmian_findex::WORD_LENGTH;
     use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+    use uuid::Uuid;
 
     use crate::findex::{
-        addresses::Addresses, bindings::Bindings, guard::Guard, words::OptionalWords,
+        addresses::Addresses,
+        bindings::Bindings,
+        guard::Guard,
+        indexed::{IndexedAddresses, IndexedWords},
+        words::OptionalWords,
     };
 
     const SEED: [u8; 32] = [1_u8; 32]; // arbitrary seed for the RNG
@@ -95,4 +101,33 @@ mod findex_tests {
 
         assert_eq!(bindings, deserialized, "Bindings do not match");
     }
+
+    #[test]
+    fn test_ser_deser_indexed_addresses_and_words() {
+        let mut rng = CsRng::from_seed(SEED);
+
+        let index1 = Uuid::new_v4();
+        let index2 = Uuid::new_v4();
+        let address1: Address<ADDRESS_LENGTH> = Address::random(&mut rng);
+        let address2: Address<ADDRESS_LENGTH> = Address::random(&mut rng);
+        let mut word = [0_u8; WORD_LENGTH];
+        rng.fill_bytes(&mut word[..]);
+
+        let addresses = IndexedAddresses(HashMap::from([
+            (index1, vec![address1, address2]),
+            (index2, vec![]),
+        ]));
+        let serialized = addresses.serialize().expect("Serialization failed");
+        let deserialized =
+            IndexedAddresses::deserialize(&serialized).expect("Deserialization failed");
+        assert_eq!(addresses, deserialized, "Indexed addresses do not match");
+
+        let words: IndexedWords<WORD_LENGTH> = IndexedWords(HashMap::from([
+            (index1, vec![Some(word), None]),
+            (index2, vec![]),
+        ]));
+        let serialized = words.serialize().expect("Serialization failed");
+        let deserialized = IndexedWords::deserialize(&serialized).expect("Deserialization failed");
+        assert_eq!(words, deserialized, "Indexed words do not match");
+    }
 }
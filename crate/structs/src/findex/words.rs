@@ -8,7 +8,7 @@ use super::SerializationResult;
 use crate::StructsError;
 
 /// Returns a `SerializationError` if any step of the serialization process fails.
-fn ser_optional_word<const WORD_LENGTH: usize>(
+pub(super) fn ser_optional_word<const WORD_LENGTH: usize>(
     ser: &mut Serializer,
     word: Option<&[u8; WORD_LENGTH]>,
 ) -> SerializationResult<usize> {
@@ -32,7 +32,7 @@ fn ser_optional_word<const WORD_LENGTH: usize>(
 /// # Errors
 ///
 /// Returns a `DeserializationError` if any step of the deserialization process fails.
-fn deser_optional_word<const WORD_LENGTH: usize>(
+pub(super) fn deser_optional_word<const WORD_LENGTH: usize>(
     de: &mut Deserializer,
 ) -> SerializationResult<Option<[u8; WORD_LENGTH]>> {
     let flag = <usize>::try_from(de.read_leb128_u64()?)?;
@@ -0,0 +1,147 @@
+//! Length-prefixed framing of the streamed request and response bodies.
+//!
+//! A streamed body is a sequence of frames, each frame being the LEB128
+//! encoded length of its payload followed by the payload. The payload of a
+//! frame is a serialized batch (addresses, words, UUIDs or entries), so that
+//! neither the server nor the client has to hold the whole body in memory.
+use crate::{StructsError, error::result::StructsResult};
+
+/// The content type of the streamed bodies
+pub const FRAMED_CONTENT_TYPE: &str = "application/x-findex-frames";
+
+/// The maximum length of the payload of a frame
+pub const MAX_FRAME_LENGTH: usize = 1 << 28;
+
+/// Frame a payload.
+#[must_use]
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    let mut length = payload.len();
+    loop {
+        let byte = u8::try_from(length & 0x7f).unwrap_or_default();
+        length >>= 7;
+        if length == 0 {
+            frame.push(byte);
+            break;
+        }
+        frame.push(byte | 0x80);
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Split the chunks of a streamed body into frames, as they are received.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    // The position of the next frame in the buffer
+    position: usize,
+}
+
+impl FrameDecoder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a chunk of the body.
+    pub fn push(&mut self, chunk: &[u8]) {
+        // Drop the frames already read before growing the buffer.
+        if self.position > 0 {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Return the payload of the next frame, or `None` if it has not been
+    /// entirely received yet.
+    ///
+    /// # Errors
+    /// Fails if the frame length is invalid or exceeds [`MAX_FRAME_LENGTH`].
+    pub fn next_frame(&mut self) -> StructsResult<Option<Vec<u8>>> {
+        let remaining = self.buffer.get(self.position..).unwrap_or_default();
+        let mut length = 0_usize;
+        let mut header_length = 0;
+        loop {
+            let Some(byte) = remaining.get(header_length) else {
+                return Ok(None);
+            };
+            let shift = 7 * header_length;
+            if shift >= usize::BITS.try_into()? {
+                return Err(StructsError::DeserializationError(
+                    "invalid frame length".to_owned(),
+                ));
+            }
+            length |= usize::from(byte & 0x7f) << shift;
+            header_length += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        if length > MAX_FRAME_LENGTH {
+            return Err(StructsError::DeserializationError(format!(
+                "frame of {length} bytes, at most {MAX_FRAME_LENGTH} bytes expected"
+            )));
+        }
+        let Some(payload) = remaining.get(header_length..header_length + length) else {
+            return Ok(None);
+        };
+        let payload = payload.to_vec();
+        self.position += header_length + length;
+        Ok(Some(payload))
+    }
+
+    /// Check that the body ends on a frame boundary, once all the frames
+    /// have been read.
+    ///
+    /// # Errors
+    /// Fails if the body ends with a truncated frame.
+    pub fn finish(&self) -> StructsResult<()> {
+        let trailing = self.buffer.len() - self.position;
+        if trailing == 0 {
+            Ok(())
+        } else {
+            Err(StructsError::DeserializationError(format!(
+                "the body ends with a truncated frame of {trailing} bytes"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::indexing_slicing, clippy::panic_in_result_fn)]
+mod tests {
+    use super::{FrameDecoder, encode_frame};
+    use crate::error::result::StructsResult;
+
+    #[test]
+    fn test_frames() -> StructsResult<()> {
+        let payloads = [vec![], vec![1; 10], vec![2; 300], vec![3; 70_000]];
+        let body = payloads
+            .iter()
+            .flat_map(|payload| encode_frame(payload))
+            .collect::<Vec<_>>();
+
+        // The frames are decoded whatever the chunks they are received in.
+        for chunk_size in [1, 7, 1000, body.len()] {
+            let mut decoder = FrameDecoder::new();
+            let mut payloads_read = Vec::new();
+            for chunk in body.chunks(chunk_size) {
+                decoder.push(chunk);
+                while let Some(payload) = decoder.next_frame()? {
+                    payloads_read.push(payload);
+                }
+            }
+            decoder.finish()?;
+            assert_eq!(payloads_read, payloads);
+        }
+
+        // A truncated frame is detected.
+        let mut decoder = FrameDecoder::new();
+        decoder.push(&body[..body.len() - 1]);
+        while decoder.next_frame()?.is_some() {}
+        assert!(decoder.finish().is_err());
+        Ok(())
+    }
+}
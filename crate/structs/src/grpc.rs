@@ -0,0 +1,562 @@
+//! The messages of the `findex.v1.Findex` gRPC service, described by
+//! `proto/findex.proto`, and its generated client and server.
+//!
+//! The addresses, words and entries are the raw bytes exchanged by the REST
+//! API; the helpers below check their lengths on the way in.
+
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use uuid::Uuid;
+
+use crate::{EncryptedEntries, Permission, Permissions, StructsError};
+
+#[allow(
+    clippy::all,
+    clippy::pedantic,
+    clippy::nursery,
+    clippy::unwrap_used,
+    clippy::indexing_slicing,
+    clippy::as_conversions,
+    unreachable_pub
+)]
+mod service {
+    include!(concat!(env!("OUT_DIR"), "/findex.v1.Findex.rs"));
+}
+
+pub use service::{findex_client, findex_server};
+
+type GrpcResult<T> = Result<T, StructsError>;
+
+fn to_array<const LENGTH: usize>(bytes: &[u8], name: &str) -> GrpcResult<[u8; LENGTH]> {
+    bytes.try_into().map_err(|_e| {
+        StructsError::DeserializationError(format!(
+            "invalid {name} length: expected {LENGTH} bytes, found {}",
+            bytes.len()
+        ))
+    })
+}
+
+fn to_address(bytes: &[u8]) -> GrpcResult<Address<ADDRESS_LENGTH>> {
+    to_array(bytes, "address").map(Address::from)
+}
+
+fn to_uuid(uuid: &str) -> GrpcResult<Uuid> {
+    Ok(Uuid::parse_str(uuid)?)
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct BatchReadRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub addresses: Vec<Vec<u8>>,
+}
+
+impl BatchReadRequest {
+    #[must_use]
+    pub fn new(index_id: &Uuid, addresses: &[Address<ADDRESS_LENGTH>]) -> Self {
+        Self {
+            index_id: index_id.to_string(),
+            addresses: addresses.iter().map(|a| a.to_vec()).collect(),
+        }
+    }
+
+    /// The addresses to read.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an address does not have the expected length.
+    pub fn addresses(&self) -> GrpcResult<Vec<Address<ADDRESS_LENGTH>>> {
+        self.addresses.iter().map(|a| to_address(a)).collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct OptionalWord {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub word: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct BatchReadResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub words: Vec<OptionalWord>,
+}
+
+impl BatchReadResponse {
+    #[must_use]
+    pub fn new<const WORD_LENGTH: usize>(words: &[Option<[u8; WORD_LENGTH]>]) -> Self {
+        Self {
+            words: words
+                .iter()
+                .map(|w| OptionalWord {
+                    word: w.map(|w| w.to_vec()),
+                })
+                .collect(),
+        }
+    }
+
+    /// The words read, in the order of the requested addresses.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a word does not have the expected length.
+    pub fn words<const WORD_LENGTH: usize>(&self) -> GrpcResult<Vec<Option<[u8; WORD_LENGTH]>>> {
+        self.words
+            .iter()
+            .map(|w| w.word.as_deref().map(|w| to_array(w, "word")).transpose())
+            .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Binding {
+    #[prost(bytes = "vec", tag = "1")]
+    pub address: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub word: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GuardedWriteRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub guard_address: Vec<u8>,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub guard_word: Option<Vec<u8>>,
+    #[prost(message, repeated, tag = "4")]
+    pub bindings: Vec<Binding>,
+}
+
+impl GuardedWriteRequest {
+    #[must_use]
+    pub fn new<const WORD_LENGTH: usize>(
+        index_id: &Uuid,
+        guard: &(Address<ADDRESS_LENGTH>, Option<[u8; WORD_LENGTH]>),
+        bindings: &[(Address<ADDRESS_LENGTH>, [u8; WORD_LENGTH])],
+    ) -> Self {
+        Self {
+            index_id: index_id.to_string(),
+            guard_address: guard.0.to_vec(),
+            guard_word: guard.1.map(|w| w.to_vec()),
+            bindings: bindings
+                .iter()
+                .map(|(a, w)| Binding {
+                    address: a.to_vec(),
+                    word: w.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The guard of the write.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the guard address or word does not have the expected length.
+    pub fn guard<const WORD_LENGTH: usize>(
+        &self,
+    ) -> GrpcResult<(Address<ADDRESS_LENGTH>, Option<[u8; WORD_LENGTH]>)> {
+        Ok((
+            to_address(&self.guard_address)?,
+            self.guard_word
+                .as_deref()
+                .map(|w| to_array(w, "word"))
+                .transpose()?,
+        ))
+    }
+
+    /// The bindings to write if the guard holds.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an address or a word does not have the expected length.
+    pub fn bindings<const WORD_LENGTH: usize>(
+        &self,
+    ) -> GrpcResult<Vec<(Address<ADDRESS_LENGTH>, [u8; WORD_LENGTH])>> {
+        self.bindings
+            .iter()
+            .map(|b| Ok((to_address(&b.address)?, to_array(&b.word, "word")?)))
+            .collect()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GuardedWriteResponse {
+    /// The word the guard address held before the write
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub word: Option<Vec<u8>>,
+}
+
+impl GuardedWriteResponse {
+    #[must_use]
+    pub fn new<const WORD_LENGTH: usize>(word: Option<[u8; WORD_LENGTH]>) -> Self {
+        Self {
+            word: word.map(|w| w.to_vec()),
+        }
+    }
+
+    /// The word the guard address held before the write.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the word does not have the expected length.
+    pub fn previous_word<const WORD_LENGTH: usize>(&self) -> GrpcResult<Option<[u8; WORD_LENGTH]>> {
+        self.word
+            .as_deref()
+            .map(|w| to_array(w, "word"))
+            .transpose()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct Entry {
+    #[prost(string, tag = "1")]
+    pub uuid: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+}
+
+/// Convert the entries of a request or a response.
+///
+/// # Errors
+///
+/// Fails if an entry UUID cannot be parsed.
+pub fn entries_from_grpc(entries: Vec<Entry>) -> GrpcResult<EncryptedEntries> {
+    entries
+        .into_iter()
+        .map(|e| Ok((to_uuid(&e.uuid)?, e.data)))
+        .collect()
+}
+
+#[must_use]
+pub fn entries_to_grpc(entries: EncryptedEntries) -> Vec<Entry> {
+    entries
+        .entries
+        .into_iter()
+        .map(|(uuid, data)| Entry {
+            uuid: uuid.to_string(),
+            data,
+        })
+        .collect()
+}
+
+/// Parse the UUIDs of a request.
+///
+/// # Errors
+///
+/// Fails if a UUID cannot be parsed.
+pub fn uuids_from_grpc(uuids: &[String]) -> GrpcResult<Vec<Uuid>> {
+    uuids.iter().map(|uuid| to_uuid(uuid)).collect()
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct AddEntriesRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(message, repeated, tag = "2")]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct DeleteEntriesRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(string, repeated, tag = "2")]
+    pub uuids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GetEntriesRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(string, repeated, tag = "2")]
+    pub uuids: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct GetEntriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: Vec<Entry>,
+}
+
+/// The response of the calls answered by a message in the REST API
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct OperationResponse {
+    #[prost(string, tag = "1")]
+    pub success: String,
+    #[prost(string, tag = "2")]
+    pub index_id: String,
+}
+
+#[allow(clippy::empty_structs_with_brackets)]
+#[derive(Clone, Copy, PartialEq, Eq, prost::Message)]
+pub struct CreateIndexRequest {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum GrpcPermission {
+    Read = 0,
+    Write = 1,
+    Admin = 2,
+}
+
+impl From<Permission> for GrpcPermission {
+    fn from(permission: Permission) -> Self {
+        match permission {
+            Permission::Read => Self::Read,
+            Permission::Write => Self::Write,
+            Permission::Admin => Self::Admin,
+        }
+    }
+}
+
+impl From<GrpcPermission> for Permission {
+    fn from(permission: GrpcPermission) -> Self {
+        match permission {
+            GrpcPermission::Read => Self::Read,
+            GrpcPermission::Write => Self::Write,
+            GrpcPermission::Admin => Self::Admin,
+        }
+    }
+}
+
+fn to_permission(permission: i32) -> GrpcResult<Permission> {
+    GrpcPermission::try_from(permission)
+        .map(Permission::from)
+        .map_err(|e| StructsError::DeserializationError(e.to_string()))
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct SetPermissionRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(enumeration = "GrpcPermission", tag = "2")]
+    pub permission: i32,
+    #[prost(string, tag = "3")]
+    pub index_id: String,
+}
+
+impl SetPermissionRequest {
+    #[must_use]
+    pub fn new(user_id: &str, permission: Permission, index_id: &Uuid) -> Self {
+        Self {
+            user_id: user_id.to_owned(),
+            permission: GrpcPermission::from(permission).into(),
+            index_id: index_id.to_string(),
+        }
+    }
+
+    /// The permission to set.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the permission is unknown.
+    pub fn parsed_permission(&self) -> GrpcResult<Permission> {
+        to_permission(self.permission)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct RevokePermissionRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+    #[prost(string, tag = "2")]
+    pub index_id: String,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct ListPermissionsRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: String,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct IndexPermission {
+    #[prost(string, tag = "1")]
+    pub index_id: String,
+    #[prost(enumeration = "GrpcPermission", tag = "2")]
+    pub permission: i32,
+}
+
+#[derive(Clone, PartialEq, Eq, prost::Message)]
+pub struct ListPermissionsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub permissions: Vec<IndexPermission>,
+}
+
+impl From<Permissions> for ListPermissionsResponse {
+    fn from(permissions: Permissions) -> Self {
+        Self {
+            permissions: permissions
+                .permissions
+                .into_iter()
+                .map(|(index_id, permission)| IndexPermission {
+                    index_id: index_id.to_string(),
+                    permission: GrpcPermission::from(permission).into(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<ListPermissionsResponse> for Permissions {
+    type Error = StructsError;
+
+    fn try_from(response: ListPermissionsResponse) -> GrpcResult<Self> {
+        response
+            .permissions
+            .into_iter()
+            .map(|p| Ok((to_uuid(&p.index_id)?, to_permission(p.permission)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use cosmian_sse_memories::Address;
+    use prost::{DecodeError, Message, bytes::Bytes};
+    use prost_reflect::{DynamicMessage, Kind, MessageDescriptor, Value};
+    use uuid::Uuid;
+
+    use super::{BatchReadRequest, GuardedWriteRequest, ListPermissionsResponse};
+    use crate::{Permission, Permissions};
+
+    /// Decode a message with its hand-written type, and encode it back.
+    type Transcode = fn(&[u8]) -> Result<Vec<u8>, DecodeError>;
+
+    fn transcode<M: Message + Default>(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        M::decode(bytes).map(|message| message.encode_to_vec())
+    }
+
+    macro_rules! transcoders {
+        ($($message:ident),* $(,)?) => {{
+            let transcoders: Vec<(&str, Transcode)> =
+                vec![$((stringify!($message), transcode::<super::$message>)),*];
+            transcoders
+        }};
+    }
+
+    /// A value of the given kind other than its default value, so that it is
+    /// encoded.
+    fn sample(kind: &Kind, seed: u8) -> Value {
+        match kind {
+            Kind::Double => Value::F64(f64::from(seed)),
+            Kind::Float => Value::F32(f32::from(seed)),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(i32::from(seed)),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(i64::from(seed)),
+            Kind::Uint32 | Kind::Fixed32 => Value::U32(u32::from(seed)),
+            Kind::Uint64 | Kind::Fixed64 => Value::U64(u64::from(seed)),
+            Kind::Bool => Value::Bool(true),
+            Kind::String => Value::String(format!("string {seed}")),
+            Kind::Bytes => Value::Bytes(Bytes::from(vec![seed; 16])),
+            Kind::Message(message) => Value::Message(sample_message(message, seed)),
+            Kind::Enum(enumeration) => Value::EnumNumber(
+                enumeration
+                    .values()
+                    .map(|value| value.number())
+                    .max()
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// A message whose fields are all set, the repeated ones to two values.
+    fn sample_message(descriptor: &MessageDescriptor, seed: u8) -> DynamicMessage {
+        let mut message = DynamicMessage::new(descriptor.clone());
+        for (field, seed) in descriptor.fields().zip(seed..) {
+            let value = if field.is_list() {
+                Value::List(vec![
+                    sample(&field.kind(), seed),
+                    sample(&field.kind(), seed.wrapping_add(100)),
+                ])
+            } else {
+                sample(&field.kind(), seed)
+            };
+            message.set_field(&field, value);
+        }
+        message
+    }
+
+    /// The messages written by hand must encode as the messages of
+    /// `proto/findex.proto`, which the other stacks generate their code from.
+    #[test]
+    fn test_grpc_messages_match_proto() {
+        let pool = protox::Compiler::new([concat!(env!("CARGO_MANIFEST_DIR"), "/proto")])
+            .unwrap()
+            .open_file("findex.proto")
+            .unwrap()
+            .descriptor_pool();
+        let transcoders = transcoders![
+            BatchReadRequest,
+            OptionalWord,
+            BatchReadResponse,
+            Binding,
+            GuardedWriteRequest,
+            GuardedWriteResponse,
+            Entry,
+            AddEntriesRequest,
+            DeleteEntriesRequest,
+            GetEntriesRequest,
+            GetEntriesResponse,
+            OperationResponse,
+            CreateIndexRequest,
+            SetPermissionRequest,
+            RevokePermissionRequest,
+            ListPermissionsRequest,
+            IndexPermission,
+            ListPermissionsResponse,
+        ];
+        let mut messages = pool
+            .all_messages()
+            .map(|message| message.name().to_owned())
+            .collect::<Vec<_>>();
+        messages.sort_unstable();
+        let mut transcoded = transcoders
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        transcoded.sort_unstable();
+        assert_eq!(messages, transcoded);
+
+        for (name, transcode) in transcoders {
+            let descriptor = pool
+                .get_message_by_name(&format!("findex.v1.{name}"))
+                .unwrap();
+            let message = sample_message(&descriptor, 1);
+            let bytes = transcode(&message.encode_to_vec()).unwrap();
+            assert_eq!(
+                DynamicMessage::decode(descriptor, bytes.as_slice()).unwrap(),
+                message,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_grpc_messages() {
+        let index_id = Uuid::new_v4();
+        let address = Address::from([1; 16]);
+
+        let request = BatchReadRequest::new(&index_id, &[address]);
+        let request = BatchReadRequest::decode(request.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(request.addresses().unwrap(), vec![address]);
+
+        let request = GuardedWriteRequest::new(&index_id, &(address, None), &[(address, [2; 4])]);
+        let request = GuardedWriteRequest::decode(request.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(request.guard::<4>().unwrap(), (address, None));
+        assert_eq!(request.bindings::<4>().unwrap(), vec![(address, [2; 4])]);
+        // The words of another length are rejected
+        request.bindings::<8>().unwrap_err();
+
+        let permissions = Permissions::new(index_id, Permission::Write);
+        let response = ListPermissionsResponse::from(permissions);
+        let response =
+            ListPermissionsResponse::decode(response.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(
+            Permissions::try_from(response).unwrap(),
+            Permissions::new(index_id, Permission::Write)
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use cosmian_crypto_core::bytes_ser_de::{Deserializer, Serializable, Serializer, to_leb128_len};
+use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::{
+    CUSTOM_WORD_LENGTH, EncryptedEntries, Permission, StructsError, VersionedEntries,
+    VersionedEntry, encrypted_entries::UUID_LENGTH, structs_bail,
+};
+
+/// The magic bytes starting an index archive
+const INDEX_ARCHIVE_MAGIC: [u8; 4] = *b"FIDX";
+
+/// The version of the index archive format written by this library
+pub const INDEX_ARCHIVE_VERSION: u64 = 2;
+
+/// The version of the archives whose entries are stored without their
+/// versions; they are read at version 1.
+const UNVERSIONED_INDEX_ARCHIVE_VERSION: u64 = 1;
+
+/// A portable copy of an index: its memory words, its dataset entries with
+/// their versions and, optionally, the permissions granted on it.
+///
+/// The memory words are stored under their Findex address, without the index
+/// ID prepended by the server, so that an archive can be imported under
+/// another index ID.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IndexArchive {
+    /// The ID of the exported index
+    pub index_id: Uuid,
+    pub words: Vec<(Address<ADDRESS_LENGTH>, [u8; CUSTOM_WORD_LENGTH])>,
+    pub entries: VersionedEntries,
+    /// The permissions of the users on the index, if they were exported
+    pub permissions: Option<Vec<(String, Permission)>>,
+}
+
+impl Serializable for IndexArchive {
+    type Error = StructsError;
+
+    fn length(&self) -> usize {
+        let words_len = self.words.len() * (ADDRESS_LENGTH + CUSTOM_WORD_LENGTH);
+        let permissions_len = self.permissions.as_ref().map_or(0, |permissions| {
+            to_leb128_len(permissions.len())
+                + permissions
+                    .iter()
+                    .map(|(user_id, _)| to_leb128_len(user_id.len()) + user_id.len() + 1)
+                    .sum::<usize>()
+        });
+        INDEX_ARCHIVE_MAGIC.len()
+            + to_leb128_len(usize::try_from(INDEX_ARCHIVE_VERSION).unwrap_or_default())
+            + UUID_LENGTH
+            + to_leb128_len(self.words.len())
+            + words_len
+            + self.entries.length()
+            + 1
+            + permissions_len
+    }
+
+    /// Serialize the `IndexArchive` struct
+    ///
+    /// Serialization format:
+    ///
+    /// +-------+---------+----------+--------------------+-----------+-----------------------+
+    /// | Magic | Version | Index ID | Words              | Entries   | Permissions           |
+    /// +-------+---------+----------+--------------------+-----------+-----------------------+
+    /// | FIDX  | LEB128  | 16 bytes | LEB128 count, then | as        | 1 byte flag, then     |
+    /// |       |         |          | address || word    | versioned | LEB128 count and      |
+    /// |       |         |          |                    | entries   | user ID || permission |
+    /// +-------+---------+----------+--------------------+-----------+-----------------------+
+    ///
+    /// The archives of version 1 store the entries as encrypted entries.
+    fn write(&self, ser: &mut Serializer) -> Result<usize, Self::Error> {
+        let mut n = ser.write_array(&INDEX_ARCHIVE_MAGIC)?;
+        n += ser.write_leb128_u64(INDEX_ARCHIVE_VERSION)?;
+        n += ser.write_array(self.index_id.as_bytes())?;
+
+        n += ser.write_leb128_u64(u64::try_from(self.words.len())?)?;
+        for (address, word) in &self.words {
+            n += ser.write_array(address.as_ref())?;
+            n += ser.write_array(word)?;
+        }
+
+        n += self.entries.write(ser)?;
+
+        match &self.permissions {
+            None => n += ser.write_leb128_u64(0)?,
+            Some(permissions) => {
+                n += ser.write_leb128_u64(1)?;
+                n += ser.write_leb128_u64(u64::try_from(permissions.len())?)?;
+                for (user_id, permission) in permissions {
+                    n += ser.write_vec(user_id.as_bytes())?;
+                    n += ser.write_leb128_u64(u64::from(u8::from(*permission)))?;
+                }
+            }
+        }
+        Ok(n)
+    }
+
+    /// Deserialize the `IndexArchive` struct
+    fn read(de: &mut Deserializer) -> Result<Self, Self::Error> {
+        let magic: [u8; 4] = de.read_array()?;
+        if magic != INDEX_ARCHIVE_MAGIC {
+            structs_bail!("Invalid index archive: unknown format");
+        }
+        let version = de.read_leb128_u64()?;
+        if version != INDEX_ARCHIVE_VERSION && version != UNVERSIONED_INDEX_ARCHIVE_VERSION {
+            structs_bail!(
+                "Unsupported index archive version {version}, version {INDEX_ARCHIVE_VERSION} \
+                 expected"
+            );
+        }
+        let index_id = Uuid::from_bytes(de.read_array()?);
+
+        let length = <usize>::try_from(de.read_leb128_u64()?)?;
+        if length > 1_000_000 {
+            debug!("IndexArchive: read: allocating {length} words");
+        }
+        let mut words = Vec::with_capacity(length);
+        for _ in 0..length {
+            let address: Address<ADDRESS_LENGTH> = de.read_array()?.into();
+            words.push((address, de.read_array()?));
+        }
+
+        let entries = if version == UNVERSIONED_INDEX_ARCHIVE_VERSION {
+            EncryptedEntries::read(de)?
+                .entries
+                .into_iter()
+                .map(|(uid, entry)| (uid, VersionedEntry { version: 1, entry }))
+                .collect()
+        } else {
+            VersionedEntries::read(de)?
+        };
+
+        let permissions = match de.read_leb128_u64()? {
+            0 => None,
+            1 => {
+                let length = <usize>::try_from(de.read_leb128_u64()?)?;
+                let mut permissions = Vec::with_capacity(length);
+                for _ in 0..length {
+                    let user_id = String::from_utf8(de.read_vec()?).map_err(|e| {
+                        StructsError::DeserializationError(format!("Invalid user ID: {e}"))
+                    })?;
+                    let permission = Permission::try_from(u8::try_from(de.read_leb128_u64()?)?)?;
+                    permissions.push((user_id, permission));
+                }
+                Some(permissions)
+            }
+            flag => structs_bail!("Invalid index archive: unexpected permissions flag {flag}"),
+        };
+
+        Ok(Self {
+            index_id,
+            words,
+            entries,
+            permissions,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(
+    clippy::panic_in_result_fn,
+    clippy::unwrap_used,
+    clippy::indexing_slicing
+)]
+mod tests {
+    use cosmian_crypto_core::bytes_ser_de::{Serializable, Serializer};
+    use cosmian_sse_memories::{ADDRESS_LENGTH, Address};
+    use uuid::Uuid;
+
+    use super::{INDEX_ARCHIVE_MAGIC, IndexArchive, UNVERSIONED_INDEX_ARCHIVE_VERSION};
+    use crate::{
+        CUSTOM_WORD_LENGTH, EncryptedEntries, Permission, VersionedEntries, VersionedEntry,
+        error::result::StructsResult,
+    };
+
+    #[test]
+    fn test_index_archive() -> StructsResult<()> {
+        let mut archive = IndexArchive {
+            index_id: Uuid::new_v4(),
+            words: (0..10_u8)
+                .map(|i| (Address::from([i; ADDRESS_LENGTH]), [i; CUSTOM_WORD_LENGTH]))
+                .collect(),
+            entries: VersionedEntries::from_iter([(
+                Uuid::new_v4(),
+                VersionedEntry {
+                    version: 3,
+                    entry: vec![1, 2, 3],
+                },
+            )]),
+            permissions: None,
+        };
+        let serialized = archive.serialize()?;
+        assert_eq!(serialized.len(), archive.length());
+        assert_eq!(archive, IndexArchive::deserialize(&serialized)?);
+
+        archive.permissions = Some(vec![
+            ("alice".to_owned(), Permission::Admin),
+            ("bob".to_owned(), Permission::Read),
+        ]);
+        let serialized = archive.serialize()?;
+        assert_eq!(serialized.len(), archive.length());
+        assert_eq!(archive, IndexArchive::deserialize(&serialized)?);
+
+        // Archives of another format or version are rejected.
+        let mut corrupted = serialized.to_vec();
+        corrupted[4] = 3;
+        IndexArchive::deserialize(&corrupted).unwrap_err();
+        corrupted[0] = b'X';
+        IndexArchive::deserialize(&corrupted).unwrap_err();
+        Ok(())
+    }
+
+    #[test]
+    fn test_unversioned_index_archive() -> StructsResult<()> {
+        let (index_id, uid) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut ser = Serializer::new();
+        ser.write_array(&INDEX_ARCHIVE_MAGIC)?;
+        ser.write_leb128_u64(UNVERSIONED_INDEX_ARCHIVE_VERSION)?;
+        ser.write_array(index_id.as_bytes())?;
+        ser.write_leb128_u64(0)?;
+        EncryptedEntries::from_iter([(uid, vec![1, 2, 3])]).write(&mut ser)?;
+        ser.write_leb128_u64(0)?;
+
+        // The entries of an archive of version 1 are read at version 1.
+        let archive = IndexArchive::deserialize(&ser.finalize())?;
+        assert_eq!(archive.index_id, index_id);
+        assert_eq!(
+            archive.entries,
+            VersionedEntries::from_iter([(
+                uid,
+                VersionedEntry {
+                    version: 1,
+                    entry: vec![1, 2, 3],
+                },
+            )])
+        );
+        Ok(())
+    }
+}
@@ -49,18 +49,26 @@
 mod encrypted_entries;
 mod error;
 mod findex;
+mod frames;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod index_archive;
 mod permissions;
 mod uuids;
+mod versioned_entries;
 
 use cosmian_sse_memories::ADDRESS_LENGTH;
 pub use encrypted_entries::EncryptedEntries;
 pub use error::StructsError;
 pub use findex::{
-    Addresses, Bindings, Guard, Keyword, KeywordToDataSetsMap, Keywords, OptionalWords,
-    SearchResults, SerializationResult, Value,
+    Addresses, Bindings, Guard, IndexedAddresses, IndexedWords, Keyword, KeywordToDataSetsMap,
+    Keywords, OptionalWords, SearchResults, SerializationResult, Value,
 };
+pub use frames::{FRAMED_CONTENT_TYPE, FrameDecoder, MAX_FRAME_LENGTH, encode_frame};
+pub use index_archive::{INDEX_ARCHIVE_VERSION, IndexArchive};
 pub use permissions::{Permission, Permissions};
 pub use uuids::Uuids;
+pub use versioned_entries::{VersionedEntries, VersionedEntry};
 
 // UID length
 pub const UID_LENGTH: usize = 16;
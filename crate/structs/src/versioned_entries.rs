@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    ops::{Deref, DerefMut},
+};
+
+use cosmian_crypto_core::bytes_ser_de::{Deserializer, Serializable, Serializer, to_leb128_len};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::{EncryptedEntries, StructsError, encrypted_entries::UUID_LENGTH};
+
+/// An encrypted dataset entry and its version.
+///
+/// The version of an entry starts at 1 and is incremented each time the
+/// entry is written. A missing entry has the version 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedEntry {
+    pub version: u64,
+    pub entry: Vec<u8>,
+}
+
+/// Encrypted dataset entries along with their versions.
+///
+/// When used to update entries, the versions are the ones the entries are
+/// expected to have before the update.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct VersionedEntries {
+    pub entries: HashMap<Uuid, VersionedEntry>,
+}
+
+impl Deref for VersionedEntries {
+    type Target = HashMap<Uuid, VersionedEntry>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for VersionedEntries {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl FromIterator<(Uuid, VersionedEntry)> for VersionedEntries {
+    fn from_iter<T: IntoIterator<Item = (Uuid, VersionedEntry)>>(iter: T) -> Self {
+        Self {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl From<VersionedEntries> for EncryptedEntries {
+    fn from(entries: VersionedEntries) -> Self {
+        entries
+            .entries
+            .into_iter()
+            .map(|(uid, versioned)| (uid, versioned.entry))
+            .collect()
+    }
+}
+
+impl Display for VersionedEntries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Versioned entries:")?;
+        for (uid, versioned) in self.iter() {
+            writeln!(
+                f,
+                "{uid}: version {}, {} bytes",
+                versioned.version,
+                versioned.entry.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Serializable for VersionedEntries {
+    type Error = StructsError;
+
+    fn length(&self) -> usize {
+        to_leb128_len(self.len())
+            + self
+                .values()
+                .map(|versioned| {
+                    UUID_LENGTH
+                        + to_leb128_len(usize::try_from(versioned.version).unwrap_or(usize::MAX))
+                        + to_leb128_len(versioned.entry.len())
+                        + versioned.entry.len()
+                })
+                .sum::<usize>()
+    }
+
+    /// Serialize the `VersionedEntries` struct
+    ///
+    /// Serialization format:
+    ///
+    /// +-------------------+----------------------+----------------------+
+    /// | Number of Entries | Entry 1              | Entry 2              |
+    /// +-------------------+----------------------+----------------------+
+    /// |  LEB128 encoded   | UUID (16 bytes)      | UUID (16 bytes)      |
+    /// |  number of        | Version (LEB128)     | Version (LEB128)     |
+    /// |  entries          | Vec length (LEB128)  | Vec length (LEB128)  |
+    /// |                   | Vec data (bytes)     | Vec data (bytes)     |
+    /// +-------------------+----------------------+----------------------+
+    fn write(&self, ser: &mut Serializer) -> Result<usize, Self::Error> {
+        let mut n = ser.write_leb128_u64(u64::try_from(self.len())?)?;
+        for (uid, versioned) in self.iter() {
+            n += ser.write_array(uid.as_bytes())?;
+            n += ser.write_leb128_u64(versioned.version)?;
+            n += ser.write_vec(&versioned.entry)?;
+        }
+        Ok(n)
+    }
+
+    /// Deserialize the `VersionedEntries` struct
+    fn read(de: &mut Deserializer) -> Result<Self, Self::Error> {
+        let length = <usize>::try_from(de.read_leb128_u64()?)?;
+        if length > 1_000_000 {
+            debug!("VersionedEntries: read: allocating {length}");
+        }
+
+        let mut entries = HashMap::with_capacity(length);
+        for _ in 0..length {
+            let uid = Uuid::from_bytes(de.read_array()?);
+            let version = de.read_leb128_u64()?;
+            let entry = de.read_vec()?;
+            entries.insert(uid, VersionedEntry { version, entry });
+        }
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmian_crypto_core::bytes_ser_de::Serializable;
+    use uuid::Uuid;
+
+    use super::{VersionedEntries, VersionedEntry};
+    use crate::error::result::StructsResult;
+
+    #[test]
+    #[allow(clippy::panic_in_result_fn)]
+    fn test_versioned_entries() -> StructsResult<()> {
+        let entries = VersionedEntries::from_iter([
+            (
+                Uuid::new_v4(),
+                VersionedEntry {
+                    version: 1,
+                    entry: vec![1, 2, 3],
+                },
+            ),
+            (
+                Uuid::new_v4(),
+                VersionedEntry {
+                    version: 300,
+                    entry: vec![4; 200],
+                },
+            ),
+        ]);
+        let serialized = entries.serialize()?;
+        assert_eq!(serialized.len(), entries.length());
+        assert_eq!(entries, VersionedEntries::deserialize(&serialized)?);
+        Ok(())
+    }
+}
@@ -24,6 +24,8 @@ use crate::test_jwt::{AUTH0_TOKEN, get_auth0_jwt_config};
 
 const REDIS_DEFAULT_URL: &str = "redis://localhost:6379";
 const SQLITE_DEFAULT_URL: &str = "sqlite-data.db";
+/// The gRPC port of a test server is its HTTP port plus this offset
+const GRPC_PORT_OFFSET: u16 = 10_000;
 
 /// In order to run most tests in parallel,
 /// we use that to avoid to try to start N Findex servers (one per test)
@@ -46,6 +48,7 @@ fn redis_db_config() -> DBConfig {
         database_type: DatabaseType::Redis,
         clear_database: false,
         database_url: url,
+        ..DBConfig::default()
     }
 }
 
@@ -56,6 +59,7 @@ fn sqlite_db_config(sqlite_url_var_env: &str) -> DBConfig {
         database_type: DatabaseType::Sqlite,
         clear_database: false,
         database_url: url,
+        ..DBConfig::default()
     }
 }
 
@@ -117,6 +121,8 @@ pub async fn start_default_test_findex_server_with_cert_auth() -> &'static Tests
 
 pub struct TestsContext {
     pub owner_client_conf: RestClientConfig,
+    /// The URL of the gRPC API, served by the plain HTTP servers only
+    pub grpc_url: Option<String>,
     pub user_client_conf: RestClientConfig,
     pub server_handle: ServerHandle,
     pub thread_handle: JoinHandle<Result<(), ClientError>>,
@@ -169,6 +175,9 @@ pub async fn start_test_server_with_options(
     let owner_client_conf = generate_owner_conf(&server_params)?;
     let user_client_conf = generate_user_conf(&owner_client_conf)?;
     let findex_client = RestClient::new(owner_client_conf.clone())?;
+    let grpc_url = server_params
+        .grpc_port
+        .map(|grpc_port| format!("http://127.0.0.1:{grpc_port}"));
 
     info!(
         "Starting Findex test server at URL: {} with server params {:?}",
@@ -184,6 +193,7 @@ pub async fn start_test_server_with_options(
 
     Ok(TestsContext {
         owner_client_conf,
+        grpc_url,
         user_client_conf,
         server_handle,
         thread_handle,
@@ -263,6 +273,7 @@ fn generate_http_config(port: u16, use_https: bool, use_client_cert: bool) -> Ht
     } else {
         HttpConfig {
             port,
+            grpc_port: Some(port + GRPC_PORT_OFFSET),
             ..HttpConfig::default()
         }
     }
@@ -342,6 +353,8 @@ fn generate_owner_conf(server_params: &ServerParams) -> Result<RestClientConfig,
                 .then(|| "password".to_owned()),
             ..Default::default()
         },
+        compression: None,
+        retry: None,
     };
 
     Ok(owner_client_conf)